@@ -0,0 +1,404 @@
+use std::fmt;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use crate::{duplicates::{DuplicateGroup, DuplicateReport}, file_tree::FileTree, long_path};
+
+/// What to do with the copies of a duplicate group that are not kept.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Resolution {
+    /// Delete the other copies.
+    Delete,
+    /// Replace the other copies with hard links to the kept one.
+    HardLink,
+    /// Replace the other copies with symbolic links to the kept one.
+    SymLink,
+}
+
+impl FromStr for Resolution {
+    type Err = io::Error;
+
+    fn from_str(text: &str) -> io::Result<Self> {
+        match text {
+            "rm" | "delete" => Ok(Resolution::Delete),
+            "hardlink" => Ok(Resolution::HardLink),
+            "symlink" => Ok(Resolution::SymLink),
+            _ => Err(io::Error::new(io::ErrorKind::InvalidInput, format!("Action invalide : {text} (attendu rm, hardlink ou symlink)"))),
+        }
+    }
+}
+
+/// The shell language of the scripts written by `DuplicateReport::write_script`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScriptShell {
+    /// A POSIX shell script.
+    Sh,
+    /// A PowerShell script.
+    PowerShell,
+}
+
+impl Default for ScriptShell {
+
+    /// Returns PowerShell on Windows, the POSIX shell elsewhere.
+    fn default() -> Self {
+        if cfg!(windows) { ScriptShell::PowerShell } else { ScriptShell::Sh }
+    }
+}
+
+/// Which copy of a duplicate group is kept when the others are resolved automatically.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum KeepPolicy {
+    /// Keep the copy modified the longest ago.
+    Oldest,
+    /// Keep the copy modified most recently.
+    Newest,
+    /// Keep the copy whose path comes first.
+    #[default]
+    FirstPath,
+}
+
+impl KeepPolicy {
+
+    /// Returns the index in `group.paths` of the copy to keep, ties being broken by path.
+    ///
+    /// # Arguments
+    ///
+    /// * `group` - The group of duplicates.
+    /// * `file_tree` - The file tree in which the group was found, giving the modification times.
+    pub fn select(&self, group: &DuplicateGroup, file_tree: &FileTree) -> usize {
+        let indices = 0..group.paths.len();
+        let modified = |index: &usize| file_tree.get_modified(&group.paths[*index]);
+        // Les chemins du groupe sont triés : en cas d'égalité, le premier l'emporte
+        let selected = match self {
+            KeepPolicy::Oldest => indices.min_by_key(|index| (modified(index).is_none(), modified(index))),
+            KeepPolicy::Newest => indices.rev().max_by_key(modified),
+            KeepPolicy::FirstPath => indices.min(),
+        };
+        selected.unwrap_or(0)
+    }
+}
+
+impl FromStr for KeepPolicy {
+    type Err = io::Error;
+
+    fn from_str(text: &str) -> io::Result<Self> {
+        match text {
+            "oldest" => Ok(KeepPolicy::Oldest),
+            "newest" => Ok(KeepPolicy::Newest),
+            "first-path" => Ok(KeepPolicy::FirstPath),
+            _ => Err(io::Error::new(io::ErrorKind::InvalidInput, format!("Politique invalide : {text} (attendu oldest, newest ou first-path)"))),
+        }
+    }
+}
+
+/// An action on a duplicate file, planned before being applied.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DuplicateAction {
+    /// Delete the file.
+    Delete(PathBuf),
+    /// Replace the file at `path` with a hard link to `target`.
+    HardLink { path: PathBuf, target: PathBuf },
+    /// Replace the file at `path` with a symbolic link to `target`.
+    SymLink { path: PathBuf, target: PathBuf },
+}
+
+impl DuplicateAction {
+
+    /// Applies the action to the file system.
+    ///
+    /// A link is first created next to the file, then renamed over it, so the file
+    /// is never lost if the link can't be created (e.g. a hard link across file systems).
+    ///
+    /// # Returns
+    ///
+    /// An empty `io::Result`, failing if the file could not be deleted or replaced.
+    pub fn apply(&self) -> io::Result<()> {
+        // Sous Windows, les chemins longs passent par la forme \\?\ ; la cible d'un lien symbolique
+        // est gardée telle quelle, car elle est écrite dans le lien
+        match self {
+            DuplicateAction::Delete(path) => fs::remove_file(long_path::extended(path)),
+            DuplicateAction::HardLink { path, target } => Self::replace(&long_path::extended(path), |temporary| fs::hard_link(long_path::extended(target), temporary)),
+            DuplicateAction::SymLink { path, target } => Self::replace(&long_path::extended(path), |temporary| Self::symlink(target, temporary)),
+        }
+    }
+
+    /// Returns `true` if the action links files of different file systems, which a hard link
+    /// can't do and a symbolic link shouldn't, the copy being then left as is. Always `false`
+    /// for a deletion.
+    ///
+    /// The device IDs are compared on Unix, the drives or shares elsewhere.
+    ///
+    /// # Returns
+    ///
+    /// An `io::Result` containing the answer, failing if the metadata of a file can't be read.
+    pub fn crosses_file_systems(&self) -> io::Result<bool> {
+        match self {
+            DuplicateAction::Delete(_) => Ok(false),
+            DuplicateAction::HardLink { path, target } | DuplicateAction::SymLink { path, target } => {
+                #[cfg(unix)]
+                {
+                    use std::os::unix::fs::MetadataExt;
+                    Ok(fs::metadata(path)?.dev() != fs::metadata(target)?.dev())
+                }
+                #[cfg(not(unix))]
+                {
+                    Ok(path.components().next() != target.components().next())
+                }
+            }
+        }
+    }
+
+    /// Creates a link with `link` at a temporary path next to `path`, then renames it over `path`.
+    fn replace(path: &Path, link: impl FnOnce(&Path) -> io::Result<()>) -> io::Result<()> {
+        let temporary = Self::temporary_path(path);
+        link(&temporary)?;
+        fs::rename(&temporary, path).inspect_err(|_| {
+            let _ = fs::remove_file(&temporary);
+        })
+    }
+
+    #[cfg(unix)]
+    fn symlink(target: &Path, link: &Path) -> io::Result<()> {
+        std::os::unix::fs::symlink(target, link)
+    }
+
+    #[cfg(windows)]
+    fn symlink(target: &Path, link: &Path) -> io::Result<()> {
+        std::os::windows::fs::symlink_file(target, link)
+    }
+
+    #[cfg(not(any(unix, windows)))]
+    fn symlink(_target: &Path, _link: &Path) -> io::Result<()> {
+        Err(io::Error::new(io::ErrorKind::Unsupported, "symbolic links are not supported on this platform"))
+    }
+
+    /// Returns the path of the file affected by the action.
+    pub fn path(&self) -> &Path {
+        match self {
+            DuplicateAction::Delete(path) | DuplicateAction::HardLink { path, .. } | DuplicateAction::SymLink { path, .. } => path,
+        }
+    }
+
+    fn temporary_path(path: &Path) -> PathBuf {
+        let mut name = path.file_name().unwrap_or_default().to_os_string();
+        name.push(".du-link");
+        path.with_file_name(name)
+    }
+}
+
+impl fmt::Display for DuplicateAction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DuplicateAction::Delete(path) => write!(f, "delete {}", path.display()),
+            DuplicateAction::HardLink { path, target } => write!(f, "hardlink {} -> {}", path.display(), target.display()),
+            DuplicateAction::SymLink { path, target } => write!(f, "symlink {} -> {}", path.display(), target.display()),
+        }
+    }
+}
+
+
+impl DuplicateGroup {
+
+    /// Returns the actions keeping the copy at index `keep` in `paths` and resolving the others.
+    /// The copies already hard-linked to the kept one are left as they are by `Resolution::HardLink`.
+    ///
+    /// # Arguments
+    ///
+    /// * `keep` - The index of the copy to keep, no action is planned if it is out of bounds.
+    /// * `resolution` - What to do with the other copies.
+    pub fn plan(&self, keep: usize, resolution: Resolution) -> Vec<DuplicateAction> {
+        let Some(kept) = self.paths.get(keep) else { return Vec::new() };
+        let kept_link = self.link(keep);
+        self.paths
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| *index != keep && !(resolution == Resolution::HardLink && kept_link.is_some() && self.link(*index) == kept_link))
+            .map(|(_, path)| match resolution {
+                Resolution::Delete => DuplicateAction::Delete(path.clone()),
+                Resolution::HardLink => DuplicateAction::HardLink { path: path.clone(), target: kept.clone() },
+                Resolution::SymLink => DuplicateAction::SymLink { path: path.clone(), target: kept.clone() },
+            })
+            .collect()
+    }
+}
+
+impl DuplicateReport {
+
+    /// Writes a script resolving every group, to be reviewed before being run: the copy chosen by
+    /// `keep` is kept and the others are deleted or replaced with links, each group being
+    /// introduced by a comment. Nothing is changed on the file system.
+    ///
+    /// Paths which are not valid Unicode are written lossily and must be checked by hand.
+    ///
+    /// # Arguments
+    ///
+    /// * `out` - The writer receiving the script.
+    /// * `file_tree` - The file tree in which the duplicates were found, giving the modification times.
+    /// * `keep` - Which copy of each group is kept.
+    /// * `resolution` - What to do with the other copies.
+    /// * `shell` - The language of the script.
+    pub fn write_script<W: Write>(&self, mut out: W, file_tree: &FileTree, keep: KeepPolicy, resolution: Resolution, shell: ScriptShell) -> io::Result<()> {
+        match shell {
+            ScriptShell::Sh => writeln!(out, "#!/bin/sh\n# Review this script before running it.\nset -e")?,
+            ScriptShell::PowerShell => writeln!(out, "# Review this script before running it.\n$ErrorActionPreference = 'Stop'")?,
+        }
+        for group in self.groups() {
+            let kept = keep.select(group, file_tree);
+            writeln!(out, "\n# {} x {}, keeping {}", group.paths.len(), group.size, quote(&group.paths[kept], shell))?;
+            for action in group.plan(kept, resolution) {
+                let command = match (&action, shell) {
+                    (DuplicateAction::Delete(path), ScriptShell::Sh) => format!("rm -- {}", quote(path, shell)),
+                    (DuplicateAction::HardLink { path, target }, ScriptShell::Sh) => format!("ln -f -- {} {}", quote(target, shell), quote(path, shell)),
+                    (DuplicateAction::SymLink { path, target }, ScriptShell::Sh) => format!("ln -sf -- {} {}", quote(target, shell), quote(path, shell)),
+                    (DuplicateAction::Delete(path), ScriptShell::PowerShell) => format!("Remove-Item -LiteralPath {}", quote(path, shell)),
+                    (DuplicateAction::HardLink { path, target }, ScriptShell::PowerShell) => {
+                        format!("Remove-Item -LiteralPath {0}; New-Item -ItemType HardLink -Path {0} -Target {1} | Out-Null", quote(path, shell), quote(target, shell))
+                    }
+                    (DuplicateAction::SymLink { path, target }, ScriptShell::PowerShell) => {
+                        format!("Remove-Item -LiteralPath {0}; New-Item -ItemType SymbolicLink -Path {0} -Target {1} | Out-Null", quote(path, shell), quote(target, shell))
+                    }
+                };
+                writeln!(out, "{}", command)?;
+            }
+        }
+        out.flush()
+    }
+}
+
+/// Quotes a path for the shell: between single quotes, the single quotes being escaped. The paths too
+/// long for Windows PowerShell are written in the extended-length form.
+fn quote(path: &Path, shell: ScriptShell) -> String {
+    match shell {
+        ScriptShell::Sh => format!("'{}'", path.to_string_lossy().replace('\'', "'\\''")),
+        // Windows PowerShell refuse les chemins de plus de 260 caractères sans la forme \\?\
+        ScriptShell::PowerShell => format!("'{}'", long_path::extended_if_long(path).to_string_lossy().replace('\'', "''")),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_tree::FileTree;
+    use crate::size::Size;
+    use crate::test_utils::create_fixture;
+
+    fn group(root: &Path) -> DuplicateGroup {
+        for name in ["a", "b", "c"] {
+            fs::write(root.join(name), [5; 100]).unwrap();
+        }
+        let mut file_tree = FileTree::new(root).unwrap();
+        file_tree.compute_signatures().unwrap();
+        file_tree.find_duplicates_with(Size::new(1)).groups()[0].clone()
+    }
+
+    #[test]
+    fn plan_test() {
+        let fixture = create_fixture();
+        let root = fixture.path().join("Test");
+        let group = group(&root);
+
+        let actions = group.plan(1, Resolution::Delete);
+        assert_eq!(actions, [DuplicateAction::Delete(root.join("a")), DuplicateAction::Delete(root.join("c"))]);
+        assert_eq!(actions[0].to_string(), format!("delete {}", root.join("a").display()));
+        assert!(group.plan(3, Resolution::Delete).is_empty());
+    }
+
+    #[test]
+    fn keep_policy_test() {
+        let fixture = create_fixture();
+        let root = fixture.path().join("Test");
+        let group = group(&root);
+        let now = std::time::SystemTime::now();
+        fs::File::options().write(true).open(root.join("b")).unwrap().set_modified(now - std::time::Duration::from_secs(3600)).unwrap();
+        fs::File::options().write(true).open(root.join("c")).unwrap().set_modified(now + std::time::Duration::from_secs(3600)).unwrap();
+        let file_tree = FileTree::new(&root).unwrap();
+
+        assert_eq!(KeepPolicy::Oldest.select(&group, &file_tree), 1);
+        assert_eq!(KeepPolicy::Newest.select(&group, &file_tree), 2);
+        assert_eq!(KeepPolicy::FirstPath.select(&group, &file_tree), 0);
+        assert_eq!("newest".parse::<KeepPolicy>().unwrap(), KeepPolicy::Newest);
+        assert!("largest".parse::<KeepPolicy>().is_err());
+    }
+
+    #[test]
+    fn apply_delete_test() {
+        let fixture = create_fixture();
+        let root = fixture.path().join("Test");
+        for action in group(&root).plan(0, Resolution::Delete) {
+            action.apply().unwrap();
+        }
+        assert!(root.join("a").exists());
+        assert!(!root.join("b").exists());
+        assert!(!root.join("c").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn apply_hard_link_test() {
+        use std::os::unix::fs::MetadataExt;
+
+        let fixture = create_fixture();
+        let root = fixture.path().join("Test");
+        for action in group(&root).plan(2, Resolution::HardLink) {
+            action.apply().unwrap();
+        }
+        let inode = fs::metadata(root.join("c")).unwrap().ino();
+        assert_eq!(fs::metadata(root.join("a")).unwrap().ino(), inode);
+        assert_eq!(fs::metadata(root.join("b")).unwrap().ino(), inode);
+        assert_eq!(fs::read(root.join("a")).unwrap(), [5; 100]);
+        assert!(!root.join("a.du-link").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn apply_symlink_test() {
+        let fixture = create_fixture();
+        let root = fixture.path().join("Test");
+        for action in group(&root).plan(0, "symlink".parse().unwrap()) {
+            action.apply().unwrap();
+        }
+        assert_eq!(fs::read_link(root.join("b")).unwrap(), root.join("a"));
+        assert_eq!(fs::read(root.join("c")).unwrap(), [5; 100]);
+    }
+
+    #[test]
+    fn write_script_test() {
+        let fixture = create_fixture();
+        let root = fixture.path().join("Test");
+        group(&root);
+        fs::write(root.join("l'autre"), [5; 100]).unwrap();
+        let mut file_tree = FileTree::new(&root).unwrap();
+        file_tree.compute_signatures().unwrap();
+        let report = file_tree.find_duplicates_with(Size::new(1));
+        let quoted = |name: &str| format!("'{}'", root.join(name).display());
+
+        let mut out = Vec::new();
+        report.write_script(&mut out, &file_tree, KeepPolicy::FirstPath, Resolution::Delete, ScriptShell::Sh).unwrap();
+        let script = String::from_utf8(out).unwrap();
+        assert!(script.starts_with("#!/bin/sh\n"));
+        assert!(script.contains(&format!("# 4 x 100 B, keeping {}\n", quoted("a"))));
+        assert!(script.contains(&format!("rm -- {}\n", quoted("c"))));
+        assert!(script.contains(&format!("rm -- {}'\\''autre'\n", quoted("l").trim_end_matches('\''))));
+        assert_eq!(script.lines().filter(|line| line.starts_with("rm")).count(), 3);
+        assert!(root.join("c").exists());
+
+        let mut out = Vec::new();
+        report.write_script(&mut out, &file_tree, KeepPolicy::FirstPath, Resolution::HardLink, ScriptShell::PowerShell).unwrap();
+        let script = String::from_utf8(out).unwrap();
+        assert!(script.contains(&format!("New-Item -ItemType HardLink -Path {} -Target {} | Out-Null", quoted("b"), quoted("a"))));
+        assert!(script.contains("l''autre"));
+    }
+
+    #[test]
+    fn crosses_file_systems_test() {
+        let fixture = create_fixture();
+        let root = fixture.path().join("Test");
+        let group = group(&root);
+        assert!(!group.plan(0, Resolution::HardLink)[0].crosses_file_systems().unwrap());
+        assert!(!group.plan(0, Resolution::Delete)[0].crosses_file_systems().unwrap());
+        let missing = DuplicateAction::SymLink { path: root.join("absent"), target: root.join("a") };
+        assert!(missing.crosses_file_systems().is_err());
+    }
+}
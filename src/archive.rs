@@ -0,0 +1,196 @@
+use flate2::read::GzDecoder;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::File;
+use std::io::{self, BufReader, Read};
+use std::path::{Component, Path, PathBuf};
+use crate::{file_tree::EntryNode, hasher::Hasher, size::Size};
+
+/// The formats of the archives listed as directories by `FileTreeBuilder::inspect_archives`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum ArchiveFormat {
+    Zip,
+    Tar,
+    /// A tar archive compressed with gzip, `.tar.gz` or `.tgz`.
+    TarGz,
+}
+
+impl ArchiveFormat {
+
+    /// Returns the format of the archive at `path` from its extension, regardless of case, or
+    /// `None` if it is not an archive.
+    pub(crate) fn of(path: &Path) -> Option<Self> {
+        let name = path.file_name()?.to_string_lossy().to_lowercase();
+        if name.ends_with(".zip") {
+            Some(ArchiveFormat::Zip)
+        } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            Some(ArchiveFormat::TarGz)
+        } else if name.ends_with(".tar") {
+            Some(ArchiveFormat::Tar)
+        } else {
+            None
+        }
+    }
+}
+
+/// Lists the content of the archive at `path` as entries of a file tree below `path`: the files
+/// with their uncompressed size, and the directories, including those only implied by the path of a
+/// file. Links and special files are left out, like the members whose path leaves the archive.
+///
+/// # Returns
+///
+/// An `io::Result` containing the entries below `path` and the children of `path` itself,
+/// failing if the archive could not be read or is corrupted.
+pub(crate) fn list(path: &Path, format: ArchiveFormat) -> io::Result<(BTreeMap<PathBuf, EntryNode>, Vec<PathBuf>)> {
+    let mut members = Vec::new();
+    visit(path, format, |member, is_dir, size, _| {
+        members.push((member, is_dir, size));
+        Ok(())
+    })?;
+
+    // Chaque membre est rattaché à son dossier, créé au besoin
+    let mut directories: BTreeMap<PathBuf, Vec<PathBuf>> = BTreeMap::from([(path.to_path_buf(), Vec::new())]);
+    let mut files = BTreeMap::new();
+    for (member, is_dir, size) in members {
+        let member = path.join(member);
+        if is_dir {
+            directories.entry(member.clone()).or_default();
+        } else {
+            files.insert(member.clone(), EntryNode::File(Size::new(size)));
+        }
+        for (child, parent) in member.ancestors().zip(member.ancestors().skip(1)).take_while(|(child, _)| *child != path) {
+            directories.entry(parent.to_path_buf()).or_default().push(child.to_path_buf());
+        }
+    }
+    let mut children = Vec::new();
+    let mut entries = files;
+    for (directory, mut directory_children) in directories {
+        directory_children.sort();
+        directory_children.dedup();
+        if directory == path {
+            children = directory_children;
+        } else {
+            entries.insert(directory, EntryNode::Directory(directory_children));
+        }
+    }
+    Ok((entries, children))
+}
+
+/// Computes the signatures of the `members` of the archive at `path`, given by their path relative
+/// to the archive, from their decompressed content, reading the archive once.
+///
+/// # Returns
+///
+/// An `io::Result` containing the signature of each member found, failing if the archive could not
+/// be read or is corrupted.
+pub(crate) fn hash_members(path: &Path, format: ArchiveFormat, members: &HashSet<PathBuf>, hasher: &dyn Hasher) -> io::Result<HashMap<PathBuf, String>> {
+    let mut signatures = HashMap::new();
+    visit(path, format, |member, is_dir, _, content| {
+        if !is_dir && members.contains(&member) {
+            let signature = hasher.hash(content)?;
+            signatures.insert(member, signature);
+        }
+        Ok(())
+    })?;
+    Ok(signatures)
+}
+
+/// Calls `visit` with the relative path, the type, the uncompressed size and the content of each
+/// file and directory of the archive at `path`, in the order of the archive.
+fn visit(path: &Path, format: ArchiveFormat, mut visit: impl FnMut(PathBuf, bool, u64, &mut dyn Read) -> io::Result<()>) -> io::Result<()> {
+    let file = BufReader::new(File::open(path)?);
+    match format {
+        ArchiveFormat::Zip => {
+            let mut archive = zip::ZipArchive::new(file).map_err(zip_error)?;
+            for index in 0..archive.len() {
+                let mut member = archive.by_index(index).map_err(zip_error)?;
+                let Some(name) = member.enclosed_name().and_then(|name| relative(&name)) else { continue };
+                let (is_dir, size) = (member.is_dir(), member.size());
+                if is_dir || member.is_file() {
+                    visit(name, is_dir, size, &mut member)?;
+                }
+            }
+            Ok(())
+        }
+        ArchiveFormat::Tar => visit_tar(tar::Archive::new(file), visit),
+        ArchiveFormat::TarGz => visit_tar(tar::Archive::new(GzDecoder::new(file)), visit),
+    }
+}
+
+fn visit_tar<R: Read>(mut archive: tar::Archive<R>, mut visit: impl FnMut(PathBuf, bool, u64, &mut dyn Read) -> io::Result<()>) -> io::Result<()> {
+    for member in archive.entries()? {
+        let mut member = member?;
+        let Some(name) = relative(&member.path()?) else { continue };
+        let entry_type = member.header().entry_type();
+        if entry_type.is_dir() || entry_type.is_file() {
+            let size = member.size();
+            visit(name, entry_type.is_dir(), size, &mut member)?;
+        }
+    }
+    Ok(())
+}
+
+/// Returns the path of a member without its `.` components, or `None` if it is empty or leaves
+/// the archive.
+fn relative(path: &Path) -> Option<PathBuf> {
+    let mut relative = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(name) => relative.push(name),
+            Component::CurDir => {}
+            _ => return None,
+        }
+    }
+    (!relative.as_os_str().is_empty()).then_some(relative)
+}
+
+fn zip_error(err: zip::result::ZipError) -> io::Error {
+    match err {
+        zip::result::ZipError::Io(err) => err,
+        err => io::Error::new(io::ErrorKind::InvalidData, err),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hasher::Blake3Hasher;
+    use crate::test_utils::create_archives;
+
+    #[test]
+    fn format_test() {
+        assert_eq!(ArchiveFormat::of(Path::new("a/photos.ZIP")), Some(ArchiveFormat::Zip));
+        assert_eq!(ArchiveFormat::of(Path::new("sources.tar.gz")), Some(ArchiveFormat::TarGz));
+        assert_eq!(ArchiveFormat::of(Path::new("sources.tgz")), Some(ArchiveFormat::TarGz));
+        assert_eq!(ArchiveFormat::of(Path::new("sources.tar")), Some(ArchiveFormat::Tar));
+        assert_eq!(ArchiveFormat::of(Path::new("notes.gz")), None);
+    }
+
+    #[test]
+    fn list_test() {
+        let dir = tempfile::tempdir().unwrap();
+        create_archives(dir.path());
+        for name in ["a.zip", "a.tar", "a.tar.gz"] {
+            let archive = dir.path().join(name);
+            let (entries, children) = list(&archive, ArchiveFormat::of(&archive).unwrap()).unwrap();
+            assert_eq!(children, [archive.join("docs"), archive.join("notes.txt")], "{}", name);
+            assert!(matches!(entries.get(&archive.join("docs")), Some(EntryNode::Directory(children)) if children == &[archive.join("docs/copie.txt"), archive.join("docs/vide")]));
+            assert!(matches!(entries.get(&archive.join("docs/copie.txt")), Some(EntryNode::File(size)) if size.value() == 11));
+            assert_eq!(entries.len(), 4, "{}", name);
+        }
+    }
+
+    #[test]
+    fn hash_members_test() {
+        let dir = tempfile::tempdir().unwrap();
+        create_archives(dir.path());
+        let members = HashSet::from([PathBuf::from("notes.txt"), PathBuf::from("docs/copie.txt")]);
+        let signatures = hash_members(&dir.path().join("a.tar.gz"), ArchiveFormat::TarGz, &members, &Blake3Hasher).unwrap();
+        let expected = Blake3Hasher.hash(&mut &b"hello world"[..]).unwrap();
+        assert_eq!(signatures.get(Path::new("docs/copie.txt")), Some(&expected));
+        assert_eq!(signatures.len(), 2);
+
+        std::fs::write(dir.path().join("corrompu.zip"), b"not a zip").unwrap();
+        assert_eq!(list(&dir.path().join("corrompu.zip"), ArchiveFormat::Zip).unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,544 @@
+use crate::file_tree::{FileTree, ScanOptions, ScanProgress};
+use crate::error::{Error, Result};
+use crate::hasher::Blake3Hasher;
+use globset::{Glob, GlobSetBuilder};
+use rayon::ThreadPoolBuilder;
+use serde::{Deserialize, Serialize};
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+
+/// Configures and builds a `FileTree`.
+///
+/// # Example
+///
+/// ```
+/// use du::FileTreeBuilder;
+///
+/// # let dir = tempfile::tempdir()?;
+/// # std::fs::write(dir.path().join("notes.txt"), b"hello")?;
+/// # std::fs::write(dir.path().join("main.o"), b"binary")?;
+/// let file_tree = FileTreeBuilder::new(dir.path())
+///     .exclude("*.o")
+///     .build()?;
+///
+/// assert_eq!(file_tree.get_size(dir.path()).unwrap().value(), 5);
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FileTreeBuilder {
+    root: PathBuf,
+    follow_symlinks: bool,
+    gitignore: bool,
+    hidden: bool,
+    count_links: bool,
+    strict: bool,
+    one_file_system: bool,
+    max_depth: Option<usize>,
+    excludes: Vec<String>,
+    hash_files: bool,
+    inspect_archives: bool,
+    threads: Option<NonZeroUsize>,
+    #[serde(skip)]
+    interrupt: Option<Arc<AtomicBool>>,
+}
+
+impl FileTreeBuilder {
+
+    /// Creates a new builder for a file tree rooted at `root`.
+    ///
+    /// By default symbolic links are not followed, `.gitignore` files are not read, the hidden entries
+    /// are skipped, nothing else is excluded and the unreadable entries are collected in `FileTree::errors`.
+    /// The whole tree is listed, the archives are not opened, no file is hashed, and the scan runs on the current rayon pool.
+    pub fn new(root: impl AsRef<Path>) -> Self {
+        FileTreeBuilder {
+            root: root.as_ref().to_path_buf(),
+            follow_symlinks: false,
+            gitignore: false,
+            hidden: false,
+            count_links: false,
+            strict: false,
+            one_file_system: false,
+            max_depth: None,
+            excludes: Vec::new(),
+            hash_files: false,
+            inspect_archives: false,
+            threads: None,
+            interrupt: None,
+        }
+    }
+
+    /// Sets whether symbolic links found below the root are followed or recorded as `EntryNode::Symlink`.
+    ///
+    /// Broken links and links to a directory containing them are never followed.
+    pub fn follow_symlinks(mut self, follow_symlinks: bool) -> Self {
+        self.follow_symlinks = follow_symlinks;
+        self
+    }
+
+    /// Sets whether the entries ignored by the `.gitignore` files found below the root, and the
+    /// `.git` directories, are skipped.
+    pub fn gitignore(mut self, gitignore: bool) -> Self {
+        self.gitignore = gitignore;
+        self
+    }
+
+    /// Sets whether the hidden entries are scanned, like `tree -a`: the entries whose name starts
+    /// with a dot and, on Windows, the entries with the hidden or system attribute.
+    ///
+    /// Skipped entries are neither scanned, hashed, nor counted in directory sizes. The root is always scanned.
+    pub fn hidden(mut self, hidden: bool) -> Self {
+        self.hidden = hidden;
+        self
+    }
+
+    /// Sets whether a file having several hard links is counted in the directory sizes once per path,
+    /// instead of once through its first path.
+    ///
+    /// The hard links are only detected on Unix.
+    pub fn count_links(mut self, count_links: bool) -> Self {
+        self.count_links = count_links;
+        self
+    }
+
+    /// Sets whether the scan fails on the first entry that can't be read, instead of collecting
+    /// the errors in `FileTree::errors` and going on.
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Sets whether the scan stays on the file system of the root, like `du -x`: the directories on
+    /// another device, such as mount points, are skipped, and links to them are not followed.
+    ///
+    /// The devices are only compared on Unix.
+    pub fn one_file_system(mut self, one_file_system: bool) -> Self {
+        self.one_file_system = one_file_system;
+        self
+    }
+
+    /// Sets the depth below which the entries are not listed, the root being at depth 0: the directories
+    /// at this depth are listed without children, and their content is only counted in their sizes.
+    ///
+    /// The directories at the maximum depth are scanned again entirely by `FileTree::refresh`.
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = Some(max_depth);
+        self
+    }
+
+    /// Sets whether the signatures of the files are computed by `build`, with BLAKE3, so that
+    /// `FileTree::find_duplicates` can be called right away. See `FileTree::compute_signatures`.
+    pub fn hash_files(mut self, hash_files: bool) -> Self {
+        self.hash_files = hash_files;
+        self
+    }
+
+    /// Sets whether the signatures of the files are computed by `build`: the former name of `hash_files`.
+    pub fn compute_signatures(self, compute_signatures: bool) -> Self {
+        self.hash_files(compute_signatures)
+    }
+
+    /// Sets whether the `.zip`, `.tar`, `.tar.gz` and `.tgz` files are listed as directories of their
+    /// content, the members being counted with their uncompressed size and compared by their decompressed
+    /// content for duplicates. The disk usage of an archive stays that of its file, see `FileTree::get_archive_size`.
+    ///
+    /// An archive that can't be read is kept as a file, with an error in `FileTree::errors`.
+    pub fn inspect_archives(mut self, inspect_archives: bool) -> Self {
+        self.inspect_archives = inspect_archives;
+        self
+    }
+
+    /// Sets the number of threads scanning and hashing the files, instead of the threads of the
+    /// current rayon pool, e.g. 1 on a spinning disk.
+    pub fn threads(mut self, threads: NonZeroUsize) -> Self {
+        self.threads = Some(threads);
+        self
+    }
+
+    /// Sets a flag stopping the scan once it is set, e.g. by a Ctrl-C handler: the entries not
+    /// explored yet are left out, the directories being explored keep the children found so far,
+    /// and the file tree is marked as interrupted, see `FileTree::is_interrupted`.
+    pub fn interrupt_on(mut self, interrupt: Arc<AtomicBool>) -> Self {
+        self.interrupt = Some(interrupt);
+        self
+    }
+
+    /// Adds a glob pattern excluding the entries whose relative path or name matches it.
+    ///
+    /// Excluded entries are neither scanned, hashed, nor counted in directory sizes.
+    pub fn exclude(mut self, pattern: &str) -> Self {
+        self.excludes.push(pattern.to_string());
+        self
+    }
+
+    /// Scans the file system and builds the configured `FileTree`.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the `FileTree`, or an `InvalidPattern` error if an exclude pattern is invalid,
+    /// failing if the root can't be read, on the first unreadable entry if the scan is strict, or if a
+    /// file can't be hashed.
+    pub fn build(self) -> Result<FileTree> {
+        self.build_with_progress(|_| {})
+    }
+
+    /// Scans the file system and builds the configured `FileTree`, reporting the progress to `callback`.
+    ///
+    /// See `FileTree::new_with_progress`. When the files are hashed, the progress of the hashing is
+    /// then reported the same way, see `FileTree::compute_signatures_with_progress`.
+    pub fn build_with_progress(self, mut callback: impl FnMut(&ScanProgress) + Send) -> Result<FileTree> {
+        let mut build = || {
+            let mut file_tree = FileTree::scan(&self, &mut callback)?;
+            if self.hash_files {
+                file_tree.compute_signatures_with_progress(&Blake3Hasher, &mut callback)?;
+            }
+            Ok(file_tree)
+        };
+        match self.threads {
+            Some(threads) => ThreadPoolBuilder::new().num_threads(threads.get()).build()?.install(build),
+            None => build(),
+        }
+    }
+
+    /// Returns the root of the scan.
+    pub(crate) fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Returns the options of the scan, compiling the exclude patterns.
+    pub(crate) fn scan_options(&self) -> Result<ScanOptions> {
+        let mut excludes = GlobSetBuilder::new();
+        for pattern in &self.excludes {
+            excludes.add(Glob::new(pattern).map_err(|source| Error::InvalidPattern { pattern: pattern.clone(), source })?);
+        }
+        let excludes = excludes.build().map_err(|source| Error::InvalidPattern { pattern: self.excludes.join(","), source })?;
+
+        Ok(ScanOptions {
+            follow_symlinks: self.follow_symlinks,
+            gitignore: self.gitignore,
+            hidden: self.hidden,
+            count_links: self.count_links,
+            strict: self.strict,
+            one_file_system: self.one_file_system,
+            max_depth: self.max_depth,
+            inspect_archives: self.inspect_archives,
+            excludes,
+            interrupt: self.interrupt.clone(),
+        })
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_tree::{EntryNode, FileKind};
+    use crate::size::Size;
+    use crate::print_tree::PrintOptions;
+    use crate::test_utils::{create_archives, create_file, create_fixture};
+
+    #[test]
+    fn exclude_test() {
+        let fixture = create_fixture();
+        let root = fixture.path().join("Test");
+        let file_tree = FileTreeBuilder::new(&root).exclude("SousDossier*").build().unwrap();
+
+        assert!(file_tree.get_map_option(&root.join("Dossier2/SousDossier1")).is_none());
+        assert_eq!(file_tree.get_size(&root.join("Dossier2")), Some(Size::new(500000)));
+    }
+
+    #[test]
+    fn exclude_relative_path_test() {
+        let fixture = create_fixture();
+        let root = fixture.path().join("Test");
+        let file_tree = FileTreeBuilder::new(&root).exclude("Dossier2/**").build().unwrap();
+
+        assert_eq!(file_tree.get_size(&root.join("Dossier2")), Some(Size::new(0)));
+        assert_eq!(file_tree.get_size(&root), Some(Size::new(1024)));
+    }
+
+    #[test]
+    fn gitignore_test() {
+        let fixture = create_fixture();
+        let root = fixture.path().join("Test");
+        create_file(&root.join("target/debug/du"), 4096);
+        create_file(&root.join("main.o"), 10);
+        create_file(&root.join("garder.o"), 10);
+        create_file(&root.join(".git/HEAD"), 10);
+        std::fs::write(root.join(".gitignore"), "target/\n*.o\n!garder.o\n").unwrap();
+        std::fs::write(root.join("Dossier2/.gitignore"), "SousDossier1\n").unwrap();
+
+        let file_tree = FileTreeBuilder::new(&root).gitignore(true).build().unwrap();
+        assert!(file_tree.get_map_option(&root.join("target")).is_none());
+        assert!(file_tree.get_map_option(&root.join("main.o")).is_none());
+        assert!(file_tree.get_map_option(&root.join("garder.o")).is_some());
+        assert!(file_tree.get_map_option(&root.join(".git")).is_none());
+        assert!(file_tree.get_map_option(&root.join("Dossier2/SousDossier1")).is_none());
+        assert!(file_tree.get_map_option(&root.join("Dossier2/SousDossier2")).is_some());
+
+        let file_tree = FileTreeBuilder::new(&root).build().unwrap();
+        assert!(file_tree.get_map_option(&root.join("target")).is_some());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn unreadable_entries_test() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let fixture = create_fixture();
+        let root = fixture.path().join("Test");
+        let locked = root.join("Dossier1/verrouille");
+        create_file(&locked.join("secret"), 100);
+        std::fs::set_permissions(&locked, std::fs::Permissions::from_mode(0o000)).unwrap();
+        // root lit les dossiers sans droits : il n'y a alors pas d'erreur à tester
+        if std::fs::read_dir(&locked).is_ok() {
+            std::fs::set_permissions(&locked, std::fs::Permissions::from_mode(0o755)).unwrap();
+            return;
+        }
+
+        let file_tree = FileTreeBuilder::new(&root).strict(false).build();
+        let strict = FileTreeBuilder::new(&root).strict(true).build();
+        std::fs::set_permissions(&locked, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let file_tree = file_tree.unwrap();
+        assert_eq!(file_tree.errors().len(), 1);
+        assert_eq!(file_tree.errors()[0].0, locked);
+        assert_eq!(file_tree.errors()[0].1.kind(), std::io::ErrorKind::PermissionDenied);
+        assert!(file_tree.get_map_option(&locked.join("secret")).is_none());
+        assert_eq!(file_tree.get_size(&root), Some(Size::new(521280)));
+
+        assert!(strict.is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn special_files_test() {
+        let fixture = create_fixture();
+        let root = fixture.path().join("Test");
+        let socket = root.join("Dossier1/socket");
+        let _listener = std::os::unix::net::UnixListener::bind(&socket).unwrap();
+
+        let file_tree = FileTreeBuilder::new(&root).strict(true).build().unwrap();
+        assert!(file_tree.errors().is_empty());
+        assert!(matches!(file_tree.get_map_option(&socket), Some(EntryNode::Other(FileKind::Socket))));
+        assert_eq!(file_tree.get_children(&root.join("Dossier1")).unwrap(), [root.join("Dossier1/Fichier1"), socket.clone()]);
+        assert_eq!(file_tree.get_size(&socket), Some(Size::new(0)));
+        assert_eq!(file_tree.get_size(&root), Some(Size::new(521280)));
+
+        let mut streamed = Vec::new();
+        FileTreeBuilder::new(&root).stream(|entry| {
+            streamed.push(entry.path.to_path_buf());
+            Ok(())
+        }).unwrap();
+        assert!(streamed.contains(&socket));
+    }
+
+    #[test]
+    fn hidden_test() {
+        let fixture = create_fixture();
+        let root = fixture.path().join("Test");
+        create_file(&root.join(".cache/data"), 100);
+        create_file(&root.join("Dossier1/.notes"), 10);
+
+        let file_tree = FileTreeBuilder::new(&root).build().unwrap();
+        assert!(file_tree.get_map_option(&root.join(".cache")).is_none());
+        assert!(file_tree.get_map_option(&root.join("Dossier1/.notes")).is_none());
+        assert_eq!(file_tree.get_size(&root), Some(Size::new(521280)));
+
+        let file_tree = FileTreeBuilder::new(&root).hidden(true).build().unwrap();
+        assert_eq!(file_tree.get_size(&root.join(".cache")), Some(Size::new(100)));
+        assert_eq!(file_tree.get_size(&root), Some(Size::new(521390)));
+
+        // A hidden root is still scanned
+        let file_tree = FileTreeBuilder::new(root.join(".cache")).build().unwrap();
+        assert_eq!(file_tree.get_size(&root.join(".cache")), Some(Size::new(100)));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn hard_links_test() {
+        let fixture = create_fixture();
+        let root = fixture.path().join("Test");
+        std::fs::hard_link(root.join("Dossier2/Fichier3"), root.join("Dossier1/lien")).unwrap();
+
+        let file_tree = FileTreeBuilder::new(&root).build().unwrap();
+        assert_eq!(file_tree.get_size(&root), Some(Size::new(521280)));
+        assert_eq!(file_tree.get_size(&root.join("Dossier1")), Some(Size::new(1024 + 500000)));
+        assert_eq!(file_tree.get_size(&root.join("Dossier2")), Some(Size::new(20256)));
+        assert_eq!(file_tree.get_size(&root.join("Dossier2/Fichier3")), Some(Size::new(500000)));
+
+        let file_tree = FileTreeBuilder::new(&root).count_links(true).build().unwrap();
+        assert_eq!(file_tree.get_size(&root), Some(Size::new(1021280)));
+    }
+
+    #[test]
+    fn invalid_exclude_test() {
+        let fixture = create_fixture();
+        let err = FileTreeBuilder::new(fixture.path()).exclude("[").build().unwrap_err();
+        assert!(matches!(err, Error::InvalidPattern { pattern, .. } if pattern == "["));
+    }
+
+    #[test]
+    fn build_does_not_hash_test() {
+        let fixture = create_fixture();
+        let root = fixture.path().join("Test");
+        std::fs::copy(root.join("Dossier1/Fichier1"), root.join("Copie")).unwrap();
+        let mut file_tree = FileTreeBuilder::new(&root).build().unwrap();
+        assert!(file_tree.find_duplicates().is_empty());
+
+        let mut hashed = 0;
+        file_tree.compute_signatures_with_progress(&Blake3Hasher, |progress| hashed = progress.bytes_hashed).unwrap();
+        assert_eq!(file_tree.find_duplicates().len(), 1);
+        // Only the two files of 1024 bytes share their size
+        assert_eq!(hashed, 2 * 1024);
+    }
+
+    #[test]
+    fn with_signatures_test() {
+        let fixture = create_fixture();
+        let root = fixture.path().join("Test");
+        std::fs::copy(root.join("Dossier1/Fichier1"), root.join("Copie")).unwrap();
+        let file_tree = FileTreeBuilder::new(&root).compute_signatures(true).build().unwrap();
+
+        assert_eq!(file_tree.find_duplicates().len(), 1);
+        assert_eq!(file_tree.get_size(&root), Some(Size::new(522304)));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinks_test() {
+        let fixture = create_fixture();
+        let root = fixture.path().join("Test");
+        std::os::unix::fs::symlink(root.join("Dossier1/Fichier1"), root.join("lien")).unwrap();
+
+        let skipped = FileTreeBuilder::new(&root).build().unwrap();
+        assert!(matches!(skipped.get_map_option(&root.join("lien")), Some(EntryNode::Symlink(target)) if *target == root.join("Dossier1/Fichier1")));
+        assert_eq!(skipped.get_size(&root), Some(Size::new(521280)));
+
+        let followed = FileTreeBuilder::new(&root).follow_symlinks(true).build().unwrap();
+        assert_eq!(followed.get_size(&root.join("lien")), Some(Size::new(1024)));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlink_cycle_test() {
+        let fixture = create_fixture();
+        let root = fixture.path().join("Test");
+        std::os::unix::fs::symlink("..", root.join("Dossier2/SousDossier1/boucle")).unwrap();
+        std::os::unix::fs::symlink(&root, root.join("Dossier1/racine")).unwrap();
+        std::os::unix::fs::symlink(root.join("absent"), root.join("casse")).unwrap();
+
+        let file_tree = FileTreeBuilder::new(&root).follow_symlinks(true).build().unwrap();
+        assert!(matches!(file_tree.get_map_option(&root.join("Dossier2/SousDossier1/boucle")), Some(EntryNode::Symlink(_))));
+        assert!(matches!(file_tree.get_map_option(&root.join("Dossier1/racine")), Some(EntryNode::Symlink(_))));
+        assert!(matches!(file_tree.get_map_option(&root.join("casse")), Some(EntryNode::Symlink(_))));
+        assert_eq!(file_tree.get_size(&root), Some(Size::new(521280)));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn one_file_system_test() {
+        let fixture = create_fixture();
+        let root = fixture.path().join("Test");
+        let file_tree = FileTreeBuilder::new(&root).one_file_system(true).build().unwrap();
+        assert_eq!(file_tree.get_size(&root), Some(Size::new(521280)));
+
+        // Les pseudo-systèmes de fichiers montés sous /dev ne sont pas parcourus
+        let dev = Path::new("/dev");
+        let Ok(mounts) = std::fs::read_to_string("/proc/self/mounts") else { return };
+        let file_tree = FileTreeBuilder::new(dev).one_file_system(true).build().unwrap();
+        for mount in mounts.lines().filter_map(|line| line.split_whitespace().nth(1)).map(Path::new) {
+            if mount.parent() == Some(dev) {
+                assert!(file_tree.get_map_option(mount).is_none());
+            }
+        }
+    }
+
+    #[test]
+    fn max_depth_test() {
+        let fixture = create_fixture();
+        let root = fixture.path().join("Test");
+        let mut file_tree = FileTreeBuilder::new(&root).max_depth(1).build().unwrap();
+
+        assert!(file_tree.get_children(&root.join("Dossier2")).unwrap().is_empty());
+        assert!(file_tree.get_map_option(&root.join("Dossier2/Fichier3")).is_none());
+        assert_eq!(file_tree.get_size(&root.join("Dossier2")), Some(Size::new(520256)));
+        assert_eq!(file_tree.get_size(&root), Some(Size::new(521280)));
+        let full = FileTree::new(&root).unwrap();
+        assert_eq!(file_tree.get_disk_usage(&root), full.get_disk_usage(&root));
+
+        // Le contenu replié est exploré à nouveau
+        create_file(&root.join("Dossier2/SousDossier1/Fichier4"), 1256);
+        file_tree.refresh().unwrap();
+        assert_eq!(file_tree.get_size(&root.join("Dossier2")), Some(Size::new(521256)));
+
+        let file_tree = FileTreeBuilder::new(&root).max_depth(0).build().unwrap();
+        assert_eq!(file_tree.iter().count(), 1);
+        assert_eq!(file_tree.get_size(&root), Some(Size::new(522280)));
+    }
+
+    #[test]
+    fn hash_files_and_threads_test() {
+        let fixture = create_fixture();
+        let root = fixture.path().join("Test");
+        std::fs::copy(root.join("Dossier1/Fichier1"), root.join("Copie")).unwrap();
+
+        let file_tree = FileTreeBuilder::new(&root).hash_files(true).threads(NonZeroUsize::new(2).unwrap()).build().unwrap();
+        assert_eq!(file_tree.find_duplicates().len(), 1);
+        assert_eq!(file_tree.get_size(&root), Some(Size::new(522304)));
+    }
+
+    #[test]
+    fn interrupt_test() {
+        use std::sync::atomic::Ordering;
+        let fixture = create_fixture();
+        let root = fixture.path().join("Test");
+        let interrupt = Arc::new(AtomicBool::new(false));
+
+        // Un seul fil : le balayage s'arrête juste après la troisième entrée
+        let flag = Arc::clone(&interrupt);
+        let builder = FileTreeBuilder::new(&root).threads(NonZeroUsize::new(1).unwrap()).interrupt_on(Arc::clone(&interrupt));
+        let file_tree = builder.build_with_progress(move |progress| flag.store(progress.entries_scanned >= 3, Ordering::Relaxed)).unwrap();
+        assert!(file_tree.is_interrupted());
+        assert!(file_tree.files().count() <= 1);
+        assert!(file_tree.iter().count() < 9);
+        assert!(file_tree.get_size(&root).unwrap() < Size::new(521280));
+
+        interrupt.store(false, Ordering::Relaxed);
+        assert!(!FileTreeBuilder::new(&root).interrupt_on(interrupt).build().unwrap().is_interrupted());
+    }
+
+    #[test]
+    fn inspect_archives_test() {
+        let fixture = create_fixture();
+        let root = fixture.path().join("Test");
+        create_archives(&root);
+        std::fs::write(root.join("Dossier1/notes.txt"), b"hello world").unwrap();
+        std::fs::write(root.join("corrompu.zip"), b"not a zip").unwrap();
+
+        let mut file_tree = FileTreeBuilder::new(&root).inspect_archives(true).build().unwrap();
+        let archive = root.join("a.tar.gz");
+        assert!(matches!(file_tree.get_map_option(&archive), Some(EntryNode::Directory(_))));
+        assert_eq!(file_tree.get_size(&archive), Some(Size::new(22)));
+        assert_eq!(file_tree.get_archive_size(&archive), Some(Size::new(std::fs::metadata(&archive).unwrap().len())));
+        assert_eq!(file_tree.get_archive_size(&root.join("corrompu.zip")), None);
+        assert_eq!(file_tree.errors().len(), 1);
+
+        file_tree.compute_signatures().unwrap();
+        let duplicates = file_tree.find_duplicates();
+        assert_eq!(duplicates.len(), 1);
+        let paths = duplicates.values().next().unwrap();
+        assert_eq!(paths.len(), 7);
+        assert!(paths.contains(&root.join("Dossier1/notes.txt")) && paths.contains(&root.join("a.zip/docs/copie.txt")));
+
+        let mut out = Vec::new();
+        file_tree.write_tree(&mut out, &PrintOptions::default()).unwrap();
+        assert!(String::from_utf8(out).unwrap().contains("a.tar [archive] ("));
+
+        file_tree.refresh().unwrap();
+        assert!(file_tree.get_archive_size(&archive).is_some());
+        assert_eq!(file_tree.get_signature(&archive.join("notes.txt")), file_tree.get_signature(&root.join("Dossier1/notes.txt")));
+        assert!(FileTreeBuilder::new(&root).build().unwrap().get_archive_size(&archive).is_none());
+    }
+}
@@ -0,0 +1,179 @@
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use crate::error::{Error, Result};
+use crate::file_tree::FileTree;
+
+/// Magic bytes identifying a cache file.
+const MAGIC: &[u8; 8] = b"DUCACHE\0";
+
+/// Version of the cache format, to bump whenever the serialized `FileTree` changes.
+const FORMAT_VERSION: u32 = 12;
+
+
+impl FileTree {
+
+    /// Saves the scan results (root, entries and signatures) to a cache file.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the cache file to write.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use du::FileTree;
+    ///
+    /// # let dir = tempfile::tempdir()?;
+    /// # std::fs::write(dir.path().join("notes.txt"), b"hello")?;
+    /// let cache = dir.path().join("tree.bin");
+    /// let file_tree = FileTree::new(dir.path())?;
+    /// file_tree.save(&cache)?;
+    ///
+    /// let loaded = FileTree::load(&cache)?;
+    /// assert_eq!(loaded.get_size(dir.path()), file_tree.get_size(dir.path()));
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let write = || -> io::Result<()> {
+            let mut writer = BufWriter::new(File::create(path)?);
+            writer.write_all(MAGIC)?;
+            writer.write_all(&FORMAT_VERSION.to_le_bytes())?;
+            bincode::serialize_into(&mut writer, self).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+            writer.flush()
+        };
+        write().map_err(|err| Error::io(path, err))
+    }
+
+    /// Loads scan results previously written by `save`, without touching the scanned file system.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the cache file to read.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the `FileTree`, or an `InvalidCache` error if the file is not
+    /// a cache file, was written by another version of the format, or is corrupted.
+    pub fn load(path: &Path) -> Result<Self> {
+        let invalid = |reason: String| Error::InvalidCache { path: path.to_path_buf(), reason };
+        let mut reader = BufReader::new(File::open(path).map_err(|err| Error::io(path, err))?);
+
+        let mut magic = [0; 8];
+        let mut version = [0; 4];
+        if reader.read_exact(&mut magic).is_err() || &magic != MAGIC {
+            return Err(invalid("not a cache file".to_string()));
+        }
+        reader.read_exact(&mut version).map_err(|err| Error::io(path, err))?;
+        let version = u32::from_le_bytes(version);
+        if version != FORMAT_VERSION {
+            return Err(invalid(format!("unsupported cache format version {} (expected {})", version, FORMAT_VERSION)));
+        }
+
+        let mut file_tree: FileTree = bincode::deserialize_from(reader).map_err(|err| invalid(format!("corrupted cache file: {}", err)))?;
+        file_tree.index_sizes();
+        Ok(file_tree)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{create_file, create_fixture};
+    use std::fs;
+
+    #[test]
+    fn round_trip_test() {
+        let fixture = create_fixture();
+        let root = fixture.path().join("Test");
+        fs::copy(root.join("Dossier1/Fichier1"), root.join("Dossier2/Copie")).unwrap();
+        let cache = fixture.path().join("tree.bin");
+
+        let mut file_tree = FileTree::new(&root).unwrap();
+        file_tree.compute_signatures().unwrap();
+        file_tree.save(&cache).unwrap();
+        let loaded = FileTree::load(&cache).unwrap();
+
+        assert_eq!(loaded.get_root(), file_tree.get_root());
+        for path in file_tree.iter() {
+            assert_eq!(loaded.get_size(path), file_tree.get_size(path));
+            assert_eq!(loaded.get_disk_usage(path), file_tree.get_disk_usage(path));
+            assert_eq!(loaded.get_children(path), file_tree.get_children(path));
+        }
+        assert_eq!(loaded.iter().count(), file_tree.iter().count());
+
+        let mut duplicates: Vec<_> = file_tree.find_duplicates().into_iter().collect();
+        let mut loaded_duplicates: Vec<_> = loaded.find_duplicates().into_iter().collect();
+        for (_, paths) in duplicates.iter_mut().chain(loaded_duplicates.iter_mut()) {
+            paths.sort();
+        }
+        duplicates.sort();
+        loaded_duplicates.sort();
+        assert_eq!(duplicates.len(), 1);
+        assert_eq!(loaded_duplicates, duplicates);
+    }
+
+    #[test]
+    fn load_from_cache_does_not_rescan_test() {
+        let fixture = create_fixture();
+        let root = fixture.path().join("Test");
+        let cache = fixture.path().join("tree.bin");
+        FileTree::new(&root).unwrap().save(&cache).unwrap();
+
+        create_file(&root.join("Dossier1/Nouveau"), 4096);
+        let loaded = FileTree::load(&cache).unwrap();
+        assert!(loaded.get_map_option(&root.join("Dossier1/Nouveau")).is_none());
+    }
+
+    #[test]
+    fn loaded_signatures_are_not_recomputed_test() {
+        let fixture = create_fixture();
+        let root = fixture.path().join("Test");
+        fs::copy(root.join("Dossier1/Fichier1"), root.join("Copie")).unwrap();
+        let cache = fixture.path().join("tree.bin");
+        let mut file_tree = FileTree::new(&root).unwrap();
+        file_tree.compute_signatures().unwrap();
+        file_tree.save(&cache).unwrap();
+
+        let mut loaded = FileTree::load(&cache).unwrap();
+        let mut hashed = 0;
+        loaded.compute_signatures_with_progress(&crate::hasher::Blake3Hasher, |progress| hashed = progress.bytes_hashed).unwrap();
+        assert_eq!(hashed, 0);
+        assert_eq!(loaded.find_duplicates().len(), 1);
+    }
+
+    #[test]
+    fn load_not_a_cache_test() {
+        let fixture = create_fixture();
+        let err = FileTree::load(&fixture.path().join("Test/Dossier1/Fichier1")).unwrap_err();
+        assert!(matches!(&err, Error::InvalidCache { path, .. } if path.ends_with("Dossier1/Fichier1")));
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn load_wrong_version_test() {
+        let fixture = create_fixture();
+        let cache = fixture.path().join("tree.bin");
+        let mut content = MAGIC.to_vec();
+        content.extend_from_slice(&(FORMAT_VERSION + 1).to_le_bytes());
+        fs::write(&cache, content).unwrap();
+
+        let err = FileTree::load(&cache).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(err.to_string().contains("version"));
+    }
+
+    #[test]
+    fn load_corrupted_test() {
+        let fixture = create_fixture();
+        let root = fixture.path().join("Test");
+        let cache = fixture.path().join("tree.bin");
+        FileTree::new(&root).unwrap().save(&cache).unwrap();
+
+        let content = fs::read(&cache).unwrap();
+        fs::write(&cache, &content[..content.len() / 2]).unwrap();
+        let err = FileTree::load(&cache).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
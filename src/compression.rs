@@ -0,0 +1,158 @@
+use rayon::prelude::*;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use crate::{error::{Error, Result}, file_tree::FileTree, size::Size};
+
+/// The number of bytes sampled at the beginning, the middle and the end of a file.
+const SAMPLE_BLOCK: u64 = 64 * 1024;
+
+/// The zstd level of the quick compression of the samples.
+const SAMPLE_LEVEL: i32 = 1;
+
+/// The estimated space saved by compressing the files of a directory.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct CompressionEstimate {
+    /// The directory.
+    pub path: PathBuf,
+    /// The total apparent size of the files below the directory, recursively.
+    pub size: Size,
+    /// The number of bytes of these files read as samples.
+    pub sampled: Size,
+    /// The estimated total size of these files once compressed.
+    pub compressed: Size,
+}
+
+impl CompressionEstimate {
+
+    /// Returns the estimated space saved by compressing the files.
+    pub fn savings(&self) -> Size {
+        Size::new(self.size.value().saturating_sub(self.compressed.value()))
+    }
+
+    /// Returns the estimated compressed size as a share of the size, between 0 and 1, or 1 if there
+    /// is nothing to compress.
+    pub fn ratio(&self) -> f64 {
+        if self.size.value() == 0 {
+            1.0
+        } else {
+            self.compressed.value() as f64 / self.size.value() as f64
+        }
+    }
+}
+
+
+impl FileTree {
+
+    /// Estimates the space saved by compressing the files of each directory, the largest savings first,
+    /// then by path.
+    ///
+    /// Only samples are read: the first, middle and last 64 KiB of each file, or the whole file if it is
+    /// smaller, are compressed with zstd at level 1, the compressed size of a file being estimated from
+    /// the ratio of its samples. A file whose samples don't shrink is counted as incompressible. The
+    /// files listed inside archives, see `FileTreeBuilder::inspect_archives`, are left out.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing an estimate per directory, failing if a file could not be read.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use du::FileTree;
+    ///
+    /// # let dir = tempfile::tempdir()?;
+    /// std::fs::write(dir.path().join("zeros.bin"), [0; 100_000])?;
+    ///
+    /// let estimates = FileTree::new(dir.path())?.compression_estimates()?;
+    /// assert_eq!(estimates[0].size.value(), 100_000);
+    /// assert!(estimates[0].ratio() < 0.01);
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn compression_estimates(&self) -> Result<Vec<CompressionEstimate>> {
+        let files: Vec<_> = self.files().filter(|path| self.archive_of(path).is_none()).collect();
+        let samples = files
+            .par_iter()
+            .map(|path| {
+                let size = self.get_size(path).unwrap_or(Size::new(0));
+                let (sampled, compressed) = estimate_file(path, size.value()).map_err(|err| Error::io(path, err))?;
+                Ok((path.as_path(), size, sampled, compressed))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        // Chaque fichier est compté dans tous ses dossiers parents
+        let mut totals: HashMap<&Path, (Size, Size, Size)> = self.directories().map(|directory| (directory.as_path(), Default::default())).collect();
+        for (path, size, sampled, compressed) in samples {
+            for directory in path.ancestors().skip(1).take_while(|directory| directory.starts_with(self.get_root())) {
+                if let Some(total) = totals.get_mut(directory) {
+                    *total = (total.0 + size, total.1 + Size::new(sampled), total.2 + Size::new(compressed));
+                }
+            }
+        }
+        let mut estimates: Vec<_> = totals
+            .into_iter()
+            .map(|(path, (size, sampled, compressed))| CompressionEstimate { path: path.to_path_buf(), size, sampled, compressed })
+            .collect();
+        estimates.sort_by(|a, b| b.savings().cmp(&a.savings()).then_with(|| a.path.cmp(&b.path)));
+        Ok(estimates)
+    }
+}
+
+/// Returns the number of bytes sampled from the file at `path`, of `size` bytes, and its estimated
+/// size once compressed.
+fn estimate_file(path: &Path, size: u64) -> io::Result<(u64, u64)> {
+    let mut file = File::open(path)?;
+    let mut sample = Vec::new();
+    if size <= 3 * SAMPLE_BLOCK {
+        file.read_to_end(&mut sample)?;
+    } else {
+        for offset in [0, size / 2 - SAMPLE_BLOCK / 2, size - SAMPLE_BLOCK] {
+            file.seek(SeekFrom::Start(offset))?;
+            file.by_ref().take(SAMPLE_BLOCK).read_to_end(&mut sample)?;
+        }
+    }
+    if sample.is_empty() {
+        return Ok((0, size));
+    }
+    let compressed = zstd::bulk::compress(&sample, SAMPLE_LEVEL)?.len().min(sample.len());
+    let estimated = (size as u128 * compressed as u128 / sample.len() as u128) as u64;
+    Ok((sample.len() as u64, estimated))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::create_fixture;
+    use std::fs;
+
+    #[test]
+    fn compression_estimates_test() {
+        let fixture = create_fixture();
+        let root = fixture.path().join("Test");
+        // Des octets pseudo-aléatoires, que zstd ne réduit pas
+        let mut state = 0x2545f4914f6cdd1du64;
+        let noise: Vec<u8> = (0..400_000).map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state as u8
+        }).collect();
+        fs::create_dir(root.join("Bruit")).unwrap();
+        fs::write(root.join("Bruit/aleatoire.bin"), &noise).unwrap();
+
+        let estimates = FileTree::new(&root).unwrap().compression_estimates().unwrap();
+        assert_eq!(estimates.len(), 6);
+        assert_eq!(estimates[0].path, root);
+        assert_eq!(estimates[0].size.value(), 921280);
+        assert_eq!(estimates[1].path, root.join("Dossier2"));
+        assert_eq!(estimates[1].sampled.value(), 3 * SAMPLE_BLOCK + 20256);
+        assert!(estimates[1].ratio() < 0.01);
+
+        let noise = estimates.iter().find(|estimate| estimate.path == root.join("Bruit")).unwrap();
+        assert_eq!(noise.compressed, noise.size);
+        assert_eq!(noise.savings().value(), 0);
+    }
+}
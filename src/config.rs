@@ -0,0 +1,171 @@
+use serde::{Deserialize, Deserializer};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use crate::{hasher::HashAlgo, print_tree::SortOrder, size::SizeUnit};
+
+/// The defaults of the command line, read from a TOML configuration file, e.g.:
+///
+/// ```toml
+/// exclude = ["target", "node_modules"]
+/// color = "never"
+/// sort = "size"
+/// hash = "xxhash"
+/// si = true
+/// unit = "MB"
+/// bookmarks = ["/var/log", "/home"]
+/// ```
+///
+/// Every setting is optional, and the flags given on the command line take precedence.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// The globs of the entries skipped by the scans, added to those given by `--exclude`.
+    pub exclude: Vec<String>,
+    /// When the output is colored: `auto`, `always` or `never`.
+    pub color: Option<String>,
+    /// The order of the children of each directory, e.g. `size` or `name:desc`.
+    #[serde(deserialize_with = "parse")]
+    pub sort: Option<SortOrder>,
+    /// The hash function comparing the files for duplicates.
+    #[serde(deserialize_with = "parse")]
+    pub hash: Option<HashAlgo>,
+    /// Display the sizes in powers of 1000 instead of powers of 1024.
+    pub si: bool,
+    /// Display all the sizes in this unit.
+    #[serde(deserialize_with = "parse")]
+    pub unit: Option<SizeUnit>,
+    /// The absolute paths of the directories bookmarked in the interactive browser.
+    pub bookmarks: Vec<PathBuf>,
+}
+
+impl Config {
+
+    /// Reads the configuration file at `path`.
+    ///
+    /// # Returns
+    ///
+    /// An `io::Result` containing the configuration, failing if the file could not be read, or
+    /// with an `InvalidData` error if it is not a valid configuration.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use du::{Config, HashAlgo};
+    ///
+    /// # let dir = tempfile::tempdir()?;
+    /// let path = dir.path().join("config.toml");
+    /// std::fs::write(&path, "exclude = [\"target\"]\nhash = \"xxhash\"\n")?;
+    ///
+    /// let config = Config::load(&path)?;
+    /// assert_eq!(config.exclude, ["target"]);
+    /// assert_eq!(config.hash, Some(HashAlgo::XxHash));
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn load(path: &Path) -> io::Result<Config> {
+        let content = fs::read_to_string(path)?;
+        toml::from_str(&content).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path.display(), err)))
+    }
+
+    /// Reads the configuration file at the default path, see `default_path`, or returns the
+    /// default configuration if there is no such file.
+    pub fn load_default() -> io::Result<Config> {
+        match Self::default_path() {
+            Some(path) if path.is_file() => Self::load(&path),
+            _ => Ok(Config::default()),
+        }
+    }
+
+    /// Writes `bookmarks` to the configuration file at `path`, creating it if needed. The other
+    /// settings of the file and its comments are kept.
+    ///
+    /// # Returns
+    ///
+    /// An `io::Result`, failing if the file could not be read or written, or with an
+    /// `InvalidData` error if it is not valid TOML.
+    pub fn save_bookmarks(path: &Path, bookmarks: &[PathBuf]) -> io::Result<()> {
+        let content = match fs::read_to_string(path) {
+            Err(err) if err.kind() == io::ErrorKind::NotFound => String::new(),
+            content => content?,
+        };
+        let mut document: toml_edit::DocumentMut = content.parse().map_err(|err| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path.display(), err)))?;
+        let bookmarks: toml_edit::Array = bookmarks.iter().map(|bookmark| bookmark.to_string_lossy().into_owned()).collect();
+        document["bookmarks"] = toml_edit::value(bookmarks);
+        if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, document.to_string())
+    }
+
+    /// Returns the default path of the configuration file: `arborescence/config.toml` in
+    /// `$XDG_CONFIG_HOME`, `~/.config` or, on Windows, `%APPDATA%`.
+    pub fn default_path() -> Option<PathBuf> {
+        let non_empty = |name: &str| std::env::var_os(name).filter(|value| !value.is_empty()).map(PathBuf::from);
+        let directory = if cfg!(windows) {
+            non_empty("APPDATA")
+        } else {
+            non_empty("XDG_CONFIG_HOME").or_else(|| non_empty("HOME").map(|home| home.join(".config")))
+        };
+        directory.map(|directory| directory.join("arborescence").join("config.toml"))
+    }
+}
+
+/// Deserializes an optional value from its text, with its `FromStr` implementation.
+fn parse<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    let text = String::deserialize(deserializer)?;
+    text.parse().map(Some).map_err(serde::de::Error::custom)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::print_tree::SortKey;
+
+    #[test]
+    fn config_test() {
+        let config: Config = toml::from_str("exclude = [\"*.tmp\", \"target\"]\ncolor = \"never\"\nsort = \"size:asc\"\nsi = true\nunit = \"mb\"\n").unwrap();
+        assert_eq!(config.exclude, ["*.tmp", "target"]);
+        assert_eq!(config.color.as_deref(), Some("never"));
+        assert_eq!(config.sort, Some(SortOrder { key: SortKey::Size, descending: false }));
+        assert_eq!(config.hash, None);
+        assert!(config.si);
+        assert_eq!(config.unit, Some(SizeUnit::MB));
+
+        assert_eq!(toml::from_str::<Config>("").unwrap(), Config::default());
+        assert!(toml::from_str::<Config>("sort = \"taille\"").is_err());
+        assert!(toml::from_str::<Config>("unknown = 1").is_err());
+    }
+
+    #[test]
+    fn load_invalid_test() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        fs::write(&path, "hash = \"crc\"").unwrap();
+        let err = Config::load(&path).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(Config::load(&dir.path().join("absent.toml")).is_err());
+    }
+
+    #[test]
+    fn save_bookmarks_test() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("arborescence/config.toml");
+        Config::save_bookmarks(&path, &[PathBuf::from("/var/log")]).unwrap();
+        assert_eq!(Config::load(&path).unwrap().bookmarks, [PathBuf::from("/var/log")]);
+
+        fs::write(&path, "# Réglages\nsi = true\nbookmarks = [\"/var/log\"]\n").unwrap();
+        Config::save_bookmarks(&path, &[PathBuf::from("/home"), PathBuf::from("/tmp")]).unwrap();
+        assert!(fs::read_to_string(&path).unwrap().starts_with("# Réglages\nsi = true\n"));
+        let config = Config::load(&path).unwrap();
+        assert!(config.si);
+        assert_eq!(config.bookmarks, [PathBuf::from("/home"), PathBuf::from("/tmp")]);
+    }
+}
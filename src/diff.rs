@@ -0,0 +1,177 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use crate::{file_tree::{EntryNode, FileTree}, print_tree::PrintOptions, size::{Size, SizeFormat}};
+
+/// How an entry changed between two scans.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Change {
+    /// The entry only exists in the new scan.
+    Added,
+    /// The entry only exists in the old scan.
+    Removed,
+    /// The entry is larger in the new scan.
+    Grown,
+    /// The entry is smaller in the new scan.
+    Shrunk,
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Change::Added => "added",
+            Change::Removed => "removed",
+            Change::Grown => "grown",
+            Change::Shrunk => "shrunk",
+        })
+    }
+}
+
+/// An entry whose size changed between two scans.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct DiffEntry {
+    /// The path of the entry relative to the roots, empty for the roots themselves.
+    pub path: PathBuf,
+    /// `true` if the entry is a directory in the new scan, or in the old one if it was removed.
+    pub is_dir: bool,
+    /// How the entry changed.
+    pub change: Change,
+    /// The size in the old scan, `None` if the entry was added.
+    pub old_size: Option<Size>,
+    /// The size in the new scan, `None` if the entry was removed.
+    pub new_size: Option<Size>,
+}
+
+impl DiffEntry {
+
+    /// Returns the difference between the new and the old size, in bytes.
+    pub fn delta(&self) -> i128 {
+        let size = |size: Option<Size>| size.map_or(0, |size| size.value() as i128);
+        size(self.new_size) - size(self.old_size)
+    }
+
+    /// Returns the depth of the entry below the roots, the roots being at depth 0.
+    pub fn depth(&self) -> usize {
+        self.path.components().count()
+    }
+
+    /// Returns the delta formatted with its sign, e.g. `+1.5 GB`.
+    pub fn display_delta(&self, format: SizeFormat) -> String {
+        let delta = self.delta();
+        let sign = if delta < 0 { '-' } else { '+' };
+        format!("{}{}", sign, Size::new(delta.unsigned_abs() as u64).display(format))
+    }
+}
+
+
+impl FileTree {
+
+    /// Compares the file tree, the old scan, with `other`, the new one, matching the entries by their
+    /// path relative to each root, so that two scans of the same directory or two copies of a directory
+    /// can be compared.
+    ///
+    /// The entries whose size changed are returned in path order, the roots first. An added or removed
+    /// directory is reported alone, without its content.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - The new scan.
+    /// * `options` - The display options, selecting the apparent size or the disk usage.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use du::{Change, FileTree, PrintOptions};
+    ///
+    /// # let dir = tempfile::tempdir()?;
+    /// std::fs::write(dir.path().join("a.txt"), [0; 10])?;
+    /// let old = FileTree::new(dir.path())?;
+    /// std::fs::write(dir.path().join("a.txt"), [0; 30])?;
+    /// let new = FileTree::new(dir.path())?;
+    ///
+    /// let diff = old.diff(&new, &PrintOptions::default());
+    /// assert_eq!(diff.len(), 2);
+    /// assert_eq!((diff[1].change, diff[1].delta()), (Change::Grown, 20));
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn diff(&self, other: &FileTree, options: &PrintOptions) -> Vec<DiffEntry> {
+        let old = self.relative_sizes(options);
+        let new = other.relative_sizes(options);
+
+        let mut paths: Vec<&Path> = old.keys().chain(new.keys()).copied().collect();
+        paths.sort();
+        paths.dedup();
+
+        let mut entries = Vec::new();
+        for path in paths {
+            let (old_entry, new_entry) = (old.get(path), new.get(path));
+            let parent_in = |sizes: &BTreeMap<&Path, (bool, Size)>| path.parent().is_none_or(|parent| sizes.contains_key(parent));
+            let change = match (old_entry, new_entry) {
+                (Some((_, old_size)), Some((_, new_size))) if new_size > old_size => Change::Grown,
+                (Some((_, old_size)), Some((_, new_size))) if new_size < old_size => Change::Shrunk,
+                (Some(_), Some(_)) => continue,
+                (None, Some(_)) if parent_in(&old) => Change::Added,
+                (Some(_), None) if parent_in(&new) => Change::Removed,
+                _ => continue,
+            };
+            let is_dir = new_entry.or(old_entry).is_some_and(|(is_dir, _)| *is_dir);
+            entries.push(DiffEntry {
+                path: path.to_path_buf(),
+                is_dir,
+                change,
+                old_size: old_entry.map(|(_, size)| *size),
+                new_size: new_entry.map(|(_, size)| *size),
+            });
+        }
+        entries
+    }
+
+    /// Returns the kind and size of every entry, by path relative to the root.
+    fn relative_sizes(&self, options: &PrintOptions) -> BTreeMap<&Path, (bool, Size)> {
+        self.iter()
+            .filter_map(|path| {
+                let relative = path.strip_prefix(self.get_root()).ok()?;
+                let is_dir = matches!(self.get_map_option(path), Some(EntryNode::Directory(_)));
+                Some((relative, (is_dir, self.node_size(path, options))))
+            })
+            .collect()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{create_file, create_fixture};
+    use std::fs;
+
+    #[test]
+    fn diff_test() {
+        let fixture = create_fixture();
+        let root = fixture.path().join("Test");
+        let old = FileTree::new(&root).unwrap();
+
+        create_file(&root.join("Dossier1/Fichier1"), 4096);
+        create_file(&root.join("Nouveau/Gros"), 1000);
+        fs::remove_dir_all(root.join("Dossier2/SousDossier2")).unwrap();
+        let new = FileTree::new(&root).unwrap();
+
+        let diff = old.diff(&new, &PrintOptions::default());
+        let changes: Vec<_> = diff.iter().map(|entry| (entry.path.to_str().unwrap(), entry.change, entry.delta())).collect();
+        assert_eq!(changes, [
+            ("", Change::Shrunk, 3072 + 1000 - 20000),
+            ("Dossier1", Change::Grown, 3072),
+            ("Dossier1/Fichier1", Change::Grown, 3072),
+            ("Dossier2", Change::Shrunk, -20000),
+            ("Dossier2/SousDossier2", Change::Removed, -20000),
+            ("Nouveau", Change::Added, 1000),
+        ]);
+        assert!(diff[4].is_dir);
+        assert_eq!(diff[3].display_delta(SizeFormat::default()), "-19.53 KB");
+        assert_eq!(diff[5].display_delta(SizeFormat::default()), "+1000 B");
+
+        assert!(new.diff(&new, &PrintOptions::default()).is_empty());
+    }
+}
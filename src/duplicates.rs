@@ -0,0 +1,710 @@
+use rayon::prelude::*;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use crate::{error::{Error, Result}, file_tree::{EntryNode, FileTree}, filter::Filter, forest::Forest, print_tree::PathStyle, size::Size};
+
+/// A group of files sharing the same signature.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DuplicateGroup {
+    /// The signature shared by the files.
+    pub signature: String,
+    /// The size of each file of the group.
+    pub size: Size,
+    /// The paths of the files, sorted.
+    pub paths: Vec<PathBuf>,
+    /// The device and inode of the files having several hard links, in the order of `paths`, so that
+    /// the paths of a same file are counted once. Empty for the groups of directories.
+    pub links: Vec<Option<(u64, u64)>>,
+}
+
+impl DuplicateGroup {
+
+    /// Returns the number of distinct files of the group, the hard links to a same file being
+    /// counted once.
+    pub fn distinct_files(&self) -> usize {
+        let mut seen = HashSet::new();
+        (0..self.paths.len()).filter(|index| self.link(*index).is_none_or(|link| seen.insert(link))).count()
+    }
+
+    /// Returns `true` if all the paths of the group are hard links to a same file, which already
+    /// takes its space once.
+    pub fn is_hard_linked(&self) -> bool {
+        self.paths.len() > 1 && self.distinct_files() == 1
+    }
+
+    /// Returns the space freed by keeping a single copy of the file, the hard links to a same
+    /// file taking no space of their own.
+    pub fn reclaimable(&self) -> Size {
+        Size::new(self.size.value() * (self.distinct_files() as u64).saturating_sub(1))
+    }
+
+    /// Returns the device and inode of the file at `index` in `paths`, if it has several hard links.
+    pub fn link(&self, index: usize) -> Option<(u64, u64)> {
+        self.links.get(index).copied().flatten()
+    }
+
+    /// Keeps the paths for which `keep` returns `true`, with their device and inode.
+    fn retain_paths(&mut self, mut keep: impl FnMut(&Path) -> bool) {
+        let (paths, links) = std::mem::take(&mut self.paths)
+            .into_iter()
+            .enumerate()
+            .filter(|(_, path)| keep(path))
+            .map(|(index, path)| (path, self.link(index)))
+            .unzip();
+        (self.paths, self.links) = (paths, links);
+    }
+}
+
+/// The groups of duplicate files of a file tree, sorted by decreasing reclaimable space.
+#[derive(Clone, Debug, Default)]
+pub struct DuplicateReport {
+    groups: Vec<DuplicateGroup>,
+}
+
+impl DuplicateReport {
+
+    /// Returns the report of `groups`, sorted by decreasing reclaimable space.
+    pub(crate) fn from_groups(groups: Vec<DuplicateGroup>) -> DuplicateReport {
+        let mut report = DuplicateReport { groups };
+        report.sort();
+        report
+    }
+
+    /// Returns the groups of duplicates, sorted by decreasing reclaimable space.
+    pub fn groups(&self) -> &[DuplicateGroup] {
+        &self.groups
+    }
+
+    /// Returns the space freed by keeping a single copy of every group.
+    pub fn total_reclaimable(&self) -> Size {
+        self.groups.iter().fold(Size::new(0), |total, group| total + group.reclaimable())
+    }
+
+    /// Keeps only the files matching `filter`, dropping the groups left with a single file.
+    ///
+    /// # Arguments
+    ///
+    /// * `file_tree` - The file tree the report was built from, against which the paths are matched.
+    /// * `filter` - The filter the files must match.
+    pub fn filtered(mut self, file_tree: &FileTree, filter: &Filter) -> DuplicateReport {
+        for group in &mut self.groups {
+            group.retain_paths(|path| filter.is_match_in(file_tree, path));
+        }
+        self.groups.retain(|group| group.paths.len() > 1);
+        self.sort();
+        self
+    }
+
+    /// Keeps only the files matching `filter`, like `filtered`, for a report built from the
+    /// trees of `forest`: each path is matched against the tree containing it.
+    pub fn filtered_across(mut self, forest: &Forest, filter: &Filter) -> DuplicateReport {
+        for group in &mut self.groups {
+            group.retain_paths(|path| forest.tree_of(path).is_some_and(|file_tree| filter.is_match_in(file_tree, path)));
+        }
+        self.groups.retain(|group| group.paths.len() > 1);
+        self.sort();
+        self
+    }
+
+    /// Compares the files of each group byte by byte, splitting the groups whose files differ
+    /// despite their signature, and dropping the files left without a copy. The groups of
+    /// directories are kept as they are.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the verified report, failing if a file could not be read.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use du::{FileTree, Size};
+    ///
+    /// # let dir = tempfile::tempdir()?;
+    /// std::fs::write(dir.path().join("a.txt"), [1; 100])?;
+    /// std::fs::write(dir.path().join("b.txt"), [1; 100])?;
+    ///
+    /// let mut file_tree = FileTree::new(dir.path())?;
+    /// file_tree.compute_signatures()?;
+    /// let report = file_tree.find_duplicates_with(Size::new(1)).verified()?;
+    /// assert_eq!(report.groups()[0].paths.len(), 2);
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn verified(mut self) -> Result<DuplicateReport> {
+        let groups = self.groups.into_par_iter().map(split_by_content).collect::<Result<Vec<_>>>()?;
+        self.groups = groups.into_iter().flatten().filter(|group| group.paths.len() > 1).collect();
+        self.sort();
+        Ok(self)
+    }
+
+    /// Adds the groups of duplicate directories found by `FileTree::find_duplicate_dirs`, dropping
+    /// the groups of files that are all inside these directories.
+    ///
+    /// # Arguments
+    ///
+    /// * `dirs` - The groups of duplicate directories.
+    pub fn merge_dirs(mut self, dirs: DuplicateReport) -> DuplicateReport {
+        let duplicated: Vec<&PathBuf> = dirs.groups.iter().flat_map(|group| &group.paths).collect();
+        self.groups.retain(|group| !group.paths.iter().all(|path| duplicated.iter().any(|dir| path.starts_with(dir))));
+        self.groups.extend(dirs.groups);
+        self.sort();
+        self
+    }
+
+    /// Returns the report with the paths displayed in `style`, see `FileTree::display_path`,
+    /// to be written out rather than acted upon.
+    ///
+    /// # Arguments
+    ///
+    /// * `file_tree` - The file tree the report was built from.
+    /// * `style` - How the paths are displayed.
+    pub fn with_path_style(mut self, file_tree: &FileTree, style: PathStyle) -> DuplicateReport {
+        for group in &mut self.groups {
+            for path in &mut group.paths {
+                *path = file_tree.display_path(path, style).into_owned();
+            }
+        }
+        self
+    }
+
+    /// Returns the report with the paths displayed in `style`, like `with_path_style`, for a
+    /// report built from the trees of `forest`.
+    pub fn with_path_style_across(mut self, forest: &Forest, style: PathStyle) -> DuplicateReport {
+        for group in &mut self.groups {
+            for path in &mut group.paths {
+                if let Some(file_tree) = forest.tree_of(path) {
+                    *path = file_tree.display_path(path, style).into_owned();
+                }
+            }
+        }
+        self
+    }
+
+    fn sort(&mut self) {
+        self.groups.sort_by(|a, b| {
+            b.reclaimable().cmp(&a.reclaimable()).then_with(|| a.signature.cmp(&b.signature))
+        });
+    }
+}
+
+
+/// Returns the groups of at least two of the `count` items related by `similar`, step by step, each
+/// group listing the indices of its items in increasing order. Every pair of items is compared once.
+pub(crate) fn cluster(count: usize, similar: impl Fn(usize, usize) -> bool) -> Vec<Vec<usize>> {
+    // Chaque groupe est repéré par son premier élément
+    let mut parents: Vec<usize> = (0..count).collect();
+    for i in 0..count {
+        for j in i + 1..count {
+            if similar(i, j) {
+                let (root_i, root_j) = (find_root(&mut parents, i), find_root(&mut parents, j));
+                parents[root_i.max(root_j)] = root_i.min(root_j);
+            }
+        }
+    }
+    let mut members: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+    for index in 0..count {
+        members.entry(find_root(&mut parents, index)).or_default().push(index);
+    }
+    members.into_values().filter(|indices| indices.len() > 1).collect()
+}
+
+/// Returns the first item of the group of the item at `index`, shortening the path to it.
+fn find_root(parents: &mut [usize], mut index: usize) -> usize {
+    while parents[index] != index {
+        parents[index] = parents[parents[index]];
+        index = parents[index];
+    }
+    index
+}
+
+/// Splits a group into groups of files having exactly the same content, in the order of the paths.
+fn split_by_content(group: DuplicateGroup) -> Result<Vec<DuplicateGroup>> {
+    if group.paths.iter().any(|path| path.is_dir()) {
+        return Ok(vec![group]);
+    }
+    let mut groups: Vec<DuplicateGroup> = Vec::new();
+    for (index, path) in group.paths.iter().enumerate() {
+        let link = group.link(index);
+        let mut found = false;
+        for other in &mut groups {
+            if same_content(&other.paths[0], path)? {
+                other.paths.push(path.clone());
+                other.links.push(link);
+                found = true;
+                break;
+            }
+        }
+        if !found {
+            groups.push(DuplicateGroup { signature: group.signature.clone(), size: group.size, paths: vec![path.clone()], links: vec![link] });
+        }
+    }
+    Ok(groups)
+}
+
+/// Returns `true` if the files at `a` and `b` have the same content, read block by block.
+fn same_content(a: &Path, b: &Path) -> Result<bool> {
+    const BLOCK: usize = 64 * 1024;
+    let mut file_a = File::open(a).map_err(|err| Error::io(a, err))?;
+    let mut file_b = File::open(b).map_err(|err| Error::io(b, err))?;
+    let (mut block_a, mut block_b) = (vec![0; BLOCK], vec![0; BLOCK]);
+    loop {
+        let read_a = fill(&mut file_a, &mut block_a).map_err(|err| Error::io(a, err))?;
+        let read_b = fill(&mut file_b, &mut block_b).map_err(|err| Error::io(b, err))?;
+        if block_a[..read_a] != block_b[..read_b] {
+            return Ok(false);
+        }
+        if read_a < BLOCK {
+            return Ok(true);
+        }
+    }
+}
+
+/// Reads into `buffer` until it is full or the end of the file is reached, returning the number of bytes read.
+fn fill(file: &mut File, buffer: &mut [u8]) -> io::Result<usize> {
+    let mut read = 0;
+    while read < buffer.len() {
+        match file.read(&mut buffer[read..]) {
+            Ok(0) => break,
+            Ok(count) => read += count,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(read)
+}
+
+
+impl FileTree {
+
+    /// Finds the duplicate files of at least `min_size` bytes, among the files hashed by
+    /// `compute_signatures`.
+    ///
+    /// # Arguments
+    ///
+    /// * `min_size` - The minimum size of the files to report.
+    ///
+    /// # Returns
+    ///
+    /// A `DuplicateReport` whose groups are sorted by decreasing reclaimable space.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use du::{FileTree, Size};
+    ///
+    /// # let dir = tempfile::tempdir()?;
+    /// std::fs::write(dir.path().join("a.txt"), [1; 100])?;
+    /// std::fs::write(dir.path().join("b.txt"), [1; 100])?;
+    /// std::fs::write(dir.path().join("c.txt"), [1; 100])?;
+    /// std::fs::write(dir.path().join("empty1"), [])?;
+    /// std::fs::write(dir.path().join("empty2"), [])?;
+    ///
+    /// let mut file_tree = FileTree::new(dir.path())?;
+    /// file_tree.compute_signatures()?;
+    /// let report = file_tree.find_duplicates_with(Size::new(1));
+    /// assert_eq!(report.groups().len(), 1);
+    /// assert_eq!(report.total_reclaimable(), Size::new(200));
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn find_duplicates_with(&self, min_size: Size) -> DuplicateReport {
+        self.find_duplicates_between(min_size, None)
+    }
+
+    /// Finds the duplicate files of at least `min_size` bytes and at most `max_size` bytes, if
+    /// given, among the files hashed by `compute_signatures`.
+    ///
+    /// # Arguments
+    ///
+    /// * `min_size` - The minimum size of the files to report.
+    /// * `max_size` - The maximum size of the files to report, if any.
+    ///
+    /// # Returns
+    ///
+    /// A `DuplicateReport` whose groups are sorted by decreasing reclaimable space.
+    pub fn find_duplicates_between(&self, min_size: Size, max_size: Option<Size>) -> DuplicateReport {
+        let groups: Vec<DuplicateGroup> = self
+            .find_duplicates()
+            .into_iter()
+            .filter_map(|(signature, mut paths)| {
+                let size = self.get_size(&paths[0])?;
+                paths.sort();
+                let links = paths.iter().map(|path| self.get_link(path)).collect();
+                Some(DuplicateGroup { signature, size, paths, links })
+            })
+            .filter(|group| group.size >= min_size && max_size.is_none_or(|max_size| group.size <= max_size))
+            .collect();
+
+        let mut report = DuplicateReport { groups };
+        report.sort();
+        report
+    }
+
+    /// Finds the directories whose whole content is duplicated elsewhere in the tree, among the
+    /// files hashed by `compute_signatures`.
+    ///
+    /// The signature of a directory is the hash of the sorted names and signatures of its
+    /// children, so two directories are duplicates when they have the same names and contents
+    /// recursively. A group is left out when all its directories are inside larger duplicate
+    /// directories, and the empty directories are ignored.
+    ///
+    /// # Returns
+    ///
+    /// A `DuplicateReport` whose groups are sorted by decreasing reclaimable space.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use du::FileTree;
+    ///
+    /// # let dir = tempfile::tempdir()?;
+    /// for copy in ["photos", "backup/photos"] {
+    ///     std::fs::create_dir_all(dir.path().join(copy).join("2023"))?;
+    ///     std::fs::write(dir.path().join(copy).join("2023/a.jpg"), [1; 100])?;
+    ///     std::fs::write(dir.path().join(copy).join("b.jpg"), [2; 100])?;
+    /// }
+    ///
+    /// let mut file_tree = FileTree::new(dir.path())?;
+    /// file_tree.compute_signatures()?;
+    /// let report = file_tree.find_duplicate_dirs();
+    /// assert_eq!(report.groups().len(), 1);
+    /// assert_eq!(report.groups()[0].paths, [dir.path().join("backup/photos"), dir.path().join("photos")]);
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn find_duplicate_dirs(&self) -> DuplicateReport {
+        let mut signatures = HashMap::new();
+        self.directory_signature(self.get_root(), &mut signatures);
+
+        let mut paths_per_signature: HashMap<String, Vec<PathBuf>> = HashMap::new();
+        for (path, signature) in signatures {
+            paths_per_signature.entry(signature).or_default().push(path);
+        }
+        paths_per_signature.retain(|_, paths| paths.len() > 1);
+
+        // Un groupe dont chaque dossier est dans un dossier dupliqué est déjà couvert par le groupe parent
+        let duplicated: HashSet<&Path> = paths_per_signature.values().flatten().map(PathBuf::as_path).collect();
+        let groups = paths_per_signature
+            .iter()
+            .filter(|(_, paths)| !paths.iter().all(|path| path.parent().is_some_and(|parent| duplicated.contains(parent))))
+            .filter_map(|(signature, paths)| {
+                let mut paths = paths.clone();
+                paths.sort();
+                Some(DuplicateGroup { signature: signature.clone(), size: self.get_size(&paths[0])?, paths, links: Vec::new() })
+            })
+            .collect();
+
+        let mut report = DuplicateReport { groups };
+        report.sort();
+        report
+    }
+
+    /// Returns the signature of an entry, recording in `signatures` the signature of each non-empty
+    /// directory whose files have all been hashed, the empty files needing no hash. `None` if the
+    /// entry can't have a duplicate.
+    fn directory_signature(&self, path: &Path, signatures: &mut HashMap<PathBuf, String>) -> Option<String> {
+        match self.get_map_option(path)? {
+            EntryNode::File(size) if size.value() == 0 => Some("empty".to_string()),
+            EntryNode::File(_) => self.get_signature(path).map(str::to_string),
+            EntryNode::Symlink(target) => Some(format!("link:{}", target.display())),
+            EntryNode::Other(kind) => Some(format!("other:{}", kind)),
+            EntryNode::Directory(children) => {
+                let mut children: Vec<_> = children.iter().collect();
+                children.sort_by_key(|child| child.file_name());
+
+                let mut hasher = blake3::Hasher::new();
+                let mut complete = !children.is_empty();
+                for child in children {
+                    // Les sous-dossiers sont tous parcourus pour enregistrer leur signature
+                    match self.directory_signature(child, signatures) {
+                        Some(signature) => {
+                            hasher.update(child.file_name().unwrap_or_default().as_encoded_bytes());
+                            hasher.update(b"\0");
+                            hasher.update(signature.as_bytes());
+                            hasher.update(b"\n");
+                        }
+                        None => complete = false,
+                    }
+                }
+                if !complete {
+                    return None;
+                }
+                let signature = format!("dir:{}", hasher.finalize().to_hex());
+                signatures.insert(path.to_path_buf(), signature.clone());
+                Some(signature)
+            }
+        }
+    }
+}
+
+
+impl Forest {
+
+    /// Finds the duplicate files of at least `min_size` bytes and at most `max_size` bytes, if
+    /// given, among the files of all the trees hashed by `Forest::compute_signatures_between`.
+    /// A file found in several trees, below nested roots, is counted once.
+    ///
+    /// # Arguments
+    ///
+    /// * `min_size` - The minimum size of the files to report.
+    /// * `max_size` - The maximum size of the files to report, if any.
+    /// * `only_cross` - Report only the groups whose files are in at least two different trees.
+    ///
+    /// # Returns
+    ///
+    /// A `DuplicateReport` whose groups are sorted by decreasing reclaimable space.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use du::{FileTree, Forest, HashAlgo, Size};
+    ///
+    /// # let dir = tempfile::tempdir()?;
+    /// for (path, content) in [("a/photo.jpg", [1; 100]), ("a/copy.jpg", [2; 100]), ("a/copy2.jpg", [2; 100]), ("b/photo.jpg", [1; 100])] {
+    ///     std::fs::create_dir_all(dir.path().join(path).parent().unwrap())?;
+    ///     std::fs::write(dir.path().join(path), content)?;
+    /// }
+    ///
+    /// let mut forest = Forest::new(vec![FileTree::new(&dir.path().join("a"))?, FileTree::new(&dir.path().join("b"))?]);
+    /// forest.compute_signatures_between(HashAlgo::default().hasher(), Size::new(1), None, |_| {})?;
+    /// assert_eq!(forest.find_duplicates_between(Size::new(1), None, false).groups().len(), 2);
+    ///
+    /// let report = forest.find_duplicates_between(Size::new(1), None, true);
+    /// assert_eq!(report.groups()[0].paths, [dir.path().join("a/photo.jpg"), dir.path().join("b/photo.jpg")]);
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn find_duplicates_between(&self, min_size: Size, max_size: Option<Size>, only_cross: bool) -> DuplicateReport {
+        // Chaque chemin est associé à l'indice du premier arbre qui le contient
+        let mut seen = HashSet::new();
+        let mut signature_map: BTreeMap<&String, Vec<(usize, &PathBuf)>> = BTreeMap::new();
+        for (index, tree) in self.trees().iter().enumerate() {
+            for (path, signature) in tree.signatures() {
+                if seen.insert(path) {
+                    signature_map.entry(signature).or_default().push((index, path));
+                }
+            }
+        }
+
+        let groups = signature_map
+            .into_iter()
+            .filter(|(_, files)| files.len() > 1)
+            .filter(|(_, files)| !only_cross || files.iter().any(|(index, _)| *index != files[0].0))
+            .filter_map(|(signature, files)| {
+                let (index, path) = files[0];
+                let size = self.trees()[index].get_size(path)?;
+                let mut files: Vec<(usize, &PathBuf)> = files;
+                files.sort_by_key(|(_, path)| *path);
+                let links = files.iter().map(|(index, path)| self.trees()[*index].get_link(path)).collect();
+                let paths = files.into_iter().map(|(_, path)| path.clone()).collect();
+                Some(DuplicateGroup { signature: signature.clone(), size, paths, links })
+            })
+            .filter(|group| group.size >= min_size && max_size.is_none_or(|max_size| group.size <= max_size))
+            .collect();
+
+        let mut report = DuplicateReport { groups };
+        report.sort();
+        report
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::actions::{DuplicateAction, Resolution};
+    use crate::test_utils::{create_fixture, create_file};
+    use std::fs;
+
+    fn hashed_tree(root: &std::path::Path) -> FileTree {
+        let mut file_tree = FileTree::new(root).unwrap();
+        file_tree.compute_signatures().unwrap();
+        file_tree
+    }
+
+    fn write_copies(root: &std::path::Path, name: &str, content: &[u8], copies: usize) {
+        for i in 0..copies {
+            let path = root.join(format!("copie{}/{}", i, name));
+            create_file(&path, 0);
+            fs::write(path, content).unwrap();
+        }
+    }
+
+    #[test]
+    fn groups_sorted_by_reclaimable_test() {
+        let fixture = create_fixture();
+        let root = fixture.path().join("Test");
+        write_copies(&root, "petit", &[1; 100], 4);
+        write_copies(&root, "grand", &[2; 1000], 2);
+
+        let report = hashed_tree(&root).find_duplicates_with(Size::new(1));
+        let groups = report.groups();
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].size, Size::new(1000));
+        assert_eq!(groups[0].reclaimable(), Size::new(1000));
+        assert_eq!(groups[1].size, Size::new(100));
+        assert_eq!(groups[1].paths.len(), 4);
+        assert_eq!(groups[1].reclaimable(), Size::new(300));
+        assert_eq!(report.total_reclaimable(), Size::new(1300));
+    }
+
+    #[test]
+    fn min_size_test() {
+        let fixture = create_fixture();
+        let root = fixture.path().join("Test");
+        write_copies(&root, "petit", &[1; 100], 4);
+        write_copies(&root, "grand", &[2; 1000], 2);
+
+        let report = hashed_tree(&root).find_duplicates_with(Size::new(500));
+        assert_eq!(report.groups().len(), 1);
+        assert_eq!(report.total_reclaimable(), Size::new(1000));
+    }
+
+    #[test]
+    fn max_size_test() {
+        let fixture = create_fixture();
+        let root = fixture.path().join("Test");
+        write_copies(&root, "petit", &[1; 100], 4);
+        write_copies(&root, "grand", &[2; 1000], 2);
+        create_file(&root.join("vide1"), 0);
+        create_file(&root.join("vide2"), 0);
+
+        let file_tree = hashed_tree(&root);
+        let report = file_tree.find_duplicates_between(Size::new(1), Some(Size::new(500)));
+        assert_eq!(report.groups().len(), 1);
+        assert_eq!(report.groups()[0].size, Size::new(100));
+        assert_eq!(file_tree.find_duplicates_between(Size::new(100), Some(Size::new(1000))).groups().len(), 2);
+
+        let mut file_tree = FileTree::new(&root).unwrap();
+        file_tree.compute_signatures_between(&crate::hasher::Blake3Hasher, Size::new(1), Some(Size::new(500)), |_| {}).unwrap();
+        assert!(file_tree.get_signature(&root.join("copie0/petit")).is_some());
+        assert!(file_tree.get_signature(&root.join("copie0/grand")).is_none());
+        assert!(file_tree.get_signature(&root.join("vide1")).is_none());
+    }
+
+    #[test]
+    fn zero_length_files_excluded_test() {
+        let fixture = create_fixture();
+        let root = fixture.path().join("Test");
+        create_file(&root.join("vide1"), 0);
+        create_file(&root.join("vide2"), 0);
+
+        let file_tree = hashed_tree(&root);
+        assert!(file_tree.find_duplicates_with(Size::new(1)).groups().is_empty());
+        assert_eq!(file_tree.find_duplicates_with(Size::new(0)).groups().len(), 1);
+    }
+
+    #[test]
+    fn filtered_test() {
+        let fixture = create_fixture();
+        let root = fixture.path().join("Test");
+        write_copies(&root, "petit.txt", &[1; 100], 3);
+        write_copies(&root, "grand.bin", &[2; 1000], 2);
+
+        let file_tree = hashed_tree(&root);
+        let report = file_tree.find_duplicates_with(Size::new(1));
+        let report = report.filtered(&file_tree, &Filter::regex(r"^copie[01]/.*\.txt$", true).unwrap());
+        assert_eq!(report.groups().len(), 1);
+        assert_eq!(report.groups()[0].paths, [root.join("copie0/petit.txt"), root.join("copie1/petit.txt")]);
+        assert_eq!(report.total_reclaimable(), Size::new(100));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn hard_linked_test() {
+        let fixture = create_fixture();
+        let root = fixture.path().join("Test");
+        fs::write(root.join("a"), [3; 1000]).unwrap();
+        fs::hard_link(root.join("a"), root.join("b")).unwrap();
+        fs::write(root.join("c"), [3; 1000]).unwrap();
+        fs::write(root.join("lien1"), [4; 500]).unwrap();
+        fs::hard_link(root.join("lien1"), root.join("lien2")).unwrap();
+
+        let file_tree = hashed_tree(&root);
+        let report = file_tree.find_duplicates_with(Size::new(1));
+        assert_eq!(report.groups().len(), 2);
+        let copies = &report.groups()[0];
+        assert_eq!((copies.paths.len(), copies.distinct_files(), copies.is_hard_linked()), (3, 2, false));
+        assert_eq!(copies.reclaimable(), Size::new(1000));
+        let linked = &report.groups()[1];
+        assert!(linked.is_hard_linked());
+        assert_eq!(linked.reclaimable(), Size::new(0));
+        assert_eq!(report.total_reclaimable(), Size::new(1000));
+
+        // Le lien vers le fichier gardé est laissé tel quel
+        assert_eq!(copies.plan(0, Resolution::HardLink), [DuplicateAction::HardLink { path: root.join("c"), target: root.join("a") }]);
+        assert_eq!(copies.plan(0, Resolution::Delete).len(), 2);
+        let filtered = report.filtered(&file_tree, &Filter::regex("^[ac]$", true).unwrap());
+        assert_eq!((filtered.groups().len(), filtered.groups()[0].distinct_files()), (1, 2));
+    }
+
+    #[test]
+    fn duplicate_dirs_test() {
+        let fixture = create_fixture();
+        let root = fixture.path().join("Test");
+        for copy in ["a/photos", "b/photos", "c/photos"] {
+            create_file(&root.join(copy).join("2023/x.jpg"), 0);
+            fs::write(root.join(copy).join("2023/x.jpg"), [1; 100]).unwrap();
+            fs::write(root.join(copy).join("y.jpg"), [2; 300]).unwrap();
+        }
+        fs::write(root.join("b/photos/2023/z.jpg"), [3; 10]).unwrap();
+        fs::write(root.join("seule.jpg"), [2; 300]).unwrap();
+
+        let file_tree = hashed_tree(&root);
+        let report = file_tree.find_duplicate_dirs();
+        // a et c ne contiennent que photos : les copies de photos et 2023 sont couvertes par leur parent
+        let paths: Vec<_> = report.groups().iter().map(|group| group.paths.clone()).collect();
+        assert_eq!(paths, [[root.join("a"), root.join("c")]]);
+        assert_eq!(report.groups()[0].size, Size::new(400));
+
+        let merged = file_tree.find_duplicates_with(Size::new(1)).merge_dirs(report);
+        let paths: Vec<_> = merged.groups().iter().map(|group| group.paths.len()).collect();
+        assert_eq!(paths, [4, 2, 3]);
+    }
+
+    #[test]
+    fn duplicates_across_trees_test() {
+        let fixture = create_fixture();
+        let root = fixture.path().join("Test");
+        write_copies(&root.join("Dossier1"), "commun", &[1; 100], 1);
+        write_copies(&root.join("Dossier2"), "commun", &[1; 100], 1);
+        write_copies(&root.join("Dossier2"), "local", &[2; 100], 2);
+        // Fichier4 n'a de même taille que dans l'autre arbre : il doit être haché
+        fs::write(root.join("Dossier1/Fichier4bis"), [0; 256]).unwrap();
+
+        let trees = vec![FileTree::new(&root.join("Dossier1")).unwrap(), FileTree::new(&root.join("Dossier2")).unwrap(), FileTree::new(&root.join("Dossier2/SousDossier1")).unwrap()];
+        let mut forest = Forest::new(trees);
+        forest.compute_signatures_between(&crate::hasher::Blake3Hasher, Size::new(1), None, |_| {}).unwrap();
+
+        let report = forest.find_duplicates_between(Size::new(1), None, false);
+        assert_eq!(report.groups().len(), 3);
+        assert!(report.groups().iter().all(|group| group.paths.len() == 2));
+
+        let report = forest.find_duplicates_between(Size::new(1), None, true);
+        let paths: Vec<_> = report.groups().iter().map(|group| group.paths.clone()).collect();
+        assert_eq!(paths.len(), 2);
+        assert!(paths.contains(&vec![root.join("Dossier1/copie0/commun"), root.join("Dossier2/copie0/commun")]));
+        assert!(paths.contains(&vec![root.join("Dossier1/Fichier4bis"), root.join("Dossier2/SousDossier1/Fichier4")]));
+
+        let filter = Filter::new("*commun", false, false).unwrap();
+        assert_eq!(report.filtered_across(&forest, &filter).groups().len(), 1);
+    }
+
+    #[test]
+    fn verified_test() {
+        let fixture = create_fixture();
+        let root = fixture.path().join("Test");
+        let mut content = vec![1; 200_000];
+        write_copies(&root, "a", &content, 2);
+        content[150_000] = 2;
+        write_copies(&root.join("autre"), "a", &content, 1);
+
+        // Une collision de signatures est simulée en regroupant des fichiers différents
+        let paths = vec![root.join("autre/copie0/a"), root.join("copie0/a"), root.join("copie1/a")];
+        let report = DuplicateReport { groups: vec![DuplicateGroup { signature: "collision".to_string(), size: Size::new(200_000), paths, links: Vec::new() }] };
+        let report = report.verified().unwrap();
+        assert_eq!(report.groups().len(), 1);
+        assert_eq!(report.groups()[0].paths, [root.join("copie0/a"), root.join("copie1/a")]);
+
+        let missing = DuplicateReport { groups: vec![DuplicateGroup { signature: "x".to_string(), size: Size::new(1), paths: vec![root.join("absent1"), root.join("absent2")], links: Vec::new() }] };
+        assert!(matches!(missing.verified(), Err(Error::Io { .. })));
+    }
+}
@@ -0,0 +1,126 @@
+use std::fs;
+use std::path::Path;
+use crate::{error::{Error, Result}, file_tree::{EntryNode, FileTree}};
+
+impl FileTree {
+
+    /// Returns the empty files and the directories containing no file, recursively, below the root,
+    /// sorted by path.
+    ///
+    /// A directory containing only empty directories is returned alone, without its subdirectories.
+    /// Links and special files are not empty: the directories containing them are not returned.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use du::FileTree;
+    ///
+    /// # let dir = tempfile::tempdir()?;
+    /// std::fs::create_dir_all(dir.path().join("a/b/c"))?;
+    /// std::fs::write(dir.path().join("empty.txt"), [])?;
+    /// std::fs::write(dir.path().join("notes.txt"), b"hello")?;
+    ///
+    /// let file_tree = FileTree::new(dir.path())?;
+    /// assert_eq!(file_tree.empty_entries(), [dir.path().join("a"), dir.path().join("empty.txt")]);
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn empty_entries(&self) -> Vec<&Path> {
+        let mut entries = Vec::new();
+        if let Some(EntryNode::Directory(children)) = self.get_map_option(self.get_root()) {
+            for child in children {
+                if self.collect_empty(child, &mut entries) {
+                    entries.push(child);
+                }
+            }
+        }
+        entries.sort();
+        entries
+    }
+
+    /// Adds the empty entries below `path` to `entries`, and returns `true` if `path` is itself an
+    /// empty directory, left for the caller to add.
+    fn collect_empty<'a>(&'a self, path: &'a Path, entries: &mut Vec<&'a Path>) -> bool {
+        match self.get_map_option(path) {
+            Some(EntryNode::File(size)) => {
+                if size.value() == 0 {
+                    entries.push(path);
+                }
+                false
+            }
+            // Le contenu d'une archive ne peut pas être supprimé à part
+            Some(EntryNode::Directory(_)) if self.get_archive_size(path).is_some() => false,
+            Some(EntryNode::Directory(children)) => {
+                let empty_children: Vec<_> = children.iter().filter(|child| self.collect_empty(child, entries)).collect();
+                if empty_children.len() == children.len() {
+                    return true;
+                }
+                entries.extend(empty_children.into_iter().map(|child| child.as_path()));
+                false
+            }
+            _ => false,
+        }
+    }
+
+    /// Deletes an entry returned by `empty_entries`: an empty file, or a directory and its
+    /// subdirectories, deepest first.
+    ///
+    /// Nothing is deleted if the file is no longer empty, and a directory in which a file was
+    /// created since the scan is left in place with an error.
+    ///
+    /// # Returns
+    ///
+    /// An empty `Result`, failing if an entry could not be deleted, or with a `NotEmpty` error.
+    pub fn remove_empty(&self, path: &Path) -> Result<()> {
+        match self.get_map_option(path) {
+            Some(EntryNode::Directory(children)) => {
+                for child in children {
+                    self.remove_empty(child)?;
+                }
+                fs::remove_dir(path).map_err(|err| Error::io(path, err))
+            }
+            Some(EntryNode::File(_)) if fs::symlink_metadata(path).map_err(|err| Error::io(path, err))?.len() == 0 => fs::remove_file(path).map_err(|err| Error::io(path, err)),
+            _ => Err(Error::NotEmpty { path: path.to_path_buf() }),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{create_file, create_fixture};
+
+    #[test]
+    fn empty_entries_test() {
+        let fixture = create_fixture();
+        let root = fixture.path().join("Test");
+        create_file(&root.join("Dossier1/vide"), 0);
+        create_file(&root.join("Vides/Sous/vide"), 0);
+        fs::create_dir_all(root.join("Dossier2/Rien/Encore")).unwrap();
+        fs::create_dir_all(root.join("Vides/Autre")).unwrap();
+        let file_tree = FileTree::new(&root).unwrap();
+
+        let entries = file_tree.empty_entries();
+        assert_eq!(entries, [root.join("Dossier1/vide"), root.join("Dossier2/Rien"), root.join("Vides/Autre"), root.join("Vides/Sous/vide")]);
+
+        for entry in &entries {
+            file_tree.remove_empty(entry).unwrap();
+        }
+        assert!(!root.join("Dossier2/Rien").exists());
+        assert!(!root.join("Vides/Sous/vide").exists());
+        assert!(root.join("Vides/Sous").exists());
+        assert!(matches!(file_tree.remove_empty(&root.join("Dossier1/Fichier1")), Err(Error::NotEmpty { .. })));
+    }
+
+    #[test]
+    fn remove_empty_refilled_test() {
+        let fixture = create_fixture();
+        let root = fixture.path().join("Test");
+        fs::create_dir_all(root.join("Vide")).unwrap();
+        let file_tree = FileTree::new(&root).unwrap();
+
+        create_file(&root.join("Vide/nouveau"), 10);
+        assert!(file_tree.remove_empty(&root.join("Vide")).is_err());
+        assert!(root.join("Vide/nouveau").exists());
+    }
+}
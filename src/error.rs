@@ -0,0 +1,102 @@
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// The errors of the scans, the hashing and the cache files of a `FileTree`.
+///
+/// Each error names the path that failed. It converts into an `io::Error` of the same kind, so
+/// that `?` can be used in functions returning an `io::Result`.
+///
+/// # Example
+///
+/// ```
+/// use du::{Error, FileTree};
+///
+/// # let dir = tempfile::tempdir()?;
+/// let missing = dir.path().join("missing");
+/// match FileTree::new(&missing) {
+///     Err(Error::Io { path, source }) => assert_eq!((path, source.kind()), (missing, std::io::ErrorKind::NotFound)),
+///     _ => unreachable!(),
+/// }
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// An entry could not be read or written.
+    #[error("{}: {source}", path.display())]
+    Io { path: PathBuf, source: io::Error },
+    /// A file could not be read while computing its signature.
+    #[error("could not hash {}: {source}", path.display())]
+    Hash { path: PathBuf, source: io::Error },
+    /// The function visiting the entries of a streaming scan failed on `path`, stopping the scan.
+    #[error("scan interrupted at {}: {source}", path.display())]
+    WalkInterrupted { path: PathBuf, source: io::Error },
+    /// An exclude pattern is not a valid glob.
+    #[error("invalid exclude pattern {pattern:?}: {source}")]
+    InvalidPattern { pattern: String, source: globset::Error },
+    /// A file is not a cache file, was written by another version of the format, or is corrupted.
+    #[error("{}: {reason}", path.display())]
+    InvalidCache { path: PathBuf, reason: String },
+    /// A line of the stream of a remote scan is not a valid entry, or the stream has no root.
+    #[error("{origin}: {reason}")]
+    InvalidStream { origin: String, reason: String },
+    /// An entry to delete as empty is not, or no longer, an empty file or directory.
+    #[error("{} is not empty", path.display())]
+    NotEmpty { path: PathBuf },
+    /// The threads of the scan could not be started.
+    #[error("could not start the threads of the scan: {0}")]
+    Threads(#[from] rayon::ThreadPoolBuildError),
+}
+
+/// The result of the operations of a `FileTree`.
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+
+    /// Returns an `Io` error on the entry at `path`.
+    pub(crate) fn io(path: &Path, source: io::Error) -> Error {
+        Error::Io { path: path.to_path_buf(), source }
+    }
+
+    /// Returns the path that failed, if any.
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            Error::Io { path, .. } | Error::Hash { path, .. } | Error::WalkInterrupted { path, .. } | Error::InvalidCache { path, .. } | Error::NotEmpty { path } => Some(path),
+            Error::InvalidPattern { .. } | Error::InvalidStream { .. } | Error::Threads(_) => None,
+        }
+    }
+
+    /// Returns the kind of the `io::Error` the error converts into: the kind of the underlying
+    /// error, `InvalidInput` for an invalid pattern or a non-empty entry, `InvalidData` for an
+    /// invalid cache file.
+    pub fn kind(&self) -> io::ErrorKind {
+        match self {
+            Error::Io { source, .. } | Error::Hash { source, .. } | Error::WalkInterrupted { source, .. } => source.kind(),
+            Error::InvalidPattern { .. } | Error::NotEmpty { .. } => io::ErrorKind::InvalidInput,
+            Error::InvalidCache { .. } | Error::InvalidStream { .. } => io::ErrorKind::InvalidData,
+            Error::Threads(_) => io::ErrorKind::Other,
+        }
+    }
+}
+
+impl From<Error> for io::Error {
+    fn from(err: Error) -> io::Error {
+        io::Error::new(err.kind(), err)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn into_io_error_test() {
+        let err = Error::io(Path::new("/tmp/absent"), io::Error::new(io::ErrorKind::NotFound, "introuvable"));
+        assert_eq!(err.to_string(), "/tmp/absent: introuvable");
+        assert_eq!(err.path(), Some(Path::new("/tmp/absent")));
+
+        let err = io::Error::from(Error::InvalidCache { path: PathBuf::from("cache.bin"), reason: "not a cache file".to_string() });
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(err.to_string(), "cache.bin: not a cache file");
+    }
+}
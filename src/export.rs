@@ -0,0 +1,486 @@
+use serde::Serialize;
+use std::io::{self, Write};
+use std::path::Path;
+use crate::{duplicates::{DuplicateGroup, DuplicateReport}, file_tree::{EntryNode, FileTree}, print_tree::PrintOptions, size::Size};
+
+/// A flat description of an entry of the file tree, as exported to tabular formats.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UsageRecord<'a> {
+    /// The path of the entry.
+    pub path: &'a Path,
+    /// `"file"`, `"directory"`, `"archive"` for an archive listed as a directory, `"symlink"`,
+    /// or the type of a special file.
+    pub kind: &'static str,
+    /// The size of the entry, or of its whole subtree for a directory.
+    pub size: Size,
+    /// The depth of the entry, the root being at depth 0.
+    pub depth: usize,
+    /// The directory containing the entry, `None` for the root.
+    pub parent: Option<&'a Path>,
+    /// The signature of a file, if it has been computed.
+    pub hash: Option<&'a str>,
+}
+
+/// A node of the nested representation of the file tree, as exported to JSON.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct UsageNode {
+    /// The path of the entry.
+    pub path: String,
+    /// The size of the entry in bytes, or of its whole subtree for a directory.
+    pub size: u64,
+    /// The children of a directory, `None` for a file or a link.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub children: Option<Vec<UsageNode>>,
+}
+
+/// The results of a command, serialized to the formats having a single document model: the
+/// usage trees, or the groups of duplicates.
+///
+/// # Example
+///
+/// ```
+/// use du::{FileTree, PrintOptions, Report};
+///
+/// # let dir = tempfile::tempdir()?;
+/// std::fs::write(dir.path().join("notes.txt"), [0; 10])?;
+/// let file_tree = FileTree::new(dir.path())?;
+///
+/// let mut out = Vec::new();
+/// Report::usage(&[file_tree], &PrintOptions::default()).write_yaml(&mut out)?;
+/// assert!(String::from_utf8(out).unwrap().contains("size: 10"));
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[derive(Serialize)]
+#[serde(rename = "report")]
+pub struct Report<'a> {
+    #[serde(rename = "tree", skip_serializing_if = "Vec::is_empty")]
+    pub(crate) trees: Vec<UsageNode>,
+    #[serde(rename = "group", skip_serializing_if = "Vec::is_empty")]
+    pub(crate) groups: Vec<DuplicateRecord<'a>>,
+}
+
+impl<'a> Report<'a> {
+
+    /// Creates the report of the usage of the file trees, see `FileTree::usage_node`.
+    pub fn usage(trees: &[FileTree], options: &PrintOptions) -> Self {
+        let trees = trees.iter().filter_map(|tree| tree.usage_node(tree.get_root(), options)).collect();
+        Report { trees, groups: Vec::new() }
+    }
+
+    /// Creates the report of the groups of duplicates, each group having the fields `hash`, `size`,
+    /// `count`, `distinct`, `reclaimable` and `paths`.
+    pub fn duplicates(report: &'a DuplicateReport) -> Self {
+        Report { trees: Vec::new(), groups: Vec::new() }.with_duplicates(report)
+    }
+
+    /// Adds the groups of duplicates of `report` to the report.
+    pub fn with_duplicates(mut self, report: &'a DuplicateReport) -> Self {
+        self.groups.extend(report.groups().iter().map(DuplicateRecord::new));
+        self
+    }
+
+    /// Writes the report as a YAML document.
+    ///
+    /// # Arguments
+    ///
+    /// * `out` - The writer receiving the YAML.
+    pub fn write_yaml<W: Write>(&self, out: W) -> io::Result<()> {
+        serde_yaml::to_writer(out, self).map_err(io::Error::other)
+    }
+
+    /// Writes the report as an XML document whose root element is `report`, containing one `tree`
+    /// element per file tree, or one `group` element per group of duplicates.
+    ///
+    /// # Arguments
+    ///
+    /// * `out` - The writer receiving the XML.
+    pub fn write_xml<W: Write>(&self, mut out: W) -> io::Result<()> {
+        let mut xml = String::new();
+        let mut serializer = quick_xml::se::Serializer::new(&mut xml);
+        serializer.indent(' ', 2);
+        self.serialize(serializer).map_err(io::Error::other)?;
+        writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(out, "{}", xml)
+    }
+}
+
+
+impl FileTree {
+
+    /// Returns one record per entry of the file tree, in depth-first order.
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - The display options, selecting the apparent size or the disk usage.
+    pub fn usage_records(&self, options: &PrintOptions) -> Vec<UsageRecord<'_>> {
+        let mut records = Vec::new();
+        self.collect_records(self.get_root(), None, 0, options, &mut records);
+        records
+    }
+
+    fn collect_records<'a>(&'a self, path: &'a Path, parent: Option<&'a Path>, depth: usize, options: &PrintOptions, records: &mut Vec<UsageRecord<'a>>) {
+        let Some(record) = self.record(path, parent, depth, options) else { return };
+        if !options.is_shown(record.size, depth) || !self.is_listed(path, options, depth) {
+            return;
+        }
+        records.push(record);
+        if let Some(EntryNode::Directory(children)) = self.get_map_option(path) {
+            if options.descends(depth) {
+                for child in children {
+                    self.collect_records(child, Some(path), depth + 1, options, records);
+                }
+            }
+        }
+    }
+
+    /// Returns the record of the entry at `path` alone, whatever the depth and size limits of
+    /// `options`, or `None` if `path` is not in the file tree.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the entry.
+    /// * `options` - The display options, selecting the apparent size or the disk usage.
+    pub fn usage_record<'a>(&'a self, path: &'a Path, options: &PrintOptions) -> Option<UsageRecord<'a>> {
+        let relative = path.strip_prefix(self.get_root()).ok()?;
+        let parent = path.parent().filter(|_| !relative.as_os_str().is_empty());
+        self.record(path, parent, relative.components().count(), options)
+    }
+
+    fn record<'a>(&'a self, path: &'a Path, parent: Option<&'a Path>, depth: usize, options: &PrintOptions) -> Option<UsageRecord<'a>> {
+        let size = if options.disk_usage { self.get_disk_usage(path) } else { self.get_size(path) }?;
+        let (kind, hash) = match self.get_map_option(path)? {
+            EntryNode::File(_) => ("file", self.get_signature(path)),
+            EntryNode::Symlink(_) => ("symlink", None),
+            EntryNode::Other(kind) => (kind.name(), None),
+            EntryNode::Directory(_) if self.get_archive_size(path).is_some() => ("archive", None),
+            EntryNode::Directory(_) => ("directory", None),
+        };
+        Some(UsageRecord { path, kind, size, depth, parent, hash })
+    }
+
+    /// Returns the nested representation of the subtree rooted at `path`, or `None` if
+    /// `path` is not in the file tree. The directories at `options.max_depth` have no children.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The root of the subtree.
+    /// * `options` - The display options, selecting the apparent size or the disk usage.
+    pub fn usage_node(&self, path: &Path, options: &PrintOptions) -> Option<UsageNode> {
+        self.usage_node_at(path, options, 0)
+    }
+
+    fn usage_node_at(&self, path: &Path, options: &PrintOptions, depth: usize) -> Option<UsageNode> {
+        let size = if options.disk_usage { self.get_disk_usage(path) } else { self.get_size(path) }?;
+        if !options.is_shown(size, depth) || !self.is_listed(path, options, depth) {
+            return None;
+        }
+        let children = match self.get_map_option(path)? {
+            EntryNode::File(_) | EntryNode::Symlink(_) | EntryNode::Other(_) => None,
+            EntryNode::Directory(_) if !options.descends(depth) => Some(Vec::new()),
+            EntryNode::Directory(children) => Some(
+                children.iter().filter_map(|child| self.usage_node_at(child, options, depth + 1)).collect(),
+            ),
+        };
+        Some(UsageNode { path: self.display_path(path, options.path_style).to_string_lossy().into_owned(), size: size.value(), children })
+    }
+
+    /// Writes the file tree as nested JSON objects with the fields `path`, `size` and,
+    /// for the directories, `children`.
+    ///
+    /// # Arguments
+    ///
+    /// * `out` - The writer receiving the JSON.
+    /// * `options` - The display options, selecting the apparent size or the disk usage.
+    pub fn write_json<W: Write>(&self, mut out: W, options: &PrintOptions) -> io::Result<()> {
+        if let Some(node) = self.usage_node(self.get_root(), options) {
+            serde_json::to_writer_pretty(&mut out, &node)?;
+            writeln!(out)?;
+        }
+        Ok(())
+    }
+
+    /// Writes the usage records of the file tree as CSV, with the columns
+    /// `path,type,size_bytes,depth,parent,hash`. The hash is empty unless it has been computed.
+    ///
+    /// # Arguments
+    ///
+    /// * `out` - The writer receiving the CSV.
+    /// * `options` - The display options, selecting the apparent size or the disk usage.
+    pub fn write_csv<W: Write>(&self, out: W, options: &PrintOptions) -> io::Result<()> {
+        let mut writer = csv::Writer::from_writer(out);
+        Self::write_csv_header(&mut writer)?;
+        self.write_csv_records(&mut writer, options)?;
+        writer.flush()
+    }
+
+    pub(crate) fn write_csv_header<W: Write>(writer: &mut csv::Writer<W>) -> io::Result<()> {
+        Ok(writer.write_record(["path", "type", "size_bytes", "depth", "parent", "hash"])?)
+    }
+
+    pub(crate) fn write_csv_records<W: Write>(&self, writer: &mut csv::Writer<W>, options: &PrintOptions) -> io::Result<()> {
+        for record in self.usage_records(options) {
+            writer.write_record([
+                self.display_path(record.path, options.path_style).display().to_string(),
+                record.kind.to_string(),
+                record.size.value().to_string(),
+                record.depth.to_string(),
+                record.parent.map(|parent| self.display_path(parent, options.path_style).display().to_string()).unwrap_or_default(),
+                record.hash.unwrap_or_default().to_string(),
+            ])?;
+        }
+        Ok(())
+    }
+}
+
+
+impl DuplicateReport {
+
+    /// Writes the duplicate report as CSV, one row per file, with the columns
+    /// `signature,path,size_bytes,group_size,distinct_files`, the hard links to a same file being
+    /// counted once in `distinct_files`.
+    ///
+    /// # Arguments
+    ///
+    /// * `out` - The writer receiving the CSV.
+    pub fn write_csv<W: Write>(&self, out: W) -> io::Result<()> {
+        let mut writer = csv::Writer::from_writer(out);
+        writer.write_record(["signature", "path", "size_bytes", "group_size", "distinct_files"])?;
+        for group in self.groups() {
+            for path in &group.paths {
+                writer.write_record([
+                    group.signature.clone(),
+                    path.display().to_string(),
+                    group.size.value().to_string(),
+                    group.paths.len().to_string(),
+                    group.distinct_files().to_string(),
+                ])?;
+            }
+        }
+        writer.flush()
+    }
+
+    /// Writes the groups as a JSON array of `{hash, size, count, distinct, reclaimable, paths}` objects,
+    /// the size being the size in bytes of each file, and `distinct` the number of files once the hard
+    /// links to a same file are counted once.
+    ///
+    /// # Arguments
+    ///
+    /// * `out` - The writer receiving the JSON.
+    pub fn write_json<W: Write>(&self, mut out: W) -> io::Result<()> {
+        let records: Vec<_> = self.groups().iter().map(DuplicateRecord::new).collect();
+        serde_json::to_writer_pretty(&mut out, &records)?;
+        writeln!(out)
+    }
+
+    /// Writes the groups as newline-delimited JSON, one `{hash, size, count, distinct, reclaimable, paths}`
+    /// object per line.
+    ///
+    /// # Arguments
+    ///
+    /// * `out` - The writer receiving the JSON lines.
+    pub fn write_ndjson<W: Write>(&self, mut out: W) -> io::Result<()> {
+        for group in self.groups() {
+            serde_json::to_writer(&mut out, &DuplicateRecord::new(group))?;
+            writeln!(out)?;
+        }
+        Ok(())
+    }
+}
+
+/// A group of duplicates, as exported to JSON.
+#[derive(Serialize)]
+pub(crate) struct DuplicateRecord<'a> {
+    pub(crate) hash: &'a str,
+    pub(crate) size: u64,
+    pub(crate) count: usize,
+    /// The number of distinct files, the hard links to a same file being counted once.
+    pub(crate) distinct: usize,
+    pub(crate) reclaimable: u64,
+    pub(crate) paths: Vec<String>,
+}
+
+impl<'a> DuplicateRecord<'a> {
+    fn new(group: &'a DuplicateGroup) -> Self {
+        DuplicateRecord {
+            hash: &group.signature,
+            size: group.size.value(),
+            count: group.paths.len(),
+            distinct: group.distinct_files(),
+            reclaimable: group.reclaimable().value(),
+            paths: group.paths.iter().map(|path| path.display().to_string()).collect(),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{create_file, create_fixture};
+    use std::fs;
+
+    fn parse(csv: &[u8]) -> Vec<csv::StringRecord> {
+        csv::Reader::from_reader(csv).records().map(Result::unwrap).collect()
+    }
+
+    #[test]
+    fn usage_csv_test() {
+        let fixture = create_fixture();
+        let root = fixture.path().join("Test");
+        create_file(&root.join("Dossier1/photo, \"vacances\".jpg"), 10);
+        let file_tree = FileTree::new(&root).unwrap();
+
+        let mut out = Vec::new();
+        file_tree.write_csv(&mut out, &PrintOptions::default()).unwrap();
+        let records = parse(&out);
+
+        assert_eq!(records.len(), file_tree.iter().count());
+        assert_eq!(&records[0][0], root.display().to_string());
+        assert_eq!(&records[0][1], "directory");
+        assert_eq!(&records[0][4], "");
+
+        let photo = records.iter().find(|record| record[0].ends_with("photo, \"vacances\".jpg")).unwrap();
+        assert_eq!(&photo[1], "file");
+        assert_eq!(&photo[2], "10");
+        assert_eq!(&photo[3], "2");
+        assert_eq!(&photo[4], root.join("Dossier1").display().to_string());
+        assert_eq!(&photo[5], "");
+    }
+
+    #[test]
+    fn usage_csv_hash_test() {
+        let fixture = create_fixture();
+        let root = fixture.path().join("Test");
+        fs::copy(root.join("Dossier1/Fichier1"), root.join("Copie")).unwrap();
+        let mut file_tree = FileTree::new(&root).unwrap();
+        file_tree.compute_signatures().unwrap();
+
+        let mut out = Vec::new();
+        file_tree.write_csv(&mut out, &PrintOptions::default()).unwrap();
+        let records = parse(&out);
+        let hash = |path: &Path| records.iter().find(|record| record[0] == path.display().to_string()).unwrap()[5].to_string();
+        assert_eq!(hash(&root.join("Copie")).len(), 64);
+        assert_eq!(hash(&root.join("Copie")), hash(&root.join("Dossier1/Fichier1")));
+        assert_eq!(hash(&root.join("Dossier2/Fichier3")), "");
+        assert_eq!(hash(&root), "");
+    }
+
+    #[test]
+    fn usage_records_test() {
+        let fixture = create_fixture();
+        let root = fixture.path().join("Test");
+        let file_tree = FileTree::new(&root).unwrap();
+
+        let records = file_tree.usage_records(&PrintOptions::default());
+        let dossier2 = records.iter().find(|record| record.path == root.join("Dossier2")).unwrap();
+        assert_eq!(dossier2.size, Size::new(520256));
+        assert_eq!(dossier2.depth, 1);
+        assert_eq!(dossier2.parent, Some(root.as_path()));
+
+        let options = PrintOptions { max_depth: Some(0), ..PrintOptions::default() };
+        assert_eq!(file_tree.usage_record(&root.join("Dossier2"), &options).as_ref(), Some(dossier2));
+        assert_eq!(file_tree.usage_record(&root, &options).unwrap().parent, None);
+        assert_eq!(file_tree.usage_record(&root.join("Absent"), &options), None);
+    }
+
+    #[test]
+    fn usage_json_test() {
+        let fixture = create_fixture();
+        let root = fixture.path().join("Test");
+        let file_tree = FileTree::new(&root).unwrap();
+
+        let mut out = Vec::new();
+        file_tree.write_json(&mut out, &PrintOptions::default()).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&out).unwrap();
+
+        assert_eq!(json["path"], root.display().to_string());
+        assert_eq!(json["size"], 521280);
+        let dossier2 = &json["children"][1];
+        assert_eq!(dossier2["path"], root.join("Dossier2").display().to_string());
+        assert_eq!(dossier2["size"], 520256);
+        assert_eq!(dossier2["children"].as_array().unwrap().len(), 3);
+        let fichier3 = &dossier2["children"][0];
+        assert_eq!(fichier3["size"], 500000);
+        assert!(fichier3.get("children").is_none());
+    }
+
+    #[test]
+    fn report_test() {
+        let fixture = create_fixture();
+        let root = fixture.path().join("Test");
+        create_file(&root.join("a & <b>"), 10);
+        let file_tree = FileTree::new(&root).unwrap();
+        let report = Report::usage(std::slice::from_ref(&file_tree), &PrintOptions::default());
+
+        let mut out = Vec::new();
+        report.write_yaml(&mut out).unwrap();
+        let yaml: serde_json::Value = serde_yaml::from_slice(&out).unwrap();
+        assert_eq!(yaml["tree"][0]["size"], 521290);
+        assert_eq!(yaml["tree"][0]["children"][1]["size"], 520256);
+
+        let mut out = Vec::new();
+        report.write_xml(&mut out).unwrap();
+        let xml = String::from_utf8(out).unwrap();
+        assert!(xml.starts_with("<?xml"));
+        assert!(xml.contains("<report>\n  <tree>"));
+        assert!(xml.contains("<size>521290</size>"));
+        assert!(xml.contains("a &amp; &lt;b&gt;</path>"));
+
+        fs::write(root.join("c"), [1; 100]).unwrap();
+        fs::write(root.join("d"), [1; 100]).unwrap();
+        let mut file_tree = FileTree::new(&root).unwrap();
+        file_tree.compute_signatures().unwrap();
+        let duplicates = file_tree.find_duplicates_with(Size::new(1));
+        let mut out = Vec::new();
+        Report::duplicates(&duplicates).write_yaml(&mut out).unwrap();
+        let yaml: serde_json::Value = serde_yaml::from_slice(&out).unwrap();
+        assert!(yaml.get("tree").is_none());
+        assert_eq!(yaml["group"][0]["count"], 2);
+    }
+
+    #[test]
+    fn duplicates_csv_test() {
+        let fixture = create_fixture();
+        let root = fixture.path().join("Test");
+        fs::write(root.join("a,b"), [1; 100]).unwrap();
+        fs::write(root.join("Dossier1/c"), [1; 100]).unwrap();
+        fs::write(root.join("Dossier2/d"), [1; 100]).unwrap();
+        let mut file_tree = FileTree::new(&root).unwrap();
+        file_tree.compute_signatures().unwrap();
+        let report = file_tree.find_duplicates_with(Size::new(1));
+
+        let mut out = Vec::new();
+        report.write_csv(&mut out).unwrap();
+        let records = parse(&out);
+
+        assert_eq!(records.len(), 3);
+        assert!(records.iter().all(|record| &record[2] == "100" && &record[3] == "3"));
+        assert!(records.iter().any(|record| record[1] == root.join("a,b").display().to_string()));
+    }
+
+    #[test]
+    fn duplicates_json_test() {
+        let fixture = create_fixture();
+        let root = fixture.path().join("Test");
+        fs::write(root.join("a"), [1; 100]).unwrap();
+        fs::write(root.join("Dossier1/b"), [1; 100]).unwrap();
+        fs::write(root.join("c"), [2; 10]).unwrap();
+        fs::write(root.join("Dossier2/d"), [2; 10]).unwrap();
+        let mut file_tree = FileTree::new(&root).unwrap();
+        file_tree.compute_signatures().unwrap();
+        let report = file_tree.find_duplicates_with(Size::new(1));
+
+        let mut out = Vec::new();
+        report.write_json(&mut out).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&out).unwrap();
+        assert_eq!(json[0]["size"], 100);
+        assert_eq!(json[0]["count"], 2);
+        assert_eq!(json[0]["hash"], report.groups()[0].signature);
+        assert_eq!(json[0]["paths"][1], root.join("a").display().to_string());
+
+        let mut out = Vec::new();
+        report.write_ndjson(&mut out).unwrap();
+        let lines: Vec<serde_json::Value> = String::from_utf8(out).unwrap().lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert_eq!(lines, json.as_array().unwrap().clone());
+    }
+}
@@ -1,40 +1,167 @@
-use crypto::digest::Digest;
-use crypto::md5::Md5;
-use hex::encode;
+use globset::GlobSet;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::Match;
+use crate::archive::{self, ArchiveFormat};
+use crate::builder::FileTreeBuilder;
+use crate::error::{Error, Result};
+use crate::hasher::{Blake3Hasher, Hasher};
+use crate::owner::Ownership;
 use crate::size::Size;
-use std::collections::hash_map::Iter;
-use std::collections::HashMap;
+use std::borrow::Cow;
+use std::collections::btree_map::Iter;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::fmt;
 use std::fs::{self, File};
-use std::path::{Path, PathBuf};
-use std::io::{self, Read};
+use std::path::{Component, Path, PathBuf};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::{Duration, Instant, SystemTime};
 use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
 
-/// Represents a file or directory entry in the file tree.
+/// The size of the files first compared by the hash of their beginning and end only.
+const QUICK_SIGNATURE_MIN_SIZE: u64 = 16 * 1024 * 1024;
 
-#[derive(Debug)]
+/// The number of bytes hashed at each end of a file for its quick signature.
+const QUICK_SIGNATURE_BLOCK: u64 = 64 * 1024;
+
+/// Represents a file or directory entry in the file tree.
+///
+/// Every path is stored as an absolute path without `.` components, so a tree can be
+/// queried with relative or absolute paths whatever the form of the root it was built from.
+///
+/// The entries are kept sorted by path, so every iteration over the tree yields them in the
+/// same order from one run to the next.
+#[derive(Debug, Serialize, Deserialize)]
 pub struct FileTree {
     root: PathBuf,
-    map: HashMap<PathBuf, EntryNode>,
-    signature: HashMap<PathBuf, String>
+    map: BTreeMap<PathBuf, EntryNode>,
+    signature: BTreeMap<PathBuf, String>,
+    /// The name of the hash function of the signatures, see `Hasher::name`.
+    hasher: Option<String>,
+    disk_usage: HashMap<PathBuf, Size>,
+    modified: HashMap<PathBuf, SystemTime>,
+    ownership: HashMap<PathBuf, Ownership>,
+    /// The device and inode of the files having several hard links.
+    links: HashMap<PathBuf, (u64, u64)>,
+    /// The hard links to a file already counted through another path, left out of directory sizes.
+    extra_links: HashSet<PathBuf>,
+    /// The apparent size and the disk usage of the content of the directories at the maximum depth
+    /// of the scan, whose children are not listed.
+    collapsed: HashMap<PathBuf, (Size, Size)>,
+    /// The size of the files of the archives listed as directories of their content.
+    archives: HashMap<PathBuf, Size>,
+    /// The apparent size and the disk usage of each directory, computed by `index_sizes`.
+    #[serde(skip)]
+    totals: HashMap<PathBuf, (Size, Size)>,
+    /// The canonical path of the root, resolved on first use by `canonical_root`.
+    #[serde(skip)]
+    canonical_root: OnceLock<Option<PathBuf>>,
+    /// The options of the scan, reused by `refresh`.
+    builder: FileTreeBuilder,
+    #[serde(skip)]
+    errors: Vec<(PathBuf, io::Error)>,
+    #[serde(skip)]
+    interrupted: bool,
+    #[serde(skip)]
+    timings: ScanTimings,
 }
 
 /// Represents the size of a file or directory in the file tree.
-
-#[derive(Clone, Debug)]
+///
+/// The children of a directory are sorted by path.
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum EntryNode {
+    /// A regular file and its size.
     File(Size),
+    /// A directory and its children.
     Directory(Vec<PathBuf>),
+    /// A symbolic link that was not followed, and its target.
+    Symlink(PathBuf),
+    /// A special file, counted as empty.
+    Other(FileKind),
 }
 
-/// Represents an iterator over the paths in the file tree.
+/// The type of a special file, neither a regular file, a directory nor a symbolic link.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FileKind {
+    /// A Unix domain socket.
+    Socket,
+    /// A named pipe.
+    Fifo,
+    /// A block device, such as a disk.
+    BlockDevice,
+    /// A character device, such as a terminal.
+    CharDevice,
+    /// Any other type of file.
+    Unknown,
+}
+
+impl FileKind {
+
+    /// Returns the type of the special file of `metadata`.
+    pub(crate) fn of(metadata: &fs::Metadata) -> Self {
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::FileTypeExt;
+            let file_type = metadata.file_type();
+            if file_type.is_socket() {
+                return FileKind::Socket;
+            } else if file_type.is_fifo() {
+                return FileKind::Fifo;
+            } else if file_type.is_block_device() {
+                return FileKind::BlockDevice;
+            } else if file_type.is_char_device() {
+                return FileKind::CharDevice;
+            }
+        }
+        #[cfg(not(unix))]
+        let _ = metadata;
+        FileKind::Unknown
+    }
 
-#[derive(Debug)] 
+    /// Returns the name of the type, e.g. `fifo`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            FileKind::Socket => "socket",
+            FileKind::Fifo => "fifo",
+            FileKind::BlockDevice => "block device",
+            FileKind::CharDevice => "char device",
+            FileKind::Unknown => "unknown",
+        }
+    }
+}
+
+impl fmt::Display for FileKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// The number of files and directories found below a directory, recursively.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct EntryCounts {
+    /// The number of regular files.
+    pub files: u64,
+    /// The number of directories, not counting the directory itself.
+    pub directories: u64,
+}
+
+impl fmt::Display for EntryCounts {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let plural = |count: u64| if count == 1 { "" } else { "s" };
+        write!(f, "{} file{}, {} dir{}", self.files, plural(self.files), self.directories, plural(self.directories))
+    }
+}
+
+/// Represents an iterator over the paths in the file tree, in path order.
+#[derive(Debug)]
 pub struct FileTreeIterator<'a> {
     iter: Iter<'a, PathBuf, EntryNode>,
 }
 
 /// Implementation of the iterator for `FileTreeIterator`
-
 impl<'a> Iterator for FileTreeIterator<'a> {
     type Item = &'a PathBuf;
 
@@ -43,7 +170,163 @@ impl<'a> Iterator for FileTreeIterator<'a> {
     }
 }
 
-/// Creates a new `FileTree` instance rooted at the specified path.
+/// An iterator over the entries of the file tree in tree order, from the root, returned by
+/// `FileTree::iter_dfs` and `FileTree::iter_bfs`.
+///
+/// Yields the depth of each entry, the root being at depth 0, its path and its node. The children
+/// of a directory are visited in path order.
+#[derive(Debug)]
+pub struct TreeWalk<'a> {
+    file_tree: &'a FileTree,
+    /// The entries left to visit, the next one first.
+    pending: VecDeque<(usize, &'a PathBuf)>,
+    depth_first: bool,
+}
+
+impl<'a> Iterator for TreeWalk<'a> {
+    type Item = (usize, &'a Path, &'a EntryNode);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (depth, path) = self.pending.pop_front()?;
+            let Some(node) = self.file_tree.map.get(path) else { continue };
+            if let EntryNode::Directory(children) = node {
+                let children = children.iter().map(|child| (depth + 1, child));
+                if self.depth_first {
+                    for child in children.rev() {
+                        self.pending.push_front(child);
+                    }
+                } else {
+                    self.pending.extend(children);
+                }
+            }
+            return Some((depth, path, node));
+        }
+    }
+}
+
+/// Progress of a scan, reported to the callback given to `FileTree::new_with_progress`
+/// or `FileTree::compute_signatures_with_progress`.
+#[derive(Clone, Debug, Default)]
+pub struct ScanProgress {
+    /// Number of files and directories scanned, or of files hashed, so far.
+    pub entries_scanned: u64,
+    /// Number of bytes read to compute the signatures so far.
+    pub bytes_hashed: u64,
+    /// The path of the last entry scanned.
+    pub current_path: PathBuf,
+}
+
+/// The time spent by a scan, see `FileTree::scan_timings`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ScanTimings {
+    /// The wall-clock time of the whole scan.
+    pub walk: Duration,
+    /// The time spent reading the metadata of the entries, added up over the threads of the
+    /// scan: it may exceed `walk` when several threads scan at once.
+    pub stat: Duration,
+}
+
+/// Keeps track of the progress of a scan and reports it to a callback.
+struct ProgressReporter<'a> {
+    progress: ScanProgress,
+    callback: &'a mut (dyn FnMut(&ScanProgress) + Send),
+    /// The entry on which the scan or the hashing failed.
+    failed: Option<PathBuf>,
+}
+
+impl ProgressReporter<'_> {
+    /// Reports that the entry at `path` has been scanned, after reading `bytes_hashed` bytes.
+    ///
+    /// Nothing is reported once the scan has failed.
+    fn entry_scanned(&mut self, path: &Path, bytes_hashed: u64) {
+        if self.failed.is_some() {
+            return;
+        }
+        self.progress.entries_scanned += 1;
+        self.progress.bytes_hashed += bytes_hashed;
+        self.progress.current_path.as_mut_os_string().clear();
+        self.progress.current_path.push(path);
+        (self.callback)(&self.progress);
+    }
+}
+
+/// Options controlling how the file system is explored, set through `FileTreeBuilder`.
+#[derive(Debug)]
+pub(crate) struct ScanOptions {
+    pub(crate) follow_symlinks: bool,
+    pub(crate) gitignore: bool,
+    pub(crate) hidden: bool,
+    pub(crate) count_links: bool,
+    pub(crate) strict: bool,
+    pub(crate) one_file_system: bool,
+    pub(crate) max_depth: Option<usize>,
+    pub(crate) inspect_archives: bool,
+    pub(crate) excludes: GlobSet,
+    pub(crate) interrupt: Option<Arc<AtomicBool>>,
+}
+
+/// The entries found by a thread of the scan, merged into the `FileTree` at the end.
+#[derive(Default)]
+struct ScannedEntries {
+    map: BTreeMap<PathBuf, EntryNode>,
+    signature: BTreeMap<PathBuf, String>,
+    disk_usage: HashMap<PathBuf, Size>,
+    modified: HashMap<PathBuf, SystemTime>,
+    ownership: HashMap<PathBuf, Ownership>,
+    /// The device and inode of the files having several hard links.
+    links: HashMap<PathBuf, (u64, u64)>,
+    collapsed: HashMap<PathBuf, (Size, Size)>,
+    archives: HashMap<PathBuf, Size>,
+    errors: Vec<(PathBuf, io::Error)>,
+}
+
+impl ScannedEntries {
+    /// Merges the entries found by two threads, moving the smallest maps into the largest.
+    fn merge(mut self, mut other: ScannedEntries) -> ScannedEntries {
+        if self.map.len() < other.map.len() {
+            std::mem::swap(&mut self, &mut other);
+        }
+        self.map.extend(other.map);
+        self.signature.extend(other.signature);
+        self.disk_usage.extend(other.disk_usage);
+        self.modified.extend(other.modified);
+        self.ownership.extend(other.ownership);
+        self.links.extend(other.links);
+        self.collapsed.extend(other.collapsed);
+        self.archives.extend(other.archives);
+        self.errors.extend(other.errors);
+        self
+    }
+}
+
+/// The state a directory inherits from the directories containing it.
+#[derive(Clone, Default)]
+pub(crate) struct Inherited {
+    /// The canonical paths of the directories being explored, when following links.
+    pub(crate) ancestors: Vec<PathBuf>,
+    /// The `.gitignore` files of the directories being explored, the deepest last.
+    pub(crate) gitignores: Vec<Arc<Gitignore>>,
+    /// The depth of the entries below the root, the root being at depth 0.
+    pub(crate) depth: usize,
+    /// `true` below a directory at the maximum depth, whose content is only added up.
+    pub(crate) collapsed: bool,
+}
+
+/// The state of a scan shared by the threads exploring the file system.
+pub(crate) struct Explorer<'a> {
+    root: &'a Path,
+    /// The device of the root, when the scan stays on its file system.
+    device: Option<u64>,
+    pub(crate) options: &'a ScanOptions,
+    reporter: Mutex<ProgressReporter<'a>>,
+    /// The time spent reading the metadata of the entries, in nanoseconds, added up over the threads.
+    stat_nanos: AtomicU64,
+}
+
+impl FileTree {
+
+/// Creates a new `FileTree` instance rooted at the specified path, with the default options of `FileTreeBuilder`.
 ///
 /// # Arguments
 ///
@@ -51,132 +334,715 @@ impl<'a> Iterator for FileTreeIterator<'a> {
 ///
 /// # Returns
 ///
-/// A `Result` containing the `FileTree` instance or an `std::io::Error`.
+/// A `Result` containing the `FileTree` instance, or an `Error` naming the path that could not be read.
+///
+/// # Example
+///
+/// ```
+/// use du::FileTree;
+///
+/// # let dir = tempfile::tempdir()?;
+/// # std::fs::write(dir.path().join("notes.txt"), b"hello")?;
+/// let file_tree = FileTree::new(dir.path())?;
+/// assert_eq!(file_tree.get_root(), std::path::absolute(dir.path())?);
+/// # Ok::<(), std::io::Error>(())
+/// ```
+    pub fn new(root: &Path) -> Result<Self> {
+        FileTreeBuilder::new(root).build()
+    }
 
-impl FileTree {
-    pub fn new(root: &Path) -> std::io::Result<Self> {
-        let mut map = HashMap::new();
-        let mut signature= HashMap::new();
-        let root_entry = FileTree::file_explorer(root, &mut map, &mut signature)?;
-        map.insert(root.to_path_buf(), root_entry.clone());
-        Ok(FileTree { root: root.to_path_buf(), map, signature })
-        
+/// Returns a `FileTreeBuilder` configuring the scan of the file tree rooted at `root`.
+///
+/// # Example
+///
+/// ```
+/// use du::FileTree;
+///
+/// # let dir = tempfile::tempdir()?;
+/// let file_tree = FileTree::builder(dir.path()).follow_symlinks(true).build()?;
+/// assert_eq!(file_tree.files().count(), 0);
+/// # Ok::<(), std::io::Error>(())
+/// ```
+    pub fn builder(root: impl AsRef<Path>) -> FileTreeBuilder {
+        FileTreeBuilder::new(root)
     }
 
-    
-/// Recursively explores a directory and builds the corresponding file tree structure.
+/// Creates a new `FileTree` like `new`, reporting the progress of the scan to `callback`.
 ///
-///# Arguments
+/// The callback is invoked once per file or directory scanned and is never invoked
+/// again once an error has occurred. As sibling directories are scanned in parallel,
+/// it may be invoked from any thread, but never concurrently.
 ///
-/// * `path` - The path to explore.
-/// * `map` - The map to store file tree entries.
-/// * `signatures` - The map to store file signatures.
+/// # Arguments
+///
+/// * `root` - The root path for the file tree.
+/// * `callback` - The function receiving the progress of the scan.
+///
+/// # Example
+///
+/// ```
+/// use du::FileTree;
+///
+/// # let dir = tempfile::tempdir()?;
+/// # std::fs::write(dir.path().join("notes.txt"), b"hello")?;
+/// let mut scanned = 0;
+/// FileTree::new_with_progress(dir.path(), |progress| scanned = progress.entries_scanned)?;
+/// assert_eq!(scanned, 2);
+/// # Ok::<(), std::io::Error>(())
+/// ```
+    pub fn new_with_progress(root: &Path, callback: impl FnMut(&ScanProgress) + Send) -> Result<Self> {
+        FileTreeBuilder::new(root).build_with_progress(callback)
+    }
+
+/// Builds the `FileTree` configured by `builder`.
+    pub(crate) fn scan(builder: &FileTreeBuilder, callback: &mut (dyn FnMut(&ScanProgress) + Send)) -> Result<Self> {
+        let start = Instant::now();
+        let options = builder.scan_options()?;
+        let root = std::path::absolute(builder.root()).map_err(|err| Error::io(builder.root(), err))?;
+        let metadata = fs::metadata(&root).map_err(|err| Error::io(&root, err))?;
+        let explorer = Explorer::new(&root, &options, callback);
+        let mut entries = ScannedEntries::default();
+        explorer.file_explorer(&root, &metadata, &Inherited::default(), &mut entries).map_err(|err| explorer.error(err))?;
+        entries.errors.sort_by(|(a, _), (b, _)| a.cmp(b));
+        let mut file_tree = FileTree {
+            root: root.clone(),
+            map: entries.map,
+            signature: entries.signature,
+            hasher: None,
+            disk_usage: entries.disk_usage,
+            modified: entries.modified,
+            ownership: entries.ownership,
+            links: entries.links,
+            extra_links: HashSet::new(),
+            collapsed: entries.collapsed,
+            archives: entries.archives,
+            totals: HashMap::new(),
+            canonical_root: OnceLock::new(),
+            builder: builder.clone(),
+            errors: entries.errors,
+            interrupted: explorer.is_interrupted(),
+            timings: ScanTimings::default(),
+        };
+        file_tree.index_links(options.count_links);
+        file_tree.index_sizes();
+        file_tree.timings = ScanTimings { walk: start.elapsed(), stat: Duration::from_nanos(explorer.stat_nanos.load(Ordering::Relaxed)) };
+        Ok(file_tree)
+    }
+
+/// Builds a `FileTree` from entries listed otherwise than by a scan, e.g. read from the stream of a
+/// remote scan, `disk_usage` holding the space used on disk by each entry itself.
+    pub(crate) fn from_entries(root: PathBuf, map: BTreeMap<PathBuf, EntryNode>, disk_usage: HashMap<PathBuf, Size>) -> Self {
+        let mut file_tree = FileTree {
+            builder: FileTreeBuilder::new(&root),
+            root,
+            map,
+            signature: BTreeMap::new(),
+            hasher: None,
+            disk_usage,
+            modified: HashMap::new(),
+            ownership: HashMap::new(),
+            links: HashMap::new(),
+            extra_links: HashSet::new(),
+            collapsed: HashMap::new(),
+            archives: HashMap::new(),
+            totals: HashMap::new(),
+            canonical_root: OnceLock::new(),
+            errors: Vec::new(),
+            interrupted: false,
+            timings: ScanTimings::default(),
+        };
+        file_tree.index_sizes();
+        file_tree
+    }
+
+/// Lists the hard links left out of the directory sizes, each physical file being counted once
+/// through its first path, unless `count_links` is set.
+    fn index_links(&mut self, count_links: bool) {
+        self.extra_links.clear();
+        if count_links {
+            return;
+        }
+        let mut paths_by_inode: HashMap<(u64, u64), Vec<&PathBuf>> = HashMap::new();
+        for (path, inode) in &self.links {
+            paths_by_inode.entry(*inode).or_default().push(path);
+        }
+        for mut paths in paths_by_inode.into_values() {
+            paths.sort();
+            self.extra_links.extend(paths.into_iter().skip(1).cloned());
+        }
+    }
+
+/// Computes the size and the disk usage of every directory in a single pass, so that they are
+/// not computed again on each call of `get_size` or `get_disk_usage`.
+    pub(crate) fn index_sizes(&mut self) {
+        let mut totals = HashMap::new();
+        // Un chemin est rangé après celui de son dossier : les enfants sont comptés avant leur parent
+        for (path, entry) in self.map.iter().rev() {
+            let EntryNode::Directory(children) = entry else { continue };
+            let (mut size, mut disk_usage) = self.collapsed.get(path).copied().unwrap_or_default();
+            disk_usage = disk_usage + self.disk_usage.get(path).copied().unwrap_or(Size::new(0));
+            for child in children.iter().filter(|child| !self.extra_links.contains(*child)) {
+                let child_disk_usage = self.disk_usage.get(child).copied().unwrap_or(Size::new(0));
+                let (child_size, child_disk_usage) = match self.map.get(child) {
+                    Some(EntryNode::File(child_size)) => (*child_size, child_disk_usage),
+                    Some(EntryNode::Directory(_)) => totals.get(child).copied().unwrap_or_default(),
+                    Some(EntryNode::Symlink(_) | EntryNode::Other(_)) => (Size::new(0), child_disk_usage),
+                    None => continue,
+                };
+                size = size + child_size;
+                disk_usage = disk_usage + child_disk_usage;
+            }
+            totals.insert(path.clone(), (size, disk_usage));
+        }
+        self.totals = totals;
+    }
+
+/// Updates the file tree with the changes made to the file system since it was scanned,
+/// with the options of the original scan.
+///
+/// A directory whose modification time has not changed keeps its children, which are refreshed
+/// in turn: only the files whose size or modification time changed are read again. A directory
+/// whose modification time changed, because entries were added, removed or renamed, is scanned
+/// again entirely. The signatures of the files left unchanged are kept, the others are dropped
+/// until `compute_signatures` is called again.
+///
+/// `errors` is replaced by the errors met during the refresh.
+///
+/// # Example
+///
+/// ```
+/// use du::FileTree;
+///
+/// # let dir = tempfile::tempdir()?;
+/// let mut file_tree = FileTree::new(dir.path())?;
+/// std::fs::write(dir.path().join("notes.txt"), b"hello")?;
+/// # let past = std::time::SystemTime::now() - std::time::Duration::from_secs(60);
+/// # std::fs::File::open(dir.path())?.set_modified(past)?;
+/// file_tree.refresh()?;
+/// assert_eq!(file_tree.get_size(dir.path()).unwrap().value(), 5);
+/// # Ok::<(), std::io::Error>(())
+/// ```
 ///
 /// # Returns
 ///
-/// An `io::Result` containing the `EntryNode` for the specified path.
+/// An empty `Result`, failing if the root can't be read, or on the first unreadable entry if
+/// the scan is strict.
+    pub fn refresh(&mut self) -> Result<()> {
+        let root = self.root.clone();
+        self.refresh_directories(&[root])
+    }
+
+/// Updates the file tree with the changes made to the given paths, like `refresh` but only
+/// checking the directories containing them, e.g. the paths reported by a file system watcher.
+///
+/// The nearest directory of the tree containing each path, or the path itself if it is such a
+/// directory, is refreshed. The paths outside the tree are ignored, and only the errors met in
+/// the refreshed directories are replaced.
+///
+/// # Arguments
+///
+/// * `paths` - The paths created, removed or modified.
 ///
+/// # Returns
+///
+/// An empty `Result`, failing if the root can't be read, or on the first unreadable entry if
+/// the scan is strict.
+    pub fn refresh_paths(&mut self, paths: &[PathBuf]) -> Result<()> {
+        let mut directories: Vec<PathBuf> = paths.iter().filter_map(|path| self.containing_directory(path)).collect();
+        directories.sort();
+        directories.dedup();
+        // Un dossier contenu dans un autre dossier à rafraîchir l'est déjà avec lui
+        let mut refreshed: Vec<PathBuf> = Vec::new();
+        for directory in directories {
+            if !refreshed.iter().any(|parent| directory.starts_with(parent)) {
+                refreshed.push(directory);
+            }
+        }
+        self.refresh_directories(&refreshed)
+    }
 
+/// Returns the nearest directory of the tree which contains `path` or is `path`, and still exists.
+    fn containing_directory(&self, path: &Path) -> Option<PathBuf> {
+        let path = Self::normalize(path);
+        path.ancestors()
+            .take_while(|ancestor| ancestor.starts_with(&self.root))
+            .find(|ancestor| matches!(self.map.get(*ancestor), Some(EntryNode::Directory(_))) && fs::metadata(ancestor).is_ok_and(|metadata| metadata.is_dir()))
+            .map(Path::to_path_buf)
+    }
 
-    fn file_explorer(path: &Path, map: &mut HashMap<PathBuf, EntryNode>, signatures: &mut HashMap<PathBuf, String>) -> std::io::Result<EntryNode> {
-        let metadata = fs::metadata(path)?;
+/// Refreshes the given directories of the tree, none of which contains another.
+    fn refresh_directories(&mut self, directories: &[PathBuf]) -> Result<()> {
+        let options = self.builder.scan_options()?;
+        let root = self.root.clone();
+        let mut callback = |_: &ScanProgress| {};
+        let explorer = Explorer::new(&root, &options, &mut callback);
+        let mut entries = ScannedEntries::default();
+        let mut stale = Vec::new();
+        for directory in directories {
+            // L'état hérité (gitignore, dossiers parents) est reconstruit depuis la racine
+            let mut inherited = Inherited::default();
+            let ancestors: Vec<_> = directory.ancestors().skip(1).take_while(|ancestor| ancestor.starts_with(&root)).collect();
+            for ancestor in ancestors.into_iter().rev() {
+                inherited = explorer.inherited_by(ancestor, &inherited).map_err(|err| Error::io(ancestor, err))?;
+            }
+            let metadata = fs::metadata(directory).map_err(|err| Error::io(directory, err))?;
+            self.refresh_directory(&explorer, directory, &metadata, &inherited, &mut entries, &mut stale).map_err(|err| explorer.error(err))?;
+        }
 
-        if metadata.is_file() {
+        // Les fichiers inchangés gardent leur signature
+        for (path, entry) in &entries.map {
+            let (EntryNode::File(size), Some(EntryNode::File(old_size))) = (entry, self.map.get(path)) else { continue };
+            if size == old_size && entries.modified.get(path) == self.modified.get(path) {
+                if let Some(signature) = self.signature.get(path) {
+                    entries.signature.insert(path.clone(), signature.clone());
+                }
+            }
+        }
+        for path in stale {
+            self.remove_subtree(&path);
+        }
+        self.map.extend(entries.map);
+        self.signature.extend(entries.signature);
+        self.disk_usage.extend(entries.disk_usage);
+        self.modified.extend(entries.modified);
+        self.ownership.extend(entries.ownership);
+        self.links.extend(entries.links);
+        self.collapsed.extend(entries.collapsed);
+        self.archives.extend(entries.archives);
+        self.errors.retain(|(path, _)| !directories.iter().any(|directory| path.starts_with(directory)));
+        self.errors.extend(entries.errors);
+        self.errors.sort_by(|(a, _), (b, _)| a.cmp(b));
+        self.index_links(options.count_links);
+        self.index_sizes();
+        Ok(())
+    }
 
-            let signature = Self::calculate_signature(path)?;
-            map.insert(path.to_path_buf(), EntryNode::File(Size::new(metadata.len())));
-            signatures.insert(path.to_path_buf(), signature);
-            Ok(EntryNode::File(Size::new(metadata.len())))
+/// Refreshes the directory at `path`, whose current metadata is `metadata`: the entries scanned again
+/// are inserted in `entries`, and the roots of the outdated subtrees they replace in `stale`.
+    fn refresh_directory(&self, explorer: &Explorer, path: &Path, metadata: &fs::Metadata, inherited: &Inherited, entries: &mut ScannedEntries, stale: &mut Vec<PathBuf>) -> io::Result<()> {
+        let Some(EntryNode::Directory(children)) = self.map.get(path) else {
+            stale.push(path.to_path_buf());
+            return explorer.file_explorer(path, metadata, inherited, entries);
+        };
+        // Le contenu d'un dossier replié n'est pas connu : il est toujours exploré à nouveau
+        if !metadata.is_dir() || self.modified.get(path) != metadata.modified().ok().as_ref() || self.collapsed.contains_key(path) {
+            stale.push(path.to_path_buf());
+            return explorer.file_explorer(path, metadata, inherited, entries);
+        }
 
-        } else if metadata.is_dir() {
-            let mut children = Vec::new();
-            for entry in fs::read_dir(path)? {
-                let entry_path = entry?.path().clone();
-                let entry_node = FileTree::file_explorer(&entry_path, map, signatures)?;
-                map.insert(entry_path.clone(), entry_node.clone());
-                children.push(entry_path);
+        let child_inherited = explorer.inherited_by(path, inherited)?;
+        for child in children {
+            let current = fs::symlink_metadata(child).and_then(|child_metadata| {
+                if child_metadata.file_type().is_symlink() {
+                    Ok(explorer.followed_metadata(child, &child_inherited.ancestors)?.map_or((child_metadata, false), |target| (target, true)))
+                } else {
+                    Ok((child_metadata, true))
+                }
+            });
+            let unchanged = match (self.map.get(child), &current) {
+                (Some(EntryNode::Directory(_)), Ok((child_metadata, true))) if child_metadata.is_dir() => {
+                    self.refresh_directory(explorer, child, child_metadata, &child_inherited, entries, stale)?;
+                    true
+                }
+                (Some(EntryNode::Directory(_)), Ok((child_metadata, true))) if child_metadata.is_file() && self.archives.contains_key(child) => {
+                    let unchanged = self.archives[child].value() == child_metadata.len() && self.modified.get(child.as_path()) == child_metadata.modified().ok().as_ref();
+                    if !unchanged {
+                        stale.push(child.clone());
+                        explorer.file_explorer(child, child_metadata, &child_inherited, entries)?;
+                    }
+                    true
+                }
+                (Some(EntryNode::File(size)), Ok((child_metadata, true))) if child_metadata.is_file() => {
+                    let unchanged = size.value() == child_metadata.len() && self.modified.get(child.as_path()) == child_metadata.modified().ok().as_ref();
+                    if !unchanged {
+                        stale.push(child.clone());
+                        explorer.file_explorer(child, child_metadata, &child_inherited, entries)?;
+                    }
+                    true
+                }
+                (Some(EntryNode::Symlink(_)), Ok((child_metadata, false))) => self.modified.get(child.as_path()) == child_metadata.modified().ok().as_ref(),
+                (Some(EntryNode::Other(kind)), Ok((child_metadata, true))) if !child_metadata.is_file() && !child_metadata.is_dir() => FileKind::of(child_metadata) == *kind,
+                _ => false,
+            };
+            // Une entrée dont le type a changé fait explorer à nouveau tout le dossier
+            if !unchanged {
+                stale.push(path.to_path_buf());
+                return explorer.file_explorer(path, metadata, inherited, entries);
+            }
+        }
+        Ok(())
+    }
+
+/// Removes the entry at `path` and all its descendants from the file tree.
+    fn remove_subtree(&mut self, path: &Path) {
+        if let Some(EntryNode::Directory(children)) = self.map.remove(path) {
+            for child in children {
+                self.remove_subtree(&child);
             }
-            Ok(EntryNode::Directory(children))
+        }
+        self.signature.remove(path);
+        self.disk_usage.remove(path);
+        self.modified.remove(path);
+        self.ownership.remove(path);
+        self.links.remove(path);
+        self.collapsed.remove(path);
+        self.archives.remove(path);
+    }
 
+/// Returns the key under which `path` is stored in the maps: an absolute path without `.` components.
+    fn normalize(path: &Path) -> Cow<'_, Path> {
+        if path.is_absolute() && !path.components().any(|component| component == Component::CurDir) {
+            Cow::Borrowed(path)
         } else {
-            Err(std::io::Error::new(
-                std::io::ErrorKind::Other,
-                "Type de fichier non pris en charge",
-            ))
+            std::path::absolute(path).map(Cow::Owned).unwrap_or(Cow::Borrowed(path))
         }
     }
 
-/// Calculates the MD5 signature of a file.
+/// Returns the space allocated on disk for the entry at `path`, from its number of 512-byte blocks.
+///
+/// On Windows, this is the compressed size of the files, which is also the allocated size of
+/// sparse files. On other platforms without block information, the apparent size is returned.
+    #[cfg(unix)]
+    pub(crate) fn allocated_size(_path: &Path, metadata: &fs::Metadata) -> Size {
+        use std::os::unix::fs::MetadataExt;
+        Size::new(metadata.blocks() * 512)
+    }
+
+    #[cfg(windows)]
+    pub(crate) fn allocated_size(path: &Path, metadata: &fs::Metadata) -> Size {
+        use std::os::windows::ffi::OsStrExt;
+        use windows_sys::Win32::Foundation::{GetLastError, NO_ERROR};
+        use windows_sys::Win32::Storage::FileSystem::{GetCompressedFileSizeW, INVALID_FILE_SIZE};
+
+        if !metadata.is_file() {
+            return Size::new(0);
+        }
+        let wide_path: Vec<u16> = crate::long_path::extended(path).as_os_str().encode_wide().chain(Some(0)).collect();
+        let mut high = 0;
+        // SAFETY: `wide_path` is a NUL-terminated UTF-16 string and `high` outlives the call.
+        let low = unsafe { GetCompressedFileSizeW(wide_path.as_ptr(), &mut high) };
+        // SAFETY: GetLastError has no precondition.
+        if low == INVALID_FILE_SIZE && unsafe { GetLastError() } != NO_ERROR {
+            return Size::new(metadata.len());
+        }
+        Size::new((u64::from(high) << 32) | u64::from(low))
+    }
+
+    #[cfg(not(any(unix, windows)))]
+    pub(crate) fn allocated_size(_path: &Path, metadata: &fs::Metadata) -> Size {
+        Size::new(metadata.len())
+    }
+
+/// Calculates the signature of a file.
 ///
 /// # Arguments
 ///
 /// * `path` - The path of the file.
+/// * `hasher` - The hash function to use.
 ///
 /// # Returns
 ///
-/// An `io::Result` containing the MD5 signature as a hexadecimal string.
+/// An `io::Result` containing the signature of the content of the file.
+    pub(crate) fn calculate_signature(path: &std::path::Path, hasher: &dyn Hasher) -> io::Result<String> {
+        hasher.hash(&mut File::open(path)?)
+    }
 
-    fn calculate_signature(path: &std::path::Path) -> io::Result<String> {
+/// Calculates the quick signature of a large file: the hash of its first and last blocks only.
+    fn calculate_quick_signature(path: &Path, hasher: &dyn Hasher) -> io::Result<String> {
         let mut file = File::open(path)?;
-        let mut hasher = Md5::new();
-    
-        let mut buffer = [0; 8192];
-    
-        loop {
-            let bytes_read = file.read(&mut buffer)?;
-    
-            if bytes_read == 0 {
-                break;
+        let mut content = Vec::with_capacity(2 * QUICK_SIGNATURE_BLOCK as usize);
+        file.by_ref().take(QUICK_SIGNATURE_BLOCK).read_to_end(&mut content)?;
+        file.seek(SeekFrom::End(-(QUICK_SIGNATURE_BLOCK as i64)))?;
+        file.take(QUICK_SIGNATURE_BLOCK).read_to_end(&mut content)?;
+        hasher.hash(&mut content.as_slice())
+    }
+
+/// Computes the BLAKE3 signature of the files of the tree that may have a duplicate.
+///
+/// Scanning doesn't read the content of the files, so this must be called before
+/// `find_duplicates`. Signatures loaded from a cache are kept.
+///
+/// # Returns
+///
+/// An empty `Result`, failing with a `Hash` error if a file could not be read.
+    pub fn compute_signatures(&mut self) -> Result<()> {
+        self.compute_signatures_with_progress(&Blake3Hasher, |_| {})
+    }
+
+/// Computes the signatures like `compute_signatures` with the given hash function,
+/// reporting the progress to `callback`.
+///
+/// The signatures computed by another hash function, e.g. loaded from a cache, are dropped
+/// and computed again.
+///
+/// Files are first grouped by size: a file whose size is unique can't have a duplicate
+/// and is not read at all. Files of 16 MiB or more are then compared by the hash of their
+/// first and last 64 KiB, and only those whose quick signatures collide are read entirely.
+/// The other files are hashed in parallel, by the threads of the current rayon pool: hashing
+/// within `rayon::ThreadPool::install` bounds the number of files read at once. The callback is invoked
+/// once per file hashed, never concurrently, and is never invoked again once an error has occurred.
+///
+/// # Arguments
+///
+/// * `hasher` - The hash function to use.
+/// * `callback` - The function receiving the progress of the hashing.
+    pub fn compute_signatures_with_progress(&mut self, hasher: &dyn Hasher, callback: impl FnMut(&ScanProgress) + Send) -> Result<()> {
+        self.compute_signatures_between(hasher, Size::new(0), None, callback)
+    }
+
+/// Computes the signatures like `compute_signatures_with_progress`, of the files of at least
+/// `min_size` bytes and at most `max_size` bytes only, so that the files too small or too large
+/// to be reported as duplicates are not read.
+///
+/// # Arguments
+///
+/// * `hasher` - The hash function to use.
+/// * `min_size` - The minimum size of the files to hash.
+/// * `max_size` - The maximum size of the files to hash, if any.
+/// * `callback` - The function receiving the progress of the hashing.
+    pub fn compute_signatures_between(&mut self, hasher: &dyn Hasher, min_size: Size, max_size: Option<Size>, callback: impl FnMut(&ScanProgress) + Send) -> Result<()> {
+        Self::compute_signatures_across(&mut [self], hasher, min_size, max_size, callback)
+    }
+
+/// Computes the signatures like `compute_signatures_between` in several trees at once, a file
+/// being hashed when a file of the same size exists in any of the trees.
+    pub(crate) fn compute_signatures_across(trees: &mut [&mut FileTree], hasher: &dyn Hasher, min_size: Size, max_size: Option<Size>, mut callback: impl FnMut(&ScanProgress) + Send) -> Result<()> {
+        for tree in trees.iter_mut() {
+            if tree.hasher.as_deref() != Some(hasher.name()) {
+                tree.signature.clear();
+                tree.hasher = Some(hasher.name().to_string());
             }
-    
-            hasher.input(&buffer[..bytes_read]);
         }
-    
-        let hex_hash = encode(hasher.result_str().as_bytes());
-    
-        Ok(hex_hash)
+        let mut files_per_size: HashMap<Size, usize> = HashMap::new();
+        let mut hashed_sizes = HashSet::new();
+        for tree in trees.iter() {
+            for (path, entry) in &tree.map {
+                if let EntryNode::File(size) = entry {
+                    *files_per_size.entry(*size).or_default() += 1;
+                    if tree.signature.contains_key(path) {
+                        hashed_sizes.insert(*size);
+                    }
+                }
+            }
+        }
+        // Chaque candidat est repéré par l'indice de son arbre et son chemin
+        let mut candidates: Vec<(usize, &PathBuf, &Size)> = trees
+            .iter()
+            .enumerate()
+            .flat_map(|(index, tree)| tree.map.iter().map(move |(path, entry)| (index, &tree.signature, path, entry)))
+            .filter_map(|(index, signature, path, entry)| match entry {
+                EntryNode::File(size) if files_per_size[size] > 1 && !signature.contains_key(path) && *size >= min_size && max_size.is_none_or(|max_size| *size <= max_size) => Some((index, path, size)),
+                _ => None,
+            })
+            .collect();
+        // Les membres des archives sont hachés à part, chaque archive n'étant lue qu'une fois
+        let mut members: BTreeMap<(usize, &Path), Vec<(&PathBuf, &Size)>> = BTreeMap::new();
+        candidates.retain(|(index, path, size)| match trees[*index].archive_of(path) {
+            Some(archive) => {
+                members.entry((*index, archive)).or_default().push((path, size));
+                false
+            }
+            None => true,
+        });
+
+        // Un gros fichier dont le début et la fin sont uniques n'a pas de doublon, sauf parmi
+        // les fichiers de même taille déjà hachés dont la signature rapide est inconnue
+        let quick_signatures = candidates
+            .par_iter()
+            .filter(|(_, _, size)| size.value() >= QUICK_SIGNATURE_MIN_SIZE && !hashed_sizes.contains(*size))
+            .map(|(index, path, size)| {
+                let quick_signature = Self::calculate_quick_signature(path, hasher).map_err(|source| Error::Hash { path: path.to_path_buf(), source })?;
+                Ok(((*index, *path), (**size, quick_signature)))
+            })
+            .collect::<Result<HashMap<_, _>>>()?;
+        let mut files_per_quick_signature: HashMap<&(Size, String), usize> = HashMap::new();
+        for quick_signature in quick_signatures.values() {
+            *files_per_quick_signature.entry(quick_signature).or_default() += 1;
+        }
+        candidates.retain(|(index, path, _)| quick_signatures.get(&(*index, *path)).is_none_or(|quick_signature| files_per_quick_signature[quick_signature] > 1));
+
+        let reporter = Mutex::new(ProgressReporter { progress: ScanProgress::default(), callback: &mut callback, failed: None });
+        let signatures = candidates
+            .into_par_iter()
+            .map(|(index, path, size)| {
+                let signature = Self::calculate_signature(path, hasher);
+                let mut reporter = reporter.lock().unwrap();
+                match signature {
+                    Ok(signature) => {
+                        reporter.entry_scanned(path, size.value());
+                        Ok((index, path.clone(), signature))
+                    }
+                    Err(source) => {
+                        reporter.failed = Some(path.clone());
+                        Err(Error::Hash { path: path.clone(), source })
+                    }
+                }
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let archived_signatures = members
+            .into_par_iter()
+            .map(|((index, archive), members)| {
+                let Some(format) = ArchiveFormat::of(archive) else { return Ok(Vec::new()) };
+                let relative: HashSet<PathBuf> = members.iter().filter_map(|(path, _)| path.strip_prefix(archive).ok()).map(Path::to_path_buf).collect();
+                let signatures = archive::hash_members(archive, format, &relative, hasher);
+                let mut reporter = reporter.lock().unwrap();
+                match signatures {
+                    Ok(signatures) => {
+                        for (path, size) in members {
+                            reporter.entry_scanned(path, size.value());
+                        }
+                        Ok(signatures.into_iter().map(|(member, signature)| (index, archive.join(member), signature)).collect())
+                    }
+                    Err(source) => {
+                        reporter.failed = Some(archive.to_path_buf());
+                        Err(Error::Hash { path: archive.to_path_buf(), source })
+                    }
+                }
+            })
+            .collect::<Result<Vec<Vec<_>>>>()?;
+        for (index, path, signature) in signatures.into_iter().chain(archived_signatures.into_iter().flatten()) {
+            trees[index].signature.insert(path, signature);
+        }
+        Ok(())
     }
 
 /// Finds and returns a map of duplicate files in the file tree based on their signatures.
 ///
+/// Only the files hashed by `compute_signatures` are considered.
+///
 /// # Returns
 ///
-/// A `HashMap` where each key is a signature and the corresponding value is a vector
-/// containing paths of files with that signature.
-/// 
-
-pub fn find_duplicates(&self) -> HashMap<String, Vec<PathBuf>> {
-    // Clone the signatures for parallel processing
-    let signatures: HashMap<_, _> = self.signature.clone();
-
-    // Create a parallel iterator over the cloned signatures
-    let signature_map: HashMap<String, Vec<PathBuf>> = signatures
-        .par_iter()
-        .map(|(path, signature)| (path.clone(), signature.clone()))
-        .fold(
-            || HashMap::new(),
-            |mut acc, (path, signature)| {
-                acc.entry(signature).or_insert_with(Vec::new).push(path);
-                acc
-            },
-        )
-        .reduce(HashMap::new, |mut acc1, acc2| {
-            for (key, mut value) in acc2 {
-                acc1.entry(key).or_insert_with(Vec::new).append(&mut value);
-            }
-            acc1
-        });
+/// A `BTreeMap` where each key is a signature and the corresponding value is a vector
+/// containing the sorted paths of files with that signature.
+///
+/// # Example
+///
+/// ```
+/// use du::FileTree;
+///
+/// # let dir = tempfile::tempdir()?;
+/// std::fs::write(dir.path().join("a.txt"), b"same content")?;
+/// std::fs::write(dir.path().join("b.txt"), b"same content")?;
+/// std::fs::write(dir.path().join("c.txt"), b"other content")?;
+///
+/// let mut file_tree = FileTree::new(dir.path())?;
+/// file_tree.compute_signatures()?;
+/// let duplicates = file_tree.find_duplicates();
+/// assert_eq!(duplicates.len(), 1);
+/// assert_eq!(duplicates.values().next().unwrap(), &[dir.path().join("a.txt"), dir.path().join("b.txt")]);
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub fn find_duplicates(&self) -> BTreeMap<String, Vec<PathBuf>> {
+    // Les signatures sont parcourues par chemin croissant : les chemins de chaque groupe sont triés
+    let mut signature_map: BTreeMap<String, Vec<PathBuf>> = BTreeMap::new();
+    for (path, signature) in &self.signature {
+        signature_map.entry(signature.clone()).or_default().push(path.clone());
+    }
 
     signature_map.into_iter().filter(|(_, paths)| paths.len() > 1).collect()
 }
 
 
 /// Returns the root path of the file tree.
-
     pub fn get_root(&self) -> &Path {
         &self.root
     }
 
+/// Returns the canonical path of the root, its symbolic links resolved, or `None` if it no longer exists.
+    pub(crate) fn canonical_root(&self) -> Option<&Path> {
+        self.canonical_root.get_or_init(|| fs::canonicalize(&self.root).ok()).as_deref()
+    }
+
+/// Returns the last modification time of an entry, if the platform provides it.
+    pub fn get_modified(&self, path: &Path) -> Option<SystemTime> {
+        self.modified.get(Self::normalize(path).as_ref()).copied()
+    }
+
+/// Returns the owner and the permissions of an entry, if they could be read.
+    pub fn get_ownership(&self, path: &Path) -> Option<&Ownership> {
+        self.ownership.get(Self::normalize(path).as_ref())
+    }
+
+/// Returns the signature of a file, if it has been computed by `compute_signatures`.
+    pub fn get_signature(&self, path: &Path) -> Option<&str> {
+        self.signature.get(Self::normalize(path).as_ref()).map(String::as_str)
+    }
+
+/// Returns the size of the file of an archive listed as a directory of its content, see
+/// `FileTreeBuilder::inspect_archives`, or `None` if `path` is not such an archive.
+///
+/// # Example
+///
+/// ```
+/// use du::{FileTree, Size};
+///
+/// # let dir = tempfile::tempdir()?;
+/// # let mut tar = tar::Builder::new(std::fs::File::create(dir.path().join("sources.tar"))?);
+/// # let mut header = tar::Header::new_gnu();
+/// # header.set_size(5);
+/// # tar.append_data(&mut header, "src/main.rs", &b"hello"[..])?;
+/// # tar.finish()?;
+/// # drop(tar);
+/// let archive = dir.path().join("sources.tar");
+/// let file_tree = FileTree::builder(dir.path()).inspect_archives(true).build()?;
+/// assert_eq!(file_tree.get_size(&archive.join("src/main.rs")).unwrap().value(), 5);
+/// assert_eq!(file_tree.get_archive_size(&archive), Some(Size::new(std::fs::metadata(&archive)?.len())));
+/// # Ok::<(), std::io::Error>(())
+/// ```
+    pub fn get_archive_size(&self, path: &Path) -> Option<Size> {
+        self.archives.get(Self::normalize(path).as_ref()).copied()
+    }
+
+/// Returns the device and inode of the file at `path`, if it has several hard links.
+    pub(crate) fn get_link(&self, path: &Path) -> Option<(u64, u64)> {
+        self.links.get(path).copied()
+    }
+
+/// Returns the archive containing the entry at `path`, listed as a directory of its content, if any.
+    pub(crate) fn archive_of(&self, path: &Path) -> Option<&Path> {
+        path.ancestors().skip(1).find_map(|ancestor| self.archives.get_key_value(ancestor)).map(|(archive, _)| archive.as_path())
+    }
+
+/// Returns the files whose signature has been computed, sorted by path, with their signature.
+    pub(crate) fn signatures(&self) -> impl Iterator<Item = (&PathBuf, &String)> {
+        self.signature.iter()
+    }
+
+/// Returns the entries that could not be read during the scan, sorted by path, with their error.
+///
+/// Unless the scan is strict, an unreadable directory is kept in the tree without children, and
+/// the other unreadable entries are left out. The errors are not saved in the cache.
+    pub fn errors(&self) -> &[(PathBuf, io::Error)] {
+        &self.errors
+    }
+
+/// Returns `true` if the scan was stopped by the flag set with `FileTreeBuilder::interrupt_on`:
+/// the tree then only holds the entries found before, and the sizes of the directories are partial.
+///
+/// # Example
+///
+/// ```
+/// use du::FileTreeBuilder;
+/// use std::sync::Arc;
+/// use std::sync::atomic::AtomicBool;
+///
+/// # let dir = tempfile::tempdir()?;
+/// std::fs::write(dir.path().join("notes.txt"), b"hello")?;
+/// let file_tree = FileTreeBuilder::new(dir.path()).interrupt_on(Arc::new(AtomicBool::new(true))).build()?;
+/// assert!(file_tree.is_interrupted());
+/// assert_eq!(file_tree.files().count(), 0);
+/// # Ok::<(), std::io::Error>(())
+/// ```
+    pub fn is_interrupted(&self) -> bool {
+        self.interrupted
+    }
+
+/// Returns the time spent by the scan that built the tree. The timings are zero for a tree
+/// loaded from a cache, and are not updated by `refresh`.
+    pub fn scan_timings(&self) -> ScanTimings {
+        self.timings
+    }
+
 /// Returns the children (sub-paths) of a directory in the file tree.
 ///
 /// # Arguments
@@ -185,22 +1051,35 @@ pub fn find_duplicates(&self) -> HashMap<String, Vec<PathBuf>> {
 ///
 /// # Returns
 ///
-/// An `Option` containing a slice of `PathBuf` representing the children of the directory.
-
-
+/// An `Option` containing a slice of `PathBuf` representing the children of the directory,
+/// sorted by path.
+///
+/// # Example
+///
+/// ```
+/// use du::FileTree;
+///
+/// # let dir = tempfile::tempdir()?;
+/// std::fs::create_dir(dir.path().join("photos"))?;
+/// let file_tree = FileTree::new(dir.path())?;
+///
+/// assert_eq!(file_tree.get_children(dir.path()).unwrap(), [dir.path().join("photos")]);
+/// assert!(file_tree.get_children(&dir.path().join("missing")).is_none());
+/// # Ok::<(), std::io::Error>(())
+/// ```
     pub fn get_children(&self, path: &Path) -> Option<&[PathBuf]> {
 
-        if let Some(entry_node) = self.map.get(path){
-            if let EntryNode::Directory(enfants) = entry_node{
-                println!("\n{:?}\n",Some(&enfants));
-                return Some(&enfants);
-            }           
+        if let Some(EntryNode::Directory(enfants)) = self.map.get(Self::normalize(path).as_ref()) {
+            return Some(enfants);
         }
         None
     }
 
 /// Returns the total size of a file or directory in the file tree.
 ///
+/// A file having several hard links in the tree is counted once in the directory sizes,
+/// through its first path, unless the tree was built with `FileTreeBuilder::count_links`.
+///
 /// # Arguments
 ///
 /// * `path` - The path of the file or directory.
@@ -208,22 +1087,123 @@ pub fn find_duplicates(&self) -> HashMap<String, Vec<PathBuf>> {
 /// # Returns
 ///
 /// An `Option` containing the total size as a `Size` instance.
-
+///
+/// # Example
+///
+/// ```
+/// use du::{FileTree, Size};
+///
+/// # let dir = tempfile::tempdir()?;
+/// std::fs::create_dir(dir.path().join("docs"))?;
+/// std::fs::write(dir.path().join("docs/a.txt"), [0; 1000])?;
+/// std::fs::write(dir.path().join("b.txt"), [0; 24])?;
+///
+/// let file_tree = FileTree::new(dir.path())?;
+/// assert_eq!(file_tree.get_size(&dir.path().join("docs")), Some(Size::new(1000)));
+/// assert_eq!(file_tree.get_size(dir.path()), Some(Size::new(1024)));
+/// # Ok::<(), std::io::Error>(())
+/// ```
     pub fn get_size(&self, path: &Path) -> Option<Size> {
+        let path = Self::normalize(path);
+        self.map.get(path.as_ref()).map(|entry| match entry {
+            EntryNode::File(size) => *size,
+            EntryNode::Symlink(_) | EntryNode::Other(_) => Size::new(0),
+            EntryNode::Directory(_) => self.totals.get(path.as_ref()).map_or(Size::new(0), |(size, _)| *size),
+        })
+    }
 
+/// Returns the number of files and directories found below a directory, recursively.
+///
+/// # Arguments
+///
+/// * `path` - The path of the directory.
+///
+/// # Returns
+///
+/// An `Option` containing the counts, `None` if `path` is not a directory of the file tree.
+///
+/// # Example
+///
+/// ```
+/// use du::FileTree;
+///
+/// # let dir = tempfile::tempdir()?;
+/// std::fs::create_dir(dir.path().join("docs"))?;
+/// std::fs::write(dir.path().join("docs/a.txt"), b"a")?;
+/// std::fs::write(dir.path().join("b.txt"), b"b")?;
+///
+/// let counts = FileTree::new(dir.path())?.get_counts(dir.path()).unwrap();
+/// assert_eq!((counts.files, counts.directories), (2, 1));
+/// # Ok::<(), std::io::Error>(())
+/// ```
+    pub fn get_counts(&self, path: &Path) -> Option<EntryCounts> {
+        let Some(EntryNode::Directory(enfants)) = self.map.get(Self::normalize(path).as_ref()) else { return None };
+        let mut counts = EntryCounts::default();
+        for child in enfants {
+            match self.map.get(child) {
+                Some(EntryNode::File(_)) => counts.files += 1,
+                Some(EntryNode::Directory(_)) => {
+                    let child_counts = self.get_counts(child).unwrap_or_default();
+                    counts.files += child_counts.files;
+                    counts.directories += child_counts.directories + 1;
+                }
+                _ => {}
+            }
+        }
+        Some(counts)
+    }
 
-        self.map.get(path).and_then(|entry| match entry {
-            EntryNode::File(size) => Some(*size),
-            EntryNode::Directory(enfants) => {
-                let total_size: u64 = enfants
-                .iter().filter_map(|child| self.get_size(child).map(|size| size.value())).sum();
-                Some(Size::new(total_size))
-            },
+/// Returns the space used on disk by a file or directory in the file tree.
+///
+/// Unlike `get_size`, which sums the apparent sizes of the files, this sums the
+/// blocks allocated for every entry, including the directories themselves, like `du`.
+/// It is smaller than the apparent size for sparse files and larger for many tiny files.
+///
+/// # Arguments
+///
+/// * `path` - The path of the file or directory.
+///
+/// # Returns
+///
+/// An `Option` containing the disk usage as a `Size` instance.
+    pub fn get_disk_usage(&self, path: &Path) -> Option<Size> {
+        let path = Self::normalize(path);
+        self.map.get(path.as_ref()).map(|entry| match entry {
+            EntryNode::File(_) | EntryNode::Symlink(_) | EntryNode::Other(_) => self.disk_usage.get(path.as_ref()).copied().unwrap_or(Size::new(0)),
+            EntryNode::Directory(_) => self.totals.get(path.as_ref()).map_or(Size::new(0), |(_, disk_usage)| *disk_usage),
         })
     }
 
-/// Returns an iterator over the paths of files in the file tree.
+/// Returns `true` if the entry at `path` is a sparse file: a file for which less than half of
+/// its apparent size is allocated on disk, its holes reading as zeros without using blocks.
+///
+/// Files compressed by the file system are reported as sparse too. Without block information,
+/// on platforms other than Unix and Windows, no file is sparse.
+///
+/// # Example
+///
+/// ```
+/// use du::FileTree;
+///
+/// # let dir = tempfile::tempdir()?;
+/// let path = dir.path().join("disk.img");
+/// std::fs::File::create(&path)?.set_len(64 * 1024 * 1024)?;
+///
+/// let file_tree = FileTree::new(dir.path())?;
+/// # #[cfg(unix)]
+/// assert!(file_tree.is_sparse(&path));
+/// assert!(!file_tree.is_sparse(dir.path()));
+/// # Ok::<(), std::io::Error>(())
+/// ```
+    pub fn is_sparse(&self, path: &Path) -> bool {
+        let path = Self::normalize(path);
+        match (self.map.get(path.as_ref()), self.disk_usage.get(path.as_ref())) {
+            (Some(EntryNode::File(size)), Some(disk_usage)) => disk_usage.value().saturating_mul(2) < size.value(),
+            _ => false,
+        }
+    }
 
+/// Returns an iterator over the paths of files in the file tree, sorted.
     pub fn files(&self) -> impl Iterator<Item = &PathBuf> {
         self.map.iter().filter_map(|(path, entry)| {
             if let EntryNode::File(_) = entry {
@@ -233,6 +1213,14 @@ pub fn find_duplicates(&self) -> HashMap<String, Vec<PathBuf>> {
             }
         })
     }
+
+/// Returns an iterator over the paths of directories in the file tree, including the root, sorted.
+    pub fn directories(&self) -> impl Iterator<Item = &PathBuf> {
+        self.map.iter().filter_map(|(path, entry)| match entry {
+            EntryNode::Directory(_) => Some(path),
+            _ => None,
+        })
+    }
     
 /// Returns the entry node for a given path in the file tree.
 ///
@@ -243,9 +1231,53 @@ pub fn find_duplicates(&self) -> HashMap<String, Vec<PathBuf>> {
 /// # Returns
 ///
 /// An `Option` containing the `EntryNode` for the specified path.
-    
     pub fn get_map_option(&self, path: &Path) -> Option<&EntryNode> {
-        self.map.get(path)
+        self.map.get(Self::normalize(path).as_ref())
+    }
+
+/// Returns an iterator over all the paths (files and directories) of the file tree, sorted.
+    pub fn iter(&self) -> FileTreeIterator<'_> {
+        FileTreeIterator { iter: self.map.iter() }
+    }
+
+/// Returns an iterator over the entries of the file tree in depth-first order: each directory
+/// is followed by its whole subtree before its next sibling, as in the printed tree.
+///
+/// # Example
+///
+/// ```
+/// use du::FileTree;
+///
+/// # let dir = tempfile::tempdir()?;
+/// std::fs::create_dir_all(dir.path().join("a/b"))?;
+/// std::fs::write(dir.path().join("c.txt"), b"hello")?;
+///
+/// let file_tree = FileTree::new(dir.path())?;
+/// let entries: Vec<_> = file_tree.iter_dfs().map(|(depth, path, _)| (depth, path.strip_prefix(dir.path()).unwrap().to_path_buf())).collect();
+/// assert_eq!(entries, [(0, "".into()), (1, "a".into()), (2, "a/b".into()), (1, "c.txt".into())]);
+/// # Ok::<(), std::io::Error>(())
+/// ```
+    pub fn iter_dfs(&self) -> TreeWalk<'_> {
+        self.walk(true)
+    }
+
+/// Returns an iterator over the entries of the file tree in breadth-first order: all the
+/// entries at a depth are yielded before those at the next depth.
+    pub fn iter_bfs(&self) -> TreeWalk<'_> {
+        self.walk(false)
+    }
+
+    fn walk(&self, depth_first: bool) -> TreeWalk<'_> {
+        TreeWalk { file_tree: self, pending: VecDeque::from([(0, &self.root)]), depth_first }
+    }
+}
+
+impl<'a> IntoIterator for &'a FileTree {
+    type Item = &'a PathBuf;
+    type IntoIter = FileTreeIterator<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
     }
 }
 
@@ -254,126 +1286,477 @@ pub fn find_duplicates(&self) -> HashMap<String, Vec<PathBuf>> {
 
 
 
+impl<'a> Explorer<'a> {
+
+/// Creates the state of a scan of the tree rooted at `root`, an absolute path.
+    pub(crate) fn new(root: &'a Path, options: &'a ScanOptions, callback: &'a mut (dyn FnMut(&ScanProgress) + Send)) -> Self {
+        let device = match options.one_file_system {
+            true => fs::metadata(root).ok().and_then(|metadata| Self::device(&metadata)),
+            false => None,
+        };
+        Explorer {
+            root,
+            device,
+            options,
+            reporter: Mutex::new(ProgressReporter { progress: ScanProgress::default(), callback, failed: None }),
+            stat_nanos: AtomicU64::new(0),
+        }
+    }
+
+/// Runs `read`, reading the metadata of an entry, adding its duration to `stat_nanos`.
+    fn timed_stat<T>(&self, read: impl FnOnce() -> T) -> T {
+        let start = Instant::now();
+        let result = read();
+        self.stat_nanos.fetch_add(start.elapsed().as_nanos() as u64, Ordering::Relaxed);
+        result
+    }
+
+/// Returns `true` once the flag set by `FileTreeBuilder::interrupt_on` is set, the entries not
+/// explored yet being left out.
+    pub(crate) fn is_interrupted(&self) -> bool {
+        self.options.interrupt.as_ref().is_some_and(|interrupt| interrupt.load(Ordering::Relaxed))
+    }
+
+/// Returns the error of a strict scan that failed with `err`, on the entry recorded by `recover`,
+/// or on the root if the scan failed before exploring any entry.
+    pub(crate) fn error(&self, err: io::Error) -> Error {
+        let path = self.reporter.lock().unwrap().failed.take().unwrap_or_else(|| self.root.to_path_buf());
+        Error::Io { path, source: err }
+    }
+
+/// Recursively explores a directory and inserts the corresponding entries in `entries`.
+///
+/// The children of a directory are explored in parallel, each thread filling its own
+/// `ScannedEntries` which are merged once the directory has been explored.
+///
+/// Symbolic links are recorded as `EntryNode::Symlink`, unless they are followed. A link is not
+/// followed when it is broken or when it points to a directory being explored, which would
+/// otherwise recurse forever.
+///
+/// When the `.gitignore` files are respected, the patterns of each directory apply to its
+/// descendants, and the `.git` directories are skipped.
+///
+///# Arguments
+///
+/// * `path` - The path to explore.
+/// * `metadata` - The metadata of `path`.
+/// * `inherited` - The state inherited from the directories containing `path`.
+/// * `entries` - The entries found so far by the current thread.
+///
+/// # Returns
+///
+/// An empty `io::Result`, failing if an entry could not be read and the scan is strict.
+    fn file_explorer(&self, path: &Path, metadata: &fs::Metadata, inherited: &Inherited, entries: &mut ScannedEntries) -> std::io::Result<()> {
+        let Err(err) = self.explore_entry(path, metadata, inherited, entries) else { return Ok(()) };
+
+        // Un dossier illisible est gardé sans enfants, les autres entrées en erreur sont retirées
+        if metadata.is_dir() {
+            entries.map.insert(path.to_path_buf(), EntryNode::Directory(Vec::new()));
+        } else {
+            entries.disk_usage.remove(path);
+            entries.modified.remove(path);
+            entries.ownership.remove(path);
+        }
+        self.recover(path, err, entries)
+    }
+
+/// Records the error that occurred on `path` so that the scan goes on, or fails if the scan is strict.
+    fn recover(&self, path: &Path, err: io::Error, entries: &mut ScannedEntries) -> io::Result<()> {
+        if self.options.strict {
+            self.reporter.lock().unwrap().failed = Some(path.to_path_buf());
+            return Err(err);
+        }
+        entries.errors.push((path.to_path_buf(), err));
+        Ok(())
+    }
+
+    fn explore_entry(&self, path: &Path, metadata: &fs::Metadata, inherited: &Inherited, entries: &mut ScannedEntries) -> std::io::Result<()> {
+        entries.disk_usage.insert(path.to_path_buf(), FileTree::allocated_size(path, metadata));
+        if let Ok(modified) = metadata.modified() {
+            entries.modified.insert(path.to_path_buf(), modified);
+        }
+        entries.ownership.insert(path.to_path_buf(), Ownership::of(path, metadata));
+
+        if metadata.is_file() {
+            if let Some(inode) = Self::link_key(metadata) {
+                entries.links.insert(path.to_path_buf(), inode);
+            }
+            self.entry_scanned(path, 0);
+            match ArchiveFormat::of(path).filter(|_| self.options.inspect_archives) {
+                Some(format) => self.explore_archive(path, format, metadata, entries),
+                None => {
+                    entries.map.insert(path.to_path_buf(), EntryNode::File(Size::new(metadata.len())));
+                    Ok(())
+                }
+            }
+
+        } else if metadata.is_dir() && !inherited.collapsed && self.options.max_depth.is_some_and(|max_depth| inherited.depth >= max_depth) {
+            // Le contenu est exploré à part, seules ses tailles sont gardées
+            let mut content = ScannedEntries::default();
+            self.explore_entry(path, metadata, &Inherited { collapsed: true, ..inherited.clone() }, &mut content)?;
+            let size = content.map.values().fold(Size::new(0), |size, entry| match entry {
+                EntryNode::File(file_size) => size + *file_size,
+                _ => size,
+            });
+            let disk_usage = content.disk_usage.iter().filter(|(entry_path, _)| *entry_path != path).fold(Size::new(0), |total, (_, size)| total + *size);
+            entries.collapsed.insert(path.to_path_buf(), (size, disk_usage));
+            entries.errors.extend(content.errors);
+            entries.map.insert(path.to_path_buf(), EntryNode::Directory(Vec::new()));
+            Ok(())
+
+        } else if metadata.is_dir() {
+            self.entry_scanned(path, 0);
+            let inherited = self.inherited_by(path, inherited)?;
+
+            // Les liens non suivis sont enregistrés tout de suite, le reste est exploré en parallèle
+            let mut children = Vec::new();
+            let mut to_explore = Vec::new();
+            for entry in fs::read_dir(path)? {
+                if self.is_interrupted() {
+                    break;
+                }
+                let entry_path = match entry {
+                    Ok(entry) => entry.path(),
+                    Err(err) => {
+                        self.recover(path, err, entries)?;
+                        continue;
+                    }
+                };
+                if self.is_excluded(&entry_path) {
+                    continue;
+                }
+
+                let mut list_entry = || -> io::Result<()> {
+                    let entry_metadata = self.timed_stat(|| fs::symlink_metadata(&entry_path))?;
+                    if self.is_skipped(&entry_path, &entry_metadata, &inherited) {
+                        return Ok(());
+                    }
+                    if entry_metadata.file_type().is_symlink() {
+                        match self.timed_stat(|| self.followed_metadata(&entry_path, &inherited.ancestors))? {
+                            Some(target_metadata) => to_explore.push((entry_path.clone(), target_metadata)),
+                            None => {
+                                let target = fs::read_link(&entry_path)?;
+                                entries.disk_usage.insert(entry_path.clone(), FileTree::allocated_size(&entry_path, &entry_metadata));
+                                if let Ok(modified) = entry_metadata.modified() {
+                                    entries.modified.insert(entry_path.clone(), modified);
+                                }
+                                entries.ownership.insert(entry_path.clone(), Ownership::of(&entry_path, &entry_metadata));
+                                entries.map.insert(entry_path.clone(), EntryNode::Symlink(target));
+                                self.entry_scanned(&entry_path, 0);
+                            }
+                        }
+                    } else {
+                        to_explore.push((entry_path.clone(), entry_metadata));
+                    }
+                    children.push(entry_path.clone());
+                    Ok(())
+                };
+                if let Err(err) = list_entry() {
+                    self.recover(&entry_path, err, entries)?;
+                }
+            }
+
+            let explored = to_explore
+                .par_iter()
+                .try_fold(ScannedEntries::default, |mut explored, (child, child_metadata)| {
+                    if !self.is_interrupted() {
+                        self.file_explorer(child, child_metadata, &inherited, &mut explored)?;
+                    }
+                    Ok::<_, io::Error>(explored)
+                })
+                .try_reduce(ScannedEntries::default, |a, b| Ok(a.merge(b)))?;
+            *entries = std::mem::take(entries).merge(explored);
+
+            children.retain(|child| entries.map.contains_key(child));
+            children.sort();
+            entries.map.insert(path.to_path_buf(), EntryNode::Directory(children));
+            Ok(())
+
+        } else {
+            // Sockets, tubes nommés et périphériques sont listés sans taille
+            entries.map.insert(path.to_path_buf(), EntryNode::Other(FileKind::of(metadata)));
+            self.entry_scanned(path, 0);
+            Ok(())
+        }
+    }
+
+/// Lists the content of the archive at `path` as a directory, or records it as a file if it can't
+/// be read, the error being recovered.
+    fn explore_archive(&self, path: &Path, format: ArchiveFormat, metadata: &fs::Metadata, entries: &mut ScannedEntries) -> io::Result<()> {
+        match archive::list(path, format) {
+            Ok((content, children)) => {
+                entries.map.extend(content);
+                entries.map.insert(path.to_path_buf(), EntryNode::Directory(children));
+                entries.archives.insert(path.to_path_buf(), Size::new(metadata.len()));
+                Ok(())
+            }
+            Err(err) => {
+                entries.map.insert(path.to_path_buf(), EntryNode::File(Size::new(metadata.len())));
+                self.recover(path, err, entries)
+            }
+        }
+    }
+
+/// Returns the state inherited by the children of the directory at `path`.
+    pub(crate) fn inherited_by(&self, path: &Path, inherited: &Inherited) -> io::Result<Inherited> {
+        let mut inherited = inherited.clone();
+        inherited.depth += 1;
+        if self.options.follow_symlinks {
+            inherited.ancestors.push(fs::canonicalize(path)?);
+        }
+        if self.options.gitignore {
+            if let Some(gitignore) = Self::read_gitignore(path) {
+                inherited.gitignores.push(Arc::new(gitignore));
+            }
+        }
+        Ok(inherited)
+    }
+
+/// Returns the device and inode of a file having several hard links, on Unix.
+    pub(crate) fn link_key(metadata: &fs::Metadata) -> Option<(u64, u64)> {
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            if metadata.nlink() > 1 {
+                return Some((metadata.dev(), metadata.ino()));
+            }
+        }
+        #[cfg(not(unix))]
+        let _ = metadata;
+        None
+    }
+
+/// Returns the device of a file, on Unix.
+    fn device(metadata: &fs::Metadata) -> Option<u64> {
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            Some(metadata.dev())
+        }
+        #[cfg(not(unix))]
+        {
+            let _ = metadata;
+            None
+        }
+    }
+
+/// Returns `true` if the scan stays on the file system of the root and the directory of `metadata` is on another one,
+/// e.g. a mount point.
+    fn is_other_file_system(&self, metadata: &fs::Metadata) -> bool {
+        metadata.is_dir() && self.device.is_some_and(|device| Self::device(metadata) != Some(device))
+    }
+
+/// Returns `true` if the entry at `path` is hidden, ignored or on another file system, and must not be scanned.
+    pub(crate) fn is_skipped(&self, path: &Path, metadata: &fs::Metadata, inherited: &Inherited) -> bool {
+        self.is_hidden(path, metadata) || self.is_ignored(path, metadata.is_dir(), &inherited.gitignores) || self.is_other_file_system(metadata)
+    }
+
+/// Returns the metadata of the target of the symbolic link at `path` if the link must be followed,
+/// `None` if it is not followed, is broken, points to one of the `ancestors`, or to a directory on
+/// another file system when the scan stays on the file system of the root.
+    pub(crate) fn followed_metadata(&self, path: &Path, ancestors: &[PathBuf]) -> io::Result<Option<fs::Metadata>> {
+        if !self.options.follow_symlinks {
+            return Ok(None);
+        }
+        let Ok(metadata) = fs::metadata(path) else { return Ok(None) };
+        if metadata.is_dir() && (self.is_other_file_system(&metadata) || ancestors.contains(&fs::canonicalize(path)?)) {
+            return Ok(None);
+        }
+        Ok(Some(metadata))
+    }
+
+/// Returns `true` if the path relative to the root, or the file name, matches an exclude pattern.
+    pub(crate) fn is_excluded(&self, path: &Path) -> bool {
+        let relative = path.strip_prefix(self.root).unwrap_or(path);
+        self.options.excludes.is_match(relative)
+            || path.file_name().is_some_and(|name| self.options.excludes.is_match(name))
+    }
+
+/// Returns `true` if the hidden entries are skipped and the entry at `path` is hidden: its name starts
+/// with a dot or, on Windows, it has the hidden or system attribute.
+    fn is_hidden(&self, path: &Path, metadata: &fs::Metadata) -> bool {
+        if self.options.hidden {
+            return false;
+        }
+        #[cfg(windows)]
+        {
+            use std::os::windows::fs::MetadataExt;
+            use windows_sys::Win32::Storage::FileSystem::{FILE_ATTRIBUTE_HIDDEN, FILE_ATTRIBUTE_SYSTEM};
+            if metadata.file_attributes() & (FILE_ATTRIBUTE_HIDDEN | FILE_ATTRIBUTE_SYSTEM) != 0 {
+                return true;
+            }
+        }
+        #[cfg(not(windows))]
+        let _ = metadata;
+        path.file_name().is_some_and(|name| name.to_string_lossy().starts_with('.'))
+    }
+
+/// Returns `true` if the `.gitignore` files are respected and the entry at `path` is ignored
+/// by the deepest `.gitignore` having an opinion on it, or is a `.git` directory.
+    fn is_ignored(&self, path: &Path, is_dir: bool, gitignores: &[Arc<Gitignore>]) -> bool {
+        if !self.options.gitignore {
+            return false;
+        }
+        if is_dir && path.file_name().is_some_and(|name| name == ".git") {
+            return true;
+        }
+        for gitignore in gitignores.iter().rev() {
+            match gitignore.matched(path, is_dir) {
+                Match::Ignore(_) => return true,
+                Match::Whitelist(_) => return false,
+                Match::None => {}
+            }
+        }
+        false
+    }
+
+/// Reads the `.gitignore` file of the directory at `path`, if any.
+///
+/// Like git, the invalid patterns are ignored.
+    fn read_gitignore(path: &Path) -> Option<Gitignore> {
+        let file = path.join(".gitignore");
+        if !file.is_file() {
+            return None;
+        }
+        let mut builder = GitignoreBuilder::new(path);
+        builder.add(file);
+        builder.build().ok()
+    }
+
+/// Reports the progress of the scan, from any thread.
+    pub(crate) fn entry_scanned(&self, path: &Path, bytes_hashed: u64) {
+        self.reporter.lock().unwrap().entry_scanned(path, bytes_hashed);
+    }
+}
+
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::print_tree::PrintOptions;
+    use crate::test_utils::{create_file, create_fixture, create_relative_fixture};
 
 
     #[test]
     fn file_tree_integrity_test() {
-        let path = Path::new("Test");
+        let fixture = create_fixture();
+        let path = fixture.path().join("Test");
         let result = FileTree::new(&path);
         // Vérifier si la création de FileTree réussit
         assert!(result.is_ok());
     }
-    
+
     #[test]
     fn get_root_test() {
         // Spécifiez la racine que vous attendez
-        let expected_root = Path::new("Test");
-        
+        let fixture = create_fixture();
+        let expected_root = fixture.path().join("Test");
+
         // Créez un FileTree avec la racine spécifiée
         let result = FileTree::new(&expected_root);
-    
+
         // Vérifiez si la création de FileTree réussit
         assert!(result.is_ok());
-    
+
         // Déballer le résultat
         let file_tree = result.unwrap();
-    
+
         // Vérifiez si la racine retournée par get_root correspond à la racine spécifiée
         assert_eq!(file_tree.get_root(), expected_root);
     }
-    
+
     #[test]
     fn get_children_test(){
-    
+
         // Déballer le résultat
-        let path = &PathBuf::from("Test");
-        let result = FileTree::new(&path);
+        let fixture = create_fixture();
+        let path = &fixture.path().join("Test");
+        let result = FileTree::new(path);
         let file_tree = result.unwrap();
-    
+
         // Vérifier la présence de certains répertoires
         let root_children = file_tree.get_children(path).expect("Failed to get children for the root directory");
-        assert!(root_children.contains(&PathBuf::from("Test/Dossier1")));
-        assert!(root_children.contains(&PathBuf::from("Test/Dossier2")));
+        assert!(root_children.contains(&path.join("Dossier1")));
+        assert!(root_children.contains(&path.join("Dossier2")));
     }
     #[test]
     fn get_children_dossier_1_test(){
 
-        let path = &PathBuf::from("Test");
-        let result = FileTree::new(&path);
+        let fixture = create_fixture();
+        let path = &fixture.path().join("Test");
+        let result = FileTree::new(path);
         let file_tree = result.unwrap();
 
         // Vérifier la présence de certains fichiers et sous-dossiers dans Dossier 1
-        let dossier1_children = file_tree.get_children(&PathBuf::from("Test/Dossier1")).expect("Failed to get children for Dossier 1");
-        println!("{:?}", dossier1_children);
-        assert!(dossier1_children.contains(&PathBuf::from("Test/Dossier1/Fichier1")));
+        let dossier1_children = file_tree.get_children(&path.join("Dossier1")).expect("Failed to get children for Dossier 1");
+        assert!(dossier1_children.contains(&path.join("Dossier1/Fichier1")));
     }
 
     #[test]
 
     fn get_children_dossier_2_test(){
 
-        let path = &PathBuf::from("Test");
-        let result = FileTree::new(&path);
+        let fixture = create_fixture();
+        let path = &fixture.path().join("Test");
+        let result = FileTree::new(path);
         let file_tree = result.unwrap();
 
         // Vérifier la présence de certains fichiers, sous-dossiers dans Dossier 2
-        let dossier2_children = file_tree.get_children(&PathBuf::from("Test/Dossier2")).expect("Failed to get children for Dossier 2");
-        assert!(dossier2_children.contains(&PathBuf::from("Test/Dossier2/SousDossier1")));
-        assert!(dossier2_children.contains(&PathBuf::from("Test/Dossier2/SousDossier2")));
-        assert!(dossier2_children.contains(&PathBuf::from("Test/Dossier2/Fichier3")));
-        
+        let dossier2_children = file_tree.get_children(&path.join("Dossier2")).expect("Failed to get children for Dossier 2");
+        assert!(dossier2_children.contains(&path.join("Dossier2/SousDossier1")));
+        assert!(dossier2_children.contains(&path.join("Dossier2/SousDossier2")));
+        assert!(dossier2_children.contains(&path.join("Dossier2/Fichier3")));
+
     }
 
     #[test]
 
     fn get_children_sous_dossier_2(){
 
-        let path = &PathBuf::from("Test");
-        let result = FileTree::new(&path);
+        let fixture = create_fixture();
+        let path = &fixture.path().join("Test");
+        let result = FileTree::new(path);
         let file_tree = result.unwrap();
 
         // Vérifier la présence de certains fichiers dans Sous Dossier 2
-        let sousdossier2_children = file_tree.get_children(&PathBuf::from("Test/Dossier2/SousDossier2")).expect("Failed to get children for Sous Dossier 2");
-        assert!(sousdossier2_children.contains(&PathBuf::from("Test/Dossier2/SousDossier2/Fichier2")));  
+        let sousdossier2_children = file_tree.get_children(&path.join("Dossier2/SousDossier2")).expect("Failed to get children for Sous Dossier 2");
+        assert!(sousdossier2_children.contains(&path.join("Dossier2/SousDossier2/Fichier2")));
     }
-    
+
     #[test]
     fn get_size_file() {
-        let metadata = fs::metadata(Path::new("Test/Dossier1/Fichier1"));
+        let fixture = create_fixture();
+        let path = &fixture.path().join("Test");
+        let metadata = fs::metadata(path.join("Dossier1/Fichier1"));
         match metadata {
             Ok(data) => {
-                let path = &PathBuf::from("Test");
-                let result = FileTree::new(&path);
+                let result = FileTree::new(path);
                 let file_tree = result.unwrap();
-                let size = file_tree.get_size(Path::new("Test/Dossier1/Fichier1"));
-                assert_eq!(size, Some(Size::new(data.len() as u64))); 
+                let size = file_tree.get_size(&path.join("Dossier1/Fichier1"));
+                assert_eq!(size, Some(Size::new(data.len())));
             }
             Err(err) => {
-                eprintln!("Erreur lors de la récupération des métadonnées : {}", err);
-                assert!(false)
+                panic!("Erreur lors de la récupération des métadonnées : {}", err);
             }
         }
-        
+
     }
 
     #[test]
     fn get_size_directory() {
-        let metadata = fs::metadata(Path::new("Test/Dossier2"));
+        let fixture = create_fixture();
+        let path = &fixture.path().join("Test");
+        let metadata = fs::metadata(path.join("Dossier2"));
         match metadata {
             Ok(_data) => {
-                let path = &PathBuf::from("Test");
-                let result = FileTree::new(&path);
+                let result = FileTree::new(path);
                 let file_tree = result.unwrap();
-                let size = file_tree.get_size(Path::new("Test/Dossier2"));
-                assert_eq!(size, Some(Size::new(520256)));  //Insérer la taille du dossier avant chaque essais car il change tout le temps
+                let size = file_tree.get_size(&path.join("Dossier2"));
+                assert_eq!(size, Some(Size::new(520256)));  //Taille totale des fichiers du dossier Dossier2 de la fixture
                                                         //data.len() ne fonctionne pas ici car il retourne l'espace occuper sur le disque
             }
             Err(err) => {
-                eprintln!("Erreur lors de la récupération des métadonnées : {}", err);
-                assert!(false);
+                panic!("Erreur lors de la récupération des métadonnées : {}", err);
             }
         }
     }
@@ -381,47 +1764,379 @@ mod tests {
     #[test]
     fn get_size_nonexistent_path() {
         let path = &PathBuf::from("Test/Chemin/Inexistant");
-        let result = FileTree::new(&path);
-        
-        match result {
-            Ok(_file_tree) => {
-                assert!(false)
+        let result = FileTree::new(path);
+
+        assert!(result.is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn get_disk_usage_sparse_file_test() {
+        let fixture = create_fixture();
+        let root = fixture.path().join("Test");
+        let mut sparse = File::create(root.join("sparse")).unwrap();
+        sparse.set_len(16 * 1024 * 1024).unwrap();
+        std::io::Seek::seek(&mut sparse, std::io::SeekFrom::Start(1024 * 1024)).unwrap();
+        std::io::Write::write_all(&mut sparse, b"data").unwrap();
+
+        let file_tree = FileTreeBuilder::new(&root).build().unwrap();
+        let apparent = file_tree.get_size(&root.join("sparse")).unwrap();
+        let on_disk = file_tree.get_disk_usage(&root.join("sparse")).unwrap();
+        assert_eq!(apparent, Size::new(16 * 1024 * 1024));
+        assert!(on_disk.value() * 100 < apparent.value());
+    }
+
+    #[test]
+    fn get_disk_usage_directory_test() {
+        let fixture = create_fixture();
+        let root = fixture.path().join("Test");
+        let file_tree = FileTree::new(&root).unwrap();
+
+        let dossier2 = root.join("Dossier2");
+        let children_total = file_tree.get_children(&dossier2).unwrap().iter()
+            .map(|child| file_tree.get_disk_usage(child).unwrap().value())
+            .sum::<u64>();
+        assert!(file_tree.get_disk_usage(&dossier2).unwrap().value() >= children_total);
+        assert!(file_tree.get_disk_usage(&root.join("Inexistant")).is_none());
+    }
+
+    #[test]
+    fn children_sorted_test() {
+        let fixture = create_fixture();
+        let root = fixture.path().join("Test");
+        for name in ["b", "a", "c", "B"] {
+            File::create(root.join("Dossier1").join(name)).unwrap();
+        }
+        let file_tree = FileTree::new(&root).unwrap();
+
+        let children = file_tree.get_children(&root.join("Dossier1")).unwrap();
+        let mut sorted = children.to_vec();
+        sorted.sort();
+        assert_eq!(children, sorted.as_slice());
+        assert_eq!(children.len(), 5);
+    }
+
+    #[test]
+    fn relative_and_absolute_roots_test() {
+        let fixture = create_relative_fixture();
+        let cwd = std::env::current_dir().unwrap();
+        let relative = fixture.path().strip_prefix(&cwd).unwrap().join("Test");
+        let dotted = Path::new(".").join(&relative);
+        let absolute = std::path::absolute(&relative).unwrap();
+
+        let trees: Vec<FileTree> = [&relative, &dotted, &absolute].iter()
+            .map(|root| FileTree::new(root).unwrap())
+            .collect();
+
+        let mut keys: Vec<_> = trees[0].iter().cloned().collect();
+        keys.sort();
+        let mut outputs = Vec::new();
+        for file_tree in &trees {
+            let mut tree_keys: Vec<_> = file_tree.iter().cloned().collect();
+            tree_keys.sort();
+            assert_eq!(tree_keys, keys);
+            assert_eq!(file_tree.get_root(), absolute);
+
+            // The tree can be queried with any form of the paths
+            for root in [&relative, &dotted, &absolute] {
+                assert_eq!(file_tree.get_size(&root.join("Dossier2")), Some(Size::new(520256)));
+                assert!(file_tree.get_children(&root.join("Dossier1")).is_some());
             }
-            Err(_err) => {
-                assert!(true);
+
+            let mut output = Vec::new();
+            file_tree.write_tree(&mut output, &PrintOptions::default()).unwrap();
+            outputs.push(output);
+        }
+        assert_eq!(outputs[0], outputs[1]);
+        assert_eq!(outputs[0], outputs[2]);
+    }
+
+    #[test]
+    fn progress_test() {
+        let fixture = create_fixture();
+        let root = fixture.path().join("Test");
+        let mut events = Vec::new();
+        let file_tree = FileTree::new_with_progress(&root, |progress| events.push(progress.clone())).unwrap();
+
+        // The fixture holds 4 files and 5 directories, counting the root
+        assert_eq!(events.len(), 9);
+        assert_eq!(events.len(), file_tree.iter().count());
+        let last = events.last().unwrap();
+        assert_eq!(last.entries_scanned, 9);
+        // Files are no longer read while scanning
+        assert_eq!(last.bytes_hashed, 0);
+        assert_eq!(events.iter().filter(|progress| file_tree.files().any(|file| *file == progress.current_path)).count(), 4);
+    }
+
+    #[test]
+    fn parallel_scan_test() {
+        let fixture = create_fixture();
+        let root = fixture.path().join("Test");
+        for i in 0..20 {
+            for j in 0..5 {
+                create_file(&root.join(format!("Parallele{}/Sous{}/Fichier{}", i, j, j)), 10);
             }
         }
+
+        let mut last = ScanProgress::default();
+        let file_tree = FileTree::new_with_progress(&root, |progress| last = progress.clone()).unwrap();
+
+        assert_eq!(file_tree.iter().count(), 9 + 20 * (1 + 5 * 2));
+        assert_eq!(last.entries_scanned, file_tree.iter().count() as u64);
+        assert_eq!(file_tree.get_size(&root), Some(Size::new(521280 + 20 * 5 * 10)));
+        assert_eq!(file_tree.get_children(&root.join("Parallele7")).unwrap().len(), 5);
     }
-    
+
+    /// Sets the modification time of `path` an hour ago, so that a change is detected whatever
+    /// the precision of the timestamps of the file system.
+    fn backdate(path: &Path) {
+        let past = SystemTime::now() - std::time::Duration::from_secs(3600);
+        File::open(path).unwrap().set_modified(past).unwrap();
+    }
+
+    #[test]
+    fn refresh_paths_test() {
+        let fixture = create_fixture();
+        let root = fixture.path().join("Test");
+        let mut file_tree = FileTree::new(&root).unwrap();
+
+        create_file(&root.join("Dossier2/SousDossier1/Nouveau"), 100);
+        backdate(&root.join("Dossier2/SousDossier1"));
+        fs::remove_dir_all(root.join("Dossier2/SousDossier2")).unwrap();
+        backdate(&root.join("Dossier2"));
+        // Dossier1 n'est pas signalé : ses changements ne sont pas vus
+        create_file(&root.join("Dossier1/Ignore"), 10);
+        backdate(&root.join("Dossier1"));
+
+        file_tree.refresh_paths(&[
+            root.join("Dossier2/SousDossier1/Nouveau"),
+            root.join("Dossier2/SousDossier2/Fichier2"),
+            fixture.path().join("ailleurs"),
+        ]).unwrap();
+        assert_eq!(file_tree.get_size(&root.join("Dossier2/SousDossier1/Nouveau")), Some(Size::new(100)));
+        assert!(file_tree.get_map_option(&root.join("Dossier2/SousDossier2")).is_none());
+        assert!(file_tree.get_map_option(&root.join("Dossier1/Ignore")).is_none());
+        assert_eq!(file_tree.get_size(&root), Some(Size::new(1024 + 500000 + 256 + 100)));
+    }
+
+    #[test]
+    fn refresh_test() {
+        let fixture = create_fixture();
+        let root = fixture.path().join("Test");
+        fs::copy(root.join("Dossier1/Fichier1"), root.join("Dossier2/Copie")).unwrap();
+        let mut file_tree = FileTree::new(&root).unwrap();
+        file_tree.compute_signatures().unwrap();
+        let signature = file_tree.get_signature(&root.join("Dossier1/Fichier1")).unwrap().to_string();
+
+        // Un fichier modifié sans changer son dossier, un fichier ajouté, un fichier supprimé
+        fs::write(root.join("Dossier2/Copie"), [1; 10]).unwrap();
+        create_file(&root.join("Dossier2/SousDossier1/Nouveau"), 100);
+        backdate(&root.join("Dossier2/SousDossier1"));
+        fs::remove_file(root.join("Dossier2/SousDossier2/Fichier2")).unwrap();
+        backdate(&root.join("Dossier2/SousDossier2"));
+
+        file_tree.refresh().unwrap();
+        assert_eq!(file_tree.get_size(&root.join("Dossier2/Copie")), Some(Size::new(10)));
+        assert!(file_tree.get_signature(&root.join("Dossier2/Copie")).is_none());
+        assert_eq!(file_tree.get_signature(&root.join("Dossier1/Fichier1")), Some(signature.as_str()));
+        assert_eq!(file_tree.get_size(&root.join("Dossier2/SousDossier1/Nouveau")), Some(Size::new(100)));
+        assert!(file_tree.get_map_option(&root.join("Dossier2/SousDossier2/Fichier2")).is_none());
+        assert_eq!(file_tree.get_size(&root), Some(Size::new(1024 + 500000 + 256 + 100 + 10)));
+
+        let rescanned = FileTree::new(&root).unwrap();
+        assert_eq!(file_tree.iter().count(), rescanned.iter().count());
+        for path in rescanned.iter() {
+            assert_eq!(file_tree.get_size(path), rescanned.get_size(path));
+            assert_eq!(file_tree.get_children(path), rescanned.get_children(path));
+        }
+    }
+
+    #[test]
+    fn refresh_keeps_options_test() {
+        let fixture = create_fixture();
+        let root = fixture.path().join("Test");
+        let mut file_tree = FileTreeBuilder::new(&root).exclude("*.tmp").build().unwrap();
+        create_file(&root.join("Dossier1/brouillon.tmp"), 10);
+        create_file(&root.join("Dossier1/garder"), 10);
+        backdate(&root.join("Dossier1"));
+
+        file_tree.refresh().unwrap();
+        assert!(file_tree.get_map_option(&root.join("Dossier1/brouillon.tmp")).is_none());
+        assert!(file_tree.get_map_option(&root.join("Dossier1/garder")).is_some());
+    }
+
+    #[test]
+    fn progress_stops_on_error_test() {
+        let mut events = 0;
+        let result = FileTree::new_with_progress(Path::new("Test/Chemin/Inexistant"), |_| events += 1);
+        assert!(result.is_err());
+        assert_eq!(events, 0);
+    }
+
     #[test]
     fn test_files() {
         // Create a sample file tree
-        let mut file_tree = FileTree::new(Path::new("TestFile")).expect("Failed to create file tree");
+        let fixture = create_fixture();
+        let root = fixture.path().join("Test");
+        let mut file_tree = FileTree::new(&root).expect("Failed to create file tree");
 
         // Add some file entries to the file tree
-        file_tree.map.insert(PathBuf::from("TestFile/file1"), EntryNode::File(Size::new(100)));
-        file_tree.map.insert(PathBuf::from("TestFile/file2"), EntryNode::File(Size::new(200)));
-        file_tree.map.insert(PathBuf::from("TestFile/dir1"), EntryNode::Directory(vec![PathBuf::from("TestFile/dir1/file3")]));
+        file_tree.map.insert(root.join("file1"), EntryNode::File(Size::new(100)));
+        file_tree.map.insert(root.join("file2"), EntryNode::File(Size::new(200)));
+        file_tree.map.insert(root.join("dir1"), EntryNode::Directory(vec![root.join("dir1/file3")]));
 
         // Some target that should be found in the directory
-        let target_file_1= Path::new("TestFile/file1");
-        let target_file_2= Path::new("TestFile/file2");
-        let target_directory_1 = Path::new("TestFile/file1");
+        let target_file_1 = root.join("file1");
+        let target_file_2 = root.join("file2");
+        let target_directory_1 = root.join("file1");
 
         // Get the files from the file tree
         let files = file_tree.files();
-        let mut buff:Vec<&Path> = Vec::new();
+        let mut buff: Vec<&Path> = Vec::new();
         for entry in files{
             buff.push(entry);
-            println!("{:?}", buff);
         }
         //Assert that the files vector contains the correct paths
 
-        assert!(buff.contains(&target_file_1));
-        assert!(buff.contains(&target_file_2));
-        assert!(buff.contains(&target_directory_1));
-       
-        
+        assert!(buff.contains(&target_file_1.as_path()));
+        assert!(buff.contains(&target_file_2.as_path()));
+        assert!(buff.contains(&target_directory_1.as_path()));
+        assert!(!buff.contains(&root.join("dir1").as_path()));
+
+}
+
+
+    #[test]
+    fn stable_order_test() {
+        let fixture = create_fixture();
+        let root = fixture.path().join("Test");
+        for name in ["z", "a", "m"] {
+            fs::write(root.join(name), [7; 64]).unwrap();
+        }
+        let mut file_tree = FileTree::new(&root).unwrap();
+        file_tree.compute_signatures().unwrap();
+
+        let paths: Vec<_> = file_tree.iter().collect();
+        assert!(paths.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!(paths[0], &root);
+        let files: Vec<_> = file_tree.files().cloned().collect();
+        assert_eq!(files, FileTree::new(&root).unwrap().files().cloned().collect::<Vec<_>>());
+        assert_eq!(file_tree.find_duplicates().into_values().next().unwrap(), [root.join("a"), root.join("m"), root.join("z")]);
+    }
+
+    #[test]
+    fn tree_walk_test() {
+        let fixture = create_fixture();
+        let root = fixture.path().join("Test");
+        let file_tree = FileTree::new(&root).unwrap();
+        let names = |walk: TreeWalk| -> Vec<_> {
+            walk.map(|(depth, path, _)| (depth, path.file_name().unwrap().to_string_lossy().into_owned())).collect()
+        };
+
+        let dfs = names(file_tree.iter_dfs());
+        let expected = [(0, "Test"), (1, "Dossier1"), (2, "Fichier1"), (1, "Dossier2"), (2, "Fichier3"), (2, "SousDossier1"), (3, "Fichier4"), (2, "SousDossier2"), (3, "Fichier2")];
+        assert_eq!(dfs, expected.map(|(depth, name)| (depth, name.to_string())));
+
+        let bfs = names(file_tree.iter_bfs());
+        assert_eq!(bfs.len(), dfs.len());
+        assert!(bfs.windows(2).all(|pair| pair[0].0 <= pair[1].0));
+        assert_eq!(bfs[3], (2, "Fichier1".to_string()));
+        assert!(matches!(file_tree.iter_bfs().last(), Some((3, _, EntryNode::File(_)))));
+    }
+
+    #[test]
+    fn hasher_change_test() {
+        let fixture = create_fixture();
+        let root = fixture.path().join("Test");
+        fs::copy(root.join("Dossier1/Fichier1"), root.join("Copie")).unwrap();
+        let mut file_tree = FileTree::new(&root).unwrap();
+
+        file_tree.compute_signatures_with_progress(&crate::hasher::Md5Hasher, |_| {}).unwrap();
+        assert_eq!(file_tree.get_signature(&root.join("Copie")).map(str::len), Some(32));
+
+        // Les signatures d'une autre fonction de hachage sont recalculées
+        file_tree.compute_signatures().unwrap();
+        assert_eq!(file_tree.get_signature(&root.join("Copie")).map(str::len), Some(64));
+        assert_eq!(file_tree.find_duplicates().len(), 1);
+    }
+
+    #[test]
+    fn quick_signature_test() {
+        let fixture = create_fixture();
+        let root = fixture.path().join("Test");
+        let size = QUICK_SIGNATURE_MIN_SIZE as usize;
+        let content = vec![1; size];
+        let mut other_start = content.clone();
+        other_start[0] = 2;
+        let mut other_middle = content.clone();
+        other_middle[size / 2] = 2;
+        fs::write(root.join("a"), &content).unwrap();
+        fs::write(root.join("b"), &other_start).unwrap();
+        fs::write(root.join("c"), &other_middle).unwrap();
+
+        let mut file_tree = FileTree::new(&root).unwrap();
+        let mut hashed = 0;
+        file_tree.compute_signatures_with_progress(&Blake3Hasher, |progress| hashed = progress.bytes_hashed).unwrap();
+        // Seuls a et c, de même début et même fin, sont lus entièrement
+        assert_eq!(hashed, 2 * size as u64);
+        assert!(file_tree.get_signature(&root.join("b")).is_none());
+        assert!(file_tree.find_duplicates().is_empty());
+
+        fs::write(root.join("d"), &other_start).unwrap();
+        let mut file_tree = FileTree::new(&root).unwrap();
+        file_tree.compute_signatures().unwrap();
+        assert_eq!(file_tree.find_duplicates().into_values().collect::<Vec<_>>(), [vec![root.join("b"), root.join("d")]]);
+    }
+
+    #[test]
+    fn bounded_thread_pool_test() {
+        let fixture = create_fixture();
+        let root = fixture.path().join("Test");
+        fs::copy(root.join("Dossier1/Fichier1"), root.join("Copie")).unwrap();
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(2).build().unwrap();
+
+        // Les rappels sont faits par les threads qui lisent les fichiers : ils doivent tous appartenir au pool
+        let mut threads = Vec::new();
+        let mut file_tree = pool.install(|| FileTree::new_with_progress(&root, |_| threads.push((rayon::current_thread_index(), rayon::current_num_threads())))).unwrap();
+        pool.install(|| file_tree.compute_signatures_with_progress(&Blake3Hasher, |_| threads.push((rayon::current_thread_index(), rayon::current_num_threads())))).unwrap();
+        assert_eq!(file_tree.find_duplicates().len(), 1);
+        assert_eq!(threads.len(), file_tree.iter().count() + 2);
+        assert!(threads.iter().all(|&(index, count)| index.is_some_and(|index| index < 2) && count == 2));
+    }
+
+    #[test]
+    fn deep_tree_sizes_test() {
+        let fixture = create_fixture();
+        let root = fixture.path().join("Test");
+        let mut deepest = root.join("Profond");
+        for i in 0..50 {
+            deepest.push(format!("N{}", i));
+        }
+        create_file(&deepest.join("feuille"), 100);
+        let mut file_tree = FileTree::new(&root).unwrap();
+
+        assert_eq!(file_tree.get_size(&root.join("Profond")), Some(Size::new(100)));
+        assert_eq!(file_tree.get_size(&root), Some(Size::new(521380)));
+        assert!(file_tree.get_disk_usage(&root.join("Profond")).unwrap() >= file_tree.get_disk_usage(&deepest).unwrap());
+
+        create_file(&deepest.join("autre"), 50);
+        backdate(&deepest);
+        file_tree.refresh().unwrap();
+        assert_eq!(file_tree.get_size(&root.join("Profond/N0")), Some(Size::new(150)));
+        assert_eq!(file_tree.get_size(&root), Some(Size::new(521430)));
+    }
+
+    #[test]
+    fn scan_timings_test() {
+        let fixture = create_fixture();
+        let root = fixture.path().join("Test");
+        let file_tree = FileTree::new(&root).unwrap();
+        let timings = file_tree.scan_timings();
+        assert!(timings.walk > Duration::ZERO);
+        assert!(timings.stat > Duration::ZERO);
+
+        let cache = fixture.path().join("cache.bin");
+        file_tree.save(&cache).unwrap();
+        assert_eq!(FileTree::load(&cache).unwrap().scan_timings(), ScanTimings::default());
+    }
 }
-    
-}
\ No newline at end of file
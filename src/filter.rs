@@ -0,0 +1,280 @@
+use globset::{GlobBuilder, GlobMatcher};
+use regex::{Regex, RegexBuilder};
+use std::io;
+use std::path::Path;
+use std::time::SystemTime;
+use crate::file_tree::FileTree;
+use crate::owner::Owner;
+
+/// A glob pattern or a regular expression selecting which files are displayed in the file tree,
+/// optionally restricted to the files whose name matches a glob, modified before or after a given
+/// time, or owned by a given user.
+///
+/// A glob pattern without any glob metacharacter or dot (e.g. `jpg`) is treated as
+/// a plain extension and expanded to `*.jpg`, so the historical `--filter jpg`
+/// usage keeps working, and a comma-separated list of extensions (e.g. `jpg,jpeg,png`)
+/// to `*.{jpg,jpeg,png}`. Unless the filter is case sensitive, `jpg` also matches `.JPG`.
+/// The default filter matches every file.
+#[derive(Clone, Debug, Default)]
+pub struct Filter {
+    matcher: Option<Matcher>,
+    match_path: bool,
+    name: Option<GlobMatcher>,
+    modified_before: Option<SystemTime>,
+    modified_after: Option<SystemTime>,
+    owned_by: Option<Owner>,
+}
+
+#[derive(Clone, Debug)]
+enum Matcher {
+    Glob(GlobMatcher),
+    Regex(Regex),
+}
+
+impl Filter {
+
+    /// Creates a new filter from a glob pattern.
+    ///
+    /// # Arguments
+    ///
+    /// * `pattern` - The glob pattern, e.g. `*.jpg` or `IMG_????.png`, or extensions, e.g. `jpg,png`.
+    /// * `match_path` - Match against the path relative to the root instead of the file name.
+    /// * `case_sensitive` - Whether the match is case sensitive.
+    ///
+    /// # Returns
+    ///
+    /// An `io::Result` containing the `Filter`, or an `InvalidInput` error if the pattern is invalid.
+    pub fn new(pattern: &str, match_path: bool, case_sensitive: bool) -> io::Result<Self> {
+        let is_extension = !pattern.contains(['*', '?', '[', '{', '.', '/']);
+        let pattern = if is_extension && pattern.contains(',') {
+            let extensions: Vec<_> = pattern.split(',').map(str::trim).filter(|extension| !extension.is_empty()).collect();
+            format!("*.{{{}}}", extensions.join(","))
+        } else if is_extension {
+            format!("*.{}", pattern)
+        } else {
+            pattern.to_string()
+        };
+
+        let glob = GlobBuilder::new(&pattern)
+            .case_insensitive(!case_sensitive)
+            .literal_separator(match_path)
+            .build()
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+
+        Ok(Filter { matcher: Some(Matcher::Glob(glob.compile_matcher())), match_path, ..Filter::default() })
+    }
+
+    /// Creates a new filter from a regular expression searched in the path relative to the root,
+    /// whose components are separated by `/` on every platform.
+    ///
+    /// # Arguments
+    ///
+    /// * `pattern` - The regular expression, e.g. `^photos/.*\.jpe?g$`.
+    /// * `case_sensitive` - Whether the match is case sensitive.
+    ///
+    /// # Returns
+    ///
+    /// An `io::Result` containing the `Filter`, or an `InvalidInput` error if the expression is invalid.
+    pub fn regex(pattern: &str, case_sensitive: bool) -> io::Result<Self> {
+        let regex = RegexBuilder::new(pattern)
+            .case_insensitive(!case_sensitive)
+            .build()
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+        Ok(Filter { matcher: Some(Matcher::Regex(regex)), match_path: true, ..Filter::default() })
+    }
+
+    /// Restricts the filter to the files whose name matches the glob `pattern`, like `find -name`,
+    /// e.g. `backup_*.tar.gz`. Unlike `new`, the pattern is never taken for an extension.
+    ///
+    /// # Arguments
+    ///
+    /// * `pattern` - The glob pattern matched against the file names.
+    /// * `case_sensitive` - Whether the match is case sensitive, like `-name`, or not, like `-iname`.
+    ///
+    /// # Returns
+    ///
+    /// An `io::Result` containing the `Filter`, or an `InvalidInput` error if the pattern is invalid.
+    pub fn name(mut self, pattern: &str, case_sensitive: bool) -> io::Result<Self> {
+        let glob = GlobBuilder::new(pattern)
+            .case_insensitive(!case_sensitive)
+            .build()
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+        self.name = Some(glob.compile_matcher());
+        Ok(self)
+    }
+
+    /// Restricts the filter to the files last modified before `time`, e.g. older than 90 days.
+    pub fn modified_before(mut self, time: SystemTime) -> Self {
+        self.modified_before = Some(time);
+        self
+    }
+
+    /// Restricts the filter to the files last modified after `time`, e.g. newer than an hour.
+    pub fn modified_after(mut self, time: SystemTime) -> Self {
+        self.modified_after = Some(time);
+        self
+    }
+
+    /// Restricts the filter to the files owned by `owner`.
+    pub fn owned_by(mut self, owner: Owner) -> Self {
+        self.owned_by = Some(owner);
+        self
+    }
+
+    /// Returns `true` if the name or path of the file at `path` matches the filter and its name pattern,
+    /// without checking its modification time nor its owner.
+    ///
+    /// # Arguments
+    ///
+    /// * `root` - The root of the file tree, used to relativize `path` in path mode.
+    /// * `path` - The path of the file to test.
+    pub fn is_match(&self, root: &Path, path: &Path) -> bool {
+        if let Some(name) = &self.name {
+            if !path.file_name().is_some_and(|file_name| name.is_match(file_name)) {
+                return false;
+            }
+        }
+        let relative = path.strip_prefix(root).unwrap_or(path);
+        match &self.matcher {
+            None => true,
+            Some(Matcher::Glob(matcher)) if self.match_path => matcher.is_match(relative),
+            Some(Matcher::Glob(matcher)) => path.file_name().is_some_and(|name| matcher.is_match(name)),
+            Some(Matcher::Regex(regex)) => {
+                let components: Vec<_> = relative.components().map(|component| component.as_os_str().to_string_lossy()).collect();
+                regex.is_match(&components.join("/"))
+            }
+        }
+    }
+
+    /// Returns `true` if the file at `path` of the file tree matches the filter, including its
+    /// modification time and its owner. A file whose modification time or owner is unknown doesn't
+    /// match a time bound or an owner.
+    ///
+    /// # Arguments
+    ///
+    /// * `file_tree` - The file tree containing the file.
+    /// * `path` - The path of the file to test.
+    pub fn is_match_in(&self, file_tree: &FileTree, path: &Path) -> bool {
+        if !self.is_match(file_tree.get_root(), path) {
+            return false;
+        }
+        if let Some(owner) = &self.owned_by {
+            if file_tree.get_ownership(path).and_then(|ownership| ownership.owner.as_ref()) != Some(owner) {
+                return false;
+            }
+        }
+        if self.modified_before.is_none() && self.modified_after.is_none() {
+            return true;
+        }
+        file_tree.get_modified(path).is_some_and(|modified| {
+            self.modified_before.is_none_or(|before| modified < before) && self.modified_after.is_none_or(|after| modified > after)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extension_shortcut_test() {
+        let filter = Filter::new("jpg", false, false).unwrap();
+        assert!(filter.is_match(Path::new("root"), Path::new("root/photo.jpg")));
+        assert!(!filter.is_match(Path::new("root"), Path::new("root/jpg")));
+    }
+
+    #[test]
+    fn extension_list_test() {
+        let filter = Filter::new("jpg, jpeg,png", false, false).unwrap();
+        assert!(filter.is_match(Path::new("root"), Path::new("root/a/photo.JPG")));
+        assert!(filter.is_match(Path::new("root"), Path::new("root/a/photo.jpeg")));
+        assert!(filter.is_match(Path::new("root"), Path::new("root/a/image.Png")));
+        assert!(!filter.is_match(Path::new("root"), Path::new("root/a/notes.txt")));
+        assert!(!Filter::new("jpg,png", false, true).unwrap().is_match(Path::new("root"), Path::new("root/a/photo.JPG")));
+    }
+
+    #[test]
+    fn name_test() {
+        let filter = Filter::default().name("backup_*.tar.gz", true).unwrap();
+        assert!(filter.is_match(Path::new("root"), Path::new("root/a/backup_2024.tar.gz")));
+        assert!(!filter.is_match(Path::new("root"), Path::new("root/a/BACKUP_2024.tar.gz")));
+        assert!(!filter.is_match(Path::new("root"), Path::new("root/backup_2024.tar.gz/notes.txt")));
+        assert!(Filter::default().name("backup_*", false).unwrap().is_match(Path::new("root"), Path::new("root/BACKUP_1")));
+
+        let filter = Filter::new("gz", false, false).unwrap().name("*2024*", false).unwrap();
+        assert!(filter.is_match(Path::new("root"), Path::new("root/backup_2024.tar.gz")));
+        assert!(!filter.is_match(Path::new("root"), Path::new("root/backup_2024.tar")));
+        assert!(Filter::default().name("[", true).is_err());
+    }
+
+    #[test]
+    fn glob_name_test() {
+        let filter = Filter::new("IMG_????.png", false, false).unwrap();
+        assert!(filter.is_match(Path::new("root"), Path::new("root/a/IMG_0001.png")));
+        assert!(!filter.is_match(Path::new("root"), Path::new("root/a/IMG_01.png")));
+    }
+
+    #[test]
+    fn case_sensitivity_test() {
+        let insensitive = Filter::new("*.jpg", false, false).unwrap();
+        let sensitive = Filter::new("*.jpg", false, true).unwrap();
+        assert!(insensitive.is_match(Path::new("root"), Path::new("root/PHOTO.JPG")));
+        assert!(!sensitive.is_match(Path::new("root"), Path::new("root/PHOTO.JPG")));
+    }
+
+    #[test]
+    fn path_mode_test() {
+        let filter = Filter::new("photos/*.jpg", true, false).unwrap();
+        assert!(filter.is_match(Path::new("root"), Path::new("root/photos/a.jpg")));
+        assert!(!filter.is_match(Path::new("root"), Path::new("root/other/photos/a.jpg")));
+    }
+
+    #[test]
+    fn regex_test() {
+        let filter = Filter::regex(r"^photos/.*\.jpe?g$", false).unwrap();
+        assert!(filter.is_match(Path::new("root"), Path::new("root/photos/2023/a.JPEG")));
+        assert!(!filter.is_match(Path::new("root"), Path::new("root/other/photos/a.jpg")));
+        assert!(Filter::regex("2023", true).unwrap().is_match(Path::new("root"), Path::new("root/photos/2023/a.png")));
+    }
+
+    #[test]
+    fn invalid_pattern_test() {
+        assert!(Filter::new("[", false, false).is_err());
+        assert!(Filter::regex("(", false).is_err());
+    }
+
+    #[test]
+    fn modified_test() {
+        use std::time::{Duration, UNIX_EPOCH};
+        let fixture = crate::test_utils::create_fixture();
+        let root = fixture.path().join("Test");
+        let old_file = root.join("Dossier1/Fichier1");
+        std::fs::File::options().write(true).open(&old_file).unwrap().set_modified(UNIX_EPOCH + Duration::from_secs(1_000_000)).unwrap();
+        let file_tree = FileTree::new(&root).unwrap();
+        let cutoff = UNIX_EPOCH + Duration::from_secs(2_000_000);
+
+        let older = Filter::default().modified_before(cutoff);
+        assert!(older.is_match_in(&file_tree, &old_file));
+        assert!(!older.is_match_in(&file_tree, &root.join("Dossier2/Fichier3")));
+
+        let newer = Filter::new("Fichier*", false, false).unwrap().modified_after(cutoff);
+        assert!(!newer.is_match_in(&file_tree, &old_file));
+        assert!(newer.is_match_in(&file_tree, &root.join("Dossier2/Fichier3")));
+        assert!(newer.is_match(&root, &old_file));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn owned_by_test() {
+        use std::os::unix::fs::MetadataExt;
+        let fixture = crate::test_utils::create_fixture();
+        let root = fixture.path().join("Test");
+        let file = root.join("Dossier1/Fichier1");
+        let file_tree = FileTree::new(&root).unwrap();
+        let uid = std::fs::metadata(&file).unwrap().uid();
+
+        assert!(Filter::default().owned_by(Owner::Uid(uid)).is_match_in(&file_tree, &file));
+        assert!(!Filter::default().owned_by(Owner::Uid(uid + 1)).is_match_in(&file_tree, &file));
+        assert!(!Filter::new("*.jpg", false, false).unwrap().owned_by(Owner::Uid(uid)).is_match_in(&file_tree, &file));
+    }
+}
@@ -0,0 +1,186 @@
+use std::io::{self, Write};
+use std::path::Path;
+use crate::{error::Result, file_tree::{FileTree, ScanProgress}, hasher::Hasher, print_tree::{PrintOptions, Summary}, size::Size, stats::{self, TypeStats}};
+
+/// Several file trees scanned in one invocation, reported together with a grand total.
+///
+/// # Example
+///
+/// ```
+/// use du::{FileTree, Forest, Size};
+///
+/// # let dir = tempfile::tempdir()?;
+/// std::fs::create_dir(dir.path().join("a"))?;
+/// std::fs::create_dir(dir.path().join("b"))?;
+/// std::fs::write(dir.path().join("a/x"), [0; 10])?;
+/// std::fs::write(dir.path().join("b/y"), [0; 20])?;
+///
+/// let forest = Forest::new(vec![FileTree::new(&dir.path().join("a"))?, FileTree::new(&dir.path().join("b"))?]);
+/// assert_eq!(forest.total_size(), Size::new(30));
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[derive(Debug, Default)]
+pub struct Forest {
+    trees: Vec<FileTree>,
+}
+
+impl Forest {
+
+    /// Creates a forest from file trees, kept in the given order.
+    pub fn new(trees: Vec<FileTree>) -> Self {
+        Forest { trees }
+    }
+
+    /// Returns the file trees of the forest.
+    pub fn trees(&self) -> &[FileTree] {
+        &self.trees
+    }
+
+    /// Returns the total size of the trees. A tree whose root is inside another tree is counted once.
+    pub fn total_size(&self) -> Size {
+        self.total(FileTree::get_size)
+    }
+
+    /// Returns the total disk usage of the trees. A tree whose root is inside another tree is counted once.
+    pub fn total_disk_usage(&self) -> Size {
+        self.total(FileTree::get_disk_usage)
+    }
+
+    /// Returns the totals of all the trees, see `FileTree::summary`. A tree whose root is inside
+    /// another tree is counted once, but its unreadable entries are all counted.
+    pub fn summary(&self, options: &PrintOptions) -> Summary {
+        let mut summary = Summary::default();
+        for (index, tree) in self.trees.iter().enumerate() {
+            let tree_summary = tree.summary(options);
+            if !self.is_nested(index, tree) {
+                summary.size = summary.size + tree_summary.size;
+                summary.counts.files += tree_summary.counts.files;
+                summary.counts.directories += tree_summary.counts.directories;
+            }
+            summary.errors += tree_summary.errors;
+        }
+        summary
+    }
+
+    /// Returns the total size and number of files per owning user in all the trees, see
+    /// `FileTree::owner_stats`. A tree whose root is inside another tree is counted once.
+    pub fn owner_stats(&self, options: &PrintOptions) -> Vec<TypeStats> {
+        let trees = self.trees.iter().enumerate().filter(|(index, tree)| !self.is_nested(*index, tree));
+        stats::merge(trees.flat_map(|(_, tree)| tree.owner_stats(options)))
+    }
+
+    /// Returns the first tree containing the entry at `path`, if any.
+    pub fn tree_of(&self, path: &Path) -> Option<&FileTree> {
+        self.trees.iter().find(|tree| tree.get_map_option(path).is_some())
+    }
+
+    /// Computes the signatures of the files of all the trees that may have a duplicate in any of
+    /// them, see `FileTree::compute_signatures_between`: a file is hashed when a file of the same
+    /// size exists in the same tree or in another one.
+    ///
+    /// # Arguments
+    ///
+    /// * `hasher` - The hash function to use.
+    /// * `min_size` - The minimum size of the files to hash.
+    /// * `max_size` - The maximum size of the files to hash, if any.
+    /// * `callback` - The function receiving the progress of the hashing.
+    ///
+    /// # Returns
+    ///
+    /// An empty `Result`, failing with a `Hash` error if a file could not be read.
+    pub fn compute_signatures_between(&mut self, hasher: &dyn Hasher, min_size: Size, max_size: Option<Size>, callback: impl FnMut(&ScanProgress) + Send) -> Result<()> {
+        let mut trees: Vec<&mut FileTree> = self.trees.iter_mut().collect();
+        FileTree::compute_signatures_across(&mut trees, hasher, min_size, max_size, callback)
+    }
+
+    fn total(&self, size: fn(&FileTree, &Path) -> Option<Size>) -> Size {
+        self.trees
+            .iter()
+            .enumerate()
+            .filter(|(index, tree)| !self.is_nested(*index, tree))
+            .filter_map(|(_, tree)| size(tree, tree.get_root()))
+            .fold(Size::new(0), |total, size| total + size)
+    }
+
+    /// Returns `true` if the root of the tree at `index` is inside another tree, or is the root of an earlier tree.
+    fn is_nested(&self, index: usize, tree: &FileTree) -> bool {
+        self.trees.iter().enumerate().any(|(other_index, other)| {
+            other_index != index
+                && tree.get_root().starts_with(other.get_root())
+                && (tree.get_root() != other.get_root() || other_index < index)
+        })
+    }
+
+    /// Writes the usage records of all the trees as a single CSV table, see `FileTree::write_csv`.
+    ///
+    /// # Arguments
+    ///
+    /// * `out` - The writer receiving the CSV.
+    /// * `options` - The display options, selecting the apparent size or the disk usage.
+    pub fn write_csv<W: Write>(&self, out: W, options: &PrintOptions) -> io::Result<()> {
+        let mut writer = csv::Writer::from_writer(out);
+        FileTree::write_csv_header(&mut writer)?;
+        for tree in &self.trees {
+            tree.write_csv_records(&mut writer, options)?;
+        }
+        writer.flush()
+    }
+
+    /// Writes the trees as a JSON array of nested objects, see `FileTree::write_json`.
+    ///
+    /// # Arguments
+    ///
+    /// * `out` - The writer receiving the JSON.
+    /// * `options` - The display options, selecting the apparent size or the disk usage.
+    pub fn write_json<W: Write>(&self, mut out: W, options: &PrintOptions) -> io::Result<()> {
+        let nodes: Vec<_> = self.trees.iter().filter_map(|tree| tree.usage_node(tree.get_root(), options)).collect();
+        serde_json::to_writer_pretty(&mut out, &nodes)?;
+        writeln!(out)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::create_fixture;
+
+    #[test]
+    fn nested_roots_counted_once_test() {
+        let fixture = create_fixture();
+        let root = fixture.path().join("Test");
+        let forest = Forest::new(vec![
+            FileTree::new(&root.join("Dossier2")).unwrap(),
+            FileTree::new(&root).unwrap(),
+            FileTree::new(&root).unwrap(),
+            FileTree::new(&root.join("Dossier1")).unwrap(),
+        ]);
+        assert_eq!(forest.total_size(), Size::new(521280));
+
+        let forest = Forest::new(vec![FileTree::new(&root.join("Dossier1")).unwrap(), FileTree::new(&root.join("Dossier2")).unwrap()]);
+        assert_eq!(forest.total_size(), Size::new(521280));
+    }
+
+    #[test]
+    fn forest_csv_test() {
+        let fixture = create_fixture();
+        let root = fixture.path().join("Test");
+        let forest = Forest::new(vec![FileTree::new(&root.join("Dossier1")).unwrap(), FileTree::new(&root.join("Dossier2")).unwrap()]);
+
+        let mut out = Vec::new();
+        forest.write_csv(&mut out, &PrintOptions::default()).unwrap();
+        let output = String::from_utf8(out).unwrap();
+        assert_eq!(output.lines().filter(|line| line.starts_with("path,")).count(), 1);
+        assert_eq!(output.lines().count(), 1 + 2 + 6);
+    }
+
+    #[test]
+    fn forest_summary_test() {
+        let fixture = create_fixture();
+        let root = fixture.path().join("Test");
+        let forest = Forest::new(vec![FileTree::new(&root.join("Dossier1")).unwrap(), FileTree::new(&root.join("Dossier2")).unwrap(), FileTree::new(&root.join("Dossier2/SousDossier1")).unwrap()]);
+        let summary = forest.summary(&PrintOptions::default());
+        assert_eq!(summary.size, Size::new(521280));
+        assert_eq!((summary.counts.files, summary.counts.directories), (4, 2));
+    }
+}
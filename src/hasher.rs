@@ -0,0 +1,188 @@
+use md5::{Digest, Md5};
+use sha2::Sha256;
+use std::fmt;
+use std::io::{self, Read};
+use std::str::FromStr;
+use xxhash_rust::xxh3::Xxh3;
+
+/// Size of the buffer used to read the files.
+const BUFFER_SIZE: usize = 64 * 1024;
+
+/// A hash function computing the signatures used to detect duplicate files.
+///
+/// Two files are considered identical when their signatures are equal, so the function
+/// should be collision resistant.
+pub trait Hasher: Send + Sync {
+    /// Returns the signature of the whole content of `reader`.
+    fn hash(&self, reader: &mut dyn Read) -> io::Result<String>;
+
+    /// Returns the name of the hash function. Signatures computed by hash functions of different
+    /// names are never compared.
+    fn name(&self) -> &'static str {
+        std::any::type_name::<Self>()
+    }
+}
+
+/// Feeds the whole content of `reader` to `update`, one buffer at a time.
+fn read_all(reader: &mut dyn Read, mut update: impl FnMut(&[u8])) -> io::Result<()> {
+    let mut buffer = vec![0; BUFFER_SIZE];
+    loop {
+        let bytes_read = reader.read(&mut buffer)?;
+        if bytes_read == 0 {
+            return Ok(());
+        }
+        update(&buffer[..bytes_read]);
+    }
+}
+
+/// The BLAKE3 hash function, fast and cryptographically strong. This is the default hasher.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Blake3Hasher;
+
+impl Hasher for Blake3Hasher {
+    fn hash(&self, reader: &mut dyn Read) -> io::Result<String> {
+        let mut hasher = blake3::Hasher::new();
+        read_all(reader, |bytes| {
+            hasher.update(bytes);
+        })?;
+        Ok(hasher.finalize().to_hex().to_string())
+    }
+
+    fn name(&self) -> &'static str {
+        "blake3"
+    }
+}
+
+/// The MD5 hash function, kept for compatibility with the signatures of previous versions.
+///
+/// MD5 collisions can be forged, prefer `Blake3Hasher`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Md5Hasher;
+
+impl Hasher for Md5Hasher {
+    fn hash(&self, reader: &mut dyn Read) -> io::Result<String> {
+        let mut hasher = Md5::new();
+        read_all(reader, |bytes| hasher.update(bytes))?;
+        Ok(format!("{:x}", hasher.finalize()))
+    }
+
+    fn name(&self) -> &'static str {
+        "md5"
+    }
+}
+
+/// The SHA-256 hash function, slower than BLAKE3 but widely available to check the signatures.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Sha256Hasher;
+
+impl Hasher for Sha256Hasher {
+    fn hash(&self, reader: &mut dyn Read) -> io::Result<String> {
+        let mut hasher = Sha256::new();
+        read_all(reader, |bytes| hasher.update(bytes))?;
+        Ok(format!("{:x}", hasher.finalize()))
+    }
+
+    fn name(&self) -> &'static str {
+        "sha256"
+    }
+}
+
+/// The 128-bit XXH3 hash function, the fastest one.
+///
+/// XXH3 is not cryptographic: accidental collisions are unlikely, but they can be forged.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct XxHasher;
+
+impl Hasher for XxHasher {
+    fn hash(&self, reader: &mut dyn Read) -> io::Result<String> {
+        let mut hasher = Xxh3::new();
+        read_all(reader, |bytes| hasher.update(bytes))?;
+        Ok(format!("{:032x}", hasher.digest128()))
+    }
+
+    fn name(&self) -> &'static str {
+        "xxh3"
+    }
+}
+
+/// The hash functions available to compute the signatures, selected by name.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum HashAlgo {
+    Md5,
+    Sha256,
+    #[default]
+    Blake3,
+    XxHash,
+}
+
+impl HashAlgo {
+    /// Returns the hash function.
+    pub fn hasher(self) -> &'static dyn Hasher {
+        match self {
+            HashAlgo::Md5 => &Md5Hasher,
+            HashAlgo::Sha256 => &Sha256Hasher,
+            HashAlgo::Blake3 => &Blake3Hasher,
+            HashAlgo::XxHash => &XxHasher,
+        }
+    }
+}
+
+impl FromStr for HashAlgo {
+    type Err = io::Error;
+
+    fn from_str(text: &str) -> io::Result<Self> {
+        match text.trim().to_ascii_lowercase().as_str() {
+            "md5" => Ok(HashAlgo::Md5),
+            "sha256" | "sha-256" => Ok(HashAlgo::Sha256),
+            "blake3" => Ok(HashAlgo::Blake3),
+            "xxhash" | "xxh3" => Ok(HashAlgo::XxHash),
+            _ => Err(io::Error::new(io::ErrorKind::InvalidInput, format!("invalid hash function: {:?} (expected md5, sha256, blake3 or xxhash)", text))),
+        }
+    }
+}
+
+impl fmt::Display for HashAlgo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.hasher().name())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blake3_test() {
+        let signature = Blake3Hasher.hash(&mut &b"abc"[..]).unwrap();
+        assert_eq!(signature, "6437b3ac38465133ffb63b75273a8db548c558465d79db03fd359c6cd5bd9d85");
+    }
+
+    #[test]
+    fn digests_test() {
+        assert_eq!(Md5Hasher.hash(&mut &b"abc"[..]).unwrap(), "900150983cd24fb0d6963f7d28e17f72");
+        assert_eq!(Sha256Hasher.hash(&mut &b"abc"[..]).unwrap(), "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+        assert_eq!(XxHasher.hash(&mut &b"abc"[..]).unwrap().len(), 32);
+    }
+
+    #[test]
+    fn same_content_same_signature_test() {
+        let hashers: [&dyn Hasher; 4] = [&Blake3Hasher, &Md5Hasher, &Sha256Hasher, &XxHasher];
+        for hasher in hashers {
+            let content = vec![7; 3 * BUFFER_SIZE + 1];
+            let a = hasher.hash(&mut content.as_slice()).unwrap();
+            let b = hasher.hash(&mut content.as_slice()).unwrap();
+            let c = hasher.hash(&mut &content[1..]).unwrap();
+            assert_eq!(a, b);
+            assert_ne!(a, c);
+        }
+    }
+
+    #[test]
+    fn hash_algo_test() {
+        assert_eq!("SHA256".parse::<HashAlgo>().unwrap(), HashAlgo::Sha256);
+        assert_eq!("xxh3".parse::<HashAlgo>().unwrap(), HashAlgo::XxHash);
+        assert_eq!(HashAlgo::default().to_string(), "blake3");
+        assert!("crc32".parse::<HashAlgo>().is_err());
+    }
+}
@@ -0,0 +1,174 @@
+use std::io::{self, Write};
+use std::path::Path;
+use crate::{export::{Report, UsageNode}, size::{Size, SizeFormat}};
+
+/// The style of the HTML report.
+const STYLE: &str = "
+body { font-family: sans-serif; margin: 2em; color: #222; }
+h1, h2 { font-weight: normal; }
+.header, summary, .file { display: grid; grid-template-columns: 7em 12em 1fr; gap: 1em; padding: 1px 0; }
+.header { font-weight: bold; border-bottom: 1px solid #ccc; }
+.header span, th { cursor: pointer; user-select: none; }
+.children { margin-left: 1.5em; }
+.size { text-align: right; font-variant-numeric: tabular-nums; }
+.bar { background: #eee; height: 0.8em; align-self: center; }
+.bar div { background: #4a90d9; height: 100%; }
+summary { cursor: pointer; }
+summary .name { color: #1a5fa8; }
+table { border-collapse: collapse; }
+th, td { text-align: left; padding: 2px 1em 2px 0; vertical-align: top; }
+td.size { text-align: right; }
+";
+
+/// Sorts the children of every directory, or the rows of a table, when a column header is clicked.
+const SCRIPT: &str = "
+function sortBy(elements, key, descending) {
+  elements.sort((a, b) => {
+    const x = a.dataset[key], y = b.dataset[key];
+    const order = key === 'name' ? x.localeCompare(y) : Number(x) - Number(y);
+    return descending ? -order : order;
+  });
+  return elements;
+}
+document.querySelectorAll('.header span[data-key]').forEach(header => header.addEventListener('click', () => {
+  header.descending = !header.descending;
+  document.querySelectorAll('.children').forEach(children =>
+    sortBy([...children.children], header.dataset.key, header.descending).forEach(child => children.appendChild(child)));
+}));
+document.querySelectorAll('th[data-key]').forEach(header => header.addEventListener('click', () => {
+  header.descending = !header.descending;
+  const body = header.closest('table').tBodies[0];
+  sortBy([...body.rows], header.dataset.key, header.descending).forEach(row => body.appendChild(row));
+}));
+";
+
+
+impl Report<'_> {
+
+    /// Writes the report as a standalone HTML page, with no external resource: the trees as
+    /// collapsible nodes whose children can be sorted by size or by name, and the groups of
+    /// duplicates as a table sortable by column.
+    ///
+    /// # Arguments
+    ///
+    /// * `out` - The writer receiving the HTML.
+    /// * `format` - How the sizes are displayed.
+    pub fn write_html<W: Write>(&self, mut out: W, format: SizeFormat) -> io::Result<()> {
+        writeln!(out, "<!DOCTYPE html>")?;
+        writeln!(out, "<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n<title>Disk usage report</title>")?;
+        writeln!(out, "<style>{}</style>\n</head>\n<body>", STYLE)?;
+
+        if !self.trees.is_empty() {
+            writeln!(out, "<h1>Disk usage</h1>")?;
+            writeln!(out, "<div class=\"header\"><span data-key=\"size\">Size</span><span></span><span data-key=\"name\">Name</span></div>")?;
+            writeln!(out, "<div class=\"children\">")?;
+            for tree in &self.trees {
+                write_node(&mut out, tree, tree.size, format, true)?;
+            }
+            writeln!(out, "</div>")?;
+        }
+
+        if !self.groups.is_empty() {
+            let reclaimable = self.groups.iter().map(|group| group.reclaimable).sum::<u64>();
+            writeln!(out, "<h2>Duplicates</h2>")?;
+            writeln!(out, "<p>{} groups, {} reclaimable</p>", self.groups.len(), Size::new(reclaimable).display(format))?;
+            writeln!(out, "<table>\n<thead><tr><th data-key=\"size\">Size</th><th data-key=\"count\">Copies</th><th data-key=\"reclaimable\">Reclaimable</th><th>Paths</th></tr></thead>\n<tbody>")?;
+            for group in &self.groups {
+                let reclaimable = group.reclaimable;
+                writeln!(
+                    out,
+                    "<tr data-size=\"{}\" data-count=\"{}\" data-reclaimable=\"{}\"><td class=\"size\">{}</td><td>{}</td><td class=\"size\">{}</td><td>{}</td></tr>",
+                    group.size,
+                    group.count,
+                    reclaimable,
+                    Size::new(group.size).display(format),
+                    group.count,
+                    Size::new(reclaimable).display(format),
+                    group.paths.iter().map(|path| escape(path)).collect::<Vec<_>>().join("<br>"),
+                )?;
+            }
+            writeln!(out, "</tbody>\n</table>")?;
+        }
+
+        writeln!(out, "<script>{}</script>\n</body>\n</html>", SCRIPT)
+    }
+}
+
+/// Writes a node and its children, each directory being a collapsible element, with a bar
+/// showing its share of `parent_size`.
+fn write_node<W: Write>(out: &mut W, node: &UsageNode, parent_size: u64, format: SizeFormat, open: bool) -> io::Result<()> {
+    let name = match Path::new(&node.path).file_name() {
+        Some(name) if !open => name.to_string_lossy(),
+        _ => node.path.as_str().into(),
+    };
+    let share = if parent_size == 0 { 0.0 } else { node.size as f64 * 100.0 / parent_size as f64 };
+    let columns = format!(
+        "<span class=\"size\">{}</span><span class=\"bar\"><div style=\"width: {:.1}%\"></div></span><span class=\"name\">{}</span>",
+        Size::new(node.size).display(format),
+        share,
+        escape(&name),
+    );
+    let data = format!("data-size=\"{}\" data-name=\"{}\"", node.size, escape(&name));
+    match &node.children {
+        Some(children) => {
+            writeln!(out, "<details {}{}><summary>{}</summary>", data, if open { " open" } else { "" }, columns)?;
+            writeln!(out, "<div class=\"children\">")?;
+            for child in children {
+                write_node(out, child, node.size, format, false)?;
+            }
+            writeln!(out, "</div></details>")
+        }
+        None => writeln!(out, "<div class=\"file\" {}>{}</div>", data, columns),
+    }
+}
+
+/// Escapes the characters having a meaning in HTML text and attribute values.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_tree::FileTree;
+    use crate::print_tree::PrintOptions;
+    use crate::test_utils::{create_file, create_fixture};
+    use std::fs;
+
+    #[test]
+    fn html_report_test() {
+        let fixture = create_fixture();
+        let root = fixture.path().join("Test");
+        create_file(&root.join("<script>"), 10);
+        fs::write(root.join("a"), [1; 100]).unwrap();
+        fs::write(root.join("b"), [1; 100]).unwrap();
+        let mut file_tree = FileTree::new(&root).unwrap();
+        file_tree.compute_signatures().unwrap();
+        let duplicates = file_tree.find_duplicates_with(Size::new(1));
+
+        let mut out = Vec::new();
+        let report = Report::usage(std::slice::from_ref(&file_tree), &PrintOptions::default()).with_duplicates(&duplicates);
+        report.write_html(&mut out, SizeFormat::default()).unwrap();
+        let html = String::from_utf8(out).unwrap();
+
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.ends_with("</html>\n"));
+        assert!(!html.contains("src=") && !html.contains("href="));
+        assert!(html.contains("&lt;script&gt;"));
+        assert_eq!(html.matches("<details").count(), file_tree.directories().count());
+        assert!(html.contains(&format!("<details data-size=\"521490\" data-name=\"{}\" open>", root.display())));
+        assert!(html.contains("<tr data-size=\"100\" data-count=\"2\" data-reclaimable=\"100\">"));
+    }
+}
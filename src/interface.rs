@@ -1,157 +1,800 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process::{self, Stdio};
+use std::time::{Duration, Instant};
 use std::{error::Error, io};
-use std::process::Command;
-
-
 
 use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEventKind},
+    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEventKind, MouseButton, MouseEvent, MouseEventKind},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
 use ratatui::{prelude::*, widgets::*};
 
-use clap::{Parser, Arg};
+use clap::Parser;
+use du::{Blake3Hasher, Config, DuplicateGroup, DuplicateReport, EntryNode, FileTree, Filter, Hasher, Resolution, Size, SortKey, SortOrder, UsageNode};
 
+/// Interactive browser of the disk usage tree.
 #[derive(Parser, Debug)]
-struct ShellCommand {
-    #[arg(short, long)]
-    lexicographic_sort: bool,
+struct Args {
+    /// The path to browse (default '.')
+    path: Option<PathBuf>,
+
+    /// Include the hidden entries: names starting with a dot and, on Windows, hidden or system files.
+    #[arg(short = 'a', long = "all")]
+    all: bool,
+}
+
+/// The longest delay between the two clicks of a double click.
+const DOUBLE_CLICK: Duration = Duration::from_millis(400);
+
+/// The number of lines scrolled by a turn of the mouse wheel.
+const WHEEL_LINES: usize = 3;
+
+/// The key bindings listed by the help popup, with what they do.
+const KEYS: [(&str, &str); 19] = [
+    ("q", "exit"),
+    ("?", "show this help"),
+    ("↑/↓", "move the selection"),
+    ("PgUp/PgDn", "scroll a page"),
+    ("Home/End", "go to the first/last line"),
+    ("→/←", "expand/collapse, or double click"),
+    ("Enter", "show the details of a file"),
+    ("o", "open with the default application"),
+    ("d", "show the duplicates of the selected file"),
+    ("space", "mark a copy in the duplicates view"),
+    ("D/H", "delete/hardlink the marked copies"),
+    ("s", "change the sort order"),
+    ("f", "filter the entries"),
+    ("0-9", "collapse to a depth"),
+    ("h/Backspace, l", "go back/forward"),
+    ("b", "bookmark the selected entry"),
+    ("g", "go to the next bookmark"),
+    ("e", "export the selected subtree to a .txt, .json or .csv file"),
+    (":", "run a command: cd, filter, depth, export, rescan, dup, quit"),
+];
+
+/// The view currently displayed by the application.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum View {
+    Tree,
+    Duplicates,
+}
 
-    #[arg(short, long)]
-    filter: Option<String>,
+/// A line of the duplicates view: the header of a group, or one of its files, by index.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct DuplicateRow {
+    group: usize,
+    file: Option<usize>,
 }
 
+/// A visible line of the tree view.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Row {
+    path: PathBuf,
+    /// Depth below the root, its children being at depth 0
+    depth: usize,
+}
+
+/// The metadata of a file displayed in a popup.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Details {
+    path: PathBuf,
+    /// Names and values of the fields, in display order
+    fields: Vec<(&'static str, String)>,
+}
+
+/// The formats of the subtrees exported from the tree view, selected by the extension of the
+/// file written.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ExportFormat {
+    Text,
+    Json,
+    Csv,
+}
+
+impl ExportFormat {
+    /// Returns the format of `path`: JSON for `.json`, CSV for `.csv`, and indented text otherwise.
+    fn of(path: &Path) -> ExportFormat {
+        match path.extension().map(|extension| extension.to_string_lossy().to_lowercase()).as_deref() {
+            Some("json") => ExportFormat::Json,
+            Some("csv") => ExportFormat::Csv,
+            _ => ExportFormat::Text,
+        }
+    }
+}
 
-enum InputMode {
-    Normal,
-    Editing,
+/// A command typed after `:`, run directly on the library instead of through a shell.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Command {
+    /// Expands the directories leading to a path, relative to the root or absolute, and selects it
+    Goto(PathBuf),
+    /// Scans the root again
+    Rescan,
+    /// Switches to the duplicates view
+    Duplicates,
+    /// Displays only the files matching a glob pattern or extensions, and their directories, or all the entries without pattern
+    Filter(Option<String>),
+    /// Expands the directories above a depth and collapses the others, the children of the root being at depth 0
+    Depth(usize),
+    /// Writes the selected subtree, as displayed with the filter and the sort, to a file
+    Export(PathBuf),
+    /// Exits the application
+    Quit,
+}
+
+/// Parses a command line typed after `:`.
+fn parse_command(line: &str) -> Result<Command, String> {
+    let line = line.trim();
+    let (name, argument) = line.split_once(char::is_whitespace).map_or((line, ""), |(name, argument)| (name, argument.trim()));
+    match (name, argument) {
+        ("cd" | "goto", "") => Err(format!("{}: missing path", name)),
+        ("cd" | "goto", path) => Ok(Command::Goto(PathBuf::from(path))),
+        ("rescan", "") => Ok(Command::Rescan),
+        ("dup" | "duplicates", "") => Ok(Command::Duplicates),
+        ("filter", "") => Ok(Command::Filter(None)),
+        ("filter", pattern) => Ok(Command::Filter(Some(pattern.to_string()))),
+        ("export", "") => Err("export: missing file".to_string()),
+        ("export", file) => Ok(Command::Export(PathBuf::from(file))),
+        ("depth", depth) => depth.parse().map(Command::Depth).map_err(|_| format!("depth: invalid depth {:?}", depth)),
+        ("q" | "quit", "") => Ok(Command::Quit),
+        _ => Err(format!("Unknown command: {}", line)),
+    }
 }
 
 /// App holds the state of the application
 struct App {
-    /// Current value of the input box
-    input: String,
-    /// Position of cursor in the editor area.
-    cursor_position: usize,
-    /// Current input mode
-    input_mode: InputMode,
-    /// History of recorded output
-    output: Vec<String>,
-    /// Position dans l'historique de sortie à partir de laquelle afficher
-    output_view_position: usize,
-    ///commande possible
-    possible_commands: &'static str,
+    /// The scanned file tree
+    file_tree: FileTree,
+    /// Directories whose children are displayed
+    expanded: HashSet<PathBuf>,
+    /// Current view
+    view: View,
+    /// Selection and scroll offset of the list
+    list_state: ListState,
+    /// Groups of duplicates sorted by decreasing reclaimable space, computed on first use
+    duplicates: Option<DuplicateReport>,
+    /// Message displayed in the status bar until the next key press
+    message: Option<String>,
+    /// Command line being typed after `:`
+    input: Option<String>,
+    /// Whether the hidden entries are scanned again by `Command::Rescan`
+    show_hidden: bool,
+    /// Copies marked in the duplicates view, to delete or to replace with hard links
+    marked: HashSet<PathBuf>,
+    /// Action on the marked copies waiting for confirmation
+    pending: Option<Resolution>,
+    /// Order of the children of each directory
+    sort: SortOrder,
+    /// Filter of the tree view, with its pattern
+    filter: Option<(String, Filter)>,
+    /// Entries displayed with the filter: the matching files and the directories containing them
+    matching: HashSet<PathBuf>,
+    /// Area of the lines of the list in the last frame, inside its borders, to find the clicked line
+    list_area: Rect,
+    /// Time and line of the last click, to detect a double click
+    last_click: Option<(Instant, usize)>,
+    /// Metadata of the file displayed in a popup until the next key press
+    details: Option<Details>,
+    /// Whether the key bindings are displayed in a popup until the next key press
+    help: bool,
+    /// Entries selected before the last jumps, the latest last
+    back: Vec<PathBuf>,
+    /// Entries left by going back, the latest last
+    forward: Vec<PathBuf>,
+    /// Absolute paths of the bookmarked entries
+    bookmarks: Vec<PathBuf>,
+    /// Configuration file the bookmarks are saved to, if any
+    config_path: Option<PathBuf>,
 }
 
-impl Default for App {
-    fn default() -> App {
+impl App {
+    fn new(file_tree: FileTree) -> App {
         App {
-            input: String::new(),
-            input_mode: InputMode::Normal,
-            output: Vec::new(),
-            cursor_position: 0,
-            output_view_position: 0,
-            possible_commands: "Possible commands:\n\
-                               - cargo run --bin main -- usage option<path>\n\
-                               - cargo run --bin --main -- --lexicographic-sort usage option<path>\n\
-                               - cargo run --bin main  -- option<--lexicographic-sort> --filter jpg usage option<path>\n\
-                               - cargo run --bin main -- duplicate",
+            file_tree,
+            expanded: HashSet::new(),
+            view: View::Tree,
+            list_state: ListState::default().with_selected(Some(0)),
+            duplicates: None,
+            message: None,
+            input: None,
+            show_hidden: false,
+            marked: HashSet::new(),
+            pending: None,
+            sort: SortOrder::new(SortKey::Size),
+            filter: None,
+            matching: HashSet::new(),
+            list_area: Rect::default(),
+            last_click: None,
+            details: None,
+            help: false,
+            back: Vec::new(),
+            forward: Vec::new(),
+            bookmarks: Vec::new(),
+            config_path: None,
         }
     }
-}
 
-impl App {
-    fn move_cursor_left(&mut self) {
-        let cursor_moved_left = self.cursor_position.saturating_sub(1);
-        self.cursor_position = self.clamp_cursor(cursor_moved_left);
+    /// Returns the children of a directory displayed with the filter, in the order of `sort`, ties
+    /// being broken by name.
+    fn children(&self, path: &Path) -> Vec<&PathBuf> {
+        let mut children: Vec<_> = self.file_tree.get_children(path).unwrap_or(&[]).iter().filter(|child| self.filter.is_none() || self.matching.contains(*child)).collect();
+        children.sort_by(|a, b| {
+            let ordering = match self.sort.key {
+                SortKey::Size => self.file_tree.get_size(a).unwrap_or(Size::new(0)).cmp(&self.file_tree.get_size(b).unwrap_or(Size::new(0))),
+                SortKey::Mtime => self.file_tree.get_modified(a).cmp(&self.file_tree.get_modified(b)),
+                SortKey::Name | SortKey::Count => a.cmp(b),
+            };
+            let ordering = if self.sort.descending { ordering.reverse() } else { ordering };
+            ordering.then_with(|| a.cmp(b))
+        });
+        children
+    }
+
+    /// Selects the line of `path` in the tree view, or the first line if it is not visible.
+    fn select_path(&mut self, path: Option<PathBuf>) {
+        let selected = path.and_then(|path| self.rows().iter().position(|row| row.path == path));
+        self.list_state = ListState::default().with_selected(Some(selected.unwrap_or(0)));
+    }
+
+    /// Sorts the tree view by the next key: size, name, then modification time.
+    fn cycle_sort(&mut self) {
+        let key = match self.sort.key {
+            SortKey::Size => SortKey::Name,
+            SortKey::Name => SortKey::Mtime,
+            SortKey::Mtime | SortKey::Count => SortKey::Size,
+        };
+        let selected = self.selected_path();
+        self.sort = SortOrder::new(key);
+        self.select_path(selected);
+    }
+
+    /// Displays only the files matching `pattern` and the directories containing them, or all
+    /// the entries without pattern.
+    fn set_filter(&mut self, pattern: Option<String>) -> io::Result<()> {
+        let filter = pattern.map(|pattern| Filter::new(&pattern, false, false).map(|filter| (pattern, filter))).transpose()?;
+        let selected = self.selected_path();
+        self.matching.clear();
+        if let Some((_, filter)) = &filter {
+            let root = self.file_tree.get_root();
+            for file in self.file_tree.files().filter(|file| filter.is_match_in(&self.file_tree, file)) {
+                self.matching.extend(file.ancestors().take_while(|ancestor| *ancestor != root).map(Path::to_path_buf));
+            }
+        }
+        self.filter = filter;
+        self.view = View::Tree;
+        self.select_path(selected);
+        Ok(())
+    }
+
+    /// Expands the directories above `depth` and collapses the others, the children of the root
+    /// being at depth 0.
+    fn collapse_to(&mut self, depth: usize) {
+        let selected = self.selected_path();
+        self.expanded.clear();
+        let mut directories = vec![(self.file_tree.get_root().to_path_buf(), 0)];
+        while let Some((directory, directory_depth)) = directories.pop() {
+            for child in self.file_tree.get_children(&directory).unwrap_or(&[]) {
+                if directory_depth < depth && matches!(self.file_tree.get_map_option(child), Some(EntryNode::Directory(_))) {
+                    self.expanded.insert(child.clone());
+                    directories.push((child.clone(), directory_depth + 1));
+                }
+            }
+        }
+        self.view = View::Tree;
+        self.select_path(selected);
     }
 
-    fn move_cursor_right(&mut self) {
-        let cursor_moved_right = self.cursor_position.saturating_add(1);
-        self.cursor_position = self.clamp_cursor(cursor_moved_right);
+    /// Returns the visible lines of the tree view: the children of the root and,
+    /// below each expanded directory, its own children.
+    fn rows(&self) -> Vec<Row> {
+        let mut rows = Vec::new();
+        self.collect_rows(self.file_tree.get_root(), 0, false, &mut rows);
+        rows
     }
 
-    fn enter_char(&mut self, new_char: char) {
-        self.input.insert(self.cursor_position, new_char);
+    /// Adds the children of `path` to `rows`, and below each directory its own children if it is
+    /// expanded or if `all` is set.
+    fn collect_rows(&self, path: &Path, depth: usize, all: bool, rows: &mut Vec<Row>) {
+        for child in self.children(path) {
+            rows.push(Row { path: child.clone(), depth });
+            if all || self.expanded.contains(child) {
+                self.collect_rows(child, depth + 1, all, rows);
+            }
+        }
+    }
 
-        self.move_cursor_right();
+    /// Returns the type of the entry at `path`, as named in the CSV exports of the library.
+    fn kind(&self, path: &Path) -> &'static str {
+        match self.file_tree.get_map_option(path) {
+            Some(EntryNode::Directory(_)) if self.file_tree.get_archive_size(path).is_some() => "archive",
+            Some(EntryNode::Directory(_)) => "directory",
+            Some(EntryNode::Symlink(_)) => "symlink",
+            Some(EntryNode::Other(kind)) => kind.name(),
+            _ => "file",
+        }
     }
 
-    fn delete_char(&mut self) {
-        let is_not_cursor_leftmost = self.cursor_position != 0;
-        if is_not_cursor_leftmost {
-            // Method "remove" is not used on the saved text for deleting the selected char.
-            // Reason: Using remove on String works on bytes instead of the chars.
-            // Using remove would require special care because of char boundaries.
+    /// Returns the nested representation of the subtree rooted at `path`, with the children
+    /// displayed with the filter, in the order of the sort.
+    fn usage_node(&self, path: &Path) -> UsageNode {
+        let children = match self.file_tree.get_map_option(path) {
+            Some(EntryNode::Directory(_)) => Some(self.children(path).into_iter().map(|child| self.usage_node(child)).collect()),
+            _ => None,
+        };
+        UsageNode { path: path.display().to_string(), size: self.file_tree.get_size(path).unwrap_or(Size::new(0)).value(), children }
+    }
 
-            let current_index = self.cursor_position;
-            let from_left_to_current_index = current_index - 1;
+    /// Writes the selected subtree of the tree view to `file`, every directory being expanded, with
+    /// the filter and the sort of the view, in the format given by the extension of `file`.
+    fn export(&mut self, file: &Path) -> io::Result<()> {
+        if self.view != View::Tree {
+            self.message = Some("Only the tree view can be exported".to_string());
+            return Ok(());
+        }
+        let Some(path) = self.selected_path() else { return Ok(()) };
+        let mut rows = vec![Row { path: path.clone(), depth: 0 }];
+        self.collect_rows(&path, 1, true, &mut rows);
+        let mut out = BufWriter::new(File::create(file)?);
+        match ExportFormat::of(file) {
+            ExportFormat::Text => {
+                for row in &rows {
+                    let size = self.file_tree.get_size(&row.path).unwrap_or(Size::new(0));
+                    let name = match row.depth {
+                        0 => row.path.display().to_string(),
+                        _ => row.path.file_name().unwrap_or_default().to_string_lossy().into_owned(),
+                    };
+                    let suffix = if self.kind(&row.path) == "directory" { "/" } else { "" };
+                    writeln!(out, "{:>12}  {}{}{}", size.to_string(), "  ".repeat(row.depth), name, suffix)?;
+                }
+            }
+            ExportFormat::Json => {
+                serde_json::to_writer_pretty(&mut out, &self.usage_node(&path))?;
+                writeln!(out)?;
+            }
+            ExportFormat::Csv => {
+                let mut writer = csv::Writer::from_writer(&mut out);
+                writer.write_record(["path", "type", "size_bytes", "depth"])?;
+                for row in &rows {
+                    let size = self.file_tree.get_size(&row.path).unwrap_or(Size::new(0));
+                    writer.write_record([row.path.display().to_string(), self.kind(&row.path).to_string(), size.value().to_string(), row.depth.to_string()])?;
+                }
+                writer.flush()?;
+            }
+        }
+        out.flush()?;
+        self.message = Some(format!("Exported {} entries to {}", rows.len(), file.display()));
+        Ok(())
+    }
 
-            // Getting all characters before the selected character.
-            let before_char_to_delete = self.input.chars().take(from_left_to_current_index);
-            // Getting all characters after selected character.
-            let after_char_to_delete = self.input.chars().skip(current_index);
+    /// Returns the path of the selected line of the tree view.
+    fn selected_path(&self) -> Option<PathBuf> {
+        self.rows().into_iter().nth(self.selected()).map(|row| row.path)
+    }
 
-            // Put all characters together except the selected one.
-            // By leaving the selected one out, it is forgotten and therefore deleted.
-            self.input = before_char_to_delete.chain(after_char_to_delete).collect();
-            self.move_cursor_left();
+    /// Expands the directories leading to `path` and selects it in the tree view, or returns
+    /// `false` if it is not in the tree.
+    fn reveal(&mut self, path: &Path) -> bool {
+        if self.file_tree.get_map_option(path).is_none() {
+            return false;
         }
+        let root = self.file_tree.get_root().to_path_buf();
+        self.expanded.extend(path.ancestors().skip(1).take_while(|ancestor| *ancestor != root).map(Path::to_path_buf));
+        self.view = View::Tree;
+        self.select_path(Some(path.to_path_buf()));
+        true
     }
 
-    fn clamp_cursor(&self, new_cursor_pos: usize) -> usize {
-        new_cursor_pos.clamp(0, self.input.len())
+    /// Selects `path` like `reveal`, recording the selected entry to come back to it.
+    fn jump_to(&mut self, path: &Path) {
+        let current = self.selected_path();
+        if !self.reveal(path) {
+            self.message = Some(format!("{} is not in the tree", path.display()));
+            return;
+        }
+        self.back.extend(current);
+        self.forward.clear();
     }
 
-    fn reset_cursor(&mut self) {
-        self.cursor_position = 0;
+    /// Selects the entry selected before the last jump.
+    fn go_back(&mut self) {
+        let current = self.selected_path();
+        while let Some(path) = self.back.pop() {
+            if self.reveal(&path) {
+                self.forward.extend(current);
+                return;
+            }
+        }
+        self.message = Some("No previous entry".to_string());
     }
 
-    fn submit_message(&mut self) {
-        self.output.push(self.input.clone());
-        self.input.clear();
-        self.reset_cursor();
+    /// Selects the entry left by the last `go_back`.
+    fn go_forward(&mut self) {
+        let current = self.selected_path();
+        while let Some(path) = self.forward.pop() {
+            if self.reveal(&path) {
+                self.back.extend(current);
+                return;
+            }
+        }
+        self.message = Some("No next entry".to_string());
     }
 
-    fn run_shell_command(&mut self) {
-        self.output.clear();
-        if !self.input.trim().is_empty() {
-            let output = Command::new("cmd")
-                .arg("/C")
-                .arg(&self.input)
-                .output();
+    /// Returns the path of `bookmark` in the tree, if it is below the root.
+    fn bookmark_in_tree(&self, bookmark: &Path) -> Option<PathBuf> {
+        let root = self.file_tree.get_root();
+        let relative = bookmark.strip_prefix(std::path::absolute(root).ok()?).ok()?;
+        let path = root.join(relative);
+        self.file_tree.get_map_option(&path).map(|_| path)
+    }
 
-            match output {
-                Ok(output) => {
-                    if output.status.success() {
-                        let result = String::from_utf8_lossy(&output.stdout);
-                        for line in result.lines() {
-                            self.output.push(line.to_owned());
-                        }
-                    } else {
-                        let error = String::from_utf8_lossy(&output.stderr).into_owned();
-                        self.output.push(format!("Error: {}", error));
+    /// Returns whether `path` is bookmarked.
+    fn is_bookmarked(&self, path: &Path) -> bool {
+        std::path::absolute(path).is_ok_and(|path| self.bookmarks.contains(&path))
+    }
+
+    /// Bookmarks the selected entry of the tree view, or removes its bookmark, and saves the
+    /// bookmarks to the configuration file.
+    fn toggle_bookmark(&mut self) {
+        if self.view != View::Tree {
+            return;
+        }
+        let Some(path) = self.selected_path() else { return };
+        let Ok(path) = std::path::absolute(&path) else { return };
+        match self.bookmarks.iter().position(|bookmark| *bookmark == path) {
+            Some(index) => {
+                self.bookmarks.remove(index);
+            }
+            None => self.bookmarks.push(path),
+        }
+        if let Some(config_path) = &self.config_path {
+            if let Err(err) = Config::save_bookmarks(config_path, &self.bookmarks) {
+                self.message = Some(format!("Could not save the bookmarks: {}", err));
+            }
+        }
+    }
+
+    /// Jumps to the bookmark following the selected entry, in the order they were added, among
+    /// those in the tree.
+    fn next_bookmark(&mut self) {
+        let bookmarks: Vec<_> = self.bookmarks.iter().filter_map(|bookmark| self.bookmark_in_tree(bookmark)).collect();
+        let current = self.selected_path().filter(|_| self.view == View::Tree);
+        let next = match current.and_then(|current| bookmarks.iter().position(|bookmark| *bookmark == current)) {
+            Some(index) => bookmarks.get(index + 1).or(bookmarks.first()),
+            None => bookmarks.first(),
+        };
+        match next.cloned() {
+            Some(bookmark) => self.jump_to(&bookmark),
+            None => self.message = Some("No bookmark in the tree".to_string()),
+        }
+    }
+
+    /// Returns the path of the selected entry of the current view: a line of the tree view, or a
+    /// file of the duplicates view.
+    fn selected_entry(&mut self) -> Option<PathBuf> {
+        match self.view {
+            View::Tree => self.selected_path(),
+            View::Duplicates => {
+                let DuplicateRow { group, file } = self.selected_duplicate()?;
+                Some(self.duplicates()[group].paths[file?].clone())
+            }
+        }
+    }
+
+    /// Opens the selected entry with the default application of the platform, without waiting
+    /// for it.
+    fn open_selected(&mut self) {
+        let Some(path) = self.selected_entry() else { return };
+        if let Err(err) = open_command(&path).stdin(Stdio::null()).stdout(Stdio::null()).stderr(Stdio::null()).spawn() {
+            self.message = Some(format!("Could not open {}: {}", path.display(), err));
+        }
+    }
+
+    /// Shows the size, modification time, signature and number of duplicates of the selected
+    /// file in a popup, or expands the selected directory.
+    fn show_details(&mut self) {
+        let Some(path) = self.selected_entry() else { return };
+        if !matches!(self.file_tree.get_map_option(&path), Some(EntryNode::File(_))) {
+            self.expand();
+            return;
+        }
+        let copies = self.duplicates().iter().find(|group| group.paths.contains(&path)).map_or(0, |group| group.paths.len() - 1);
+        // Un fichier sans autre fichier de même taille n'a pas de signature : il est lu ici
+        let signature = match self.file_tree.get_signature(&path) {
+            Some(signature) => signature.to_string(),
+            None => File::open(&path).and_then(|mut file| Blake3Hasher.hash(&mut file)).unwrap_or_else(|err| format!("unreadable: {}", err)),
+        };
+        let size = |size: Option<Size>| size.map_or_else(|| "-".to_string(), |size| format!("{} ({} bytes)", size, size.value()));
+        let fields = vec![
+            ("Size", size(self.file_tree.get_size(&path))),
+            ("Disk usage", size(self.file_tree.get_disk_usage(&path))),
+            ("Modified", self.file_tree.get_modified(&path).map_or_else(|| "-".to_string(), |time| humantime::format_rfc3339_seconds(time).to_string())),
+            ("BLAKE3", signature),
+            ("Duplicates", copies.to_string()),
+        ];
+        self.details = Some(Details { path, fields });
+    }
+
+    /// Returns the groups of duplicates, hashing the files and computing them if needed.
+    ///
+    /// The files that could not be read are left out of the groups.
+    fn duplicates(&mut self) -> &[DuplicateGroup] {
+        if self.duplicates.is_none() {
+            // Une erreur de lecture laisse simplement les fichiers non lus hors des groupes
+            let _ = self.file_tree.compute_signatures();
+            self.duplicates = Some(self.file_tree.find_duplicates_with(Size::new(1)));
+        }
+        self.duplicates.as_ref().map_or(&[], |report| report.groups())
+    }
+
+    /// Returns the lines of the duplicates view: each group followed by its files.
+    fn duplicate_rows(&mut self) -> Vec<DuplicateRow> {
+        let mut rows = Vec::new();
+        for (group, duplicates) in self.duplicates().iter().enumerate() {
+            rows.push(DuplicateRow { group, file: None });
+            rows.extend((0..duplicates.paths.len()).map(|file| DuplicateRow { group, file: Some(file) }));
+        }
+        rows
+    }
+
+    /// Returns the line selected in the duplicates view.
+    fn selected_duplicate(&mut self) -> Option<DuplicateRow> {
+        let selected = self.selected();
+        self.duplicate_rows().get(selected).copied()
+    }
+
+    /// Returns the number of lines of the current view.
+    fn len(&mut self) -> usize {
+        match self.view {
+            View::Tree => self.rows().len(),
+            View::Duplicates => self.duplicate_rows().len(),
+        }
+    }
+
+    fn selected(&self) -> usize {
+        self.list_state.selected().unwrap_or(0)
+    }
+
+    fn move_up(&mut self) {
+        let selected = self.selected().saturating_sub(1);
+        self.list_state.select(Some(selected));
+    }
+
+    fn move_down(&mut self) {
+        let last = self.len().saturating_sub(1);
+        let selected = (self.selected() + 1).min(last);
+        self.list_state.select(Some(selected));
+    }
+
+    /// Moves the selection by `lines` lines, up if negative, within the current view.
+    fn scroll(&mut self, lines: isize) {
+        let last = self.len().saturating_sub(1);
+        let selected = self.selected().saturating_add_signed(lines).min(last);
+        self.list_state.select(Some(selected));
+    }
+
+    /// Returns the number of lines displayed at once by the list in the last frame, scrolled by
+    /// the page keys.
+    fn page_height(&self) -> usize {
+        (self.list_area.height as usize).max(1)
+    }
+
+    /// Selects the last line of the current view.
+    fn select_last(&mut self) {
+        let last = self.len().saturating_sub(1);
+        self.list_state.select(Some(last));
+    }
+
+    /// Selects the line clicked at row `y` of the terminal, if any, at time `now`. A second click
+    /// on the same line expands or collapses the directory of the tree view.
+    fn click(&mut self, x: u16, y: u16, now: Instant) {
+        let area = self.list_area;
+        if !area.intersects(Rect::new(x, y, 1, 1)) {
+            return;
+        }
+        let line = self.list_state.offset() + (y - area.y) as usize;
+        if line >= self.len() {
+            return;
+        }
+        self.list_state.select(Some(line));
+        let double = self.last_click.is_some_and(|(time, last)| last == line && now.duration_since(time) <= DOUBLE_CLICK);
+        if double {
+            self.last_click = None;
+            self.toggle_expanded();
+        } else {
+            self.last_click = Some((now, line));
+        }
+    }
+
+    /// Collapses the selected directory if it is expanded, or expands it.
+    fn toggle_expanded(&mut self) {
+        if self.view != View::Tree {
+            return;
+        }
+        match self.selected_path() {
+            Some(path) if self.expanded.contains(&path) => {
+                self.expanded.remove(&path);
+            }
+            _ => self.expand(),
+        }
+    }
+
+    /// Expands the selected directory.
+    fn expand(&mut self) {
+        if self.view != View::Tree {
+            return;
+        }
+        if let Some(path) = self.selected_path() {
+            if let Some(EntryNode::Directory(_)) = self.file_tree.get_map_option(&path) {
+                self.expanded.insert(path);
+            }
+        }
+    }
+
+    /// Collapses the selected directory, or selects the directory containing the selected entry.
+    fn collapse(&mut self) {
+        if self.view != View::Tree {
+            return;
+        }
+        let Some(path) = self.selected_path() else { return };
+        if self.expanded.remove(&path) {
+            return;
+        }
+        if let Some(parent) = path.parent().filter(|parent| *parent != self.file_tree.get_root()) {
+            let selected = self.rows().iter().position(|row| row.path == parent);
+            self.list_state.select(selected);
+        }
+    }
+
+    /// Switches from the tree view to the duplicates view, selecting the group of the selected
+    /// file if it has duplicates, and back.
+    fn toggle_duplicates(&mut self) {
+        match self.view {
+            View::Tree => {
+                let selected = self.selected_path();
+                let file = selected.as_ref().and_then(|path| {
+                    let duplicates = self.duplicates();
+                    let group = duplicates.iter().position(|group| group.paths.contains(path))?;
+                    Some(DuplicateRow { group, file: duplicates[group].paths.iter().position(|file| file == path) })
+                });
+                if file.is_none() {
+                    if let Some(Some(EntryNode::File(_))) = selected.as_ref().map(|path| self.file_tree.get_map_option(path)) {
+                        self.message = Some("The selected file has no duplicate".to_string());
                     }
                 }
-                Err(err) => {
-                    self.output.push(format!("Error executing command: {}", err));
-                }
+                self.view = View::Duplicates;
+                let row = file.and_then(|file| self.duplicate_rows().iter().position(|row| *row == file));
+                self.list_state = ListState::default().with_selected(Some(row.unwrap_or(0)));
+            }
+            View::Duplicates => {
+                self.view = View::Tree;
+                self.list_state = ListState::default().with_selected(Some(0));
             }
+        }
+    }
+
+    /// Marks the copy selected in the duplicates view, or unmarks it. The last unmarked copy of a
+    /// group can't be marked, so that a copy is always kept.
+    fn toggle_mark(&mut self) {
+        if self.view != View::Duplicates {
+            return;
+        }
+        let Some(DuplicateRow { group, file: Some(file) }) = self.selected_duplicate() else { return };
+        let paths = self.duplicates()[group].paths.clone();
+        let path = &paths[file];
+        if self.marked.remove(path) {
+            return;
+        }
+        if paths.iter().filter(|other| *other != path).all(|other| self.marked.contains(other)) {
+            self.message = Some("At least one copy of each group is kept".to_string());
+            return;
+        }
+        self.marked.insert(path.clone());
+    }
 
-            self.input.clear();
-            self.reset_cursor();
+    /// Returns the space freed by deleting or linking the marked copies, a file having several hard
+    /// links being freed once all its paths are marked.
+    fn marked_reclaimable(&self) -> Size {
+        let groups = self.duplicates.as_ref().map_or(&[][..], |report| report.groups());
+        groups.iter().fold(Size::new(0), |total, group| {
+            // Chaque fichier est repéré par son inode, ou par l'indice de son unique chemin
+            let mut files: HashMap<Result<(u64, u64), usize>, bool> = HashMap::new();
+            for (index, path) in group.paths.iter().enumerate() {
+                *files.entry(group.link(index).ok_or(index)).or_insert(true) &= self.marked.contains(path);
+            }
+            let marked = files.values().filter(|marked| **marked).count() as u64;
+            total + Size::new(group.size.value() * marked)
+        })
+    }
+
+    /// Asks for the confirmation of `resolution` on the marked copies.
+    fn confirm(&mut self, resolution: Resolution) {
+        if self.view != View::Duplicates || self.marked.is_empty() {
+            return;
+        }
+        let action = if resolution == Resolution::Delete { "Delete" } else { "Replace with hard links" };
+        self.message = Some(format!("{} {} marked files? (y/n)", action, self.marked.len()));
+        self.pending = Some(resolution);
+    }
+
+    /// Deletes the marked copies, or replaces them with hard links to the first unmarked copy of
+    /// their group, then updates the tree and the duplicates.
+    fn apply_marked(&mut self, resolution: Resolution) -> io::Result<()> {
+        let groups = self.duplicates().to_vec();
+        let (mut applied, mut freed, mut failed) = (Vec::new(), 0, 0);
+        for group in &groups {
+            let Some(kept) = group.paths.iter().position(|path| !self.marked.contains(path)) else { continue };
+            for action in group.plan(kept, resolution).into_iter().filter(|action| self.marked.contains(action.path())) {
+                if action.crosses_file_systems().unwrap_or(true) || action.apply().is_err() {
+                    failed += 1;
+                    continue;
+                }
+                applied.push(action.path().to_path_buf());
+                freed += group.size.value();
+            }
         }
+        self.file_tree.refresh_paths(&applied)?;
+        self.marked.clear();
+        self.duplicates = None;
+        let selected = self.selected().min(self.len().saturating_sub(1));
+        self.list_state.select(Some(selected));
+        self.message = Some(match failed {
+            0 => format!("Freed {} from {} files", Size::new(freed), applied.len()),
+            _ => format!("Freed {} from {} files, {} failed", Size::new(freed), applied.len(), failed),
+        });
+        Ok(())
     }
 
-    fn scroll_output(&mut self, lines: isize) {
-        // Faites défiler l'historique en ajustant la position de vue
-        let new_position = self.output_view_position as isize + lines;
-        self.output_view_position = new_position.clamp(0, self.output.len() as isize) as usize;
+    /// Runs a command typed after `:`. `Command::Quit` is handled by the event loop.
+    fn execute(&mut self, command: Command) -> io::Result<()> {
+        match command {
+            Command::Goto(path) => {
+                let path = self.file_tree.get_root().join(path);
+                self.jump_to(&path);
+            }
+            Command::Filter(pattern) => self.set_filter(pattern)?,
+            Command::Depth(depth) => self.collapse_to(depth),
+            Command::Export(file) => self.export(&file)?,
+            Command::Rescan => {
+                self.file_tree = FileTree::builder(self.file_tree.get_root()).hidden(self.show_hidden).build()?;
+                let pattern = self.filter.take().map(|(pattern, _)| pattern);
+                self.set_filter(pattern)?;
+                self.expanded.retain(|path| self.file_tree.get_map_option(path).is_some());
+                self.duplicates = None;
+                self.marked.clear();
+                self.list_state = ListState::default().with_selected(Some(0));
+            }
+            Command::Duplicates => {
+                if self.view == View::Tree {
+                    self.toggle_duplicates();
+                }
+            }
+            Command::Quit => {}
+        }
+        Ok(())
     }
+}
 
+/// Returns a bar of `width` characters filled in proportion of `size` to `total`.
+fn size_bar(size: Size, total: Size, width: usize) -> String {
+    let filled = if total.value() == 0 {
+        0
+    } else {
+        ((size.value() as f64 / total.value() as f64) * width as f64).round() as usize
+    };
+    let filled = filled.min(width);
+    format!("{}{}", "█".repeat(filled), "░".repeat(width - filled))
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
+    let args = Args::parse();
+    let path = args.path.unwrap_or_else(|| PathBuf::from("."));
+    let file_tree = FileTree::builder(&path).hidden(args.all).build()?;
+    // La configuration est lue avant de passer le terminal en mode brut, qu'une erreur ne laisserait pas restauré
+    let config = Config::load_default()?;
+
     // setup terminal
     enable_raw_mode()?;
     let mut stdout = io::stdout();
@@ -160,7 +803,7 @@ fn main() -> Result<(), Box<dyn Error>> {
     let mut terminal = Terminal::new(backend)?;
 
     // create app and run it
-    let app = App::default();
+    let app = App { show_hidden: args.all, bookmarks: config.bookmarks, config_path: Config::default_path(), ..App::new(file_tree) };
     let res = run_app(&mut terminal, app);
 
     // restore terminal
@@ -181,163 +824,630 @@ fn main() -> Result<(), Box<dyn Error>> {
 
 fn run_app<B: Backend>(terminal: &mut Terminal<B>, mut app: App) -> io::Result<()> {
     loop {
-        terminal.draw(|f| ui(f, &app))?;
+        terminal.draw(|f| ui(f, &mut app))?;
 
-        if let Event::Key(key) = event::read()? {
-            match app.input_mode {
-                InputMode::Normal => match key.code {
-                    KeyCode::Char('e') => {
-                        app.input_mode = InputMode::Editing;
-                    }
-                    KeyCode::Char('q') => {
-                        return Ok(());
-                    }
-                    KeyCode::Up => {
-                        if app.output_view_position > 0 {
-                            app.output_view_position -= 1;
-                        }
-                    }
-                    KeyCode::Down => {
-                        if app.output_view_position < app.output.len() - 26 {
-                            app.output_view_position += 1;
-                        }
-                    }
-                    KeyCode::Right => {
-                        if app.output_view_position < app.output.len() - 26 {
-                            app.output_view_position += 25;
-                        }
-                        else {
-                            app.output_view_position = app.output.len() - 26;
-                        }
-                    }
-                    KeyCode::Left => {
-                        if app.output_view_position > 25 {
-                            app.output_view_position -= 25;
-                        }
-                        else {
-                            app.output_view_position = 0;
-                        }
-                    }
-                    _ => {}
-                },
-                InputMode::Editing if key.kind == KeyEventKind::Press => match key.code {
-                    KeyCode::Enter => {
-                        app.run_shell_command();
-                    },
-                    KeyCode::Char(to_insert) => {
-                        app.enter_char(to_insert);
-                    }
-                    KeyCode::Backspace => {
-                        app.delete_char();
-                    }
-                    KeyCode::Left => {
-                        app.move_cursor_left();
-                    }
-                    KeyCode::Right => {
-                        app.move_cursor_right();
-                    }
-                    KeyCode::Esc => {
-                        app.input_mode = InputMode::Normal;
-                    }
-                    KeyCode::Up => {
-                        if app.output_view_position > 0 {
-                            app.output_view_position -= 1;
-                        }
-                    }
-                    KeyCode::Down => {
-                        if app.output_view_position < app.output.len() - 1 {
-                            app.output_view_position += 1;
+        let key = match event::read()? {
+            Event::Mouse(mouse) if app.input.is_none() && app.pending.is_none() && app.details.is_none() && !app.help => {
+                handle_mouse(&mut app, mouse);
+                continue;
+            }
+            Event::Key(key) => key,
+            // Un redimensionnement redessine simplement la vue, la liste gardant la sélection visible
+            _ => continue,
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        app.message = None;
+        if app.details.take().is_some() || std::mem::take(&mut app.help) {
+            continue;
+        }
+        if let Some(resolution) = app.pending.take() {
+            if key.code == KeyCode::Char('y') {
+                if let Err(err) = app.apply_marked(resolution) {
+                    app.message = Some(err.to_string());
+                }
+            }
+            continue;
+        }
+        if let Some(input) = &mut app.input {
+            match key.code {
+                KeyCode::Char(c) => input.push(c),
+                KeyCode::Backspace => {
+                    input.pop();
+                }
+                KeyCode::Esc => app.input = None,
+                KeyCode::Enter => {
+                    let line = app.input.take().unwrap_or_default();
+                    match parse_command(&line) {
+                        Ok(Command::Quit) => return Ok(()),
+                        Ok(command) => {
+                            if let Err(err) = app.execute(command) {
+                                app.message = Some(err.to_string());
+                            }
                         }
+                        Err(message) => app.message = Some(message),
                     }
-                    _ => {}
-                },
+                }
                 _ => {}
             }
+            continue;
+        }
+        match key.code {
+            KeyCode::Char('q') => {
+                return Ok(());
+            }
+            KeyCode::Char('?') => app.help = true,
+            KeyCode::Up => app.move_up(),
+            KeyCode::Down => app.move_down(),
+            KeyCode::PageUp => app.scroll(-(app.page_height() as isize)),
+            KeyCode::PageDown => app.scroll(app.page_height() as isize),
+            KeyCode::Home => app.list_state.select(Some(0)),
+            KeyCode::End => app.select_last(),
+            KeyCode::Right => app.expand(),
+            KeyCode::Enter => app.show_details(),
+            KeyCode::Char('o') => app.open_selected(),
+            KeyCode::Left => app.collapse(),
+            KeyCode::Char('h') | KeyCode::Backspace => app.go_back(),
+            KeyCode::Char('l') => app.go_forward(),
+            KeyCode::Char('b') => app.toggle_bookmark(),
+            KeyCode::Char('g') => app.next_bookmark(),
+            KeyCode::Char('d') => app.toggle_duplicates(),
+            KeyCode::Char(' ') => app.toggle_mark(),
+            KeyCode::Char('D') => app.confirm(Resolution::Delete),
+            KeyCode::Char('H') => app.confirm(Resolution::HardLink),
+            KeyCode::Char(':') => app.input = Some(String::new()),
+            KeyCode::Char('s') => app.cycle_sort(),
+            KeyCode::Char('f') => app.input = Some("filter ".to_string()),
+            KeyCode::Char('e') => app.input = Some("export ".to_string()),
+            KeyCode::Char(digit @ '0'..='9') => app.collapse_to(digit as usize - '0' as usize),
+            _ => {}
         }
     }
 }
 
-fn ui(f: &mut Frame, app: &App) {
+/// Returns the command opening `path` with the default application: `xdg-open`, `open` on macOS,
+/// or `start` on Windows.
+fn open_command(path: &Path) -> process::Command {
+    let mut command;
+    if cfg!(windows) {
+        command = process::Command::new("cmd");
+        // Le premier argument entre guillemets de start est le titre de la fenêtre
+        command.args(["/C", "start", ""]);
+    } else if cfg!(target_os = "macos") {
+        command = process::Command::new("open");
+    } else {
+        command = process::Command::new("xdg-open");
+    }
+    command.arg(path);
+    command
+}
+
+/// Selects the clicked line, expands or collapses it on a double click, and scrolls with the wheel.
+fn handle_mouse(app: &mut App, mouse: MouseEvent) {
+    match mouse.kind {
+        MouseEventKind::Down(MouseButton::Left) => app.click(mouse.column, mouse.row, Instant::now()),
+        MouseEventKind::ScrollUp => app.scroll(-(WHEEL_LINES as isize)),
+        MouseEventKind::ScrollDown => app.scroll(WHEEL_LINES as isize),
+        _ => {}
+    }
+}
+
+fn ui(f: &mut Frame, app: &mut App) {
     let chunks = Layout::default()
     .direction(Direction::Vertical)
     .constraints([
         Constraint::Length(1),
-        Constraint::Length(29),
-        Constraint::Length(7),
         Constraint::Min(1),
-        
+        Constraint::Length(1),
     ])
     .split(f.size());
 
-    let (msg, style) = match app.input_mode {
-        InputMode::Normal => (
-            vec![
-                "Press ".into(),
-                "q".bold(),
-                " to exit, ".into(),
-                "e".bold(),
-                " to start editing.".bold(),
-            ],
-            Style::default().add_modifier(Modifier::RAPID_BLINK),
-        ),
-        InputMode::Editing => (
-            vec![
-                "Press ".into(),
-                "Esc".bold(),
-                " to stop editing, ".into(),
-                "Enter".bold(),
-                " to record the message".into(),
-            ],
-            Style::default(),
-        ),
+    let msg = vec![
+        "Press ".into(),
+        "q".bold(),
+        " to exit, ".into(),
+        "?".bold(),
+        " for the list of keys.".into(),
+    ];
+    f.render_widget(Paragraph::new(Line::from(msg)), chunks[0]);
+
+    let (title, items): (String, Vec<ListItem>) = match app.view {
+        View::Tree => {
+            let items = app
+                .rows()
+                .into_iter()
+                .map(|row| {
+                    let path = &row.path;
+                    let size = app.file_tree.get_size(path).unwrap_or(Size::new(0));
+                    let parent_size = path.parent().and_then(|parent| app.file_tree.get_size(parent)).unwrap_or(size);
+                    let name = path.file_name().map_or_else(|| path.display().to_string(), |name| name.to_string_lossy().into_owned());
+                    let indent = "  ".repeat(row.depth);
+                    let (name, style) = match app.file_tree.get_map_option(path) {
+                        Some(EntryNode::Directory(_)) if app.file_tree.get_archive_size(path).is_some() => {
+                            let marker = if app.expanded.contains(path) { "▾" } else { "▸" };
+                            (format!("{}{} {} [archive]", indent, marker, name), Style::default().fg(Color::Magenta))
+                        }
+                        Some(EntryNode::Directory(_)) => {
+                            let marker = if app.expanded.contains(path) { "▾" } else { "▸" };
+                            (format!("{}{} {}/", indent, marker, name), Style::default().fg(Color::Blue))
+                        }
+                        Some(EntryNode::Symlink(target)) => (format!("{}  {} -> {}", indent, name, target.display()), Style::default().fg(Color::Cyan)),
+                        Some(EntryNode::Other(kind)) => (format!("{}  {} [{}]", indent, name, kind), Style::default().fg(Color::Yellow)),
+                        _ => (format!("{}  {}", indent, name), Style::default()),
+                    };
+                    let name = if app.is_bookmarked(path) { format!("{} ★", name) } else { name };
+                    ListItem::new(Line::from(vec![
+                        Span::raw(format!("{:>12} ", size.to_string())),
+                        Span::styled(size_bar(size, parent_size, 10), Style::default().fg(Color::Green)),
+                        Span::raw(" "),
+                        Span::styled(name, style),
+                    ]))
+                })
+                .collect();
+            let mut title = format!("{} - sorted by {}", app.file_tree.get_root().display(), app.sort);
+            if let Some((pattern, _)) = &app.filter {
+                title.push_str(&format!(", filter {}", pattern));
+            }
+            (title, items)
+        }
+        View::Duplicates => {
+            let rows = app.duplicate_rows();
+            let groups = app.duplicates.as_ref().map_or(&[][..], |report| report.groups());
+            let items = rows
+                .into_iter()
+                .map(|row| {
+                    let group = &groups[row.group];
+                    match row.file {
+                        None => ListItem::new(Line::from(format!("{} x {}", group.paths.len(), group.size).bold())),
+                        Some(file) if app.marked.contains(&group.paths[file]) => {
+                            ListItem::new(format!("  [x] {}", group.paths[file].display())).style(Style::default().fg(Color::Red))
+                        }
+                        Some(file) => ListItem::new(format!("  [ ] {}", group.paths[file].display())),
+                    }
+                })
+                .collect();
+            let title = format!("Duplicates - {} marked, {} to reclaim", app.marked.len(), app.marked_reclaimable());
+            (title, items)
+        }
     };
-    let mut text = Text::from(Line::from(msg));
-    text.patch_style(style);
-    let help_message = Paragraph::new(text);
-    f.render_widget(help_message, chunks[0]);
-
-    let input = Paragraph::new(app.input.as_str())
-        .style(match app.input_mode {
-            InputMode::Normal => Style::default(),
-            InputMode::Editing => Style::default().fg(Color::Yellow),
-        })
-        .block(Block::default().borders(Borders::ALL).title("Input"));
-    f.render_widget(input, chunks[3]);
-    match app.input_mode {
-        InputMode::Normal =>
-            // Hide the cursor. `Frame` does this by default, so we don't need to do anything here
-            {}
-
-        InputMode::Editing => {
-            // Make the cursor visible and ask ratatui to put it at the specified coordinates after
-            // rendering
-            f.set_cursor(
-                // Draw the cursor at the current position in the input field.
-                // This position is can be controlled via the left and right arrow key
-                chunks[3].x + app.cursor_position as u16 + 1,
-                // Move one line down, from the border to the input line
-                chunks[3].y + 1,
-            )
-        }
-    }
-
-    let visible_output: Vec<ListItem> = app
-        .output
-        .iter()
-        .skip(app.output_view_position)
-        .take(27) 
-        .enumerate()
-        .map(|(i, m)| {
-            let content = Line::from(Span::raw(format!("{}: {}", i + app.output_view_position, m)));
-            ListItem::new(content)
-        })
-        .collect();
 
-    let output = List::new(visible_output).block(Block::default().borders(Borders::ALL).title("Output"));
-    f.render_widget(output, chunks[1]);
+    let block = Block::default().borders(Borders::ALL).title(title);
+    app.list_area = block.inner(chunks[1]);
+    let lines = items.len();
+    let list = List::new(items)
+        .block(block)
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+        .highlight_symbol("> ");
+    f.render_stateful_widget(list, chunks[1], &mut app.list_state);
+    if lines > app.page_height() {
+        let mut scrollbar_state = ScrollbarState::new(lines).position(app.selected()).viewport_content_length(app.page_height());
+        let scrollbar = Scrollbar::new(ScrollbarOrientation::VerticalRight).begin_symbol(None).end_symbol(None);
+        f.render_stateful_widget(scrollbar, chunks[1].inner(&Margin { vertical: 1, horizontal: 0 }), &mut scrollbar_state);
+    }
+
+    if let Some(details) = &app.details {
+        let lines: Vec<Line> = details.fields.iter().map(|(name, value)| Line::from(vec![format!("{:>11}: ", name).bold(), value.clone().into()])).collect();
+        let area = centered(chunks[1], 80, lines.len() as u16 + 2);
+        let title = details.path.file_name().map_or_else(|| details.path.display().to_string(), |name| name.to_string_lossy().into_owned());
+        f.render_widget(Clear, area);
+        f.render_widget(Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title(title)), area);
+    }
+
+    if app.help {
+        let lines: Vec<Line> = KEYS.iter().map(|(key, action)| Line::from(vec![format!("{:>14}  ", key).bold(), (*action).into()])).collect();
+        let area = centered(f.size(), 80, lines.len() as u16 + 2);
+        f.render_widget(Clear, area);
+        f.render_widget(Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title("Keys")), area);
+    }
+
+    let total = app.file_tree.get_size(app.file_tree.get_root()).unwrap_or(Size::new(0));
+    let status = match (&app.input, &app.message) {
+        (Some(input), _) => format!(":{}", input),
+        (None, Some(message)) => format!("Total: {}  {}", total, message),
+        (None, None) => format!("Total: {}", total),
+    };
+    let status = Paragraph::new(status)
+        .style(Style::default().fg(Color::Blue).add_modifier(Modifier::BOLD));
+    f.render_widget(status, chunks[2]);
+}
+
+/// Returns an area of at most `width` by `height` cells centered in `area`.
+fn centered(area: Rect, width: u16, height: u16) -> Rect {
+    let (width, height) = (width.min(area.width), height.min(area.height));
+    Rect::new(area.x + (area.width - width) / 2, area.y + (area.height - height) / 2, width, height)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::{self, File};
+    use tempfile::TempDir;
+
+    fn create_app() -> (TempDir, App) {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("big/sub")).unwrap();
+        fs::create_dir_all(dir.path().join("small")).unwrap();
+        File::create(dir.path().join("big/sub/a")).unwrap().set_len(1000).unwrap();
+        File::create(dir.path().join("big/b")).unwrap().set_len(1000).unwrap();
+        File::create(dir.path().join("small/c")).unwrap().set_len(10).unwrap();
+        File::create(dir.path().join("file")).unwrap().set_len(100).unwrap();
+        let file_tree = FileTree::new(dir.path()).unwrap();
+        (dir, App::new(file_tree))
+    }
+
+    fn paths(app: &App) -> Vec<PathBuf> {
+        app.rows().into_iter().map(|row| row.path).collect()
+    }
+
+    #[test]
+    fn rows_sorted_by_size_test() {
+        let (dir, app) = create_app();
+        assert_eq!(paths(&app), [dir.path().join("big"), dir.path().join("file"), dir.path().join("small")]);
+    }
+
+    #[test]
+    fn selection_is_clamped_test() {
+        let (_dir, mut app) = create_app();
+        app.move_up();
+        assert_eq!(app.selected(), 0);
+        for _ in 0..10 {
+            app.move_down();
+        }
+        assert_eq!(app.selected(), 2);
+    }
+
+    #[test]
+    fn expand_and_collapse_test() {
+        let (dir, mut app) = create_app();
+        app.expand();
+        assert_eq!(paths(&app)[..3], [dir.path().join("big"), dir.path().join("big/b"), dir.path().join("big/sub")]);
+        assert_eq!(app.rows()[2].depth, 1);
+
+        app.move_down();
+        app.move_down();
+        app.expand();
+        assert_eq!(paths(&app)[3], dir.path().join("big/sub/a"));
+
+        // Collapsing an entry inside a directory selects the directory, then collapses it
+        app.move_down();
+        app.collapse();
+        assert_eq!(app.selected(), 2);
+        app.collapse();
+        assert_eq!(paths(&app).len(), 5);
+        app.collapse();
+        assert_eq!(app.selected(), 0);
+        app.collapse();
+        assert_eq!(paths(&app).len(), 3);
+    }
+
+    #[test]
+    fn expand_file_does_nothing_test() {
+        let (_dir, mut app) = create_app();
+        app.move_down();
+        app.expand();
+        assert_eq!(paths(&app).len(), 3);
+        assert_eq!(app.selected(), 1);
+    }
+
+    #[test]
+    fn jump_to_duplicates_test() {
+        let (dir, mut app) = create_app();
+        app.move_down();
+        app.move_down();
+        app.expand();
+        app.move_down();
+        assert_eq!(app.selected_path(), Some(dir.path().join("small/c")));
+
+        // A file without duplicates opens the list with a message
+        app.toggle_duplicates();
+        assert_eq!(app.view, View::Duplicates);
+        assert_eq!(app.selected(), 0);
+        assert!(app.message.is_some());
+
+        // Navigation in the tree is disabled in the duplicates view
+        app.move_up();
+        app.collapse();
+        assert!(app.expanded.contains(&dir.path().join("small")));
+        app.toggle_duplicates();
+        assert_eq!(app.view, View::Tree);
+
+        File::create(dir.path().join("small/d")).unwrap().set_len(10).unwrap();
+        let mut app = App::new(FileTree::new(dir.path()).unwrap());
+        app.move_down();
+        app.move_down();
+        app.expand();
+        app.move_down();
+        app.toggle_duplicates();
+        assert_eq!(app.duplicates().len(), 2);
+        let selected = app.selected_duplicate().unwrap();
+        assert_eq!(app.duplicates()[selected.group].paths, [dir.path().join("small/c"), dir.path().join("small/d")]);
+        assert_eq!(selected.file, Some(0));
+        assert!(app.message.is_none());
+    }
+
+    #[test]
+    fn mark_and_delete_duplicates_test() {
+        let (dir, _) = create_app();
+        File::create(dir.path().join("small/d")).unwrap().set_len(10).unwrap();
+        File::create(dir.path().join("small/e")).unwrap().set_len(10).unwrap();
+        let mut app = App::new(FileTree::new(dir.path()).unwrap());
+        app.toggle_duplicates();
+        let group = app.duplicates().iter().position(|group| group.paths.len() == 3).unwrap();
+        let first = app.duplicate_rows().iter().position(|row| *row == DuplicateRow { group, file: Some(0) }).unwrap();
+
+        // Marking the header does nothing, the last unmarked copy can't be marked
+        app.list_state.select(Some(first - 1));
+        app.toggle_mark();
+        assert!(app.marked.is_empty());
+        for row in first..first + 3 {
+            app.list_state.select(Some(row));
+            app.toggle_mark();
+        }
+        assert_eq!(app.marked.len(), 2);
+        assert!(app.message.is_some());
+        assert_eq!(app.marked_reclaimable(), Size::new(20));
+
+        app.confirm(Resolution::Delete);
+        assert_eq!(app.pending, Some(Resolution::Delete));
+        app.apply_marked(Resolution::Delete).unwrap();
+        assert!(dir.path().join("small/e").exists());
+        assert!(!dir.path().join("small/c").exists() && !dir.path().join("small/d").exists());
+        assert!(app.marked.is_empty());
+        assert_eq!(app.file_tree.get_size(&dir.path().join("small")), Some(Size::new(10)));
+        assert!(app.duplicates().iter().all(|group| group.paths.len() < 3));
+    }
+
+    #[test]
+    fn size_bar_test() {
+        assert_eq!(size_bar(Size::new(50), Size::new(100), 4), "██░░");
+        assert_eq!(size_bar(Size::new(100), Size::new(100), 4), "████");
+        assert_eq!(size_bar(Size::new(0), Size::new(0), 2), "░░");
+    }
+
+    #[test]
+    fn parse_command_test() {
+        assert_eq!(parse_command("cd big/sub"), Ok(Command::Goto(PathBuf::from("big/sub"))));
+        assert_eq!(parse_command(" rescan "), Ok(Command::Rescan));
+        assert_eq!(parse_command("dup"), Ok(Command::Duplicates));
+        assert_eq!(parse_command("q"), Ok(Command::Quit));
+        assert_eq!(parse_command("filter jpg,png"), Ok(Command::Filter(Some("jpg,png".to_string()))));
+        assert_eq!(parse_command("filter"), Ok(Command::Filter(None)));
+        assert_eq!(parse_command("depth 2"), Ok(Command::Depth(2)));
+        assert!(parse_command("depth deux").is_err());
+        assert_eq!(parse_command("export big.json"), Ok(Command::Export(PathBuf::from("big.json"))));
+        assert!(parse_command("export").is_err());
+        assert!(parse_command("cd").is_err());
+        assert!(parse_command("rm -rf /").is_err());
+    }
+
+    #[test]
+    fn goto_command_test() {
+        let (dir, mut app) = create_app();
+        app.execute(Command::Goto(PathBuf::from("big/sub/a"))).unwrap();
+        assert_eq!(app.selected_path(), Some(dir.path().join("big/sub/a")));
+        assert!(app.expanded.contains(&dir.path().join("big/sub")));
+
+        app.execute(Command::Goto(PathBuf::from("missing"))).unwrap();
+        assert!(app.message.is_some());
+
+        File::create(dir.path().join("new")).unwrap().set_len(1).unwrap();
+        app.execute(Command::Rescan).unwrap();
+        assert!(paths(&app).contains(&dir.path().join("new")));
+        assert!(app.expanded.contains(&dir.path().join("big/sub")));
+    }
+
+    #[test]
+    fn cycle_sort_test() {
+        let (dir, mut app) = create_app();
+        app.move_down();
+        app.cycle_sort();
+        assert_eq!(app.sort, SortOrder::new(SortKey::Name));
+        assert_eq!(paths(&app), [dir.path().join("big"), dir.path().join("file"), dir.path().join("small")]);
+        assert_eq!(app.selected_path(), Some(dir.path().join("file")));
+
+        app.cycle_sort();
+        assert_eq!(app.sort.key, SortKey::Mtime);
+        app.cycle_sort();
+        assert_eq!(app.sort, SortOrder::new(SortKey::Size));
+    }
+
+    #[test]
+    fn filter_test() {
+        let (dir, mut app) = create_app();
+        File::create(dir.path().join("big/sub/photo.jpg")).unwrap().set_len(5).unwrap();
+        app.execute(Command::Rescan).unwrap();
+
+        app.execute(Command::Filter(Some("jpg".to_string()))).unwrap();
+        app.collapse_to(9);
+        assert_eq!(paths(&app), [dir.path().join("big"), dir.path().join("big/sub"), dir.path().join("big/sub/photo.jpg")]);
 
-    let commands = Paragraph::new(app.possible_commands)
-        .style(Style::default().fg(Color::Blue).add_modifier(Modifier::BOLD))
-        .block(Block::default().borders(Borders::ALL).title("Possible Commands"));
-    f.render_widget(commands, chunks[2]);
+        app.execute(Command::Rescan).unwrap();
+        assert_eq!(paths(&app).len(), 3);
+        app.execute(Command::Filter(None)).unwrap();
+        assert_eq!(paths(&app).len(), 8);
+        assert!(app.execute(Command::Filter(Some("[".to_string()))).is_err());
+        assert_eq!(paths(&app).len(), 8);
+    }
+
+    #[test]
+    fn collapse_to_test() {
+        let (dir, mut app) = create_app();
+        app.collapse_to(1);
+        assert_eq!(paths(&app), [dir.path().join("big"), dir.path().join("big/b"), dir.path().join("big/sub"), dir.path().join("file"), dir.path().join("small"), dir.path().join("small/c")]);
+
+        app.collapse_to(2);
+        assert!(paths(&app).contains(&dir.path().join("big/sub/a")));
+        app.collapse_to(0);
+        assert_eq!(paths(&app).len(), 3);
+    }
+
+    #[test]
+    fn mouse_test() {
+        let (dir, mut app) = create_app();
+        app.list_area = Rect::new(1, 2, 40, 10);
+        let now = Instant::now();
+        app.click(5, 4, now);
+        assert_eq!(app.selected_path(), Some(dir.path().join("small")));
+        app.click(5, 20, now);
+        app.click(5, 9, now);
+        assert_eq!(app.selected(), 2);
+
+        app.click(5, 2, now);
+        app.click(5, 2, now + Duration::from_millis(100));
+        assert!(app.expanded.contains(&dir.path().join("big")));
+        app.click(5, 2, now + Duration::from_secs(1));
+        app.click(5, 2, now + Duration::from_secs(2));
+        assert!(app.expanded.contains(&dir.path().join("big")));
+        app.click(5, 2, now + Duration::from_millis(2100));
+        assert!(!app.expanded.contains(&dir.path().join("big")));
+
+        app.scroll(WHEEL_LINES as isize);
+        assert_eq!(app.selected(), 2);
+        app.scroll(-(WHEEL_LINES as isize));
+        assert_eq!(app.selected(), 0);
+    }
+
+    #[test]
+    fn page_scroll_test() {
+        let (_dir, mut app) = create_app();
+        app.collapse_to(9);
+        app.list_area = Rect::new(1, 2, 40, 3);
+        app.scroll(app.page_height() as isize);
+        assert_eq!(app.selected(), 3);
+        app.scroll(app.page_height() as isize);
+        assert_eq!(app.selected(), 6);
+        app.scroll(-(app.page_height() as isize));
+        assert_eq!(app.selected(), 3);
+
+        app.list_area = Rect::default();
+        assert_eq!(app.page_height(), 1);
+        app.select_last();
+        assert_eq!(app.selected(), 6);
+    }
+
+    #[test]
+    fn draw_short_and_long_lists_test() {
+        let (_dir, mut app) = create_app();
+        for height in [2, 5, 6, 30] {
+            let mut terminal = Terminal::new(backend::TestBackend::new(60, height)).unwrap();
+            terminal.draw(|f| ui(f, &mut app)).unwrap();
+            assert_eq!(app.page_height(), (height as usize).saturating_sub(4).max(1));
+            app.collapse_to(9);
+            app.select_last();
+            terminal.draw(|f| ui(f, &mut app)).unwrap();
+        }
+    }
+
+    #[test]
+    fn show_details_test() {
+        let (dir, mut app) = create_app();
+        app.show_details();
+        assert_eq!(app.details, None);
+        assert!(app.expanded.contains(&dir.path().join("big")));
+
+        app.move_down();
+        app.show_details();
+        let details = app.details.clone().unwrap();
+        assert_eq!(details.path, dir.path().join("big/b"));
+        assert_eq!(details.fields[0], ("Size", "1000 B (1000 bytes)".to_string()));
+        assert_eq!(details.fields[3].1, Blake3Hasher.hash(&mut &[0; 1000][..]).unwrap());
+        assert_eq!(details.fields[4], ("Duplicates", "1".to_string()));
+
+        let mut terminal = Terminal::new(backend::TestBackend::new(60, 20)).unwrap();
+        terminal.draw(|f| ui(f, &mut app)).unwrap();
+    }
+
+    #[test]
+    fn help_test() {
+        let (_dir, mut app) = create_app();
+        let mut terminal = Terminal::new(backend::TestBackend::new(80, 24)).unwrap();
+        terminal.draw(|f| ui(f, &mut app)).unwrap();
+        let header: String = terminal.backend().buffer().content()[..80].iter().map(|cell| cell.symbol.as_str()).collect();
+        assert_eq!(header.trim_end(), "Press q to exit, ? for the list of keys.");
+
+        app.help = true;
+        terminal.draw(|f| ui(f, &mut app)).unwrap();
+        let buffer = terminal.backend().buffer();
+        let screen: Vec<String> = buffer.content().chunks(80).map(|line| line.iter().map(|cell| cell.symbol.as_str()).collect()).collect();
+        for (key, action) in KEYS {
+            assert!(screen.iter().any(|line| line.contains(&format!("{:>14}  {}", key, action))), "{} is not displayed", key);
+        }
+    }
+
+    #[test]
+    fn open_command_test() {
+        let command = open_command(Path::new("/tmp/a b"));
+        let args: Vec<_> = command.get_args().collect();
+        assert_eq!(args.last().copied(), Some(Path::new("/tmp/a b").as_os_str()));
+        let program = if cfg!(windows) { "cmd" } else if cfg!(target_os = "macos") { "open" } else { "xdg-open" };
+        assert_eq!(command.get_program(), program);
+    }
+
+    #[test]
+    fn export_test() {
+        let (dir, mut app) = create_app();
+        let out = tempfile::tempdir().unwrap();
+        app.execute(Command::Filter(Some("{a,b}".to_string()))).unwrap();
+        app.cycle_sort();
+        app.execute(Command::Export(out.path().join("big.txt"))).unwrap();
+        let text = fs::read_to_string(out.path().join("big.txt")).unwrap();
+        let lines: Vec<_> = text.lines().map(str::trim_start).collect();
+        assert_eq!(lines, [format!("1.95 KB  {}/", dir.path().join("big").display()), "1000 B    b".to_string(), "1000 B    sub/".to_string(), "1000 B      a".to_string()]);
+
+        app.execute(Command::Export(out.path().join("big.JSON"))).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&fs::read_to_string(out.path().join("big.JSON")).unwrap()).unwrap();
+        assert_eq!(json["children"][1]["children"][0]["path"], dir.path().join("big/sub/a").display().to_string());
+
+        app.execute(Command::Export(out.path().join("big.csv"))).unwrap();
+        let csv = fs::read_to_string(out.path().join("big.csv")).unwrap();
+        assert_eq!(csv.lines().nth(3), Some(format!("{},directory,1000,1", dir.path().join("big/sub").display()).as_str()));
+        assert_eq!(app.message.as_deref(), Some(format!("Exported 4 entries to {}", out.path().join("big.csv").display()).as_str()));
+    }
+
+    #[test]
+    fn history_test() {
+        let (dir, mut app) = create_app();
+        app.execute(Command::Goto(PathBuf::from("big/sub/a"))).unwrap();
+        app.execute(Command::Goto(PathBuf::from("small/c"))).unwrap();
+        app.go_back();
+        assert_eq!(app.selected_path(), Some(dir.path().join("big/sub/a")));
+        app.go_back();
+        assert_eq!(app.selected_path(), Some(dir.path().join("big")));
+        app.go_back();
+        assert!(app.message.is_some());
+
+        app.go_forward();
+        app.go_forward();
+        assert_eq!(app.selected_path(), Some(dir.path().join("small/c")));
+        app.go_back();
+        app.execute(Command::Goto(PathBuf::from("file"))).unwrap();
+        assert!(app.forward.is_empty());
+    }
+
+    #[test]
+    fn bookmarks_test() {
+        let (dir, mut app) = create_app();
+        let config = tempfile::tempdir().unwrap();
+        app.config_path = Some(config.path().join("config.toml"));
+        app.bookmarks.push(PathBuf::from("/ailleurs"));
+        app.move_down();
+        app.toggle_bookmark();
+        app.execute(Command::Goto(PathBuf::from("big/sub"))).unwrap();
+        app.toggle_bookmark();
+        assert!(app.is_bookmarked(&dir.path().join("file")));
+        let saved = Config::load(&config.path().join("config.toml")).unwrap().bookmarks;
+        assert_eq!(saved, [PathBuf::from("/ailleurs"), std::path::absolute(dir.path().join("file")).unwrap(), std::path::absolute(dir.path().join("big/sub")).unwrap()]);
+
+        app.next_bookmark();
+        assert_eq!(app.selected_path(), Some(dir.path().join("file")));
+        app.next_bookmark();
+        assert_eq!(app.selected_path(), Some(dir.path().join("big/sub")));
+        app.go_back();
+        assert_eq!(app.selected_path(), Some(dir.path().join("file")));
+
+        app.toggle_bookmark();
+        assert!(!app.is_bookmarked(&dir.path().join("file")));
+    }
 }
@@ -0,0 +1,125 @@
+//! Disk usage analysis and duplicate file detection.
+//!
+//! The central type is `FileTree`, built either with `FileTree::new` or with a
+//! `FileTreeBuilder`, returned by `FileTree::builder`, to configure the scan.
+//! Once built, a file tree is queried by path (`get_size`, `get_children`, `get_map_option`...),
+//! iterated over (`iter`, `files`, `directories`), searched for duplicates
+//! (`compute_signatures` then `find_duplicates_with`) or written out
+//! (`write_tree`, `write_csv`, `write_json`). Sizes are represented by `Size`. Scans, hashing and
+//! cache files fail with an `Error` naming the path that failed.
+//!
+//! The `main` and `interface` binaries are thin command line and terminal front ends over this library.
+//!
+//! ```
+//! use du::FileTreeBuilder;
+//!
+//! # let dir = tempfile::tempdir()?;
+//! # std::fs::write(dir.path().join("notes.txt"), b"hello")?;
+//! let file_tree = FileTreeBuilder::new(dir.path()).build()?;
+//! for path in file_tree.files() {
+//!     println!("{}: {}", path.display(), file_tree.get_size(path).unwrap());
+//! }
+//! # Ok::<(), std::io::Error>(())
+//! ```
+
+/// A module providing the actions resolving duplicate files.
+pub mod actions;
+
+/// A module listing and hashing the content of the zip and tar archives.
+mod archive;
+
+/// A module reading the defaults of the command line from a configuration file.
+pub mod config;
+
+/// A module providing the persistence of scan results to a cache file.
+mod cache;
+
+/// A module estimating the space saved by compressing the files.
+pub mod compression;
+
+/// A module providing the builder used to configure a file tree scan.
+pub mod builder;
+
+/// A module comparing two scans of a file tree.
+pub mod diff;
+
+/// A module finding and deleting the empty files and directories.
+mod empty;
+
+/// A module defining the errors of the file tree operations.
+pub mod error;
+
+/// A module grouping duplicate files into a report.
+pub mod duplicates;
+
+/// A module exporting the file tree and the duplicates to tabular formats.
+pub mod export;
+
+/// A module grouping several file trees scanned together.
+pub mod forest;
+
+/// A module representing a file tree structure and related functionalities.
+pub mod file_tree;
+
+/// A module providing glob-based filtering of the displayed files.
+pub mod filter;
+
+/// A module writing the reports as a standalone HTML page.
+mod html;
+
+/// A module providing the hash functions used to compute the signatures of the files.
+pub mod hasher;
+
+/// A module converting the long paths to the extended-length form of Windows.
+mod long_path;
+
+/// A module grouping the files of the same size having similar names.
+mod near;
+
+/// A module reading the owner and the permissions of the entries.
+pub mod owner;
+
+/// A module grouping the similar images by perceptual hash.
+mod perceptual;
+
+/// A module writing the sizes of the file tree as Prometheus metrics.
+mod prometheus;
+
+/// A module providing functionality to print the file tree.
+mod print_tree;
+
+/// A module drawing the file tree as an SVG treemap.
+mod treemap;
+
+/// A module listing the largest files and directories of the file tree.
+mod top;
+
+/// A module aggregating the sizes of the files by type.
+pub mod stats;
+
+/// A module scanning the file system without building the file tree in memory.
+pub mod stream;
+
+/// A module defining the Size struct used to represent the size of files or directories.
+pub mod size;
+
+#[cfg(test)]
+mod test_utils;
+
+pub use actions::{DuplicateAction, KeepPolicy, Resolution, ScriptShell};
+pub use builder::FileTreeBuilder;
+pub use compression::CompressionEstimate;
+pub use config::Config;
+pub use diff::{Change, DiffEntry};
+pub use duplicates::{DuplicateGroup, DuplicateReport};
+pub use error::{Error, Result};
+pub use export::{Report, UsageNode, UsageRecord};
+pub use file_tree::{EntryCounts, EntryNode, FileKind, FileTree, FileTreeIterator, ScanProgress, ScanTimings, TreeWalk};
+pub use filter::Filter;
+pub use forest::Forest;
+pub use hasher::{Blake3Hasher, HashAlgo, Hasher, Md5Hasher, Sha256Hasher, XxHasher};
+pub use owner::{Owner, Ownership};
+pub use print_tree::{PathStyle, PrintOptions, SortKey, SortOrder, Summary, Threshold};
+pub use size::{Size, SizeDisplay, SizeFormat, SizeUnit};
+pub use stats::TypeStats;
+pub use stream::{StreamEntry, StreamRecord};
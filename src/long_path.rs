@@ -0,0 +1,73 @@
+use std::borrow::Cow;
+use std::path::Path;
+
+/// The length from which a path must be in the extended-length form to be accepted by the
+/// Windows API: `MAX_PATH`, 260 characters including the terminating NUL.
+const MAX_PATH: usize = 260;
+
+/// Returns `path` in the extended-length form, `\\?\C:\...` or `\\?\UNC\server\share\...`, on
+/// Windows, so that the calls of the Windows API accept it even beyond 260 characters. The
+/// relative paths, the paths already in this form and all the paths on other platforms are
+/// returned unchanged.
+///
+/// The functions of `std::fs` already convert the long paths they are given: only the paths
+/// passed to the Windows API directly, or written to a script, need this.
+pub(crate) fn extended(path: &Path) -> Cow<'_, Path> {
+    #[cfg(windows)]
+    if let Some(extended) = path.to_str().and_then(extended_str) {
+        return Cow::Owned(extended.into());
+    }
+    Cow::Borrowed(path)
+}
+
+/// Returns `path` in the extended-length form like `extended`, only if it is too long for the
+/// Windows API, so that the short paths written to a script stay readable.
+pub(crate) fn extended_if_long(path: &Path) -> Cow<'_, Path> {
+    if path.as_os_str().len() >= MAX_PATH {
+        return extended(path);
+    }
+    Cow::Borrowed(path)
+}
+
+/// Returns the extended-length form of an absolute Windows path, or `None` if it is relative or
+/// already in this form. The separators are turned into backslashes, as the extended-length form
+/// is passed to the file system without being normalized.
+#[cfg_attr(not(windows), allow(dead_code))]
+fn extended_str(path: &str) -> Option<String> {
+    if path.starts_with(r"\\?\") || path.starts_with(r"\\.\") {
+        return None;
+    }
+    let path = path.replace('/', r"\");
+    if let Some(share) = path.strip_prefix(r"\\") {
+        return Some(format!(r"\\?\UNC\{}", share));
+    }
+    let bytes = path.as_bytes();
+    if bytes.len() >= 3 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':' && bytes[2] == b'\\' {
+        return Some(format!(r"\\?\{}", path));
+    }
+    None
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extended_str_test() {
+        assert_eq!(extended_str(r"C:\Users\node_modules").as_deref(), Some(r"\\?\C:\Users\node_modules"));
+        assert_eq!(extended_str("D:/projets/a").as_deref(), Some(r"\\?\D:\projets\a"));
+        assert_eq!(extended_str(r"\\serveur\partage\dossier").as_deref(), Some(r"\\?\UNC\serveur\partage\dossier"));
+        assert_eq!(extended_str(r"\\?\C:\Users"), None);
+        assert_eq!(extended_str(r"relatif\dossier"), None);
+        assert_eq!(extended_str("C:relatif"), None);
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn unchanged_off_windows_test() {
+        let long = "/a".repeat(200);
+        assert_eq!(extended(Path::new(&long)), Path::new(&long));
+        assert_eq!(extended_if_long(Path::new(&long)), Path::new(&long));
+    }
+}
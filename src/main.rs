@@ -1,18 +1,18 @@
-/// A module representing a file tree structure and related functionalities.
-mod file_tree;
-
-/// A module providing functionality to print the file tree.
-mod print_tree;
-
-/// A module defining the Size struct used to represent the size of files or directories.
-mod size;
-
-use clap::{Parser, Subcommand};
-use file_tree::FileTree;
+use clap::{Parser, Subcommand, ValueEnum};
+use du::{CompressionEstimate, Config, DuplicateAction, Owner, DuplicateReport, FileTree, FileTreeBuilder, Filter, Forest, HashAlgo, KeepPolicy, PathStyle, PrintOptions, Report, Resolution, ScanProgress, ScriptShell, Size, SizeFormat, SizeUnit, SortOrder, StreamRecord, Summary, Threshold, TypeStats};
+use indicatif::{ProgressBar, ProgressStyle};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, IsTerminal, Write};
+use std::net::SocketAddr;
+use std::num::NonZeroUsize;
 use std::path::{Path, PathBuf};
+use std::process::{ExitCode, Stdio};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant, SystemTime};
 
 /// Command-line interface structure defined using the `clap` crate.
-
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
 #[command(propagate_version = true)]
@@ -20,79 +20,2052 @@ struct Cli {
     #[command(subcommand)]
     command: Commands,
 
-    /// Enable lexicographic sorting of file paths.
-    #[arg(long = "lexicographic-sort")]
+    /// Sort the children of each directory by name, an alias of '--sort name'.
+    #[arg(long = "lexicographic-sort", conflicts_with = "sort")]
     lexicographic_sort: bool,
 
-    /// Filter the file tree based on a provided string.
+    /// Sort the children of each directory: name, size, mtime or count, optionally followed by :asc or :desc.
+    #[arg(long = "sort", value_name = "KEY[:asc|:desc]")]
+    sort: Option<SortOrder>,
+
+    /// Filter the file tree with a glob pattern matched against file names (e.g. "*.jpg"), or with
+    /// comma-separated extensions, regardless of case (e.g. "jpg,jpeg,png").
     #[arg(long = "filter")]
     filter: Option<String>,
+
+    /// Filter the file tree with a regular expression searched in the path relative to the root (e.g. '^photos/.*\.jpe?g$').
+    #[arg(long = "filter-regex", value_name = "REGEX", conflicts_with = "filter")]
+    filter_regex: Option<String>,
+
+    /// Match the filter against the path relative to the root instead of the file name.
+    #[arg(long = "filter-path")]
+    filter_path: bool,
+
+    /// Keep only the files whose name matches this glob pattern, case sensitively, like 'find -name' (e.g. 'backup_*.tar.gz').
+    #[arg(long = "name", value_name = "PATTERN")]
+    name: Option<String>,
+
+    /// Keep only the files whose name matches this glob pattern, regardless of case, like 'find -iname'.
+    #[arg(long = "iname", value_name = "PATTERN", conflicts_with = "name")]
+    iname: Option<String>,
+
+    /// Keep only the files last modified more than this long ago, e.g. 90d or 2weeks.
+    #[arg(long = "older-than", value_name = "DURATION", value_parser = humantime::parse_duration)]
+    older_than: Option<Duration>,
+
+    /// Keep only the files last modified less than this long ago, e.g. 1h or 30min.
+    #[arg(long = "newer-than", value_name = "DURATION", value_parser = humantime::parse_duration)]
+    newer_than: Option<Duration>,
+
+    /// Keep only the files owned by this user, a name or a uid (an account name on Windows).
+    #[arg(long = "owned-by", value_name = "USER")]
+    owned_by: Option<String>,
+
+    /// Make the filter case sensitive.
+    #[arg(long = "case-sensitive")]
+    case_sensitive: bool,
+
+    /// Draw the tree with ASCII characters, for terminals without Unicode support.
+    #[arg(long = "ascii")]
+    ascii: bool,
+
+    /// The width of each level of the tree, in columns.
+    #[arg(long = "indent", value_name = "N", default_value_t = 4)]
+    indent: usize,
+
+    /// Write the full path of each entry on its own line instead of drawing the tree, like du.
+    #[arg(long = "flat")]
+    flat: bool,
+
+    /// Display the paths relative to the scanned root, the root itself being '.'.
+    #[arg(long = "relative", global = true, conflicts_with = "absolute")]
+    relative: bool,
+
+    /// Display the canonical absolute paths, the symbolic links of the root resolved.
+    #[arg(long = "absolute", global = true)]
+    absolute: bool,
+
+    /// Color the tree: always, never, or auto when writing to a terminal and NO_COLOR is not set (the default).
+    #[arg(long = "color", value_enum)]
+    color: Option<ColorChoice>,
+
+    /// End the output with the total size, the numbers of files, directories and unreadable entries,
+    /// and the reclaimable space for duplicate.
+    #[arg(long = "summary")]
+    summary: bool,
+
+    /// Display the space used on disk instead of the apparent size of files.
+    #[arg(long = "disk-usage")]
+    disk_usage: bool,
+
+    /// Display the sizes in powers of 1000 (kB, MB, GB) instead of powers of 1024.
+    #[arg(long = "si")]
+    si: bool,
+
+    /// With --format du, display the sizes like du -h, e.g. 1.5K or 12M, instead of in 1 KiB blocks (-h being --help).
+    #[arg(long = "human-readable")]
+    human_readable: bool,
+
+    /// With --format du, count the apparent size of the files instead of the space used on disk, like du --apparent-size
+    /// but without the size of the directories themselves.
+    #[arg(long = "apparent-size", conflicts_with = "disk_usage")]
+    apparent_size: bool,
+
+    /// Display the sizes as raw numbers of bytes.
+    #[arg(long = "bytes", conflicts_with_all = ["si", "unit"])]
+    bytes: bool,
+
+    /// Display all the sizes in this unit: B, KB, MB, GB, TB or PB.
+    #[arg(long = "unit", value_name = "UNIT")]
+    unit: Option<SizeUnit>,
+
+    /// Follow symbolic links instead of listing them as links (cycles are detected).
+    #[arg(long = "follow-symlinks")]
+    follow_symlinks: bool,
+
+    /// Collapse the entries below this share of their directory, e.g. 1%, or this size, e.g. 10M, into a single line.
+    #[arg(long = "prune-below", value_name = "THRESHOLD")]
+    prune_below: Option<Threshold>,
+
+    /// Ignore the entries smaller than this size, e.g. 10M (duplicate defaults to 1, ignoring empty files).
+    #[arg(long = "min-size", value_name = "SIZE", global = true)]
+    min_size: Option<Size>,
+
+    /// Skip the entries whose name or path relative to the root matches this glob (repeatable, e.g. 'target/**').
+    #[arg(long = "exclude", value_name = "GLOB")]
+    exclude: Vec<String>,
+
+    /// Include the hidden entries: names starting with a dot and, on Windows, hidden or system files.
+    #[arg(short = 'a', long = "all")]
+    all: bool,
+
+    /// Count the files having several hard links once per path instead of once in total.
+    #[arg(long = "count-links")]
+    count_links: bool,
+
+    /// Skip the entries ignored by the .gitignore files, and the .git directories.
+    #[arg(long = "gitignore")]
+    gitignore: bool,
+
+    /// Stay on the file system of each root, skipping the mount points below it.
+    #[arg(short = 'x', long = "one-file-system")]
+    one_file_system: bool,
+
+    /// List the content of the .zip, .tar and .tar.gz files as directories, and look for duplicates inside them.
+    #[arg(long = "inspect-archives", conflicts_with = "remote")]
+    inspect_archives: bool,
+
+    /// Stop at the first entry that can't be read instead of reporting the unreadable entries at the end.
+    #[arg(long = "strict")]
+    strict: bool,
+
+    /// The number of threads scanning and hashing the files, e.g. 1 on a spinning disk (defaults to the number of CPUs).
+    #[arg(long = "threads", value_name = "N")]
+    threads: Option<NonZeroUsize>,
+
+    /// Display the progress of the scan on stderr even when it is not a terminal.
+    #[arg(long = "progress", overrides_with = "no_progress")]
+    progress: bool,
+
+    /// Do not display the progress of the scan on stderr.
+    #[arg(long = "no-progress", overrides_with = "progress")]
+    no_progress: bool,
+
+    /// Print on stderr the time spent walking, reading metadata, hashing and printing, with the rates
+    /// of the scan and of the hashing, e.g. to choose the number of --threads.
+    #[arg(long = "timing")]
+    timing: bool,
+
+    /// End each path with a NUL byte instead of a newline, for 'xargs -0': the files listed by usage --files-only,
+    /// the copies of each duplicate but the one chosen by --keep, or the entries listed by empty.
+    #[arg(long = "print0", global = true, group = "keeping")]
+    print0: bool,
+
+    /// Scan this directory of another machine over SSH, e.g. user@host:/srv, instead of local paths (usage only).
+    /// The scan runs there with --remote-binary and is read back as --format ndjson usage --stream.
+    #[arg(long = "remote", value_name = "USER@HOST:PATH")]
+    remote: Option<String>,
+
+    /// The command running this program on the remote machine, e.g. ~/bin/main.
+    #[arg(long = "remote-binary", value_name = "COMMAND", default_value = "main")]
+    remote_binary: String,
+
+    /// Read the defaults from this configuration file instead of ~/.config/arborescence/config.toml.
+    #[arg(long = "config", value_name = "PATH", global = true)]
+    config: Option<PathBuf>,
+
+    /// The output format.
+    #[arg(long = "format", alias = "output", global = true, value_enum, default_value_t = OutputFormat::Text)]
+    format: OutputFormat,
+
+    /// Write the output to a file instead of stdout.
+    #[arg(long = "output-file")]
+    output_file: Option<PathBuf>,
 }
 
-/// Enum representing different commands that can be executed via the command-line interface.
+/// The formats in which the results can be written.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum OutputFormat {
+    /// Indented tree, for humans
+    Text,
+    /// One row per entry, for spreadsheets
+    Csv,
+    /// Nested objects for usage, one object per group for duplicate
+    Json,
+    /// One JSON object per line and per group of duplicates for duplicate, or per entry for usage --stream
+    Ndjson,
+    /// A YAML document listing the trees or the groups of duplicates
+    Yaml,
+    /// An XML document listing the trees or the groups of duplicates
+    Xml,
+    /// A standalone HTML page with a collapsible tree and, for duplicate, a table of the groups
+    Html,
+    /// 'SIZE<TAB>PATH' lines of the directories in 1 KiB blocks, deepest first, like du (usage only)
+    Du,
+}
+
+/// How the sizes are aggregated instead of drawing the tree.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum GroupBy {
+    /// By user owning the files
+    Owner,
+}
+
+/// When the tree is colored.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum ColorChoice {
+    /// When writing to a terminal, unless NO_COLOR is set
+    Auto,
+    /// Even when redirected
+    Always,
+    /// Plain text
+    Never,
+}
+
+/// The kinds of links replacing the duplicates.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum LinkKind {
+    /// Hard links, sharing the content
+    Hard,
+    /// Symbolic links, pointing to the kept path
+    Sym,
+}
 
+/// Enum representing different commands that can be executed via the command-line interface.
 #[derive(Subcommand, Debug)]
 enum Commands {
 
-    /// Show the disk usage tree for the given path 
-    
+    /// Show the disk usage tree for the given paths, followed by their grand total if there are several
     Usage {
 
         /// (default '.')
-        
+        paths: Vec<PathBuf>,
+
+        /// Save the scan results to a cache file (a single path only).
+        #[arg(long = "save-cache")]
+        save_cache: Option<PathBuf>,
+
+        /// Load the scan results from a cache file instead of scanning the path.
+        #[arg(long = "load-cache", alias = "from-cache", conflicts_with = "paths")]
+        from_cache: Option<PathBuf>,
+
+        /// Update the loaded scan results with the changes made since, rescanning only the modified directories.
+        #[arg(long = "refresh", requires = "from_cache")]
+        refresh: bool,
+
+        /// Display the entries down to this depth only, like `du --max-depth` (the root is at depth 0).
+        #[arg(long = "max-depth")]
+        max_depth: Option<usize>,
+
+        /// Display the number of files and subdirectories of each directory.
+        #[arg(long = "show-counts")]
+        show_counts: bool,
+
+        /// Display the last modification time of each entry, in UTC (see also '--sort mtime').
+        #[arg(long = "show-mtime")]
+        show_mtime: bool,
+
+        /// Display the user and the group owning each entry.
+        #[arg(long = "show-owner")]
+        show_owner: bool,
+
+        /// Display the permissions of each entry, e.g. rwxr-xr-x.
+        #[arg(long = "show-perms")]
+        show_perms: bool,
+
+        /// Display the share of each entry in its directory and in the total.
+        #[arg(long = "percent")]
+        percent: bool,
+
+        /// Print each entry as soon as its subtree is scanned, after its children, without keeping the tree in memory.
+        #[arg(long = "stream", conflicts_with_all = ["save_cache", "from_cache", "show_counts", "show_mtime", "show_owner", "show_perms", "percent"])]
+        stream: bool,
+
+        /// Add up the sizes of the files by owner across all the paths and list the owners, the largest first.
+        #[arg(long = "group-by", value_name = "KEY", value_enum, conflicts_with_all = ["stream", "save_cache"])]
+        group_by: Option<GroupBy>,
+
+        /// List the paths of the files only, one per line, or NUL-terminated with --print0.
+        #[arg(long = "files-only", conflicts_with = "stream")]
+        files_only: bool,
+
+        /// Display the directories only, each with the size of its whole subtree, like du.
+        #[arg(long = "dirs-only", conflicts_with = "files_only")]
+        dirs_only: bool,
+    },
+
+    /// Find and display duplicate files within the given paths, compared across all of them.
+    Duplicate{
+        /// (default '.')
+        paths: Vec<PathBuf>,
+
+        /// Save the scan results and the signatures of the files to a cache file (a single path only).
+        #[arg(long = "save-cache")]
+        save_cache: Option<PathBuf>,
+
+        /// Load the scan results from a cache file instead of scanning the path, only hashing the files not hashed yet.
+        #[arg(long = "load-cache", alias = "from-cache", conflicts_with = "paths")]
+        from_cache: Option<PathBuf>,
+
+        /// Update the loaded scan results with the changes made since, rescanning only the modified directories.
+        #[arg(long = "refresh", requires = "from_cache")]
+        refresh: bool,
+
+        /// Report only the duplicates found in at least two of the given paths.
+        #[arg(long = "only-cross", conflicts_with = "from_cache")]
+        only_cross: bool,
+
+        /// The hash function comparing the files: md5, sha256, blake3 (the default) or xxhash (the fastest, not cryptographic).
+        #[arg(long = "hash", value_name = "ALGO")]
+        hash: Option<HashAlgo>,
+
+        /// Ignore the files larger than this size, e.g. 1G, without reading them.
+        #[arg(long = "max-size", value_name = "SIZE")]
+        max_size: Option<Size>,
+
+        /// Compare the files of each group byte by byte before reporting or acting on them, splitting the groups
+        /// whose files differ despite their signature.
+        #[arg(long = "verify")]
+        verify: bool,
+
+        /// Also report the directories duplicated as a whole, instead of the files they contain (a single path only).
+        #[arg(long = "dirs", conflicts_with = "resolve")]
+        dirs: bool,
+
+        /// Group the images that look the same, e.g. resized or saved at another quality, by perceptual hash,
+        /// only listing them: their contents differ, so that no copy can be removed without loss
+        /// instead of the files having the same content (a single path only).
+        #[arg(long = "perceptual", conflicts_with_all = ["hash", "verify", "dirs", "interactive", "delete", "link", "emit_script"])]
+        perceptual: bool,
+
+        /// The maximum number of bits, out of 64, by which the perceptual hashes of two similar images differ.
+        #[arg(long = "threshold", value_name = "BITS", default_value_t = 8, requires = "perceptual")]
+        threshold: u32,
+
+        /// Ask for each group which copy to keep and whether to delete or hardlink the others.
+        #[arg(long = "interactive", group = "resolve")]
+        interactive: bool,
+
+        /// Delete the copies of each group except the one chosen by --keep (requires --yes or --dry-run).
+        #[arg(long = "delete", group = "resolve", group = "keeping", group = "apply", requires = "confirm")]
+        delete: bool,
+
+        /// Replace the copies of each group except the one chosen by --keep with hard or symbolic links to it,
+        /// on the same file system only (requires --yes or --dry-run).
+        #[arg(long = "link", value_name = "KIND", value_enum, group = "resolve", group = "keeping", group = "apply", requires = "confirm")]
+        link: Option<LinkKind>,
+
+        /// Write a script deleting (rm) or replacing with links (hardlink, symlink) the copies of each group
+        /// except the one chosen by --keep, to review and run by hand (PowerShell on Windows).
+        #[arg(long = "emit-script", value_name = "ACTION", group = "resolve", group = "keeping")]
+        emit_script: Option<Resolution>,
+
+        /// The copy kept by --delete, --link or --emit-script: oldest, newest or first-path.
+        #[arg(long = "keep", value_name = "POLICY", default_value = "first-path", requires = "keeping")]
+        keep: KeepPolicy,
+
+        /// Confirm the changes made by --delete or --link.
+        #[arg(long = "yes", group = "confirm", requires = "apply")]
+        yes: bool,
+
+        /// Only print the actions chosen interactively, by --delete or by --link instead of applying them.
+        #[arg(long = "dry-run", group = "confirm", requires = "resolve", conflicts_with = "emit_script")]
+        dry_run: bool,
+    },
+
+    /// Group the files of the same size and extension with nearly identical names, e.g. report_v1.docx and
+    /// report_final.docx, without reading them: copies made by hand to review.
+    NearDuplicates {
+        /// (default '.')
+        path: Option<PathBuf>,
+
+        /// The maximum number of characters inserted, removed or replaced between two similar names,
+        /// without their extension and regardless of case, and at most a third of the shorter name.
+        #[arg(long = "max-distance", value_name = "EDITS", default_value_t = 2)]
+        max_distance: usize,
+
+        /// Also group the names starting with the same characters, at least this many (0 to disable).
+        #[arg(long = "min-prefix", value_name = "CHARS", default_value_t = 0)]
+        min_prefix: usize,
+    },
+
+    /// List the largest files and directories within the given path (the 10 largest files by default).
+    Top {
+        /// (default '.')
         path: Option<PathBuf>,
+
+        /// The number of files to list.
+        #[arg(long = "files")]
+        files: Option<usize>,
+
+        /// The number of directories to list.
+        #[arg(long = "dirs")]
+        dirs: Option<usize>,
     },
 
-    /// Find and display duplicate files within the given path.
+    /// Compare two scans, each a path or a cache file, and show the entries added, removed, grown or shrunk.
+    Diff {
+        /// The old scan.
+        old: PathBuf,
 
-    Duplicate{
+        /// The new scan.
+        new: PathBuf,
+
+        /// Display the entries down to this depth only (the roots are at depth 0).
+        #[arg(long = "max-depth")]
+        max_depth: Option<usize>,
+    },
+
+    /// Export the disk usage of the given path as an image.
+    Export {
+        #[command(subcommand)]
+        export: Export,
+    },
+
+    /// Show the total size and number of files per extension within the given path, the largest first.
+    Stats {
+        /// (default '.')
+        path: Option<PathBuf>,
+
+        /// Group the files by MIME type, detected from their first bytes, instead of by extension.
+        #[arg(long = "mime")]
+        mime: bool,
+
+        /// The number of types to list.
+        #[arg(long = "limit")]
+        limit: Option<usize>,
+    },
+
+    /// Estimate the space saved by compressing the files of each directory within the given path, from
+    /// samples compressed with zstd, the largest savings first.
+    Compressible {
+        /// (default '.')
+        path: Option<PathBuf>,
+
+        /// List the directories down to this depth only (the root is at depth 0).
+        #[arg(long = "max-depth")]
+        max_depth: Option<usize>,
+
+        /// The number of directories to list.
+        #[arg(long = "limit")]
+        limit: Option<usize>,
+    },
+
+    /// Check that each directory stays within the given size, printing one line per directory,
+    /// 'OK|EXCEEDED size=BYTES max=BYTES PATH', the path last so that it may contain spaces, and exiting
+    /// with status 2 if one exceeds it, e.g. from cron.
+    Check {
+        /// A directory to check, repeatable (default '.').
+        #[arg(long = "path", value_name = "PATH")]
+        paths: Vec<PathBuf>,
+
+        /// The maximum size of each directory (e.g. 5G).
+        #[arg(long = "max", value_name = "SIZE")]
+        max: Size,
+    },
+
+    /// List the empty files and the directories containing no file within the given path.
+    Empty {
+        /// (default '.')
+        path: Option<PathBuf>,
+
+        /// Delete the empty files and directories listed (requires --yes or --dry-run).
+        #[arg(long = "delete", requires = "confirm")]
+        delete: bool,
+
+        /// Confirm the deletions made by --delete.
+        #[arg(long = "yes", group = "confirm", requires = "delete")]
+        yes: bool,
+
+        /// Only print the deletions instead of applying them.
+        #[arg(long = "dry-run", group = "confirm", requires = "delete")]
+        dry_run: bool,
+    },
+
+    /// Show the disk usage tree for the given path and redraw it whenever the files change, until interrupted.
+    Watch {
+        /// (default '.')
+        path: Option<PathBuf>,
+
+        /// Display the entries down to this depth only (the root is at depth 0).
+        #[arg(long = "max-depth")]
+        max_depth: Option<usize>,
+    },
+
+    /// Keep the scan of the given path in memory, refreshed periodically, and answer over HTTP with JSON:
+    /// GET /usage?path=PATH&max_depth=N (1 by default) and GET /duplicates, until interrupted.
+    Serve {
+        /// (default '.')
+        path: Option<PathBuf>,
+
+        /// The delay between two refreshes of the scan, which only read again the changed directories (e.g. 30min).
+        #[arg(long = "interval", value_name = "DURATION", value_parser = humantime::parse_duration, default_value = "1h")]
+        interval: Duration,
+
+        /// Also refresh the directories as soon as their files change.
+        #[arg(long = "watch")]
+        watch: bool,
+
+        /// The address to listen on.
+        #[arg(long = "listen", value_name = "ADDRESS", default_value = "127.0.0.1:8080")]
+        listen: SocketAddr,
+    },
+
+    /// Keep the scan of the given path in memory, only refreshed on demand, and answer a JSON API over HTTP
+    /// for a web frontend, until interrupted: GET /api/children?path=PATH, /api/size?path=PATH,
+    /// /api/search?pattern=GLOB&limit=N and /api/duplicates, and POST /api/rescan?path=PATH to refresh the
+    /// scan, the paths being relative to the root or absolute.
+    ServeHttp {
+        /// (default '.')
+        path: Option<PathBuf>,
+
+        /// The address to listen on.
+        #[arg(long = "listen", value_name = "ADDRESS", default_value = "127.0.0.1:8080")]
+        listen: SocketAddr,
+    },
+}
+
+/// The images written by the `export` command.
+#[derive(Subcommand, Debug)]
+enum Export {
+    /// Write an SVG treemap of the sizes, colored by top-level directory.
+    Treemap {
+        /// (default '.')
+        path: Option<PathBuf>,
+
+        /// The width of the image, in pixels.
+        #[arg(long = "width", default_value_t = 1280)]
+        width: u32,
+
+        /// The height of the image, in pixels.
+        #[arg(long = "height", default_value_t = 800)]
+        height: u32,
+
+        /// Draw the entries down to this depth only (the root is at depth 0).
+        #[arg(long = "max-depth")]
+        max_depth: Option<usize>,
+    },
+
+    /// Write the size and the number of files of each directory as Prometheus gauges, or serve them with --listen.
+    Prometheus {
+        /// (default '.')
         path: Option<PathBuf>,
+
+        /// Write the directories down to this depth only (the root is at depth 0).
+        #[arg(long = "max-depth")]
+        max_depth: Option<usize>,
+
+        /// Also hash the files to expose the space reclaimable from the duplicates.
+        #[arg(long = "duplicates")]
+        duplicates: bool,
+
+        /// Serve the metrics at http://ADDRESS/metrics, refreshing the scan on each request, until interrupted.
+        #[arg(long = "listen", value_name = "ADDRESS")]
+        listen: Option<SocketAddr>,
+    },
+}
+
+/// The answer to the question asked for a group of duplicates in interactive mode.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Choice {
+    /// Keep the copy at this index and resolve the others.
+    Keep(usize, Resolution),
+    /// Leave the group untouched.
+    Skip,
+    /// Leave this group and the next ones untouched.
+    Quit,
+}
+
+/// Parses an answer such as "2d" (keep the second copy, delete the others), "1l" (keep the
+/// first copy, hardlink the others), "s" (skip) or "q" (quit).
+///
+/// Returns `None` if the answer is invalid for a group of `copies` files.
+fn parse_choice(answer: &str, copies: usize) -> Option<Choice> {
+    let answer = answer.trim();
+    match answer {
+        "s" | "" => return Some(Choice::Skip),
+        "q" => return Some(Choice::Quit),
+        _ => {}
+    }
+    let resolution = match answer.chars().last()? {
+        'd' => Resolution::Delete,
+        'l' => Resolution::HardLink,
+        _ => return None,
+    };
+    let index: usize = answer[..answer.len() - 1].trim().parse().ok()?;
+    (1..=copies).contains(&index).then(|| Choice::Keep(index - 1, resolution))
+}
+
+/// Asks on stderr which copy of each group to keep, then applies the chosen actions, or only
+/// prints them if `dry_run` is set.
+fn resolve_interactively(report: &DuplicateReport, dry_run: bool, out: &mut dyn Write) -> std::io::Result<()> {
+    let mut answers = std::io::stdin().lines();
+    let mut actions = Vec::new();
+    'groups: for (number, group) in report.groups().iter().enumerate() {
+        eprintln!("Group {}/{}: {} x {} ({} reclaimable)", number + 1, report.groups().len(), group.paths.len(), group.size, group.reclaimable());
+        for (index, path) in group.paths.iter().enumerate() {
+            eprintln!("  [{}] {}", index + 1, path.display());
+        }
+        loop {
+            eprint!("Keep which copy [1-{}], then d to delete or l to hardlink the others (e.g. 1d), s to skip, q to quit: ", group.paths.len());
+            let Some(answer) = answers.next().transpose()? else { break 'groups };
+            match parse_choice(&answer, group.paths.len()) {
+                Some(Choice::Keep(keep, resolution)) => {
+                    actions.extend(group.plan(keep, resolution));
+                    break;
+                }
+                Some(Choice::Skip) => break,
+                Some(Choice::Quit) => break 'groups,
+                None => eprintln!("Invalid answer: {}", answer.trim()),
+            }
+        }
+    }
+
+    apply_actions(&actions, dry_run, out)?;
+    Ok(())
+}
+
+/// Deletes the copies of each group except the one chosen by `keep`, or replaces them with links
+/// to it, or only prints the actions if `dry_run` is set, then prints the space freed.
+///
+/// The copies on another file system than the kept one are not linked.
+fn resolve_duplicates(file_tree: &FileTree, report: &DuplicateReport, keep: KeepPolicy, resolution: Resolution, dry_run: bool, out: &mut dyn Write) -> std::io::Result<()> {
+    let mut freed = 0;
+    for group in report.groups() {
+        let mut actions = Vec::new();
+        for action in group.plan(keep.select(group, file_tree), resolution) {
+            match action.crosses_file_systems() {
+                Ok(false) => actions.push(action),
+                Ok(true) => eprintln!("Skipping {}: not on the file system of the kept copy", action.path().display()),
+                Err(err) => eprintln!("Skipping {}: {}", action.path().display(), err),
+            }
+        }
+        freed += apply_actions(&actions, dry_run, out)? as u64 * group.size.value();
+    }
+    writeln!(out, "{} {}", if dry_run { "Would free" } else { "Freed" }, Size::new(freed))
+}
+
+/// Writes groups of similar but not identical files, e.g. the similar images or names, in the format chosen on the
+/// command line, `listed` being `report` with the paths to display. Each file is listed with its own size,
+/// without any space to reclaim: removing a file of a group would lose its content.
+fn write_similar(out: &mut dyn Write, report: &DuplicateReport, listed: &DuplicateReport, file_tree: &FileTree, cli: &Cli) -> std::io::Result<()> {
+    let groups: Vec<Vec<(&Path, Size)>> = report
+        .groups()
+        .iter()
+        .zip(listed.groups())
+        .map(|(group, listed)| group.paths.iter().zip(&listed.paths).map(|(path, shown)| (shown.as_path(), file_tree.get_size(path).unwrap_or(group.size))).collect())
+        .collect();
+    match cli.format {
+        OutputFormat::Text => {
+            for files in &groups {
+                writeln!(out, "Similar files ({}):", files.len())?;
+                for (path, size) in files {
+                    writeln!(out, "  {:>12}  {}", size.display(size_format(cli)).to_string(), path.display())?;
+                }
+            }
+            writeln!(out, "{} groups of similar files", groups.len())
+        }
+        OutputFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(out);
+            writer.write_record(["group", "path", "size_bytes"])?;
+            for (index, files) in groups.iter().enumerate() {
+                for (path, size) in files {
+                    writer.write_record([(index + 1).to_string(), path.to_string_lossy().into_owned(), size.value().to_string()])?;
+                }
+            }
+            writer.flush()
+        }
+        OutputFormat::Json => {
+            let groups: Vec<_> = groups
+                .iter()
+                .map(|files| files.iter().map(|(path, size)| serde_json::json!({ "path": path, "size": size })).collect::<Vec<_>>())
+                .collect();
+            serde_json::to_writer_pretty(&mut *out, &groups)?;
+            writeln!(out)
+        }
+        _ => Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "the similar files only support the text, CSV and JSON formats")),
+    }
+}
+
+/// Writes the duplicates in the format chosen on the command line, `listed` being `report` with
+/// the paths to display, `trees` the scanned trees and `summary` their totals.
+fn write_duplicates(mut out: &mut dyn Write, report: &DuplicateReport, listed: &DuplicateReport, trees: &[FileTree], summary: Summary, cli: &Cli) -> std::io::Result<()> {
+    match cli.format {
+        OutputFormat::Du => Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "the du format is only supported by usage")),
+        OutputFormat::Csv => listed.write_csv(out),
+        OutputFormat::Json => listed.write_json(out), //cargo run --bin main -- duplicate --format json option<path>
+        OutputFormat::Ndjson => listed.write_ndjson(out),
+        OutputFormat::Yaml => Report::duplicates(listed).write_yaml(out), //cargo run --bin main -- duplicate --format yaml option<path>
+        OutputFormat::Xml => Report::duplicates(listed).write_xml(out), //cargo run --bin main -- duplicate --format xml option<path>
+        OutputFormat::Html => {
+            let options = PrintOptions { disk_usage: cli.disk_usage, path_style: path_style(cli), ..PrintOptions::default() };
+            Report::usage(trees, &options).with_duplicates(listed).write_html(out, size_format(cli)) //cargo run --bin main -- duplicate --format html --output-file report.html option<path>
+        }
+        _ => {
+            for group in listed.groups() {
+                // Les liens physiques vers un même fichier n'occupent sa place qu'une fois
+                let links = match group.distinct_files() {
+                    _ if group.is_hard_linked() => " [already hard-linked]".to_string(),
+                    distinct if distinct < group.paths.len() => format!(" [{} distinct files]", distinct),
+                    _ => String::new(),
+                };
+                writeln!(out, "Signature de Doublon : {} ({} x {}){}", group.signature, group.paths.len(), group.size.display(size_format(cli)), links)?;
+                for path in &group.paths {
+                    writeln!(out, "  - {}", path.display())?;
+                }
+            }
+            let linked = report.groups().iter().filter(|group| group.is_hard_linked()).count();
+            write!(out, "Potential savings: {} across {} groups", report.total_reclaimable().display(size_format(cli)), report.groups().len() - linked)?;
+            if linked > 0 {
+                write!(out, " ({} already hard-linked)", linked)?;
+            }
+            writeln!(out)?;
+            if cli.summary {
+                let summary = Summary { reclaimable: Some(report.total_reclaimable()), ..summary };
+                summary.write(&mut out, size_format(cli))?; //cargo run --bin main -- --summary duplicate option<path>
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Finds the duplicates across the trees scanned from `paths`, only those found in at least two
+/// of them if `only_cross` is set, compared byte by byte if `verify` is set, then lists them or
+/// resolves them interactively.
+#[allow(clippy::too_many_arguments)]
+fn duplicate_across(paths: &[PathBuf], min_size: Size, hash: HashAlgo, max_size: Option<Size>, only_cross: bool, verify: bool, interactive: bool, dry_run: bool, cli: &Cli) -> std::io::Result<()> {
+    let mut forest = Forest::new(paths.iter().map(|path| scan(FileTreeBuilder::new(path), cli)).collect::<std::io::Result<_>>()?);
+    hash_with_progress(cli, |callback| forest.compute_signatures_between(hash.hasher(), min_size, max_size, callback))?;
+
+    let mut report = forest.find_duplicates_between(min_size, max_size, only_cross);
+    if let Some(filter) = filter(cli)? {
+        report = report.filtered_across(&forest, &filter);
+    }
+    if verify {
+        report = report.verified()?;
+    }
+
+    let mut out = output(cli)?;
+    if interactive {
+        resolve_interactively(&report, dry_run, &mut out)?; //cargo run --bin main -- duplicate --interactive --dry-run path1 path2
+    } else {
+        let listed = report.clone().with_path_style_across(&forest, path_style(cli));
+        let options = PrintOptions { disk_usage: cli.disk_usage, ..PrintOptions::default() };
+        write_duplicates(&mut out, &report, &listed, forest.trees(), forest.summary(&options), cli)?;
+    }
+    out.flush()?;
+    for file_tree in forest.trees() {
+        warn_scan_errors(file_tree.errors());
+    }
+    Ok(())
+}
+
+/// Applies the actions, or only prints them if `dry_run` is set.
+///
+/// Returns the number of actions applied, or that would be applied.
+fn apply_actions(actions: &[DuplicateAction], dry_run: bool, out: &mut dyn Write) -> std::io::Result<usize> {
+    let mut applied = 0;
+    for action in actions {
+        if dry_run {
+            writeln!(out, "would {}", action)?;
+        } else if let Err(err) = action.apply() {
+            eprintln!("Failed to {}: {}", action, err);
+            continue;
+        } else {
+            writeln!(out, "{}", action)?;
+        }
+        applied += 1;
+    }
+    Ok(applied)
+}
+
+/// Runs `task`, drawing the progress it reports on stderr when it is a terminal and
+/// `--no-progress` was not passed, or when `--progress` was passed.
+///
+/// On a terminal the progress is drawn on a single line, otherwise a line is printed every second.
+fn with_progress<T>(cli: &Cli, task: impl FnOnce(&mut (dyn FnMut(&ScanProgress) + Send)) -> T) -> T {
+    let terminal = std::io::stderr().is_terminal();
+    if !cli.progress && (cli.no_progress || !terminal) {
+        return task(&mut |_| {});
+    }
+
+    let bar = ProgressBar::new_spinner();
+    bar.set_style(ProgressStyle::with_template("{spinner} {msg}").unwrap());
+    let interval = Duration::from_millis(if terminal { 100 } else { 1000 });
+    let mut last_draw = Instant::now();
+    let result = task(&mut |progress| {
+        // Limit the refresh rate so that the progress line doesn't slow down the scan.
+        if last_draw.elapsed() >= interval {
+            last_draw = Instant::now();
+            let message = format!(
+                "{} entries, {} hashed - {}",
+                progress.entries_scanned,
+                Size::new(progress.bytes_hashed),
+                progress.current_path.display()
+            );
+            if terminal {
+                bar.set_message(message);
+                bar.tick();
+            } else {
+                eprintln!("{}", message);
+            }
+        }
+    });
+    bar.finish_and_clear();
+    result
+}
+
+/// Applies the scan options of the command line to the builder.
+fn configure(builder: FileTreeBuilder, cli: &Cli) -> FileTreeBuilder {
+    let builder = builder.follow_symlinks(cli.follow_symlinks).gitignore(cli.gitignore).hidden(cli.all).count_links(cli.count_links).one_file_system(cli.one_file_system).inspect_archives(cli.inspect_archives).strict(cli.strict);
+    cli.exclude.iter().fold(builder, |builder, pattern| builder.exclude(pattern))
+}
+
+/// The flag set by Ctrl-C, once the handler is installed by `interrupt`.
+static INTERRUPT: OnceLock<Arc<AtomicBool>> = OnceLock::new();
+
+/// Returns the flag stopping the scans, installing the Ctrl-C handler on the first call: the first
+/// Ctrl-C sets the flag, so that the partial results are printed, and a second one exits right away.
+fn interrupt() -> Arc<AtomicBool> {
+    Arc::clone(INTERRUPT.get_or_init(|| {
+        let interrupt = Arc::new(AtomicBool::new(false));
+        // Le gestionnaire d'arrêt est enregistré en premier : il ne voit le drapeau levé qu'au second Ctrl-C
+        let _ = signal_hook::flag::register_conditional_shutdown(signal_hook::consts::SIGINT, 130, Arc::clone(&interrupt));
+        let _ = signal_hook::flag::register(signal_hook::consts::SIGINT, Arc::clone(&interrupt));
+        interrupt
+    }))
+}
+
+/// Saves the scan results to a cache file, unless the scan was interrupted.
+fn save(file_tree: &FileTree, cache: &Path) -> std::io::Result<()> {
+    if file_tree.is_interrupted() {
+        eprintln!("warning: the scan was interrupted, {} is not saved", cache.display());
+        return Ok(());
+    }
+    Ok(file_tree.save(cache)?)
+}
+
+/// Scans the file system with the given builder, drawing the progress of the scan on stderr.
+fn scan(builder: FileTreeBuilder, cli: &Cli) -> std::io::Result<FileTree> {
+    let builder = configure(builder, cli).interrupt_on(interrupt());
+    let file_tree = with_progress(cli, |callback| builder.build_with_progress(callback))?;
+    if let Some(timings) = TIMINGS.get() {
+        let mut timings = timings.lock().unwrap();
+        timings.scan += file_tree.scan_timings().walk;
+        timings.stat += file_tree.scan_timings().stat;
+        timings.entries += file_tree.iter().count() as u64;
+    }
+    Ok(file_tree)
+}
+
+/// Hashes the files with `task` like `with_progress`, recording the time spent for --timing.
+fn hash_with_progress(cli: &Cli, task: impl FnOnce(&mut (dyn FnMut(&ScanProgress) + Send)) -> du::Result<()>) -> std::io::Result<()> {
+    let start = Instant::now();
+    let mut last = ScanProgress::default();
+    with_progress(cli, |callback| task(&mut |progress| {
+        last.clone_from(progress);
+        callback(progress);
+    }))?;
+    if let Some(timings) = TIMINGS.get() {
+        let mut timings = timings.lock().unwrap();
+        timings.hash += start.elapsed();
+        timings.files_hashed += last.entries_scanned;
+        timings.bytes_hashed += last.bytes_hashed;
     }
+    Ok(())
+}
+
+/// The time spent by each step of the command, printed on stderr by --timing.
+#[derive(Debug, Default)]
+struct Timings {
+    scan: Duration,
+    stat: Duration,
+    entries: u64,
+    hash: Duration,
+    files_hashed: u64,
+    bytes_hashed: u64,
 }
 
-/// The main function of the program.
+/// The timings of the command, recorded only with --timing.
+static TIMINGS: OnceLock<Mutex<Timings>> = OnceLock::new();
+
+impl Timings {
+
+    /// Writes the timings on stderr, the command having run for `total`: the time left once the
+    /// files are scanned and hashed is spent printing the results.
+    fn print(&self, total: Duration) {
+        let rate = |count: u64, duration: Duration| if duration.is_zero() { 0.0 } else { count as f64 / duration.as_secs_f64() };
+        let printing = total.saturating_sub(self.scan + self.hash);
+        eprintln!("Walking   {:>8.3}s  {} entries ({:.0}/s)", self.scan.as_secs_f64(), self.entries, rate(self.entries, self.scan));
+        eprintln!("Stating   {:>8.3}s  added up over the threads", self.stat.as_secs_f64());
+        eprintln!(
+            "Hashing   {:>8.3}s  {} files ({:.0}/s), {} ({}/s)",
+            self.hash.as_secs_f64(),
+            self.files_hashed,
+            rate(self.files_hashed, self.hash),
+            Size::new(self.bytes_hashed),
+            Size::new(rate(self.bytes_hashed, self.hash) as u64)
+        );
+        eprintln!("Printing  {:>8.3}s", printing.as_secs_f64());
+        eprintln!("Total     {:>8.3}s", total.as_secs_f64());
+    }
+}
+
+/// Prints the tree of `path`, then updates and redraws it each time the file system reports
+/// changes below it, the changes arriving together being applied at once.
+fn watch(path: &Path, options: &PrintOptions, cli: &Cli) -> std::io::Result<()> {
+    use notify::{RecursiveMode, Watcher};
+
+    // Ctrl-C doit arrêter la surveillance : le balayage initial n'est pas interruptible
+    let builder = configure(FileTreeBuilder::new(path), cli);
+    let mut file_tree = with_progress(cli, |callback| builder.build_with_progress(callback))?;
+    let (sender, events) = std::sync::mpsc::channel();
+    let mut watcher = notify::recommended_watcher(sender).map_err(std::io::Error::other)?;
+    watcher.watch(file_tree.get_root(), RecursiveMode::Recursive).map_err(std::io::Error::other)?;
+
+    let filter = filter(cli)?;
+    loop {
+        let mut out = std::io::stdout().lock();
+        // Efface l'écran et replace le curseur en haut à gauche
+        write!(out, "\x1b[2J\x1b[H")?;
+        match &filter {
+            Some(filter) => file_tree.write_filtered(&mut out, filter, false, options)?,
+            None => file_tree.write_tree(&mut out, options)?,
+        }
+        out.flush()?;
+        drop(out);
+
+        let Ok(event) = events.recv() else { return Ok(()) };
+        let mut paths = Vec::new();
+        let mut next = Some(event);
+        while let Some(event) = next {
+            match event {
+                Ok(event) => paths.extend(event.paths),
+                Err(err) => eprintln!("Watch error: {}", err),
+            }
+            next = events.recv_timeout(Duration::from_millis(200)).ok();
+        }
+        file_tree.refresh_paths(&paths)?;
+    }
+}
+
+/// Writes the size of each of `paths`, the current directory if there is none, against `max`, one
+/// line or JSON object per path.
+///
+/// # Returns
+///
+/// An `io::Result` containing `true` if a path exceeds `max`.
+fn check(paths: &[PathBuf], max: Size, out: &mut dyn Write, cli: &Cli) -> std::io::Result<bool> {
+    let paths = if paths.is_empty() { vec![PathBuf::from(".")] } else { paths.to_vec() };
+    let mut checks = Vec::new();
+    for path in &paths {
+        let file_tree = scan(FileTreeBuilder::new(path), cli)?;
+        let root = file_tree.get_root();
+        let size = if cli.disk_usage { file_tree.get_disk_usage(root) } else { file_tree.get_size(root) };
+        checks.push((file_tree.display_path(root, path_style(cli)).into_owned(), size.unwrap_or(Size::new(0))));
+        warn_scan_errors(file_tree.errors());
+    }
 
-fn main() -> std::io::Result<()> {
+    // Les tailles restent en octets et le chemin vient en dernier, pour être lus par les scripts de supervision
+    match cli.format {
+        OutputFormat::Text => {
+            for (path, size) in &checks {
+                let status = if *size > max { "EXCEEDED" } else { "OK" };
+                writeln!(out, "{} size={} max={} {}", status, size.value(), max.value(), path.display())?;
+            }
+        }
+        OutputFormat::Json => {
+            let checks: Vec<_> = checks.iter().map(|(path, size)| serde_json::json!({ "path": path, "size": size, "max": max, "exceeded": *size > max })).collect();
+            serde_json::to_writer_pretty(&mut *out, &checks)?; //cargo run --bin main -- --format json check --path /var/log --max 5G
+            writeln!(out)?;
+        }
+        _ => return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "check only supports the text and JSON formats")),
+    }
+    Ok(checks.iter().any(|(_, size)| *size > max))
+}
+
+/// Hashes the files of the tree and returns their duplicates, for the metrics and the server. The
+/// signatures of the files unchanged since the previous call are kept.
+fn hash_duplicates(file_tree: &mut FileTree, cli: &Cli) -> std::io::Result<DuplicateReport> {
+    let min_size = cli.min_size.unwrap_or(Size::new(1));
+    file_tree.compute_signatures_between(HashAlgo::default().hasher(), min_size, None, |_| {})?;
+    Ok(file_tree.find_duplicates_between(min_size, None))
+}
+
+/// Serves the metrics of `path` at http://address/metrics, refreshing the scan before each answer,
+/// until interrupted.
+fn serve_metrics(path: &Path, address: SocketAddr, options: &PrintOptions, duplicates: bool, cli: &Cli) -> std::io::Result<()> {
+    // Comme pour watch, Ctrl-C arrête le serveur : le balayage initial n'est pas interruptible
+    let builder = configure(FileTreeBuilder::new(path), cli);
+    let mut file_tree = with_progress(cli, |callback| builder.build_with_progress(callback))?;
+    let server = tiny_http::Server::http(address).map_err(std::io::Error::other)?;
+    eprintln!("Serving the metrics at http://{}/metrics", server.server_addr());
+
+    let content_type = tiny_http::Header::from_bytes("Content-Type", "text/plain; version=0.0.4").unwrap();
+    for request in server.incoming_requests() {
+        if request.url().split('?').next() != Some("/metrics") {
+            let _ = request.respond(tiny_http::Response::empty(404));
+            continue;
+        }
+        let mut metrics = Vec::new();
+        let result = file_tree.refresh().map_err(std::io::Error::from).and_then(|()| {
+            let report = duplicates.then(|| hash_duplicates(&mut file_tree, cli)).transpose()?;
+            file_tree.write_prometheus(&mut metrics, options, report.as_ref())
+        });
+        // Une erreur n'arrête pas le serveur : elle est rendue au client, qui réessaiera
+        let response = match result {
+            Ok(()) => tiny_http::Response::from_data(metrics).with_header(content_type.clone()),
+            Err(err) => tiny_http::Response::from_string(err.to_string()).with_status_code(500),
+        };
+        if let Err(err) = request.respond(response) {
+            eprintln!("warning: could not answer a request: {}", err);
+        }
+    }
+    Ok(())
+}
+
+/// Keeps the scan of `path` in memory and answers the requests of `answer` to `routes` at `address`,
+/// until interrupted. The scan is refreshed every `interval`, if any, and on the changes of the files
+/// if `watch` is set.
+fn serve(path: &Path, address: SocketAddr, routes: &[Route], interval: Option<Duration>, watch: bool, cli: &Cli) -> std::io::Result<()> {
+    use notify::{RecursiveMode, Watcher};
+
+    // Comme pour watch, Ctrl-C arrête le serveur : le balayage initial n'est pas interruptible
+    let builder = configure(FileTreeBuilder::new(path), cli);
+    let file_tree = Arc::new(Mutex::new(with_progress(cli, |callback| builder.build_with_progress(callback))?));
+    let (sender, events) = std::sync::mpsc::channel();
+    let mut watcher = None;
+    if watch {
+        let watcher = watcher.insert(notify::recommended_watcher(sender).map_err(std::io::Error::other)?);
+        watcher.watch(file_tree.lock().unwrap().get_root(), RecursiveMode::Recursive).map_err(std::io::Error::other)?;
+    } else {
+        drop(sender);
+    }
+
+    // Les rafraîchissements se font dans un fil à part, les requêtes attendant la fin de chacun
+    let refreshed = Arc::clone(&file_tree);
+    std::thread::spawn(move || {
+        let mut next = interval.map(|interval| Instant::now() + interval);
+        loop {
+            let timeout = next.map_or(Duration::MAX, |next| next.saturating_duration_since(Instant::now()));
+            let paths = match events.recv_timeout(timeout) {
+                Ok(event) => {
+                    let mut paths = Vec::new();
+                    let mut next = Some(event);
+                    while let Some(event) = next {
+                        match event {
+                            Ok(event) => paths.extend(event.paths),
+                            Err(err) => eprintln!("Watch error: {}", err),
+                        }
+                        next = events.recv_timeout(Duration::from_millis(200)).ok();
+                    }
+                    Some(paths)
+                }
+                Err(std::sync::mpsc::RecvTimeoutError::Timeout) => None,
+                Err(std::sync::mpsc::RecvTimeoutError::Disconnected) if interval.is_some() => {
+                    std::thread::sleep(timeout);
+                    None
+                }
+                Err(std::sync::mpsc::RecvTimeoutError::Disconnected) => return,
+            };
+            let mut file_tree = refreshed.lock().unwrap();
+            let result = match paths {
+                Some(paths) => file_tree.refresh_paths(&paths),
+                None => {
+                    next = interval.map(|interval| Instant::now() + interval);
+                    file_tree.refresh()
+                }
+            };
+            if let Err(err) = result {
+                eprintln!("warning: could not refresh the scan: {}", err);
+            }
+        }
+    });
+
+    let server = tiny_http::Server::http(address).map_err(std::io::Error::other)?;
+    eprintln!("Serving {} at http://{}", file_tree.lock().unwrap().get_root().display(), server.server_addr());
+    let content_type = tiny_http::Header::from_bytes("Content-Type", "application/json").unwrap();
+    for request in server.incoming_requests() {
+        let (status, body) = respond(request.method(), request.url(), routes, &mut file_tree.lock().unwrap(), cli);
+        let response = tiny_http::Response::from_data(body).with_status_code(status).with_header(content_type.clone());
+        if let Err(err) = request.respond(response) {
+            eprintln!("warning: could not answer a request: {}", err);
+        }
+    }
+    drop(watcher);
+    Ok(())
+}
+
+/// Answers the request for `url`, the route followed by its query, with `answer`: returns the HTTP
+/// status and the JSON body, an `{"error": ...}` object if it failed.
+fn respond(method: &tiny_http::Method, url: &str, routes: &[Route], file_tree: &mut FileTree, cli: &Cli) -> (u16, Vec<u8>) {
+    let (route, query) = url.split_once('?').unwrap_or((url, ""));
+    let query: HashMap<String, String> = form_urlencoded::parse(query.as_bytes()).into_owned().collect();
+    // Les erreurs sont rendues au client en JSON, avec le code HTTP de leur type
+    match answer(method, route, &query, routes, file_tree, cli) {
+        Ok(body) => (200, body),
+        Err(err) => {
+            let status = match err.kind() {
+                std::io::ErrorKind::NotFound => 404,
+                std::io::ErrorKind::InvalidInput => 400,
+                std::io::ErrorKind::Unsupported => 405,
+                _ => 500,
+            };
+            (status, serde_json::json!({ "error": err.to_string() }).to_string().into_bytes())
+        }
+    }
+}
+
+/// A route of a server, with its method.
+type Route = (tiny_http::Method, &'static str);
+
+/// The routes answered by `serve`, for the dashboards.
+const SERVE_ROUTES: [Route; 2] = [
+    (tiny_http::Method::Get, "/usage"),
+    (tiny_http::Method::Get, "/duplicates"),
+];
+
+/// The routes answered by `serve-http`, for a web frontend.
+const API_ROUTES: [Route; 5] = [
+    (tiny_http::Method::Get, "/api/children"),
+    (tiny_http::Method::Get, "/api/size"),
+    (tiny_http::Method::Get, "/api/search"),
+    (tiny_http::Method::Get, "/api/duplicates"),
+    (tiny_http::Method::Post, "/api/rescan"),
+];
+
+/// Answers a request to one of `routes` with a JSON document, the paths of the query being relative
+/// to the root or absolute, and inside the root:
+///
+/// * `GET /usage?path=PATH&max_depth=N` - The nested sizes of `PATH`, the root by default, down to depth `N`, 1 by default.
+/// * `GET /duplicates`, `GET /api/duplicates` - The groups of duplicates, hashing the new files.
+/// * `GET /api/children?path=PATH` - The path, kind and size of each child of the directory `PATH`.
+/// * `GET /api/size?path=PATH` - The size, disk usage and number of files and directories of `PATH`.
+/// * `GET /api/search?pattern=GLOB&limit=N` - The entries whose name matches `GLOB`, the largest `N`
+///   first, 100 by default.
+/// * `POST /api/rescan?path=PATH` - Refreshes the directories changed below `PATH`, the root by default,
+///   or the directory containing it, then returns its path, kind and size, `null` if it no longer exists.
+fn answer(method: &tiny_http::Method, route: &str, query: &HashMap<String, String>, routes: &[Route], file_tree: &mut FileTree, cli: &Cli) -> std::io::Result<Vec<u8>> {
+    if !routes.iter().any(|(_, known)| *known == route) {
+        return Err(std::io::Error::new(std::io::ErrorKind::NotFound, format!("no such route: {}", route)));
+    }
+    if !routes.contains(&(method.clone(), route)) {
+        return Err(std::io::Error::new(std::io::ErrorKind::Unsupported, format!("{} is not supported by {}", method, route)));
+    }
+
+    let invalid = |name: &str, value: &str, err: std::num::ParseIntError| std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("invalid {} {:?}: {}", name, value, err));
+    let path = match query.get("path") {
+        Some(path) => file_tree.get_root().join(path),
+        None if matches!(route, "/api/children" | "/api/size") => return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "missing path")),
+        None => file_tree.get_root().to_path_buf(),
+    };
+    // Les chemins sont comparés sans être résolus : un `..` pourrait sortir de la racine
+    if !path.starts_with(file_tree.get_root()) || path.components().any(|component| component == std::path::Component::ParentDir) {
+        return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("{} is outside the scan of {}", path.display(), file_tree.get_root().display())));
+    }
+    // Un chemin absent du balayage peut encore être ajouté par un rafraîchissement
+    if route != "/api/rescan" && file_tree.get_map_option(&path).is_none() {
+        return Err(std::io::Error::new(std::io::ErrorKind::NotFound, format!("{} is not in the scan", path.display())));
+    }
+    let options = PrintOptions { disk_usage: cli.disk_usage, path_style: path_style(cli), ..PrintOptions::default() };
+    let record = |file_tree: &FileTree, path: &Path| {
+        file_tree.usage_record(path, &options).map(|record| serde_json::json!({ "path": file_tree.display_path(path, options.path_style), "kind": record.kind, "size": record.size }))
+    };
+
+    let mut body = Vec::new();
+    match route {
+        "/usage" => {
+            let max_depth = match query.get("max_depth") {
+                Some(max_depth) => max_depth.parse().map_err(|err| invalid("max_depth", max_depth, err))?,
+                None => 1,
+            };
+            let options = PrintOptions { max_depth: Some(max_depth), ..options };
+            serde_json::to_writer_pretty(&mut body, &file_tree.usage_node(&path, &options))?;
+        }
+        "/duplicates" | "/api/duplicates" => hash_duplicates(file_tree, cli)?.with_path_style(file_tree, path_style(cli)).write_json(&mut body)?,
+        "/api/children" => {
+            let children = file_tree.get_children(&path).ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("{} is not a directory", path.display())))?;
+            let children: Vec<_> = children.iter().filter_map(|child| record(file_tree, child)).collect();
+            serde_json::to_writer_pretty(&mut body, &children)?;
+        }
+        "/api/size" => {
+            let counts = file_tree.get_counts(&path).unwrap_or_default();
+            let size = serde_json::json!({
+                "path": file_tree.display_path(&path, options.path_style),
+                "size": file_tree.get_size(&path),
+                "disk_usage": file_tree.get_disk_usage(&path),
+                "files": counts.files,
+                "directories": counts.directories,
+            });
+            serde_json::to_writer_pretty(&mut body, &size)?;
+        }
+        "/api/search" => {
+            let pattern = query.get("pattern").ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidInput, "missing pattern"))?;
+            let limit = match query.get("limit") {
+                Some(limit) => limit.parse().map_err(|err| invalid("limit", limit, err))?,
+                None => 100,
+            };
+            let filter = Filter::new(pattern, false, cli.case_sensitive)?;
+            let mut matches: Vec<_> = file_tree.iter().filter(|entry| filter.is_match(file_tree.get_root(), entry)).filter_map(|entry| file_tree.usage_record(entry, &options)).collect();
+            matches.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.path.cmp(b.path)));
+            let matches: Vec<_> = matches.iter().take(limit).filter_map(|found| record(file_tree, found.path)).collect();
+            serde_json::to_writer_pretty(&mut body, &matches)?;
+        }
+        "/api/rescan" => {
+            // Seuls les dossiers modifiés depuis le dernier balayage sont relus
+            file_tree.refresh_paths(std::slice::from_ref(&path))?;
+            serde_json::to_writer_pretty(&mut body, &record(file_tree, &path))?;
+        }
+        _ => return Err(std::io::Error::new(std::io::ErrorKind::NotFound, format!("no such route: {}", route))),
+    }
+    Ok(body)
+}
+
+/// Returns the scan results of `path`: loaded if it is a file, which must be a cache file, scanned if it is a directory.
+fn open_scan(path: &Path, cli: &Cli) -> std::io::Result<FileTree> {
+    if path.is_file() {
+        Ok(FileTree::load(path)?)
+    } else {
+        scan(FileTreeBuilder::new(path), cli)
+    }
+}
+
+/// Loads scan results from a cache file, updating them with the changes made since if `refresh` is set.
+fn load(cache: &Path, refresh: bool) -> std::io::Result<FileTree> {
+    let mut file_tree = FileTree::load(cache)?;
+    if refresh {
+        file_tree.refresh()?; //cargo run --bin main -- usage --load-cache tree.bin --refresh --save-cache tree.bin
+    }
+    Ok(file_tree)
+}
+
+/// Prints each entry as soon as its subtree is scanned, like `du`, then the unreadable entries.
+fn stream_usage(path: &Path, max_depth: Option<usize>, dirs_only: bool, cli: &Cli) -> std::io::Result<()> {
+    if !matches!(cli.format, OutputFormat::Text | OutputFormat::Ndjson) || filter(cli)?.is_some() || cli.sort.is_some() || cli.inspect_archives {
+        return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "--stream only supports the text and NDJSON formats, without filter, sort nor --inspect-archives"));
+    }
+    let mut out = output(cli)?;
+    let errors = configure(FileTreeBuilder::new(path), cli).stream(|entry| {
+        if cli.format == OutputFormat::Ndjson {
+            // Toutes les entrées sont écrites, pour que l'arbre puisse être reconstruit par --remote
+            serde_json::to_writer(&mut out, &StreamRecord::from(entry))?;
+            return writeln!(out); //cargo run --bin main -- --format ndjson usage --stream option<path>
+        }
+        let size = if cli.disk_usage { entry.disk_usage } else { entry.size };
+        let shown = entry.depth == 0
+            || (max_depth.is_none_or(|max_depth| entry.depth <= max_depth) && cli.min_size.is_none_or(|min_size| size >= min_size) && (entry.is_dir || !dirs_only));
+        if !shown {
+            return Ok(());
+        }
+        writeln!(out, "{:>12}  {}", size.display(size_format(cli)).to_string(), entry.path.display())
+    })?;
+    out.flush()?;
+    warn_scan_errors(&errors);
+    Ok(())
+}
+
+/// Scans a directory of another machine, given as `user@host:/path`, by running this program there
+/// over SSH and rebuilding the tree from its streaming scan. The errors of the remote scan are
+/// printed on stderr by the remote program itself.
+fn remote_scan(remote: &str, cli: &Cli) -> std::io::Result<FileTree> {
+    let (host, path) = remote
+        .split_once(':')
+        .filter(|(host, path)| !host.is_empty() && !path.is_empty())
+        .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("invalid remote {:?} (expected USER@HOST:PATH)", remote)))?;
+    let mut child = std::process::Command::new("ssh")
+        .args(["--", host, &remote_command(path, cli)])
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .spawn()
+        .map_err(|err| std::io::Error::new(err.kind(), format!("could not run ssh: {}", err)))?;
+    let stdout = child.stdout.take().expect("the output of ssh is piped");
+    let file_tree = FileTree::read_stream(BufReader::new(stdout), remote);
+    let status = child.wait()?;
+    if !status.success() {
+        return Err(std::io::Error::other(format!("the remote scan of {} failed: ssh {}", remote, status)));
+    }
+    Ok(file_tree?)
+}
+
+/// Returns the shell command streaming the scan of `path` on the remote machine, with the options
+/// of the scan given on the command line.
+fn remote_command(path: &str, cli: &Cli) -> String {
+    let quote = |arg: &str| format!("'{}'", arg.replace('\'', "'\\''"));
+    let mut command = vec![cli.remote_binary.clone(), "--no-progress".to_string()];
+    let flags = [(cli.all, "--all"), (cli.follow_symlinks, "--follow-symlinks"), (cli.gitignore, "--gitignore"), (cli.count_links, "--count-links"), (cli.one_file_system, "--one-file-system"), (cli.strict, "--strict")];
+    command.extend(flags.iter().filter(|(set, _)| *set).map(|(_, flag)| flag.to_string()));
+    for pattern in &cli.exclude {
+        command.push(format!("--exclude={}", quote(pattern)));
+    }
+    command.extend(["--format", "ndjson", "usage", "--stream", "--"].map(String::from));
+    command.push(quote(path));
+    command.join(" ")
+}
+
+/// Prints on stderr a summary of the entries that could not be read during the scan.
+fn warn_scan_errors(errors: &[(PathBuf, std::io::Error)]) {
+    const MAX_LISTED: usize = 10;
+    if errors.is_empty() {
+        return;
+    }
+    eprintln!("warning: {} {} could not be read and skipped:", errors.len(), if errors.len() == 1 { "entry" } else { "entries" });
+    for (path, err) in errors.iter().take(MAX_LISTED) {
+        eprintln!("  {}: {}", path.display(), err);
+    }
+    if errors.len() > MAX_LISTED {
+        eprintln!("  ... and {} more", errors.len() - MAX_LISTED);
+    }
+}
+
+/// Writes the statistics as a table, text or CSV, the `column` header naming their keys.
+///
+/// The share of each key in the text table is relative to `total`.
+fn write_stats(out: &mut dyn Write, stats: &[TypeStats], total: Size, column: &str, options: &PrintOptions, format: OutputFormat) -> std::io::Result<()> {
+    if format == OutputFormat::Csv {
+        let mut writer = csv::Writer::from_writer(out);
+        writer.write_record([column, "count", "size_bytes"])?;
+        for stats in stats {
+            writer.write_record([stats.key.clone(), stats.count.to_string(), stats.size.value().to_string()])?;
+        }
+        return writer.flush();
+    }
+    writeln!(out, "{:<24} {:>8} {:>12} {:>7}", column.to_uppercase(), "FILES", "SIZE", "SHARE")?;
+    for stats in stats {
+        let share = if total.value() == 0 { 0.0 } else { stats.size.value() as f64 * 100.0 / total.value() as f64 };
+        writeln!(out, "{:<24} {:>8} {:>12} {:>6.1}%", stats.key, stats.count, stats.size.display(options.size_format).to_string(), share)?;
+    }
+    Ok(())
+}
+
+/// Writes the estimates of `compressible` as a table or as CSV, the sizes in bytes.
+fn write_compression(out: &mut dyn Write, estimates: &[CompressionEstimate], size_format: SizeFormat, format: OutputFormat) -> std::io::Result<()> {
+    if format == OutputFormat::Csv {
+        let mut writer = csv::Writer::from_writer(out);
+        writer.write_record(["path", "size_bytes", "compressed_bytes", "savings_bytes", "sampled_bytes"])?;
+        for estimate in estimates {
+            let sizes = [estimate.size, estimate.compressed, estimate.savings(), estimate.sampled].map(|size| size.value().to_string());
+            writer.write_record(std::iter::once(estimate.path.to_string_lossy().into_owned()).chain(sizes))?;
+        }
+        return writer.flush();
+    }
+    writeln!(out, "{:>12} {:>12} {:>12} {:>6}  DIRECTORY", "SIZE", "COMPRESSED", "SAVINGS", "RATIO")?;
+    for estimate in estimates {
+        let [size, compressed, savings] = [estimate.size, estimate.compressed, estimate.savings()].map(|size| size.display(size_format).to_string());
+        writeln!(out, "{:>12} {:>12} {:>12} {:>5.1}%  {}", size, compressed, savings, estimate.ratio() * 100.0, estimate.path.display())?;
+    }
+    Ok(())
+}
+
+/// Completes the command line with the defaults of the configuration file: the flags given
+/// on the command line take precedence, and the excludes are added to those of `--exclude`.
+fn apply_config(cli: &mut Cli, config: Config) -> std::io::Result<()> {
+    cli.exclude.extend(config.exclude);
+    if cli.color.is_none() {
+        if let Some(color) = config.color {
+            cli.color = Some(ColorChoice::from_str(&color, true).map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, format!("invalid color in the configuration: {}", err)))?);
+        }
+    }
+    if !cli.lexicographic_sort {
+        cli.sort = cli.sort.or(config.sort);
+    }
+    if !cli.bytes {
+        cli.si |= config.si;
+        cli.unit = cli.unit.or(config.unit);
+    }
+    if let Commands::Duplicate { hash, .. } = &mut cli.command {
+        *hash = hash.or(config.hash);
+    }
+    Ok(())
+}
+
+/// Returns the filter given by `--filter` or `--filter-regex`, restricted by `--older-than`, `--newer-than` and `--owned-by`, if any.
+fn filter(cli: &Cli) -> std::io::Result<Option<Filter>> {
+    let filter = match (&cli.filter, &cli.filter_regex) {
+        (Some(pattern), _) => Some(Filter::new(pattern, cli.filter_path, cli.case_sensitive)?),
+        (None, Some(pattern)) => Some(Filter::regex(pattern, cli.case_sensitive)?),
+        (None, None) => None,
+    };
+    if cli.name.is_none() && cli.iname.is_none() && cli.older_than.is_none() && cli.newer_than.is_none() && cli.owned_by.is_none() {
+        return Ok(filter);
+    }
+
+    let now = SystemTime::now();
+    let mut filter = filter.unwrap_or_default();
+    if let Some(pattern) = &cli.name {
+        filter = filter.name(pattern, true)?; //cargo run --bin main -- --name 'backup_*.tar.gz' duplicate option<path>
+    }
+    if let Some(pattern) = &cli.iname {
+        filter = filter.name(pattern, false)?; //cargo run --bin main -- --iname '*.log' usage option<path>
+    }
+    if let Some(age) = cli.older_than {
+        filter = filter.modified_before(now - age); //cargo run --bin main -- --older-than 90d usage option<path>
+    }
+    if let Some(age) = cli.newer_than {
+        filter = filter.modified_after(now - age);
+    }
+    if let Some(user) = &cli.owned_by {
+        filter = filter.owned_by(Owner::parse(user)?); //cargo run --bin main -- --owned-by alice usage --show-owner option<path>
+    }
+    Ok(Some(filter))
+}
+
+/// Returns the format of the sizes chosen by `--si`, `--bytes` and `--unit`.
+fn size_format(cli: &Cli) -> SizeFormat {
+    SizeFormat { si: cli.si, bytes: cli.bytes, unit: cli.unit }
+}
+
+/// Returns how the paths are displayed, see `--relative` and `--absolute`.
+fn path_style(cli: &Cli) -> PathStyle {
+    match (cli.relative, cli.absolute) {
+        (true, _) => PathStyle::Relative,
+        (_, true) => PathStyle::Absolute,
+        _ => PathStyle::Scanned,
+    }
+}
+
+/// Returns `true` if the output is colored, see `--color` and https://no-color.org.
+fn use_color(cli: &Cli) -> bool {
+    match cli.color.unwrap_or(ColorChoice::Auto) {
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+        ColorChoice::Auto => {
+            cli.output_file.is_none()
+                && std::io::stdout().is_terminal()
+                && std::env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+        }
+    }
+}
+
+/// Opens the writer receiving the output: the `--output-file` if given, stdout otherwise.
+fn output(cli: &Cli) -> std::io::Result<Box<dyn Write>> {
+    Ok(match &cli.output_file {
+        Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+        None => Box::new(std::io::stdout().lock()),
+    })
+}
+
+/// The main function of the program: reports the error of the command, naming the path that failed.
+fn main() -> ExitCode {
+    let start = Instant::now();
+    let result = run();
+    if let Some(timings) = TIMINGS.get() {
+        timings.lock().unwrap().print(start.elapsed()); //cargo run --bin main -- --timing duplicate option<path>
+    }
+    match result {
+        Ok(_) if INTERRUPT.get().is_some_and(|interrupt| interrupt.load(Ordering::Relaxed)) => {
+            eprintln!("warning: scan interrupted, the results are partial");
+            ExitCode::from(130)
+        }
+        Ok(code) => code,
+        Err(err) => {
+            eprintln!("error: {}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Runs the command given on the command line, returning the status of the program: 2 if check
+/// found a directory exceeding its maximum size.
+fn run() -> std::io::Result<ExitCode> {
     // Parse the command-line arguments using the defined CLI structure.
 
-    let cli = Cli::parse();
+    let mut cli = Cli::parse();
+    let config = match &cli.config {
+        Some(path) => Config::load(path)?, //cargo run --bin main -- --config config.toml usage option<path>
+        None => Config::load_default()?,
+    };
+    apply_config(&mut cli, config)?;
+    if cli.timing {
+        TIMINGS.get_or_init(Mutex::default);
+    }
+    if let Some(threads) = cli.threads {
+        rayon::ThreadPoolBuilder::new().num_threads(threads.get()).build_global().map_err(std::io::Error::other)?; //cargo run --bin main -- --threads 1 duplicate option<path>
+    }
 
     // Match on the provided subcommand and execute the corresponding functionality.
 
+    if cli.remote.is_some() && !matches!(cli.command, Commands::Usage { .. }) {
+        return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "--remote only supports usage"));
+    }
     match &cli.command {
-        Commands::Usage { path } => {
-            // Determine the path to analyze, defaulting to '.' if not provided.
-            let path = path.as_deref().unwrap_or(Path::new("."));
-            let file_tree = FileTree::new(path)?;
+        Commands::Usage { paths, save_cache, from_cache, refresh, max_depth, show_counts, show_mtime, show_owner, show_perms, percent, stream, group_by, files_only, dirs_only } => {
+            if cli.remote.is_some() && (!paths.is_empty() || from_cache.is_some() || *stream) {
+                return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "--remote replaces the paths, --from-cache and --stream"));
+            }
+            // Determine the paths to analyze, defaulting to '.' if not provided.
+            let paths = if paths.is_empty() { vec![PathBuf::from(".")] } else { paths.clone() };
+
+            if cli.format == OutputFormat::Ndjson && !*stream {
+                return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "the NDJSON format is only supported by duplicate and usage --stream"));
+            }
+            if *stream {
+                for path in &paths {
+                    stream_usage(path, *max_depth, *dirs_only, &cli)?; //cargo run --bin main -- usage --stream option<--dirs-only> option<path>
+                }
+                return Ok(ExitCode::SUCCESS);
+            }
+            if save_cache.is_some() && paths.len() > 1 {
+                return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "--save-cache only supports a single path"));
+            }
+
+            // Create a file tree for each specified path.
+            let forest = match (from_cache, &cli.remote) {
+                (Some(cache), _) => Forest::new(vec![load(cache, *refresh)?]),
+                (None, Some(remote)) => Forest::new(vec![remote_scan(remote, &cli)?]), //cargo run --bin main -- --remote user@host:/srv usage
+                (None, None) => Forest::new(paths.iter().map(|path| scan(FileTreeBuilder::new(path), &cli)).collect::<std::io::Result<_>>()?),
+            };
+            if let Some(cache) = save_cache {
+                save(&forest.trees()[0], cache)?; //cargo run --bin main -- usage --save-cache tree.bin option<path>
+            }
 
-            // Create a file tree for the specified path.
-            if let Some(filter) = &cli.filter {
-                file_tree.show_filtered(filter, cli.lexicographic_sort); //cargo run --bin main  -- option<--lexicographic-sort> --filter jpg usage option<path>
-            } else if cli.lexicographic_sort {
-                file_tree.show_lexicographic(); //cargo run --bin --main -- --lexicographic-sort usage option<path>
+            let options = PrintOptions { disk_usage: cli.disk_usage, max_depth: *max_depth, show_counts: *show_counts, show_mtime: *show_mtime, show_owner: *show_owner, show_perms: *show_perms, percent: *percent, min_size: cli.min_size, sort: cli.sort.unwrap_or_default(), ascii: cli.ascii, color: use_color(&cli), size_format: size_format(&cli), prune_below: cli.prune_below, indent: Some(cli.indent), flat: cli.flat, path_style: path_style(&cli), dirs_only: *dirs_only };
+            let mut out = output(&cli)?;
+            if let Some(GroupBy::Owner) = group_by {
+                let stats = forest.owner_stats(&options); //cargo run --bin main -- usage --group-by owner option<path>
+                match cli.format {
+                    OutputFormat::Text | OutputFormat::Csv => {
+                        let total = stats.iter().fold(Size::new(0), |total, stats| total + stats.size);
+                        write_stats(&mut out, &stats, total, "owner", &options, cli.format)?
+                    }
+                    OutputFormat::Json => {
+                        let owners: Vec<_> = stats.iter().map(|stats| serde_json::json!({ "owner": stats.key, "count": stats.count, "size": stats.size })).collect();
+                        serde_json::to_writer_pretty(&mut out, &owners)?;
+                        writeln!(out)?;
+                    }
+                    _ => return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "--group-by only supports the text, CSV and JSON formats")),
+                }
+            } else if *files_only {
+                let filter = filter(&cli)?;
+                let terminator = if cli.print0 { b'\0' } else { b'\n' };
+                for file_tree in forest.trees() {
+                    file_tree.write_files(&mut out, filter.as_ref(), &options, terminator)?; //cargo run --bin main -- --filter "*.tmp" --print0 usage --files-only option<path> | xargs -0 rm
+                }
+            } else if cli.format == OutputFormat::Du {
+                // Comme du, l'espace occupé sur le disque est compté par défaut
+                let options = PrintOptions { disk_usage: !cli.apparent_size, ..options };
+                let filter = filter(&cli)?;
+                for file_tree in forest.trees() {
+                    file_tree.write_du(&mut out, filter.as_ref(), &options, cli.human_readable)?; //cargo run --bin main -- --format du option<--human-readable> usage option<path>
+                }
+            } else if cli.format == OutputFormat::Csv {
+                forest.write_csv(&mut out, &options)?; //cargo run --bin main -- --format csv --output-file report.csv usage option<path>
+            } else if cli.format == OutputFormat::Json && forest.trees().len() > 1 {
+                forest.write_json(&mut out, &options)?; //cargo run --bin main -- usage --format json path1 path2
+            } else if cli.format == OutputFormat::Json {
+                forest.trees()[0].write_json(&mut out, &options)?; //cargo run --bin main -- usage --format json option<path>
+            } else if cli.format == OutputFormat::Yaml {
+                Report::usage(forest.trees(), &options).write_yaml(&mut out)?; //cargo run --bin main -- usage --format yaml option<path>
+            } else if cli.format == OutputFormat::Xml {
+                Report::usage(forest.trees(), &options).write_xml(&mut out)?; //cargo run --bin main -- usage --format xml option<path>
+            } else if cli.format == OutputFormat::Html {
+                // Les doublons ne sont connus que si les signatures ont été chargées d'un cache
+                let duplicates: Vec<_> = forest.trees().iter().map(|file_tree| file_tree.find_duplicates_with(Size::new(1)).with_path_style(file_tree, options.path_style)).collect();
+                let report = duplicates.iter().fold(Report::usage(forest.trees(), &options), Report::with_duplicates);
+                report.write_html(&mut out, options.size_format)?; //cargo run --bin main -- usage --format html --output-file report.html option<path>
             } else {
-                file_tree.show(); //cargo run --bin main -- usage option<path>
+                let filter = filter(&cli)?;
+                for file_tree in forest.trees() {
+                    match &filter {
+                        Some(filter) => file_tree.write_filtered(&mut out, filter, false, &options)?, //cargo run --bin main  -- option<--sort size> --filter "*.jpg" usage option<path>
+                        None => file_tree.write_tree(&mut out, &options)?, //cargo run --bin main -- option<--sort size:asc> usage option<path>
+                    }
+                }
+                if forest.trees().len() > 1 {
+                    let total = if cli.disk_usage { forest.total_disk_usage() } else { forest.total_size() };
+                    writeln!(out, "{}  total", total.display(options.size_format))?; //cargo run --bin main -- usage path1 path2 path3
+                }
+                if cli.summary {
+                    forest.summary(&options).write(&mut out, options.size_format)?; //cargo run --bin main -- --summary usage option<path>
+                }
+            }
+            out.flush()?;
+            for file_tree in forest.trees() {
+                warn_scan_errors(file_tree.errors());
             }
         }
-        Commands::Duplicate { path } => { //cargo run --bin main -- duplicate
+        Commands::Duplicate { paths, save_cache, from_cache, refresh, only_cross, hash, max_size, verify, dirs, perceptual, threshold, interactive, delete, link, emit_script, keep, yes: _, dry_run } => { //cargo run --bin main -- duplicate
+
+            // Les fichiers vides ne sont jamais des doublons, sauf avec --min-size 0
+            let min_size = cli.min_size.unwrap_or(Size::new(1));
+            // Les membres des archives ne sont lus qu'à travers leur archive
+            if cli.inspect_archives && (*verify || *interactive || *delete || link.is_some() || emit_script.is_some() || cli.print0) {
+                return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "--inspect-archives only lists the duplicates, without --verify, --interactive, --delete, --link, --emit-script nor --print0"));
+            }
+            // Les images semblables ne sont pas identiques : les supprimer ou les lier perdrait leur contenu
+            if *perceptual && cli.print0 {
+                return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "--perceptual only lists the similar images, without --print0"));
+            }
+            if paths.len() > 1 {
+                if save_cache.is_some() || *dirs || *perceptual || *delete || link.is_some() || emit_script.is_some() || cli.print0 {
+                    return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "--save-cache, --dirs, --perceptual, --delete, --link, --emit-script and --print0 only support a single path"));
+                }
+                return duplicate_across(paths, min_size, hash.unwrap_or_default(), *max_size, *only_cross, *verify, *interactive, *dry_run, &cli).map(|()| ExitCode::SUCCESS); //cargo run --bin main -- duplicate --only-cross path1 path2
+            }
+            if *only_cross {
+                return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "--only-cross requires several paths"));
+            }
 
             // Determine the path to analyze, defaulting to '.' if not provided.
+            let path = paths.first().map_or(Path::new("."), PathBuf::as_path);
+
+
+            // Create a file tree for the specified path, then hash its files: only duplicates need the signatures.
+            let mut file_tree = match from_cache {
+                Some(cache) => load(cache, *refresh)?,
+                None => scan(FileTreeBuilder::new(path), &cli)?,
+            };
+            if !*perceptual {
+                hash_with_progress(&cli, |callback| file_tree.compute_signatures_between(hash.unwrap_or_default().hasher(), min_size, *max_size, callback))?; //cargo run --bin main -- duplicate --hash xxhash --min-size 4K --max-size 1G option<path>
+            }
+            if let Some(cache) = save_cache {
+                save(&file_tree, cache)?; //cargo run --bin main -- duplicate --save-cache tree.bin option<path>
+            }
+
+            // Find and display duplicate files in the file tree, largest savings first.
+            let mut report = match perceptual {
+                true => file_tree.find_similar_images(*threshold, min_size, *max_size)?, //cargo run --bin main -- duplicate --perceptual --threshold 10 option<path>
+                false => file_tree.find_duplicates_between(min_size, *max_size),
+            };
+            if *dirs {
+                report = report.merge_dirs(file_tree.find_duplicate_dirs()); //cargo run --bin main -- duplicate --dirs option<path>
+            }
+            if let Some(filter) = filter(&cli)? {
+                report = report.filtered(&file_tree, &filter); //cargo run --bin main -- --filter-regex '\.jpg$' duplicate option<path>
+            }
+            if *verify {
+                report = report.verified()?; //cargo run --bin main -- duplicate --verify --delete --yes option<path>
+            }
+
+            // Display the duplicates, the actions using the scanned paths.
+            let listed = report.clone().with_path_style(&file_tree, path_style(&cli));
+            let mut out = output(&cli)?;
+            if *interactive {
+                resolve_interactively(&report, *dry_run, &mut out)?; //cargo run --bin main -- duplicate --interactive --dry-run option<path>
+            } else if *delete {
+                resolve_duplicates(&file_tree, &report, *keep, Resolution::Delete, *dry_run, &mut out)?; //cargo run --bin main -- duplicate --delete --keep oldest --dry-run option<path>
+            } else if let Some(link) = link {
+                let resolution = match link {
+                    LinkKind::Hard => Resolution::HardLink,
+                    LinkKind::Sym => Resolution::SymLink,
+                };
+                resolve_duplicates(&file_tree, &report, *keep, resolution, *dry_run, &mut out)?; //cargo run --bin main -- duplicate --link hard --yes option<path>
+            } else if cli.print0 {
+                for group in report.groups() {
+                    for action in group.plan(keep.select(group, &file_tree), Resolution::Delete) {
+                        out.write_all(file_tree.display_path(action.path(), path_style(&cli)).as_os_str().as_encoded_bytes())?;
+                        out.write_all(b"\0")?; //cargo run --bin main -- --print0 duplicate --keep oldest option<path> | xargs -0 rm
+                    }
+                }
+            } else if let Some(resolution) = emit_script {
+                report.write_script(&mut out, &file_tree, *keep, *resolution, ScriptShell::default())?; //cargo run --bin main -- duplicate --emit-script hardlink --keep oldest option<path> > dedup.sh
+            } else if *perceptual {
+                write_similar(&mut out, &report, &listed, &file_tree, &cli)?;
+            } else {
+                let options = PrintOptions { disk_usage: cli.disk_usage, ..PrintOptions::default() };
+                write_duplicates(&mut out, &report, &listed, std::slice::from_ref(&file_tree), file_tree.summary(&options), &cli)?;
+            }
+            out.flush()?;
+            warn_scan_errors(file_tree.errors());
+        }
+        Commands::NearDuplicates { path, max_distance, min_prefix } => { //cargo run --bin main -- near-duplicates --max-distance 3 --min-prefix 7 option<path>
+            let path = path.as_deref().unwrap_or(Path::new("."));
+            let file_tree = scan(FileTreeBuilder::new(path), &cli)?;
+
+            // Comme pour les doublons, les fichiers vides ne sont comparés qu'avec --min-size 0
+            let mut report = file_tree.find_near_duplicates(*max_distance, *min_prefix, cli.min_size.unwrap_or(Size::new(1)));
+            if let Some(filter) = filter(&cli)? {
+                report = report.filtered(&file_tree, &filter);
+            }
+            // Les noms proches ne font pas des doublons : les groupes sont listés sans place à récupérer
+            let listed = report.clone().with_path_style(&file_tree, path_style(&cli));
+            let mut out = output(&cli)?;
+            write_similar(&mut out, &report, &listed, &file_tree, &cli)?;
+            out.flush()?;
+            warn_scan_errors(file_tree.errors());
+        }
+        Commands::Serve { path, interval, watch, listen } => { //cargo run --bin main -- serve --interval 1h --watch --listen 127.0.0.1:8080 option<path>
+            let path = path.as_deref().unwrap_or(Path::new("."));
+            serve(path, *listen, &SERVE_ROUTES, Some(*interval), *watch, &cli)?;
+        }
+        Commands::ServeHttp { path, listen } => { //cargo run --bin main -- serve-http --listen 127.0.0.1:8080 option<path>
+            let path = path.as_deref().unwrap_or(Path::new("."));
+            serve(path, *listen, &API_ROUTES, None, false, &cli)?;
+        }
+        Commands::Watch { path, max_depth } => { //cargo run --bin main -- watch --max-depth 2 option<path>
             let path = path.as_deref().unwrap_or(Path::new("."));
+            if cli.format != OutputFormat::Text || cli.output_file.is_some() {
+                return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "watch only supports the text format on stdout"));
+            }
+            let options = PrintOptions { disk_usage: cli.disk_usage, max_depth: *max_depth, min_size: cli.min_size, sort: cli.sort.unwrap_or_default(), ascii: cli.ascii, color: use_color(&cli), size_format: size_format(&cli), prune_below: cli.prune_below, indent: Some(cli.indent), flat: cli.flat, path_style: path_style(&cli), ..PrintOptions::default() };
+            watch(path, &options, &cli)?;
+        }
+        Commands::Diff { old, new, max_depth } => { //cargo run --bin main -- diff old.bin option<path>
+            let options = PrintOptions { disk_usage: cli.disk_usage, size_format: size_format(&cli), ..PrintOptions::default() };
+            let (old, new) = (open_scan(old, &cli)?, open_scan(new, &cli)?);
+            let entries: Vec<_> = old
+                .diff(&new, &options)
+                .into_iter()
+                .filter(|entry| max_depth.is_none_or(|max_depth| entry.depth() <= max_depth))
+                .collect();
 
-            // Create a file tree for the specified path.
-            let file_tree = FileTree::new(path)?;
-        
-            // Find and display duplicate files in the file tree.
-            let duplicates = file_tree.find_duplicates();
-        
-            // Display the duplicates.
-            for (signature, paths) in duplicates {
-                println!("Signature de Doublon : {}", signature);
-                for path in paths {
-                    println!("  - {}", path.display());
+            let mut out = output(&cli)?;
+            match cli.format {
+                OutputFormat::Text => {
+                    for entry in &entries {
+                        let name = if entry.path.as_os_str().is_empty() { Path::new(".") } else { &entry.path };
+                        let slash = if entry.is_dir { "/" } else { "" };
+                        writeln!(out, "{:>12}  {:<7}  {}{}", entry.display_delta(options.size_format), entry.change.to_string(), name.display(), slash)?;
+                    }
+                }
+                OutputFormat::Json => {
+                    serde_json::to_writer_pretty(&mut out, &entries)?; //cargo run --bin main -- --format json diff path1 path2
+                    writeln!(out)?;
                 }
+                _ => return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "diff only supports the text and JSON formats")),
             }
+            out.flush()?;
+            warn_scan_errors(old.errors());
+            warn_scan_errors(new.errors());
+        }
+        Commands::Export { export: Export::Treemap { path, width, height, max_depth } } => { //cargo run --bin main -- --output-file usage.svg export treemap option<path>
+            let path = path.as_deref().unwrap_or(Path::new("."));
+            if cli.format != OutputFormat::Text {
+                return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "export treemap only writes SVG"));
+            }
+
+            let file_tree = scan(FileTreeBuilder::new(path), &cli)?;
+            let options = PrintOptions { disk_usage: cli.disk_usage, max_depth: *max_depth, size_format: size_format(&cli), ..PrintOptions::default() };
+            let mut out = output(&cli)?;
+            file_tree.write_treemap(&mut out, *width, *height, &options)?;
+            out.flush()?;
+            warn_scan_errors(file_tree.errors());
+        }
+        Commands::Export { export: Export::Prometheus { path, max_depth, duplicates, listen } } => { //cargo run --bin main -- --output-file du.prom export prometheus --duplicates option<path>
+            let path = path.as_deref().unwrap_or(Path::new("."));
+            if cli.format != OutputFormat::Text {
+                return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "export prometheus only writes the text exposition format"));
+            }
+            let options = PrintOptions { disk_usage: cli.disk_usage, max_depth: *max_depth, path_style: path_style(&cli), ..PrintOptions::default() };
+            if let Some(address) = listen {
+                if cli.output_file.is_some() {
+                    return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "--listen serves the metrics instead of writing them to --output-file"));
+                }
+                return serve_metrics(path, *address, &options, *duplicates, &cli).map(|()| ExitCode::SUCCESS); //cargo run --bin main -- export prometheus --listen 0.0.0.0:9100 option<path>
+            }
+
+            let mut file_tree = scan(FileTreeBuilder::new(path), &cli)?;
+            let report = duplicates.then(|| hash_duplicates(&mut file_tree, &cli)).transpose()?;
+            let mut out = output(&cli)?;
+            file_tree.write_prometheus(&mut out, &options, report.as_ref())?;
+            out.flush()?;
+            warn_scan_errors(file_tree.errors());
+        }
+        Commands::Stats { path, mime, limit } => { //cargo run --bin main -- stats option<--mime> option<path>
+            let path = path.as_deref().unwrap_or(Path::new("."));
+            let file_tree = scan(FileTreeBuilder::new(path), &cli)?;
+            let options = PrintOptions { disk_usage: cli.disk_usage, size_format: size_format(&cli), ..PrintOptions::default() };
+            let stats = if *mime { file_tree.mime_stats(&options)? } else { file_tree.extension_stats(&options) };
+            let total = stats.iter().fold(Size::new(0), |total, stats| total + stats.size);
+            let stats = &stats[..stats.len().min(limit.unwrap_or(usize::MAX))];
+
+            let mut out = output(&cli)?;
+            match cli.format {
+                OutputFormat::Text if !*mime => write_stats(&mut out, stats, total, "extension", &options, cli.format)?,
+                OutputFormat::Text | OutputFormat::Csv => write_stats(&mut out, stats, total, "type", &options, cli.format)?,
+                OutputFormat::Json => {
+                    serde_json::to_writer_pretty(&mut out, &stats)?; //cargo run --bin main -- --format json stats option<path>
+                    writeln!(out)?;
+                }
+                _ => return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "stats only supports the text, CSV and JSON formats")),
+            }
+            out.flush()?;
+            warn_scan_errors(file_tree.errors());
+        }
+        Commands::Compressible { path, max_depth, limit } => { //cargo run --bin main -- compressible --max-depth 2 --limit 10 option<path>
+            let path = path.as_deref().unwrap_or(Path::new("."));
+            let file_tree = scan(FileTreeBuilder::new(path), &cli)?;
+            let mut estimates = file_tree.compression_estimates()?;
+            estimates.retain(|estimate| max_depth.is_none_or(|max_depth| estimate.path.strip_prefix(file_tree.get_root()).map_or(0, |relative| relative.components().count()) <= max_depth));
+            estimates.truncate(limit.unwrap_or(usize::MAX));
+            for estimate in &mut estimates {
+                estimate.path = file_tree.display_path(&estimate.path, path_style(&cli)).into_owned();
+            }
+
+            let mut out = output(&cli)?;
+            match cli.format {
+                OutputFormat::Text | OutputFormat::Csv => write_compression(&mut out, &estimates, size_format(&cli), cli.format)?,
+                OutputFormat::Json => {
+                    serde_json::to_writer_pretty(&mut out, &estimates)?; //cargo run --bin main -- --format json compressible option<path>
+                    writeln!(out)?;
+                }
+                _ => return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "compressible only supports the text, CSV and JSON formats")),
+            }
+            out.flush()?;
+            warn_scan_errors(file_tree.errors());
+        }
+        Commands::Check { paths, max } => { //cargo run --bin main -- check --path /var/log --max 5G
+            let mut out = output(&cli)?;
+            let exceeded = check(paths, *max, &mut out, &cli)?;
+            out.flush()?;
+            if exceeded {
+                return Ok(ExitCode::from(2));
+            }
+        }
+        Commands::Empty { path, delete, yes: _, dry_run } => { //cargo run --bin main -- empty option<path>
+            let path = path.as_deref().unwrap_or(Path::new("."));
+            if cli.format != OutputFormat::Text {
+                return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "empty only supports the text format"));
+            }
+
+            let file_tree = scan(FileTreeBuilder::new(path), &cli)?;
+            let mut out = output(&cli)?;
+            let entries = file_tree.empty_entries();
+            let shown = |entry| file_tree.display_path(entry, path_style(&cli));
+            if *delete {
+                let mut deleted = 0;
+                for entry in &entries {
+                    if *dry_run {
+                        writeln!(out, "would delete {}", shown(entry).display())?; //cargo run --bin main -- empty --delete --dry-run option<path>
+                    } else if let Err(err) = file_tree.remove_empty(entry) {
+                        eprintln!("Failed to delete {}: {}", shown(entry).display(), err);
+                        continue;
+                    } else {
+                        writeln!(out, "delete {}", shown(entry).display())?;
+                    }
+                    deleted += 1;
+                }
+                writeln!(out, "{} {} empty {}", if *dry_run { "Would delete" } else { "Deleted" }, deleted, if deleted == 1 { "entry" } else { "entries" })?;
+            } else if cli.print0 {
+                for entry in &entries {
+                    out.write_all(shown(entry).as_os_str().as_encoded_bytes())?;
+                    out.write_all(b"\0")?; //cargo run --bin main -- --print0 empty option<path> | xargs -0 rm -d
+                }
+            } else {
+                for entry in &entries {
+                    let slash = if file_tree.get_children(entry).is_some() { std::path::MAIN_SEPARATOR_STR } else { "" };
+                    writeln!(out, "{}{}", shown(entry).display(), slash)?;
+                }
+            }
+            out.flush()?;
+            warn_scan_errors(file_tree.errors());
+        }
+        Commands::Top { path, files, dirs } => { //cargo run --bin main -- top --files 20 --dirs 10 option<path>
+            let path = path.as_deref().unwrap_or(Path::new("."));
+            if cli.format != OutputFormat::Text {
+                return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "top only supports the text format"));
+            }
+
+            let file_tree = scan(FileTreeBuilder::new(path), &cli)?;
+            let options = PrintOptions { disk_usage: cli.disk_usage, size_format: size_format(&cli), path_style: path_style(&cli), ..PrintOptions::default() };
+            let files = if dirs.is_some() { *files } else { Some(files.unwrap_or(10)) };
+
+            let mut out = output(&cli)?;
+            let sections = [
+                ("Largest files", files.map(|count| file_tree.largest_files(count, &options))),
+                ("Largest directories", dirs.map(|count| file_tree.largest_directories(count, &options))),
+            ];
+            for (title, entries) in sections {
+                let Some(entries) = entries else { continue };
+                writeln!(out, "{}:", title)?;
+                for (path, size) in entries {
+                    writeln!(out, "{:>12}  {}", size.display(options.size_format).to_string(), file_tree.display_path(path, options.path_style).display())?;
+                }
+            }
+            out.flush()?;
+            warn_scan_errors(file_tree.errors());
+        }
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_choice_test() {
+        assert_eq!(parse_choice("2d\n", 3), Some(Choice::Keep(1, Resolution::Delete)));
+        assert_eq!(parse_choice(" 1 l ", 3), Some(Choice::Keep(0, Resolution::HardLink)));
+        assert_eq!(parse_choice("s", 3), Some(Choice::Skip));
+        assert_eq!(parse_choice("", 3), Some(Choice::Skip));
+        assert_eq!(parse_choice("q", 3), Some(Choice::Quit));
+        assert_eq!(parse_choice("4d", 3), None);
+        assert_eq!(parse_choice("0d", 3), None);
+        assert_eq!(parse_choice("2x", 3), None);
+        assert_eq!(parse_choice("d", 3), None);
+    }
+
+    #[test]
+    fn threads_test() {
+        let cli = Cli::parse_from(["main", "--threads", "2", "usage"]);
+        assert_eq!(cli.threads, NonZeroUsize::new(2));
+        assert!(Cli::try_parse_from(["main", "--threads", "0", "usage"]).is_err());
+    }
+
+    #[test]
+    fn remote_command_test() {
+        let cli = Cli::parse_from(["main", "-a", "--exclude", "it's", "--remote", "hôte:/srv", "usage"]);
+        assert_eq!(remote_command("/srv/l'été", &cli), "main --no-progress --all --exclude='it'\\''s' --format ndjson usage --stream -- '/srv/l'\\''été'");
+    }
+
+    #[test]
+    fn check_test() {
+        let dir = tempfile::tempdir().unwrap();
+        let full = dir.path().join("mes logs");
+        std::fs::create_dir(&full).unwrap();
+        std::fs::write(full.join("app.log"), [0; 2000]).unwrap();
+        let full = full.to_string_lossy().into_owned();
+
+        let mut out = Vec::new();
+        let cli = Cli::parse_from(["main", "check", "--path", &full, "--max", "2K"]);
+        assert!(!check(&[PathBuf::from(&full)], Size::new(2048), &mut out, &cli).unwrap());
+        assert_eq!(String::from_utf8(out).unwrap(), format!("OK size=2000 max=2048 {}\n", full));
+
+        let mut out = Vec::new();
+        let cli = Cli::parse_from(["main", "--format", "json", "check", "--path", &full, "--max", "1K"]);
+        assert!(check(&[PathBuf::from(&full)], Size::new(1024), &mut out, &cli).unwrap());
+        let checks: serde_json::Value = serde_json::from_slice(&out).unwrap();
+        assert_eq!(checks[0]["exceeded"], true);
+        assert_eq!(checks[0]["size"], 2000);
+    }
+
+    /// Scans a directory holding `a.txt`, `sub/b.txt`, a copy of `a.txt`, and `sub/c.txt`, 16 bytes in all.
+    fn served_tree() -> (tempfile::TempDir, FileTree) {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("sub")).unwrap();
+        std::fs::write(dir.path().join("a.txt"), b"hello").unwrap();
+        std::fs::write(dir.path().join("sub/b.txt"), b"hello").unwrap();
+        std::fs::write(dir.path().join("sub/c.txt"), b"other!").unwrap();
+        let file_tree = FileTree::new(dir.path()).unwrap();
+        (dir, file_tree)
+    }
+
+    /// Sends a request to `respond`, returning the status and the parsed JSON body.
+    fn request(method: tiny_http::Method, url: &str, file_tree: &mut FileTree, cli: &Cli) -> (u16, serde_json::Value) {
+        let routes = if url.starts_with("/api/") { &API_ROUTES[..] } else { &SERVE_ROUTES[..] };
+        let (status, body) = respond(&method, url, routes, file_tree, cli);
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[test]
+    fn serve_routes_test() {
+        let (dir, mut file_tree) = served_tree();
+        let cli = Cli::parse_from(["main", "serve"]);
+        let get = tiny_http::Method::Get;
+
+        let (status, usage) = request(get.clone(), "/usage", &mut file_tree, &cli);
+        assert_eq!((status, usage["size"].as_u64()), (200, Some(16)));
+        assert_eq!(usage["children"].as_array().unwrap().len(), 2);
+        let (status, usage) = request(get.clone(), "/usage?path=sub&max_depth=0", &mut file_tree, &cli);
+        assert_eq!((status, usage["size"].as_u64()), (200, Some(11)));
+        assert_eq!(usage["path"], dir.path().join("sub").to_string_lossy().as_ref());
+        assert_eq!(usage["children"], serde_json::json!([]));
+
+        let (status, duplicates) = request(get.clone(), "/duplicates", &mut file_tree, &cli);
+        assert_eq!(status, 200);
+        assert_eq!(duplicates[0]["count"], 2);
+        assert_eq!(duplicates[0]["reclaimable"], 5);
+    }
+
+    #[test]
+    fn serve_errors_test() {
+        let (_dir, mut file_tree) = served_tree();
+        let cli = Cli::parse_from(["main", "serve"]);
+
+        let (status, error) = request(tiny_http::Method::Get, "/usage?max_depth=deux", &mut file_tree, &cli);
+        assert_eq!(status, 400);
+        assert!(error["error"].as_str().unwrap().contains("invalid max_depth"));
+        assert_eq!(request(tiny_http::Method::Get, "/usage?path=absent", &mut file_tree, &cli).0, 404);
+        assert_eq!(request(tiny_http::Method::Get, "/inconnue", &mut file_tree, &cli).0, 404);
+        assert_eq!(request(tiny_http::Method::Post, "/usage", &mut file_tree, &cli).0, 405);
+        assert_eq!(request(tiny_http::Method::Delete, "/duplicates", &mut file_tree, &cli).0, 405);
+        assert_eq!(request(tiny_http::Method::Get, "/usage?path=/", &mut file_tree, &cli).0, 400);
+    }
+
+    #[test]
+    fn api_routes_test() {
+        let (dir, mut file_tree) = served_tree();
+        let cli = Cli::parse_from(["main", "serve-http"]);
+        let get = tiny_http::Method::Get;
+
+        let (status, children) = request(get.clone(), "/api/children?path=.", &mut file_tree, &cli);
+        assert_eq!(status, 200);
+        assert_eq!(children, serde_json::json!([
+            { "path": dir.path().join("a.txt"), "kind": "file", "size": 5 },
+            { "path": dir.path().join("sub"), "kind": "directory", "size": 11 },
+        ]));
+
+        let (status, size) = request(get.clone(), &format!("/api/size?path={}", dir.path().join("sub").display()), &mut file_tree, &cli);
+        assert_eq!(status, 200);
+        assert_eq!((size["size"].as_u64(), size["files"].as_u64(), size["directories"].as_u64()), (Some(11), Some(2), Some(0)));
+
+        let (status, found) = request(get.clone(), "/api/search?pattern=*.txt&limit=2", &mut file_tree, &cli);
+        assert_eq!(status, 200);
+        assert_eq!(found.as_array().unwrap().len(), 2);
+        assert_eq!(found[0]["path"], dir.path().join("sub/c.txt").to_string_lossy().as_ref());
+
+        let (status, duplicates) = request(get.clone(), "/api/duplicates", &mut file_tree, &cli);
+        assert_eq!((status, duplicates[0]["count"].as_u64()), (200, Some(2)));
+
+        std::fs::write(dir.path().join("sub/d.txt"), b"new").unwrap();
+        // Le dossier est antidaté pour que son changement soit vu même si les dates sont grossières
+        File::open(dir.path().join("sub")).unwrap().set_modified(SystemTime::now() - Duration::from_secs(3600)).unwrap();
+        let (status, rescanned) = request(tiny_http::Method::Post, "/api/rescan?path=sub/d.txt", &mut file_tree, &cli);
+        assert_eq!(status, 200);
+        assert_eq!(rescanned["size"], 3);
+        assert_eq!(request(get.clone(), "/api/size?path=sub", &mut file_tree, &cli).1["size"], 14);
+    }
+
+    #[test]
+    fn api_errors_test() {
+        let (dir, mut file_tree) = served_tree();
+        let cli = Cli::parse_from(["main", "serve-http"]);
+        let get = tiny_http::Method::Get;
+
+        assert_eq!(request(get.clone(), "/api/children", &mut file_tree, &cli), (400, serde_json::json!({ "error": "missing path" })));
+        assert_eq!(request(get.clone(), "/api/size", &mut file_tree, &cli).0, 400);
+        assert_eq!(request(get.clone(), "/api/children?path=a.txt", &mut file_tree, &cli).0, 400);
+        assert_eq!(request(get.clone(), "/api/search", &mut file_tree, &cli).0, 400);
+        assert_eq!(request(get.clone(), "/api/search?pattern=*&limit=-1", &mut file_tree, &cli).0, 400);
+        assert_eq!(request(get.clone(), "/api/rescan", &mut file_tree, &cli).0, 405);
+        let outside = dir.path().parent().unwrap().to_string_lossy().into_owned();
+        let (status, error) = request(tiny_http::Method::Post, &format!("/api/rescan?path={}", outside), &mut file_tree, &cli);
+        assert_eq!(status, 400);
+        assert!(error["error"].as_str().unwrap().contains("outside the scan"));
+        assert_eq!(request(tiny_http::Method::Post, "/api/rescan?path=../autre", &mut file_tree, &cli).0, 400);
+
+        // Les routes de serve ne sont pas servies par serve-http, et inversement
+        assert_eq!(respond(&get, "/usage", &API_ROUTES, &mut file_tree, &cli).0, 404);
+        assert_eq!(respond(&get, "/api/size?path=.", &SERVE_ROUTES, &mut file_tree, &cli).0, 404);
+    }
+
+    #[test]
+    fn perceptual_conflicts_test() {
+        assert!(Cli::try_parse_from(["main", "duplicate", "--perceptual", "--threshold", "4"]).is_ok());
+        for args in [&["--delete", "--yes"][..], &["--link", "hard", "--yes"], &["--interactive"], &["--emit-script", "rm"]] {
+            let args = ["main", "duplicate", "--perceptual"].iter().chain(args);
+            assert!(Cli::try_parse_from(args).is_err());
         }
     }
-    Ok(())
 }
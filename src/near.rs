@@ -0,0 +1,133 @@
+use std::collections::HashMap;
+use crate::{duplicates::{cluster, DuplicateGroup, DuplicateReport}, file_tree::FileTree, size::Size};
+
+impl FileTree {
+
+    /// Finds the files that look like copies of one another by their name, e.g. `report_v1.docx`
+    /// and `report_final.docx`, without reading them: files of exactly the same size and the same
+    /// extension whose names, without extension and regardless of case, are within `max_distance`
+    /// edits of one another, or start with the same `min_prefix` characters or more. So that short
+    /// names like `1.txt` and `2.txt` are not grouped, the edits are also limited to a third of the
+    /// length of the shorter name.
+    ///
+    /// A group holds the files similar to one another, step by step. As the contents are not compared,
+    /// the groups are only candidates to review; the exact copies are found by `find_duplicates`.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_distance` - The maximum number of characters inserted, removed or replaced between two similar names.
+    /// * `min_prefix` - The minimum length of the common beginning of two similar names, `0` to compare the edits only.
+    /// * `min_size` - The minimum size of the files to compare.
+    ///
+    /// # Returns
+    ///
+    /// The groups of files with similar names, sorted by decreasing reclaimable space.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use du::{FileTree, Size};
+    ///
+    /// # let dir = tempfile::tempdir()?;
+    /// std::fs::write(dir.path().join("report_v1.docx"), b"first draft")?;
+    /// std::fs::write(dir.path().join("report_final.docx"), b"final draft")?;
+    ///
+    /// let report = FileTree::new(dir.path())?.find_near_duplicates(2, 7, Size::new(1));
+    /// assert_eq!(report.groups()[0].paths.len(), 2);
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn find_near_duplicates(&self, max_distance: usize, min_prefix: usize, min_size: Size) -> DuplicateReport {
+        // Seuls les fichiers de même taille et de même extension sont comparés deux à deux
+        let mut candidates: HashMap<_, Vec<_>> = HashMap::new();
+        for path in self.files().filter(|path| self.archive_of(path).is_none()) {
+            let Some(size) = self.get_size(path).filter(|size| *size >= min_size) else { continue };
+            let extension = path.extension().map(|extension| extension.to_string_lossy().to_lowercase()).unwrap_or_default();
+            let stem = path.file_stem().map(|stem| stem.to_string_lossy().to_lowercase()).unwrap_or_default();
+            candidates.entry((size, extension)).or_default().push((path.as_path(), stem.chars().collect::<Vec<_>>()));
+        }
+
+        let mut groups = Vec::new();
+        for ((size, _), mut files) in candidates.into_iter().filter(|(_, files)| files.len() > 1) {
+            files.sort();
+            let similar = |i: usize, j: usize| {
+                let (a, b): (&[char], &[char]) = (&files[i].1, &files[j].1);
+                (min_prefix > 0 && a.iter().zip(b).take_while(|(a, b)| a == b).count() >= min_prefix) || edit_distance(a, b) <= max_distance.min(a.len().min(b.len()) / 3)
+            };
+            for indices in cluster(files.len(), similar) {
+                let paths: Vec<_> = indices.iter().map(|index| files[*index].0.to_path_buf()).collect();
+                groups.push(DuplicateGroup {
+                    signature: paths[0].file_name().unwrap_or_default().to_string_lossy().into_owned(),
+                    size,
+                    links: paths.iter().map(|path| self.get_link(path)).collect(),
+                    paths,
+                });
+            }
+        }
+        DuplicateReport::from_groups(groups)
+    }
+}
+
+/// Returns the Levenshtein distance between `a` and `b`: the number of characters inserted, removed
+/// or replaced to turn one into the other.
+fn edit_distance(a: &[char], b: &[char]) -> usize {
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, a) in a.iter().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b) in b.iter().enumerate() {
+            current.push((previous[j] + usize::from(a != b)).min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::create_fixture;
+    use std::fs;
+
+    #[test]
+    fn edit_distance_test() {
+        let chars = |text: &str| text.chars().collect::<Vec<_>>();
+        assert_eq!(edit_distance(&chars("rapport"), &chars("rapport")), 0);
+        assert_eq!(edit_distance(&chars("rapport (1)"), &chars("rapport")), 4);
+        assert_eq!(edit_distance(&chars("report_v1"), &chars("report_v2")), 1);
+        assert_eq!(edit_distance(&chars(""), &chars("été")), 3);
+    }
+
+    #[test]
+    fn find_near_duplicates_test() {
+        let fixture = create_fixture();
+        let root = fixture.path().join("Test");
+        fs::write(root.join("report_v1.docx"), b"first draft").unwrap();
+        fs::write(root.join("Dossier1/Report_v2.docx"), b"final draft").unwrap();
+        fs::write(root.join("report_final.docx"), b"other draft").unwrap();
+        fs::write(root.join("report_v3.pdf"), b"first draft").unwrap();
+        fs::write(root.join("report_v4.docx"), b"a longer draft").unwrap();
+
+        let file_tree = FileTree::new(&root).unwrap();
+        let report = file_tree.find_near_duplicates(2, 0, Size::new(1));
+        let group = report.groups().iter().find(|group| group.size.value() == 11).unwrap();
+        assert_eq!(group.paths, [root.join("Dossier1/Report_v2.docx"), root.join("report_v1.docx")]);
+        assert_eq!(group.signature, "Report_v2.docx");
+
+        let report = file_tree.find_near_duplicates(2, 7, Size::new(1));
+        let group = report.groups().iter().find(|group| group.size.value() == 11).unwrap();
+        assert_eq!(group.paths.len(), 3);
+        assert!(file_tree.find_near_duplicates(2, 7, Size::new(12)).groups().iter().all(|group| group.size.value() != 11));
+    }
+
+    #[test]
+    fn short_names_test() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["1.txt", "2.txt", "ab.jpg", "ac.jpg", "img1.png", "img2.png"] {
+            fs::write(dir.path().join(name), b"same size").unwrap();
+        }
+
+        let report = FileTree::new(dir.path()).unwrap().find_near_duplicates(2, 0, Size::new(1));
+        assert_eq!(report.groups().len(), 1);
+        assert_eq!(report.groups()[0].paths, [dir.path().join("img1.png"), dir.path().join("img2.png")]);
+    }
+}
@@ -0,0 +1,216 @@
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::OnceLock;
+
+/// The user owning an entry.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Owner {
+    /// A Unix user, by uid.
+    Uid(u32),
+    /// A Windows account, by name, read from the owner of the ACL of the entry.
+    Account(String),
+}
+
+impl Owner {
+
+    /// Returns the name of the owner: the name of the user if the uid is known, the uid otherwise.
+    pub fn name(&self) -> Cow<'_, str> {
+        match self {
+            Owner::Uid(uid) => accounts().users.get(uid).map_or_else(|| Cow::Owned(uid.to_string()), |name| Cow::Borrowed(name.as_str())),
+            Owner::Account(name) => Cow::Borrowed(name),
+        }
+    }
+
+    /// Returns the owner named `name`: a user name or a uid on Unix, an account name on Windows.
+    ///
+    /// # Returns
+    ///
+    /// An `io::Result` containing the owner, or an `InvalidInput` error if the user is unknown.
+    pub fn parse(name: &str) -> io::Result<Owner> {
+        if cfg!(windows) {
+            return Ok(Owner::Account(name.to_string()));
+        }
+        if let Ok(uid) = name.parse() {
+            return Ok(Owner::Uid(uid));
+        }
+        accounts()
+            .users
+            .iter()
+            .find(|(_, user)| *user == name)
+            .map(|(uid, _)| Owner::Uid(*uid))
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, format!("unknown user: {}", name)))
+    }
+}
+
+impl fmt::Display for Owner {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.name())
+    }
+}
+
+/// The owner and the permissions of an entry.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Ownership {
+    /// The user owning the entry, unknown if it could not be read.
+    pub owner: Option<Owner>,
+    /// The group owning the entry, on Unix only.
+    pub gid: Option<u32>,
+    /// The permission bits, e.g. `0o755`. On Windows, read-only entries are `0o555` and the others `0o777`.
+    pub mode: u32,
+}
+
+impl Ownership {
+
+    /// Returns the owner and the permissions of the entry at `path`, whose metadata is `metadata`.
+    #[cfg(unix)]
+    pub(crate) fn of(_path: &Path, metadata: &fs::Metadata) -> Ownership {
+        use std::os::unix::fs::MetadataExt;
+        Ownership { owner: Some(Owner::Uid(metadata.uid())), gid: Some(metadata.gid()), mode: metadata.mode() & 0o7777 }
+    }
+
+    #[cfg(windows)]
+    pub(crate) fn of(path: &Path, metadata: &fs::Metadata) -> Ownership {
+        let mode = if metadata.permissions().readonly() { 0o555 } else { 0o777 };
+        Ownership { owner: windows_owner(path).map(Owner::Account), gid: None, mode }
+    }
+
+    #[cfg(not(any(unix, windows)))]
+    pub(crate) fn of(_path: &Path, metadata: &fs::Metadata) -> Ownership {
+        let mode = if metadata.permissions().readonly() { 0o555 } else { 0o777 };
+        Ownership { owner: None, gid: None, mode }
+    }
+
+    /// Returns the permissions like `ls -l`, e.g. `rwxr-xr-x`, the setuid, setgid and sticky
+    /// bits replacing the execution bits by `s` or `t`.
+    pub fn permissions(&self) -> String {
+        let mut permissions = String::with_capacity(9);
+        for (shift, special, letter) in [(6, 0o4000, 's'), (3, 0o2000, 's'), (0, 0o1000, 't')] {
+            let bits = self.mode >> shift;
+            permissions.push(if bits & 0o4 != 0 { 'r' } else { '-' });
+            permissions.push(if bits & 0o2 != 0 { 'w' } else { '-' });
+            permissions.push(match (bits & 0o1 != 0, self.mode & special != 0) {
+                (true, true) => letter,
+                (false, true) => letter.to_ascii_uppercase(),
+                (true, false) => 'x',
+                (false, false) => '-',
+            });
+        }
+        permissions
+    }
+
+    /// Returns the name of the owner, or `-` if it is unknown.
+    pub fn owner_name(&self) -> Cow<'_, str> {
+        self.owner.as_ref().map_or(Cow::Borrowed("-"), Owner::name)
+    }
+
+    /// Returns the name of the group if it is known, its gid otherwise, or `-` without group.
+    pub fn group_name(&self) -> Cow<'_, str> {
+        match self.gid {
+            Some(gid) => accounts().groups.get(&gid).map_or_else(|| Cow::Owned(gid.to_string()), |name| Cow::Borrowed(name.as_str())),
+            None => Cow::Borrowed("-"),
+        }
+    }
+}
+
+/// The names of the users and groups of the system, by id.
+#[derive(Debug, Default)]
+struct Accounts {
+    users: HashMap<u32, String>,
+    groups: HashMap<u32, String>,
+}
+
+/// Returns the users and groups listed in `/etc/passwd` and `/etc/group`, read once.
+///
+/// The accounts of other sources, such as LDAP, are not listed: they are displayed by id.
+fn accounts() -> &'static Accounts {
+    static ACCOUNTS: OnceLock<Accounts> = OnceLock::new();
+    ACCOUNTS.get_or_init(|| {
+        if cfg!(windows) {
+            return Accounts::default();
+        }
+        Accounts { users: read_ids(Path::new("/etc/passwd")), groups: read_ids(Path::new("/etc/group")) }
+    })
+}
+
+/// Reads the names and ids of a file such as `/etc/passwd`, one account per line: `name:password:id:...`.
+fn read_ids(path: &Path) -> HashMap<u32, String> {
+    let Ok(content) = fs::read_to_string(path) else { return HashMap::new() };
+    let mut ids = HashMap::new();
+    for line in content.lines() {
+        let mut fields = line.split(':');
+        let (Some(name), Some(_), Some(id)) = (fields.next(), fields.next(), fields.next()) else { continue };
+        if let Ok(id) = id.parse() {
+            // Le premier nom d'un identifiant partagé l'emporte, comme pour ls
+            ids.entry(id).or_insert_with(|| name.to_string());
+        }
+    }
+    ids
+}
+
+/// Returns the name of the account owning the entry at `path`, from its security descriptor.
+#[cfg(windows)]
+fn windows_owner(path: &Path) -> Option<String> {
+    use std::os::windows::ffi::OsStrExt;
+    use windows_sys::Win32::Foundation::{LocalFree, ERROR_SUCCESS, PSID};
+    use windows_sys::Win32::Security::Authorization::{GetNamedSecurityInfoW, SE_FILE_OBJECT};
+    use windows_sys::Win32::Security::{LookupAccountSidW, OWNER_SECURITY_INFORMATION, PSECURITY_DESCRIPTOR};
+
+    let wide_path: Vec<u16> = crate::long_path::extended(path).as_os_str().encode_wide().chain(Some(0)).collect();
+    let mut sid: PSID = std::ptr::null_mut();
+    let mut descriptor: PSECURITY_DESCRIPTOR = std::ptr::null_mut();
+    // SAFETY: `wide_path` is a NUL-terminated UTF-16 string, the output pointers outlive the call,
+    // and `sid` points into `descriptor`, freed below with LocalFree.
+    let status = unsafe {
+        GetNamedSecurityInfoW(wide_path.as_ptr(), SE_FILE_OBJECT, OWNER_SECURITY_INFORMATION, &mut sid, std::ptr::null_mut(), std::ptr::null_mut(), std::ptr::null_mut(), &mut descriptor)
+    };
+    if status != ERROR_SUCCESS {
+        return None;
+    }
+
+    let mut name = [0u16; 256];
+    let mut domain = [0u16; 256];
+    let (mut name_len, mut domain_len, mut kind) = (name.len() as u32, domain.len() as u32, 0);
+    // SAFETY: the buffers and their lengths match, and `sid` is valid until `descriptor` is freed.
+    let found = unsafe { LookupAccountSidW(std::ptr::null(), sid, name.as_mut_ptr(), &mut name_len, domain.as_mut_ptr(), &mut domain_len, &mut kind) };
+    // SAFETY: `descriptor` was allocated by GetNamedSecurityInfoW and is no longer used.
+    unsafe { LocalFree(descriptor as _) };
+    (found != 0).then(|| String::from_utf16_lossy(&name[..name_len as usize]))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn permissions_test() {
+        let ownership = |mode| Ownership { owner: None, gid: None, mode };
+        assert_eq!(ownership(0o755).permissions(), "rwxr-xr-x");
+        assert_eq!(ownership(0o640).permissions(), "rw-r-----");
+        assert_eq!(ownership(0o4755).permissions(), "rwsr-xr-x");
+        assert_eq!(ownership(0o1777).permissions(), "rwxrwxrwt");
+        assert_eq!(ownership(0o2644).permissions(), "rw-r-Sr--");
+        assert_eq!(ownership(0o644).owner_name(), "-");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn owner_test() {
+        use std::os::unix::fs::MetadataExt;
+        let fixture = crate::test_utils::create_fixture();
+        let path = fixture.path().join("Test/Dossier1/Fichier1");
+        let metadata = fs::metadata(&path).unwrap();
+
+        let ownership = Ownership::of(&path, &metadata);
+        assert_eq!(ownership.owner, Some(Owner::Uid(metadata.uid())));
+        assert_eq!(ownership.mode, metadata.mode() & 0o777);
+        assert_eq!(Owner::parse(&ownership.owner_name()).unwrap(), Owner::Uid(metadata.uid()));
+        assert_eq!(Owner::parse("1234").unwrap(), Owner::Uid(1234));
+        assert!(Owner::parse("utilisateur-inconnu").is_err());
+    }
+}
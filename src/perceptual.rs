@@ -0,0 +1,113 @@
+use image::{imageops::FilterType, ImageError, ImageFormat, ImageReader};
+use rayon::prelude::*;
+use std::path::Path;
+use crate::{duplicates::{cluster, DuplicateGroup, DuplicateReport}, error::{Error, Result}, file_tree::FileTree, size::Size};
+
+impl FileTree {
+
+    /// Finds the images that look the same, e.g. a photo saved at another size or quality, by
+    /// comparing their perceptual hashes: the 64-bit difference hash (dHash) of each image, computed
+    /// from its thumbnail of 9 x 8 gray pixels.
+    ///
+    /// Two images are similar when their hashes differ by at most `threshold` bits, and a group holds
+    /// the images similar to one another, step by step. As the images of a group differ, the size of
+    /// a group is that of its smallest image, so that the reclaimable space is a lower bound.
+    ///
+    /// The images are recognized by their extension, and the files which can't be decoded are left
+    /// out, like those listed inside archives.
+    ///
+    /// # Arguments
+    ///
+    /// * `threshold` - The maximum number of bits by which the hashes of two similar images differ.
+    /// * `min_size` - The minimum size of the images to compare.
+    /// * `max_size` - The maximum size of the images to compare, if any.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the groups of similar images, sorted by decreasing reclaimable space,
+    /// failing with a `Hash` error if an image could not be read.
+    pub fn find_similar_images(&self, threshold: u32, min_size: Size, max_size: Option<Size>) -> Result<DuplicateReport> {
+        let images: Vec<(&Path, Size)> = self
+            .files()
+            .filter(|path| ImageFormat::from_path(path).is_ok() && self.archive_of(path).is_none())
+            .filter_map(|path| Some((path.as_path(), self.get_size(path)?)))
+            .filter(|(_, size)| *size >= min_size && max_size.is_none_or(|max_size| *size <= max_size))
+            .collect();
+        let hashes: Vec<(&Path, Size, u64)> = images
+            .par_iter()
+            .map(|(path, size)| Ok(dhash(path)?.map(|hash| (*path, *size, hash))))
+            .collect::<Result<Vec<_>>>()?
+            .into_iter()
+            .flatten()
+            .collect();
+
+        let groups = cluster(hashes.len(), |i, j| (hashes[i].2 ^ hashes[j].2).count_ones() <= threshold)
+            .into_iter()
+            .map(|mut indices| {
+                indices.sort_by_key(|index| hashes[*index].0);
+                DuplicateGroup {
+                    signature: format!("{:016x}", hashes[indices[0]].2),
+                    size: indices.iter().map(|index| hashes[*index].1).min().unwrap_or(Size::new(0)),
+                    paths: indices.iter().map(|index| hashes[*index].0.to_path_buf()).collect(),
+                    links: indices.iter().map(|index| self.get_link(hashes[*index].0)).collect(),
+                }
+            })
+            .collect();
+        Ok(DuplicateReport::from_groups(groups))
+    }
+}
+
+/// Returns the difference hash of the image at `path`: one bit per pair of neighboring pixels of
+/// its thumbnail, set when the left one is darker. `None` if the file is not an image that can be decoded.
+fn dhash(path: &Path) -> Result<Option<u64>> {
+    let hash_error = |source| Error::Hash { path: path.to_path_buf(), source };
+    let image = match ImageReader::open(path).map_err(hash_error)?.with_guessed_format().map_err(hash_error)?.decode() {
+        Ok(image) => image,
+        Err(ImageError::IoError(source)) => return Err(hash_error(source)),
+        Err(_) => return Ok(None),
+    };
+    let thumbnail = image.resize_exact(9, 8, FilterType::Triangle).into_luma8();
+    let mut hash = 0;
+    for y in 0..8 {
+        for x in 0..8 {
+            hash = hash << 1 | u64::from(thumbnail.get_pixel(x, y)[0] < thumbnail.get_pixel(x + 1, y)[0]);
+        }
+    }
+    Ok(Some(hash))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::create_fixture;
+    use image::{GrayImage, Luma};
+
+    /// Creates an image of blocks of different shades, inverted if `inverted` is set.
+    fn blocks(width: u32, height: u32, inverted: bool) -> GrayImage {
+        GrayImage::from_fn(width, height, |x, y| {
+            let shade = ((x * 8 / width) * 37 + (y * 8 / height) * 91) % 256;
+            Luma([if inverted { 255 - shade as u8 } else { shade as u8 }])
+        })
+    }
+
+    #[test]
+    fn find_similar_images_test() {
+        let fixture = create_fixture();
+        let root = fixture.path().join("Test");
+        blocks(64, 64, false).save(root.join("photo.png")).unwrap();
+        blocks(200, 200, false).save(root.join("Dossier1/photo-grande.jpg")).unwrap();
+        blocks(64, 64, true).save(root.join("negatif.png")).unwrap();
+        std::fs::write(root.join("faux.png"), b"not an image").unwrap();
+
+        let file_tree = FileTree::new(&root).unwrap();
+        let report = file_tree.find_similar_images(8, Size::new(1), None).unwrap();
+        assert_eq!(report.groups().len(), 1);
+        let group = &report.groups()[0];
+        assert_eq!(group.paths, [root.join("Dossier1/photo-grande.jpg"), root.join("photo.png")]);
+        assert_eq!(group.size, file_tree.get_size(&root.join("photo.png")).unwrap().min(file_tree.get_size(&root.join("Dossier1/photo-grande.jpg")).unwrap()));
+
+        assert!(file_tree.find_similar_images(8, Size::new(1), Some(Size::new(10))).unwrap().groups().is_empty());
+        assert_eq!(file_tree.find_similar_images(64, Size::new(1), None).unwrap().groups()[0].paths.len(), 3);
+    }
+}
@@ -1,147 +1,1098 @@
-use std::{path::Path, ffi::OsStr};
-use crate::{file_tree::{FileTree, EntryNode}, size::Size};
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::fmt;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use crate::{file_tree::{EntryCounts, EntryNode, FileTree}, filter::Filter, size::{Size, SizeFormat}};
 
+/// ANSI escape sequences of the colored output.
+const BLUE: &str = "\x1b[1;34m";
+const GREEN: &str = "\x1b[32m";
+const YELLOW: &str = "\x1b[33m";
+const RED: &str = "\x1b[31m";
+const MAGENTA: &str = "\x1b[35m";
+const RESET: &str = "\x1b[0m";
+
+/// The criterion used to order the children of a directory.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SortKey {
+    /// By path.
+    #[default]
+    Name,
+    /// By size, or disk usage when it is displayed.
+    Size,
+    /// By last modification time.
+    Mtime,
+    /// By number of files and subdirectories contained, files counting as 0.
+    Count,
+}
+
+/// How the paths of the entries are displayed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PathStyle {
+    /// As they were scanned: below the root, made absolute from the current directory.
+    #[default]
+    Scanned,
+    /// Relative to the root of the tree, the root itself being `.`.
+    Relative,
+    /// Below the canonical path of the root, its symbolic links resolved.
+    Absolute,
+}
+
+/// How the children of a directory are ordered, e.g. `size` or `name:desc`.
+///
+/// Without an explicit direction, names are sorted in ascending order and
+/// the other keys in descending order, the largest or newest entries first.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SortOrder {
+    /// The criterion.
+    pub key: SortKey,
+    /// Whether the order is reversed.
+    pub descending: bool,
+}
+
+impl SortOrder {
+
+    /// Returns the order on `key` in its default direction.
+    pub fn new(key: SortKey) -> Self {
+        SortOrder { key, descending: key != SortKey::Name }
+    }
+}
+
+impl FromStr for SortOrder {
+    type Err = io::Error;
+
+    fn from_str(text: &str) -> io::Result<Self> {
+        let invalid = || io::Error::new(io::ErrorKind::InvalidInput, format!("Ordre de tri invalide : {text} (attendu name|size|mtime|count, suivi de :asc ou :desc)"));
+        let (key, direction) = match text.split_once(':') {
+            Some((key, direction)) => (key, Some(direction)),
+            None => (text, None),
+        };
+        let key = match key.to_ascii_lowercase().as_str() {
+            "name" => SortKey::Name,
+            "size" => SortKey::Size,
+            "mtime" => SortKey::Mtime,
+            "count" => SortKey::Count,
+            _ => return Err(invalid()),
+        };
+        let mut order = SortOrder::new(key);
+        match direction.map(str::to_ascii_lowercase).as_deref() {
+            None => {}
+            Some("asc") => order.descending = false,
+            Some("desc") => order.descending = true,
+            Some(_) => return Err(invalid()),
+        }
+        Ok(order)
+    }
+}
+
+impl fmt::Display for SortOrder {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let key = match self.key {
+            SortKey::Name => "name",
+            SortKey::Size => "size",
+            SortKey::Mtime => "mtime",
+            SortKey::Count => "count",
+        };
+        write!(f, "{}:{}", key, if self.descending { "desc" } else { "asc" })
+    }
+}
+
+/// The share of its directory below which an entry is collapsed, e.g. `1%` or `10M`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Threshold {
+    /// A percentage of the size of the parent directory.
+    Percent(f64),
+    /// An absolute size.
+    Size(Size),
+}
+
+impl Threshold {
+
+    /// Returns `true` if an entry of `size` in a directory of `parent_size` is below the threshold.
+    fn is_below(&self, size: Size, parent_size: Size) -> bool {
+        match *self {
+            Threshold::Percent(percent) => share(size, parent_size) * 100.0 < percent,
+            Threshold::Size(threshold) => size < threshold,
+        }
+    }
+}
+
+impl FromStr for Threshold {
+    type Err = io::Error;
+
+    fn from_str(text: &str) -> io::Result<Self> {
+        match text.trim().strip_suffix('%') {
+            Some(percent) => match percent.trim().parse::<f64>() {
+                Ok(percent) if (0.0..=100.0).contains(&percent) => Ok(Threshold::Percent(percent)),
+                _ => Err(io::Error::new(io::ErrorKind::InvalidInput, format!("invalid percentage: {:?} (expected between 0% and 100%)", text))),
+            },
+            None => Size::parse(text).map(Threshold::Size),
+        }
+    }
+}
+
+/// Options controlling how the file tree is displayed.
+#[derive(Clone, Debug, Default)]
+pub struct PrintOptions {
+    /// Display the space used on disk instead of the apparent size.
+    pub disk_usage: bool,
+    /// The depth below which entries are not displayed, the root being at depth 0.
+    ///
+    /// The directories at this depth are still displayed with the size of their whole subtree.
+    pub max_depth: Option<usize>,
+    /// Display the number of files and subdirectories of each directory.
+    pub show_counts: bool,
+    /// Hide the entries smaller than this size, except the root.
+    pub min_size: Option<Size>,
+    /// The order of the children of each directory.
+    pub sort: SortOrder,
+    /// Draw the branches of the tree with ASCII characters instead of Unicode box-drawing characters.
+    pub ascii: bool,
+    /// Display the last modification time of each entry, in UTC.
+    pub show_mtime: bool,
+    /// How the sizes are displayed.
+    pub size_format: SizeFormat,
+    /// Display the share of each entry in the size of its directory and in the size of the root.
+    pub percent: bool,
+    /// Color the output with ANSI escape sequences: directories in blue, the size of large files
+    /// from green to red by share of their directory, and duplicates in magenta.
+    ///
+    /// Duplicates are only known once the signatures have been computed, e.g. in a tree loaded
+    /// from a cache saved by the `duplicate` command.
+    pub color: bool,
+    /// Collapse the children of a directory below this threshold into a single line giving their
+    /// number and total size.
+    pub prune_below: Option<Threshold>,
+    /// The width of each level of the tree, in columns, 4 by default.
+    pub indent: Option<usize>,
+    /// Write the full path of each entry on its own line, without drawing the tree, like `du`.
+    pub flat: bool,
+    /// Display the user and the group owning each entry.
+    pub show_owner: bool,
+    /// Display the permissions of each entry, like `ls -l`.
+    pub show_perms: bool,
+    /// How the paths are displayed, in the tree and in every export format.
+    pub path_style: PathStyle,
+    /// Display the directories only, each with the size of its whole subtree, like `du`.
+    pub dirs_only: bool,
+}
+
+impl PrintOptions {
+
+    /// Returns `true` if an entry of `size` at `depth` is large enough to be displayed.
+    pub(crate) fn is_shown(&self, size: Size, depth: usize) -> bool {
+        depth == 0 || self.min_size.is_none_or(|min_size| size >= min_size)
+    }
+
+    /// Returns `true` if the children of an entry at `depth` are displayed.
+    pub(crate) fn descends(&self, depth: usize) -> bool {
+        self.max_depth.is_none_or(|max_depth| depth < max_depth)
+    }
+
+    /// Returns the connectors drawing the branches of the tree: before the last child of a
+    /// directory, before its other children, and below them, each `indent` columns wide.
+    fn connectors(&self) -> [String; 4] {
+        if self.flat {
+            return Default::default();
+        }
+        let [last_connector, connector, line, branch] = if self.ascii { ['`', '|', '-', '|'] } else { ['└', '├', '─', '│'] };
+        let width = self.indent.unwrap_or(4);
+        // Le coin, puis des traits jusqu'à l'espace qui précède le nom
+        let draw = |corner: char| match width {
+            0 => String::new(),
+            1 => corner.to_string(),
+            _ => format!("{}{} ", corner, line.to_string().repeat(width - 2)),
+        };
+        let pad = |corner: char| match width {
+            0 => String::new(),
+            _ => format!("{}{}", corner, " ".repeat(width - 1)),
+        };
+        [draw(last_connector), draw(connector), " ".repeat(width), pad(branch)]
+    }
+}
+
+
+/// The totals printed at the end of the output with `--summary`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Summary {
+    /// The total size, or disk usage when it is displayed.
+    pub size: Size,
+    /// The number of files and of directories below the roots.
+    pub counts: EntryCounts,
+    /// The number of entries that could not be read.
+    pub errors: usize,
+    /// The space freed by keeping a single copy of each duplicate, for a duplicate report.
+    pub reclaimable: Option<Size>,
+}
+
+impl Summary {
+
+    /// Writes the summary on a single line, e.g. `Total: 1.5 MB, 12 files, 3 dirs, 0 errors`.
+    ///
+    /// # Arguments
+    ///
+    /// * `out` - The writer receiving the line.
+    /// * `format` - How the sizes are displayed.
+    pub fn write<W: Write>(&self, out: &mut W, format: SizeFormat) -> io::Result<()> {
+        let plural = if self.errors == 1 { "" } else { "s" };
+        write!(out, "Total: {}, {}, {} error{}", self.size.display(format), self.counts, self.errors, plural)?;
+        if let Some(reclaimable) = self.reclaimable {
+            write!(out, ", {} reclaimable", reclaimable.display(format))?;
+        }
+        writeln!(out)
+    }
+}
 
 impl FileTree {
 
+    /// Returns the path of the entry at `path` as displayed with `style`.
+    ///
+    /// The paths outside the tree are returned unchanged, and so are the paths below a root
+    /// that no longer exists in the `Absolute` style.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use du::{FileTree, PathStyle};
+    /// use std::path::Path;
+    ///
+    /// # let dir = tempfile::tempdir()?;
+    /// std::fs::write(dir.path().join("notes.txt"), b"hello")?;
+    /// let file_tree = FileTree::new(dir.path())?;
+    ///
+    /// assert_eq!(file_tree.display_path(&dir.path().join("notes.txt"), PathStyle::Relative), Path::new("notes.txt"));
+    /// assert_eq!(file_tree.display_path(dir.path(), PathStyle::Relative), Path::new("."));
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn display_path<'a>(&self, path: &'a Path, style: PathStyle) -> Cow<'a, Path> {
+        let Ok(relative) = path.strip_prefix(self.get_root()) else { return Cow::Borrowed(path) };
+        match style {
+            PathStyle::Scanned => Cow::Borrowed(path),
+            PathStyle::Relative if relative.as_os_str().is_empty() => Cow::Borrowed(Path::new(".")),
+            PathStyle::Relative => Cow::Borrowed(relative),
+            PathStyle::Absolute => match self.canonical_root() {
+                Some(root) if relative.as_os_str().is_empty() => Cow::Owned(root.to_path_buf()),
+                Some(root) => Cow::Owned(root.join(relative)),
+                None => Cow::Borrowed(path),
+            },
+        }
+    }
+
+    /// Returns the totals of the file tree: its size according to `options`, the number of files
+    /// and directories below the root, and the number of unreadable entries.
+    pub fn summary(&self, options: &PrintOptions) -> Summary {
+        let counts = match self.get_map_option(self.get_root()) {
+            Some(EntryNode::File(_)) => EntryCounts { files: 1, directories: 0 },
+            _ => self.get_counts(self.get_root()).unwrap_or_default(),
+        };
+        Summary { size: self.node_size(self.get_root(), options), counts, errors: self.errors().len(), reclaimable: None }
+    }
+
     /// Display the entire file tree rooted at the specified path.
-    pub fn show(&self) {
-        self.show_recursive(self.get_root(), 0);
+    pub fn show(&self, options: &PrintOptions) -> io::Result<()> {
+        self.write_tree(&mut io::stdout().lock(), options)
+    }
+
+    /// Write the entire file tree to `out`, children being listed in the order of `options.sort`.
+    ///
+    /// # Arguments
+    ///
+    /// * `out` - The writer receiving the output.
+    /// * `options` - The display options.
+    pub fn write_tree<W: Write>(&self, out: &mut W, options: &PrintOptions) -> io::Result<()> {
+        self.write_root(out, None, options)
+    }
+
+
+    /// Display the file tree rooted at the specified path, sorted by decreasing size.
+    pub fn show_lexicographic(&self, options: &PrintOptions) -> io::Result<()> {
+        self.write_lexicographic(&mut io::stdout().lock(), options)
     }
-    
 
-    /// Display the file tree rooted at the specified path, sorted lexicographically
-    pub fn show_lexicographic(&self){
-        self.show_lexicographic_recursive(self.get_root(), 0);
+    /// Write the file tree to `out`, sorted by decreasing size whatever `options.sort`.
+    ///
+    /// Kept for compatibility, prefer `write_tree` with `SortKey::Size`.
+    ///
+    /// # Arguments
+    ///
+    /// * `out` - The writer receiving the output.
+    /// * `options` - The display options.
+    pub fn write_lexicographic<W: Write>(&self, out: &mut W, options: &PrintOptions) -> io::Result<()> {
+        let options = PrintOptions { sort: SortOrder::new(SortKey::Size), ..options.clone() };
+        self.write_tree(out, &options)
     }
-    
+
     /// Display the file tree rooted at the specified path, applying a filter if provided.
     ///
     /// # Arguments
     ///
-    /// * `filter` - The filter string to apply.
-    /// * `lexicographic_sort` - A flag indicating whether to sort lexicographically.
-    pub fn show_filtered(&self, filter: &str, lexicographic_sort: bool) {
+    /// * `filter` - The filter to apply.
+    /// * `lexicographic_sort` - A flag forcing the children to be sorted by name.
+    /// * `options` - The display options.
+    pub fn show_filtered(&self, filter: &Filter, lexicographic_sort: bool, options: &PrintOptions) -> io::Result<()> {
+        self.write_filtered(&mut io::stdout().lock(), filter, lexicographic_sort, options)
+    }
+
+    /// Write the file tree to `out`, keeping only the files matching the filter.
+    ///
+    /// Directories are written only if at least one of their descendants matches.
+    ///
+    /// # Arguments
+    ///
+    /// * `out` - The writer receiving the output.
+    /// * `filter` - The filter to apply.
+    /// * `lexicographic_sort` - A flag forcing the children to be sorted by name, instead of `options.sort`.
+    /// * `options` - The display options.
+    pub fn write_filtered<W: Write>(&self, out: &mut W, filter: &Filter, lexicographic_sort: bool, options: &PrintOptions) -> io::Result<()> {
         if lexicographic_sort {
-            self.show_lexicographic_filtered_recursive(self.get_root(), filter, 0);
+            let options = PrintOptions { sort: SortOrder::default(), ..options.clone() };
+            self.write_root(out, Some(filter), &options)
         } else {
-            self.show_filtered_recursive(self.get_root(), filter, 0);
+            self.write_root(out, Some(filter), options)
         }
     }
 
 
-    /// Display the entire file tree rooted at the specified path.
+    /// Write the paths of the files of the tree, and of the links and special files, each followed
+    /// by `terminator`, in the order of `options.sort`.
+    ///
+    /// The paths are written as is, even if they are not valid UTF-8, so that a NUL terminator
+    /// allows piping them safely into `xargs -0`.
     ///
     /// # Arguments
     ///
-    /// * `path` - The root path of the file tree.
-    /// * `depth` - The depth of the current recursive call.
-    fn show_recursive(&self, path: &Path, depth: usize) {     
-            if let Some(entry_node) = self.get_map_option(path) {
-                match entry_node {
-                    EntryNode::File(size) => {
-                        self.print_node(path, size, depth);
-                    },
-                    EntryNode::Directory(children) => {
-                        self.print_node(path, &self.get_size(path).unwrap_or(Size::new(0)), depth);
-
-                        for child_path in children {
-                            self.show_recursive(child_path, depth + 1);
-                        }
-                    },
-                }
-            }
-        }
-
+    /// * `out` - The writer receiving the paths.
+    /// * `filter` - The filter the files must match, if any.
+    /// * `options` - The display options: the order and the minimum size.
+    /// * `terminator` - The byte written after each path, `b'\n'` or `b'\0'`.
+    pub fn write_files<W: Write>(&self, out: &mut W, filter: Option<&Filter>, options: &PrintOptions, terminator: u8) -> io::Result<()> {
+        self.write_files_recursive(out, self.get_root(), filter, options, terminator, 0)
+    }
 
-    /// Display the file tree rooted at the specified path, sorted lexicographically.
+    /// Writes one `SIZE<TAB>PATH` line per directory, like `du`: each directory after its
+    /// subdirectories and the root last, with the size of its whole subtree in blocks of 1 KiB
+    /// rounded up, or formatted like `du -h` if `human` is set. A root which is not a directory
+    /// is written alone.
     ///
     /// # Arguments
     ///
-    /// * `path` - The root path of the file tree.
-    /// * `depth` - The depth of the current recursive call.
-    fn show_lexicographic_recursive(&self, path: &Path, depth: usize) {     
-        if let Some(entry_node) = self.get_map_option(path) {
-            match entry_node {
-                EntryNode::File(size) => {
-                    self.print_node(path, size, depth);
-                },
-                EntryNode::Directory(children) => {
-                    // Triage par taille
-                    let mut sorted_children: Vec<_> = children.into_iter().collect();
-                    sorted_children.sort_by(|a, b| {
-                        self.get_size(b).unwrap_or(Size::new(0)).cmp(&self.get_size(a).unwrap_or(Size::new(0)))
-                    });
-
-                    self.print_node(path, &self.get_size(path).unwrap_or(Size::new(0)), depth);
-
-                    for child_path in sorted_children {
-                        self.show_lexicographic_recursive(child_path, depth + 1);
+    /// * `out` - The writer receiving the lines.
+    /// * `filter` - The filter the files of the listed directories must match, if any.
+    /// * `options` - The display options: the size, the depth, the order, the minimum size and
+    ///   the style of the paths, `options.size_format.si` selecting the powers of 1000 with `human`.
+    /// * `human` - Whether the sizes are written like `du -h` instead of in blocks.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use du::{FileTree, PrintOptions};
+    ///
+    /// # let dir = tempfile::tempdir()?;
+    /// std::fs::create_dir(dir.path().join("logs"))?;
+    /// std::fs::write(dir.path().join("logs/app.log"), [0; 3000])?;
+    ///
+    /// let mut out = Vec::new();
+    /// FileTree::new(dir.path())?.write_du(&mut out, None, &PrintOptions::default(), false)?;
+    /// let logs = dir.path().join("logs");
+    /// assert_eq!(String::from_utf8(out).unwrap(), format!("3\t{}\n3\t{}\n", logs.display(), dir.path().display()));
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn write_du<W: Write>(&self, out: &mut W, filter: Option<&Filter>, options: &PrintOptions, human: bool) -> io::Result<()> {
+        if !self.is_visible(self.get_root(), filter, options, 0) {
+            return Ok(());
+        }
+        self.write_du_recursive(out, self.get_root(), filter, options, human, 0)
+    }
+
+    fn write_du_recursive<W: Write>(&self, out: &mut W, path: &Path, filter: Option<&Filter>, options: &PrintOptions, human: bool, depth: usize) -> io::Result<()> {
+        match self.get_map_option(path) {
+            Some(EntryNode::Directory(children)) if options.descends(depth) => {
+                for child in self.sorted_children(children, options) {
+                    if matches!(self.get_map_option(child), Some(EntryNode::Directory(_))) && self.is_visible(child, filter, options, depth + 1) {
+                        self.write_du_recursive(out, child, filter, options, human, depth + 1)?;
                     }
-                },
+                }
+            }
+            Some(EntryNode::Directory(_)) => {}
+            _ if depth > 0 => return Ok(()),
+            _ => {}
+        }
+        let size = self.node_size(path, options);
+        let size = if human { size.du_human(options.size_format.si) } else { size.blocks(1024).to_string() };
+        out.write_all(size.as_bytes())?;
+        out.write_all(b"\t")?;
+        out.write_all(self.display_path(path, options.path_style).as_os_str().as_encoded_bytes())?;
+        out.write_all(b"\n")
+    }
+
+    fn write_files_recursive<W: Write>(&self, out: &mut W, path: &Path, filter: Option<&Filter>, options: &PrintOptions, terminator: u8, depth: usize) -> io::Result<()> {
+        if !self.is_visible(path, filter, options, depth) {
+            return Ok(());
+        }
+        match self.get_map_option(path) {
+            Some(EntryNode::Directory(children)) => {
+                for child in self.sorted_children(children, options) {
+                    self.write_files_recursive(out, child, filter, options, terminator, depth + 1)?;
+                }
+                Ok(())
+            }
+            Some(_) => {
+                out.write_all(self.display_path(path, options.path_style).as_os_str().as_encoded_bytes())?;
+                out.write_all(&[terminator])
             }
+            None => Ok(()),
         }
     }
 
-    /// Display the file tree rooted at the specified path, applying a filter.
+    /// Writes the file tree, or nothing if its root is hidden by the filter.
+    fn write_root<W: Write>(&self, out: &mut W, filter: Option<&Filter>, options: &PrintOptions) -> io::Result<()> {
+        if !self.is_visible(self.get_root(), filter, options, 0) {
+            return Ok(());
+        }
+        let duplicates = if options.color {
+            self.find_duplicates().into_values().flatten().collect()
+        } else {
+            HashSet::new()
+        };
+        self.write_recursive(out, self.get_root(), filter, options, &duplicates, 0, "", true)
+    }
+
+    /// Display the entry at `path` and its visible descendants, below the branches drawn by `prefix`.
     ///
     /// # Arguments
     ///
-    /// * `path` - The root path of the file tree.
-    /// * `filter` - The filter string to apply.
+    /// * `out` - The writer receiving the output.
+    /// * `path` - The path of the entry, which must be visible.
+    /// * `filter` - The filter to apply, if any.
+    /// * `options` - The display options.
+    /// * `duplicates` - The files having a duplicate, colored when `options.color` is set.
     /// * `depth` - The depth of the current recursive call.
-    fn show_filtered_recursive(&self, path: &Path, filter: &str, depth: usize) {
-        if let Some(entry_node) = self.get_map_option(path) {
-            match entry_node {
-                EntryNode::File(size) if path.extension() == Some(OsStr::new(filter)) => {
-                    self.print_node(path, size, depth);
-                }
-                EntryNode::Directory(children) => {
-                    self.print_node(path, &self.get_size(path).unwrap_or(Size::new(0)), depth);
+    /// * `prefix` - The branches of the ancestors of the entry.
+    /// * `last` - Whether the entry is the last visible child of its directory.
+    #[allow(clippy::too_many_arguments)]
+    fn write_recursive<W: Write>(&self, out: &mut W, path: &Path, filter: Option<&Filter>, options: &PrintOptions, duplicates: &HashSet<PathBuf>, depth: usize, prefix: &str, last: bool) -> io::Result<()> {
+        let [last_connector, connector, last_branch, branch] = options.connectors();
+        let (connector, branch) = match depth {
+            0 => ("", ""),
+            _ if last => (last_connector.as_str(), last_branch.as_str()),
+            _ => (connector.as_str(), branch.as_str()),
+        };
+        self.print_node(out, path, options, duplicates, &format!("{}{}", prefix, connector))?;
 
-                    for child_path in children {
-                        self.show_filtered_recursive(child_path, filter, depth + 1);
+        if let Some(EntryNode::Directory(children)) = self.get_map_option(path) {
+            if options.descends(depth) {
+                let prefix = format!("{}{}", prefix, branch);
+                let (visible, pruned): (Vec<_>, Vec<_>) = self
+                    .sorted_children(children, options)
+                    .into_iter()
+                    .filter(|child| self.is_visible(child, filter, options, depth + 1))
+                    .partition(|child| !self.is_pruned(child, path, options));
+                for (index, child_path) in visible.iter().enumerate() {
+                    self.write_recursive(out, child_path, filter, options, duplicates, depth + 1, &prefix, pruned.is_empty() && index + 1 == visible.len())?;
+                }
+                if !pruned.is_empty() {
+                    let size = pruned.iter().fold(Size::new(0), |total, child| total + self.node_size(child, options));
+                    let plural = if pruned.len() == 1 { "entry" } else { "entries" };
+                    if options.flat {
+                        writeln!(out, "{}  {}", size.display(options.size_format), self.display_path(path, options.path_style).join(format!("… {} smaller {}", pruned.len(), plural)).display())?;
+                    } else {
+                        writeln!(out, "{}{}… {} smaller {} ({})", prefix, last_connector, pruned.len(), plural, size.display(options.size_format))?;
                     }
                 }
-                _ => {}
             }
         }
+        Ok(())
     }
 
+    /// Returns `true` if the entry at `path` is large enough, and matches the filter or contains a match.
+    fn is_visible(&self, path: &Path, filter: Option<&Filter>, options: &PrintOptions, depth: usize) -> bool {
+        self.is_listed(path, options, depth)
+            && options.is_shown(self.node_size(path, options), depth)
+            && filter.is_none_or(|filter| self.has_match(path, filter))
+    }
 
-    /// Display the file tree rooted at the specified path, sorted lexicographically, and filtered.
-    ///
-    /// # Arguments
-    ///
-    /// * `path` - The root path of the file tree.
-    /// * `filter` - The filter string to apply.
-    /// * `depth` - The depth of the current recursive call.
-    pub fn show_lexicographic_filtered_recursive(&self, path: &Path, filter: &str, depth: usize) {
-        if let Some(entry_node) = self.get_map_option(path) {
-            match entry_node {
-                EntryNode::File(size) if path.extension() == Some(OsStr::new(filter)) => {
-                    self.print_node(path, size, depth);
-                }
-                EntryNode::Directory(children) => {
-                    // Triage lexicographique
-                    let mut sorted_children: Vec<_> = children.into_iter().collect();
-                    sorted_children.sort();
+    /// Returns `true` unless the entry at `path` is not a directory and `options.dirs_only` hides it.
+    /// The root is always listed.
+    pub(crate) fn is_listed(&self, path: &Path, options: &PrintOptions, depth: usize) -> bool {
+        depth == 0 || !options.dirs_only || matches!(self.get_map_option(path), Some(EntryNode::Directory(_)))
+    }
 
-                    self.print_node(path, &self.get_size(path).unwrap_or(Size::new(0)), depth);
+    /// Returns `true` if the entry at `path` is collapsed by `options.prune_below` in the display of `parent`.
+    fn is_pruned(&self, path: &Path, parent: &Path, options: &PrintOptions) -> bool {
+        options
+            .prune_below
+            .is_some_and(|threshold| threshold.is_below(self.node_size(path, options), self.node_size(parent, options)))
+    }
 
-                    for child_path in sorted_children {
-                        self.show_lexicographic_filtered_recursive(child_path, filter, depth + 1);
-                    }
-                }
-                _ => {}
+    /// Returns the children of a directory in the order of `options.sort`, ties being broken by ascending name.
+    fn sorted_children<'a>(&self, children: &'a [PathBuf], options: &PrintOptions) -> Vec<&'a PathBuf> {
+        let mut sorted_children: Vec<_> = children.iter().collect();
+        let count = |path: &Path| self.get_counts(path).map_or(0, |counts| counts.files + counts.directories);
+        sorted_children.sort_by(|a, b| {
+            let ordering = match options.sort.key {
+                SortKey::Name => a.cmp(b),
+                SortKey::Size => self.node_size(a, options).cmp(&self.node_size(b, options)),
+                SortKey::Mtime => self.get_modified(a).cmp(&self.get_modified(b)),
+                SortKey::Count => count(a).cmp(&count(b)),
+            };
+            let ordering = if options.sort.descending { ordering.reverse() } else { ordering };
+            ordering.then_with(|| a.cmp(b))
+        });
+        sorted_children
+    }
+
+    /// Returns `true` if the entry at `path`, or any of its descendants, is a file or link matching the filter.
+    fn has_match(&self, path: &Path, filter: &Filter) -> bool {
+        match self.get_map_option(path) {
+            Some(EntryNode::File(_) | EntryNode::Symlink(_) | EntryNode::Other(_)) => filter.is_match_in(self, path),
+            Some(EntryNode::Directory(children)) => children.iter().any(|child| self.has_match(child, filter)),
+            None => false,
+        }
+    }
+
+
+    /// Returns the size displayed for an entry, according to the display options.
+    pub(crate) fn node_size(&self, path: &Path, options: &PrintOptions) -> Size {
+        let size = if options.disk_usage {
+            self.get_disk_usage(path)
+        } else {
+            self.get_size(path)
+        };
+        size.unwrap_or(Size::new(0))
+    }
+
+    fn print_node<W: Write>(&self, out: &mut W, path: &Path, options: &PrintOptions, duplicates: &HashSet<PathBuf>, indent: &str) -> io::Result<()> {
+        let size = self.node_size(path, options);
+        let node = self.get_map_option(path);
+        let (size_color, path_color) = match node {
+            _ if !options.color => ("", ""),
+            Some(EntryNode::Directory(_)) => ("", BLUE),
+            Some(EntryNode::File(_)) => {
+                let size_color = match path.parent() {
+                    Some(parent) if path != self.get_root() => heat(size, self.node_size(parent, options)),
+                    _ => "",
+                };
+                (size_color, if duplicates.contains(path) { MAGENTA } else { "" })
+            }
+            _ => ("", ""),
+        };
+        let mut columns = paint(size.display(options.size_format), size_color);
+        if options.percent {
+            let parent_size = match path.parent() {
+                Some(parent) if path != self.get_root() => self.node_size(parent, options),
+                _ => size,
+            };
+            let root_size = self.node_size(self.get_root(), options);
+            columns += &format!("  {:5.1}%  ({:.1}% total)", share(size, parent_size) * 100.0, share(size, root_size) * 100.0);
+        }
+        if options.show_mtime {
+            match self.get_modified(path) {
+                Some(modified) => columns += &format!("  {}", humantime::format_rfc3339_seconds(modified)),
+                None => columns += &format!("  {:20}", "-"),
+            }
+        }
+        if options.show_perms || options.show_owner {
+            let ownership = self.get_ownership(path);
+            if options.show_perms {
+                columns += &format!("  {}", ownership.map_or_else(|| "-".repeat(9), |ownership| ownership.permissions()));
+            }
+            if options.show_owner {
+                let (owner, group) = ownership.map_or(("-".into(), "-".into()), |ownership| (ownership.owner_name(), ownership.group_name()));
+                columns += &format!("  {:<8} {:<8}", owner, group);
             }
         }
+        if let (Some(EntryNode::Directory(_)), true) = (node, options.show_counts) {
+            columns += &format!("  ({})", self.get_counts(path).unwrap_or_default());
+        }
+        // La racine et, à plat, chaque entrée sont affichées avec leur chemin, les autres par leur nom
+        let name = match path.file_name() {
+            Some(name) if !options.flat && path != self.get_root() => name.to_string_lossy(),
+            _ => Cow::Owned(self.display_path(path, options.path_style).to_string_lossy().into_owned()),
+        };
+        let name = paint(name, path_color);
+        match node {
+            Some(EntryNode::Symlink(target)) => writeln!(out, "{}{}  {} -> {}", indent, columns, name, target.display()),
+            Some(EntryNode::Other(kind)) => writeln!(out, "{}{}  {} [{}]", indent, columns, name, kind),
+            Some(EntryNode::Directory(_)) if self.get_archive_size(path).is_some() => {
+                let archive_size = self.get_archive_size(path).unwrap_or(Size::new(0));
+                writeln!(out, "{}{}  {} [archive] ({} compressed)", indent, columns, name, archive_size.display(options.size_format))
+            }
+            Some(EntryNode::File(_)) if self.is_sparse(path) => {
+                // La taille absente de la colonne est donnée après le marqueur
+                let (other, label) = match options.disk_usage {
+                    true => (self.get_size(path), "apparent"),
+                    false => (self.get_disk_usage(path), "allocated"),
+                };
+                writeln!(out, "{}{}  {} [sparse] ({} {})", indent, columns, name, other.unwrap_or(Size::new(0)).display(options.size_format), label)
+            }
+            _ => writeln!(out, "{}{}  {}", indent, columns, name),
+        }
+    }
+}
+
+/// Returns the share of `total` taken by `size`, between 0 and 1, or 0 if `total` is empty.
+fn share(size: Size, total: Size) -> f64 {
+    if total.value() == 0 {
+        0.0
+    } else {
+        size.value() as f64 / total.value() as f64
+    }
+}
+
+/// Returns the color of a file by its share of the size of its directory: green from 10%,
+/// yellow from 25% and red from 50%, none below.
+fn heat(size: Size, parent_size: Size) -> &'static str {
+    match share(size, parent_size) {
+        share if share >= 0.5 => RED,
+        share if share >= 0.25 => YELLOW,
+        share if share >= 0.1 => GREEN,
+        _ => "",
+    }
+}
+
+/// Wraps `text` in the escape sequences of `color`, if any.
+fn paint(text: impl fmt::Display, color: &str) -> String {
+    if color.is_empty() {
+        text.to_string()
+    } else {
+        format!("{}{}{}", color, text, RESET)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::file_tree::FileTree;
+    use crate::filter::Filter;
+    use crate::print_tree::{PathStyle, PrintOptions, SortKey, SortOrder, Summary, Threshold};
+    use crate::size::Size;
+    use crate::test_utils::{create_file, create_fixture};
+    use std::path::Path;
+
+    fn filtered_output(file_tree: &FileTree, pattern: &str, case_sensitive: bool) -> String {
+        let filter = Filter::new(pattern, false, case_sensitive).unwrap();
+        let mut out = Vec::new();
+        file_tree.write_filtered(&mut out, &filter, true, &PrintOptions::default()).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn filter_glob_extension_test() {
+        let fixture = create_fixture();
+        let root = fixture.path().join("Test");
+        create_file(&root.join("Dossier1/photo.jpg"), 10);
+        create_file(&root.join("Dossier2/SousDossier1/vacances.jpg"), 10);
+        let file_tree = FileTree::new(&root).unwrap();
+
+        let output = filtered_output(&file_tree, "*.jpg", false);
+        assert!(output.contains("photo.jpg"));
+        assert!(output.contains("vacances.jpg"));
+        assert!(!output.contains("Fichier1"));
+    }
+
+    #[test]
+    fn filter_question_mark_test() {
+        let fixture = create_fixture();
+        let root = fixture.path().join("Test");
+        create_file(&root.join("Dossier1/IMG_0001.png"), 10);
+        create_file(&root.join("Dossier1/IMG_01.png"), 10);
+        let file_tree = FileTree::new(&root).unwrap();
+
+        let output = filtered_output(&file_tree, "IMG_????.png", false);
+        assert!(output.contains("IMG_0001.png"));
+        assert!(!output.contains("IMG_01.png"));
+    }
+
+    #[test]
+    fn filter_case_insensitive_test() {
+        let fixture = create_fixture();
+        let root = fixture.path().join("Test");
+        create_file(&root.join("Dossier1/PHOTO.JPG"), 10);
+        let file_tree = FileTree::new(&root).unwrap();
+
+        assert!(filtered_output(&file_tree, "jpg", false).contains("PHOTO.JPG"));
+        assert!(!filtered_output(&file_tree, "jpg", true).contains("PHOTO.JPG"));
+    }
+
+    #[test]
+    fn filter_prunes_empty_directories_test() {
+        let fixture = create_fixture();
+        let root = fixture.path().join("Test");
+        create_file(&root.join("Dossier1/photo.jpg"), 10);
+        let file_tree = FileTree::new(&root).unwrap();
+
+        let output = filtered_output(&file_tree, "*.jpg", false);
+        assert!(output.contains("Dossier1"));
+        assert!(!output.contains("Dossier2"));
+        assert_eq!(output.lines().count(), 3);
+    }
+
+    #[test]
+    fn disk_usage_option_test() {
+        let fixture = create_fixture();
+        let root = fixture.path().join("Test");
+        let file_tree = FileTree::new(&root).unwrap();
+        let filter = Filter::new("Fichier1*", false, false).unwrap();
+
+        let mut out = Vec::new();
+        let options = PrintOptions { disk_usage: true, ..PrintOptions::default() };
+        file_tree.write_filtered(&mut out, &filter, true, &options).unwrap();
+        let expected = format!("{}", file_tree.get_disk_usage(&root.join("Dossier1/Fichier1")).unwrap());
+        let output = String::from_utf8(out).unwrap();
+        assert!(output.lines().last().unwrap().starts_with(&format!("    └── {expected}")));
+    }
+
+    #[test]
+    fn max_depth_test() {
+        let fixture = create_fixture();
+        let root = fixture.path().join("Test");
+        let file_tree = FileTree::new(&root).unwrap();
+
+        let mut out = Vec::new();
+        let options = PrintOptions { max_depth: Some(1), ..PrintOptions::default() };
+        file_tree.write_tree(&mut out, &options).unwrap();
+        let output = String::from_utf8(out).unwrap();
+        assert_eq!(output.lines().count(), 3);
+        assert!(output.lines().last().unwrap().starts_with("└── 508.06 KB"));
+        assert!(!output.contains("SousDossier"));
+
+        let mut out = Vec::new();
+        file_tree.write_tree(&mut out, &PrintOptions { max_depth: Some(0), ..PrintOptions::default() }).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap().lines().count(), 1);
+    }
+
+    #[test]
+    fn show_counts_test() {
+        let fixture = create_fixture();
+        let root = fixture.path().join("Test");
+        let file_tree = FileTree::new(&root).unwrap();
+
+        let mut out = Vec::new();
+        file_tree.write_tree(&mut out, &PrintOptions { show_counts: true, ..PrintOptions::default() }).unwrap();
+        let output = String::from_utf8(out).unwrap();
+        assert!(output.lines().next().unwrap().contains("(4 files, 4 dirs)"));
+        assert!(output.contains("(1 file, 0 dirs)"));
+        assert!(!output.lines().any(|line| line.contains("Fichier1") && line.contains("files")));
+    }
+
+    #[test]
+    fn min_size_test() {
+        let fixture = create_fixture();
+        let root = fixture.path().join("Test");
+        let file_tree = FileTree::new(&root).unwrap();
+
+        let mut out = Vec::new();
+        let options = PrintOptions { min_size: Some(Size::new(1024)), ..PrintOptions::default() };
+        file_tree.write_lexicographic(&mut out, &options).unwrap();
+        let output = String::from_utf8(out).unwrap();
+        assert!(output.contains("Fichier1"));
+        assert!(output.contains("SousDossier2"));
+        assert!(!output.contains("SousDossier1"));
+        assert!(!output.contains("Fichier4"));
+    }
+
+    fn sorted_output(file_tree: &FileTree, sort: &str) -> Vec<String> {
+        let mut out = Vec::new();
+        let options = PrintOptions { sort: sort.parse().unwrap(), ..PrintOptions::default() };
+        file_tree.write_tree(&mut out, &options).unwrap();
+        String::from_utf8(out).unwrap().lines().map(|line| line.rsplit(['/', ' ']).next().unwrap().to_string()).collect()
+    }
+
+    #[test]
+    fn sort_parse_test() {
+        assert_eq!("name".parse::<SortOrder>().unwrap(), SortOrder::default());
+        assert_eq!("SIZE".parse::<SortOrder>().unwrap(), SortOrder { key: SortKey::Size, descending: true });
+        assert_eq!("size:asc".parse::<SortOrder>().unwrap(), SortOrder { key: SortKey::Size, descending: false });
+        assert_eq!("count:desc".parse::<SortOrder>().unwrap().to_string(), "count:desc");
+        assert!("taille".parse::<SortOrder>().is_err());
+        assert!("size:up".parse::<SortOrder>().is_err());
+    }
+
+    #[test]
+    fn sort_test() {
+        let fixture = create_fixture();
+        let root = fixture.path().join("Test");
+        let file_tree = FileTree::new(&root).unwrap();
+
+        assert_eq!(sorted_output(&file_tree, "name"), ["Test", "Dossier1", "Fichier1", "Dossier2", "Fichier3", "SousDossier1", "Fichier4", "SousDossier2", "Fichier2"]);
+        assert_eq!(sorted_output(&file_tree, "size"), ["Test", "Dossier2", "Fichier3", "SousDossier2", "Fichier2", "SousDossier1", "Fichier4", "Dossier1", "Fichier1"]);
+        assert_eq!(sorted_output(&file_tree, "size:asc")[1..4], ["Dossier1", "Fichier1", "Dossier2"]);
+        assert_eq!(sorted_output(&file_tree, "count")[1..3], ["Dossier2", "SousDossier1"]);
+        assert_eq!(sorted_output(&file_tree, "name:desc")[1], "Dossier2");
+    }
+
+    #[test]
+    fn connectors_test() {
+        let fixture = create_fixture();
+        let root = fixture.path().join("Test");
+        let file_tree = FileTree::new(&root).unwrap();
+
+        let mut out = Vec::new();
+        file_tree.write_tree(&mut out, &PrintOptions::default()).unwrap();
+        let prefixes: Vec<_> = String::from_utf8(out).unwrap().lines().map(|line| line.split(|c: char| c.is_ascii_digit()).next().unwrap().to_string()).collect();
+        assert_eq!(prefixes, ["", "├── ", "│   └── ", "└── ", "    ├── ", "    ├── ", "    │   └── ", "    └── ", "        └── "]);
+
+        let mut out = Vec::new();
+        file_tree.write_tree(&mut out, &PrintOptions { ascii: true, ..PrintOptions::default() }).unwrap();
+        let output = String::from_utf8(out).unwrap();
+        assert!(output.is_ascii());
+        assert!(output.lines().nth(2).unwrap().starts_with("|   `-- "));
+    }
+
+    #[test]
+    fn color_test() {
+        let fixture = create_fixture();
+        let root = fixture.path().join("Test");
+        std::fs::copy(root.join("Dossier1/Fichier1"), root.join("Dossier2/Copie")).unwrap();
+        let mut file_tree = FileTree::new(&root).unwrap();
+        file_tree.compute_signatures().unwrap();
+
+        let mut out = Vec::new();
+        file_tree.write_tree(&mut out, &PrintOptions::default()).unwrap();
+        assert!(!String::from_utf8(out).unwrap().contains('\x1b'));
+
+        let mut out = Vec::new();
+        file_tree.write_tree(&mut out, &PrintOptions { color: true, ..PrintOptions::default() }).unwrap();
+        let output = String::from_utf8(out).unwrap();
+        let line = |name: &str| output.lines().find(|line| line.ends_with(&format!("{name}{}", super::RESET)) || line.ends_with(name)).unwrap();
+        assert!(line("Dossier2").contains(&format!("{}Dossier2", super::BLUE)));
+        assert!(line("Fichier3").contains(&format!("{}488.28 KB", super::RED)));
+        assert!(line("Fichier2").contains(&format!("{}19.53 KB", super::RED)));
+        assert!(line("Copie").contains(super::MAGENTA));
+        assert!(!line("Copie").contains(super::GREEN));
+    }
+
+    #[test]
+    fn percent_test() {
+        let fixture = create_fixture();
+        let root = fixture.path().join("Test");
+        let file_tree = FileTree::new(&root).unwrap();
+
+        let mut out = Vec::new();
+        file_tree.write_tree(&mut out, &PrintOptions { percent: true, ..PrintOptions::default() }).unwrap();
+        let output = String::from_utf8(out).unwrap();
+        let line = |name: &str| output.lines().find(|line| line.ends_with(name)).unwrap().to_string();
+        assert!(line("/Test").contains("100.0%  (100.0% total)"));
+        assert!(line("Fichier3").contains(" 96.1%  (95.9% total)"));
+        assert!(line("Fichier4").contains("100.0%  (0.0% total)"));
+    }
+
+    #[test]
+    fn show_mtime_test() {
+        let fixture = create_fixture();
+        let root = fixture.path().join("Test");
+        let file = std::fs::File::options().write(true).open(root.join("Dossier1/Fichier1")).unwrap();
+        file.set_modified(std::time::UNIX_EPOCH + std::time::Duration::from_secs(1_700_000_000)).unwrap();
+        let file_tree = FileTree::new(&root).unwrap();
+
+        let mut out = Vec::new();
+        file_tree.write_tree(&mut out, &PrintOptions { show_mtime: true, ..PrintOptions::default() }).unwrap();
+        let output = String::from_utf8(out).unwrap();
+        let line = output.lines().find(|line| line.ends_with("Fichier1")).unwrap();
+        assert!(line.ends_with("1 KB  2023-11-14T22:13:20Z  Fichier1"));
+    }
+
+    #[test]
+    fn summary_test() {
+        let fixture = create_fixture();
+        let root = fixture.path().join("Test");
+        let summary = FileTree::new(&root).unwrap().summary(&PrintOptions::default());
+        assert_eq!((summary.size, summary.counts.files, summary.counts.directories, summary.errors), (Size::new(521280), 4, 4, 0));
+
+        let mut out = Vec::new();
+        Summary { reclaimable: Some(Size::new(1024)), ..summary }.write(&mut out, Default::default()).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "Total: 509.06 KB, 4 files, 4 dirs, 0 errors, 1 KB reclaimable\n");
+
+        let summary = FileTree::new(&root.join("Dossier1/Fichier1")).unwrap().summary(&PrintOptions::default());
+        assert_eq!((summary.counts.files, summary.counts.directories), (1, 0));
     }
 
+    #[test]
+    fn prune_below_test() {
+        let fixture = create_fixture();
+        let root = fixture.path().join("Test");
+        let file_tree = FileTree::new(&root).unwrap();
+
+        let mut out = Vec::new();
+        file_tree.write_tree(&mut out, &PrintOptions { prune_below: Some("5%".parse().unwrap()), ..PrintOptions::default() }).unwrap();
+        let output = String::from_utf8(out).unwrap();
+        assert!(output.contains("└── … 1 smaller entry (1 KB)"));
+        assert!(output.contains("│   └── … 2 smaller entries (19.78 KB)"));
+        assert!(!output.contains("Dossier1"));
+        assert!(!output.contains("SousDossier2"));
+
+        let mut out = Vec::new();
+        file_tree.write_tree(&mut out, &PrintOptions { prune_below: Some(Threshold::Size(Size::new(10000))), ..PrintOptions::default() }).unwrap();
+        let output = String::from_utf8(out).unwrap();
+        assert!(output.contains("│   └── … 1 smaller entry (256 B)"));
+        assert!(output.contains("Fichier2"));
+
+        assert_eq!("1%".parse::<Threshold>().unwrap(), Threshold::Percent(1.0));
+        assert_eq!("10K".parse::<Threshold>().unwrap(), Threshold::Size(Size::new(10240)));
+        assert!("150%".parse::<Threshold>().is_err());
+    }
+
+    #[test]
+    fn indent_and_flat_test() {
+        let fixture = create_fixture();
+        let root = fixture.path().join("Test");
+        let file_tree = FileTree::new(&root).unwrap();
+
+        let mut out = Vec::new();
+        file_tree.write_tree(&mut out, &PrintOptions::default()).unwrap();
+        let output = String::from_utf8(out).unwrap();
+        assert!(output.starts_with(&format!("509.06 KB  {}\n├── 1 KB  Dossier1\n│   └── 1 KB  Fichier1\n", root.display())));
+
+        let mut out = Vec::new();
+        file_tree.write_tree(&mut out, &PrintOptions { indent: Some(2), ..PrintOptions::default() }).unwrap();
+        let output = String::from_utf8(out).unwrap();
+        assert_eq!(output.lines().nth(2).unwrap(), "│ └ 1 KB  Fichier1");
+
+        let mut out = Vec::new();
+        file_tree.write_tree(&mut out, &PrintOptions { indent: Some(6), ascii: true, ..PrintOptions::default() }).unwrap();
+        let output = String::from_utf8(out).unwrap();
+        assert_eq!(output.lines().nth(2).unwrap(), "|     `---- 1 KB  Fichier1");
+
+        let mut out = Vec::new();
+        file_tree.write_tree(&mut out, &PrintOptions { flat: true, ..PrintOptions::default() }).unwrap();
+        let output = String::from_utf8(out).unwrap();
+        assert_eq!(output.lines().count(), 9);
+        assert_eq!(output.lines().nth(2).unwrap(), format!("1 KB  {}", root.join("Dossier1/Fichier1").display()));
+    }
+
+    #[test]
+    fn write_du_test() {
+        let fixture = create_fixture();
+        let root = fixture.path().join("Test");
+        let file_tree = FileTree::new(&root).unwrap();
+
+        let mut out = Vec::new();
+        file_tree.write_du(&mut out, None, &PrintOptions { path_style: PathStyle::Relative, ..PrintOptions::default() }, false).unwrap();
+        let sub = |name: &str| Path::new("Dossier2").join(name).display().to_string();
+        let expected = format!("1\tDossier1\n1\t{}\n20\t{}\n509\tDossier2\n510\t.\n", sub("SousDossier1"), sub("SousDossier2"));
+        assert_eq!(String::from_utf8(out).unwrap(), expected);
+
+        let mut out = Vec::new();
+        file_tree.write_du(&mut out, None, &PrintOptions { max_depth: Some(1), path_style: PathStyle::Relative, ..PrintOptions::default() }, true).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "1.0K\tDossier1\n509K\tDossier2\n510K\t.\n");
+
+        let mut out = Vec::new();
+        FileTree::new(&root.join("Dossier1/Fichier1")).unwrap().write_du(&mut out, None, &PrintOptions::default(), false).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), format!("1\t{}\n", root.join("Dossier1/Fichier1").display()));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn sparse_test() {
+        let fixture = create_fixture();
+        let root = fixture.path().join("Test");
+        std::fs::File::create(root.join("Dossier1/disque.img")).unwrap().set_len(64 * 1024 * 1024).unwrap();
+        let file_tree = FileTree::new(&root).unwrap();
+        assert!(file_tree.is_sparse(&root.join("Dossier1/disque.img")));
+        assert!(!file_tree.is_sparse(&root.join("Dossier1/Fichier1")));
+
+        let mut out = Vec::new();
+        file_tree.write_tree(&mut out, &PrintOptions::default()).unwrap();
+        let output = String::from_utf8(out).unwrap();
+        assert!(output.contains("64 MB  disque.img [sparse] (0 B allocated)\n"), "{}", output);
+        assert!(output.contains("1 KB  Fichier1\n"));
+
+        let mut out = Vec::new();
+        file_tree.write_tree(&mut out, &PrintOptions { disk_usage: true, ..PrintOptions::default() }).unwrap();
+        assert!(String::from_utf8(out).unwrap().contains("0 B  disque.img [sparse] (64 MB apparent)\n"));
+    }
+
+    #[test]
+    fn dirs_only_test() {
+        let fixture = create_fixture();
+        let root = fixture.path().join("Test");
+        let file_tree = FileTree::new(&root).unwrap();
+        let options = PrintOptions { dirs_only: true, flat: true, path_style: PathStyle::Relative, ..PrintOptions::default() };
+
+        let mut out = Vec::new();
+        file_tree.write_tree(&mut out, &options).unwrap();
+        let output = String::from_utf8(out).unwrap();
+        let lines: Vec<_> = output.lines().collect();
+        assert_eq!(lines, ["509.06 KB  .", "1 KB  Dossier1", "508.06 KB  Dossier2", "256 B  Dossier2/SousDossier1", "19.53 KB  Dossier2/SousDossier2"]);
+
+        let records = file_tree.usage_records(&options);
+        assert_eq!(records.len(), 5);
+        assert!(records.iter().all(|record| record.kind == "directory"));
+
+        // Une racine qui est un fichier reste affichée
+        let file_tree = FileTree::new(&root.join("Dossier1/Fichier1")).unwrap();
+        assert_eq!(file_tree.usage_records(&options).len(), 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn path_style_test() {
+        let fixture = create_fixture();
+        let root = fixture.path().join("Test");
+        let link = fixture.path().join("lien");
+        std::os::unix::fs::symlink(&root, &link).unwrap();
+        let file_tree = FileTree::new(&link).unwrap();
+        let fichier1 = link.join("Dossier1/Fichier1");
+
+        assert_eq!(file_tree.display_path(&fichier1, PathStyle::Scanned), fichier1);
+        assert_eq!(file_tree.display_path(&fichier1, PathStyle::Relative), Path::new("Dossier1/Fichier1"));
+        let canonical = std::fs::canonicalize(&root).unwrap();
+        assert_eq!(file_tree.display_path(&fichier1, PathStyle::Absolute), canonical.join("Dossier1/Fichier1"));
+        assert_eq!(file_tree.display_path(&link, PathStyle::Absolute), canonical);
+        assert_eq!(file_tree.display_path(Path::new("/ailleurs"), PathStyle::Relative), Path::new("/ailleurs"));
+
+        let mut out = Vec::new();
+        file_tree.write_tree(&mut out, &PrintOptions { flat: true, path_style: PathStyle::Relative, ..PrintOptions::default() }).unwrap();
+        let output = String::from_utf8(out).unwrap();
+        assert_eq!(output.lines().next().unwrap(), "509.06 KB  .");
+        assert_eq!(output.lines().nth(2).unwrap(), "1 KB  Dossier1/Fichier1");
+
+        let mut csv = Vec::new();
+        file_tree.write_csv(&mut csv, &PrintOptions { path_style: PathStyle::Relative, ..PrintOptions::default() }).unwrap();
+        assert!(String::from_utf8(csv).unwrap().contains("\nDossier1/Fichier1,file,1024,2,Dossier1,\n"));
+    }
+
+    #[test]
+    fn write_files_test() {
+        let fixture = create_fixture();
+        let root = fixture.path().join("Test");
+        create_file(&root.join("Dossier1/nom avec\nretour.txt"), 10);
+        let file_tree = FileTree::new(&root).unwrap();
+
+        let mut out = Vec::new();
+        let options = PrintOptions { sort: "size".parse().unwrap(), ..PrintOptions::default() };
+        file_tree.write_files(&mut out, None, &options, b'\0').unwrap();
+        let paths: Vec<_> = out.split(|byte| *byte == 0).map(|path| String::from_utf8_lossy(path).into_owned()).collect();
+        assert_eq!(paths, [
+            root.join("Dossier2/Fichier3").display().to_string(),
+            root.join("Dossier2/SousDossier2/Fichier2").display().to_string(),
+            root.join("Dossier2/SousDossier1/Fichier4").display().to_string(),
+            root.join("Dossier1/Fichier1").display().to_string(),
+            root.join("Dossier1/nom avec\nretour.txt").display().to_string(),
+            String::new(),
+        ]);
+
+        let mut out = Vec::new();
+        let filter = Filter::new("*.txt", false, false).unwrap();
+        file_tree.write_files(&mut out, Some(&filter), &PrintOptions::default(), b'\n').unwrap();
+        assert_eq!(out, format!("{}\n", root.join("Dossier1/nom avec\nretour.txt").display()).into_bytes());
+    }
 
+    #[cfg(unix)]
+    #[test]
+    fn show_owner_test() {
+        use std::os::unix::fs::PermissionsExt;
+        let fixture = create_fixture();
+        let root = fixture.path().join("Test");
+        std::fs::set_permissions(root.join("Dossier1/Fichier1"), std::fs::Permissions::from_mode(0o640)).unwrap();
+        let file_tree = FileTree::new(&root).unwrap();
 
-    fn print_node(&self, path: &Path, size: &Size, depth: usize) {
-        let indent = "      ".repeat(depth);
-        println!("{}{}  /{}",indent, size, path.display());
+        let mut out = Vec::new();
+        file_tree.write_tree(&mut out, &PrintOptions { show_perms: true, show_owner: true, ..PrintOptions::default() }).unwrap();
+        let output = String::from_utf8(out).unwrap();
+        let ownership = file_tree.get_ownership(&root.join("Dossier1/Fichier1")).unwrap();
+        let expected = format!("1 KB  rw-r-----  {:<8} {:<8}  Fichier1", ownership.owner_name(), ownership.group_name());
+        assert!(output.lines().any(|line| line.ends_with(&expected)), "{output}");
     }
-}
\ No newline at end of file
+}
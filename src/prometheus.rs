@@ -0,0 +1,126 @@
+use std::io::{self, Write};
+use std::path::Path;
+use crate::{duplicates::DuplicateReport, file_tree::FileTree, print_tree::PrintOptions};
+
+impl FileTree {
+
+    /// Writes the sizes of the file tree as Prometheus gauges, in the text exposition format, to be
+    /// scraped or written to the textfile directory of the node exporter:
+    ///
+    /// * `du_directory_size_bytes` - The size of each directory, recursively.
+    /// * `du_directory_files` - The number of files below each directory, recursively.
+    /// * `du_duplicate_reclaimable_bytes` - The space freed by keeping a single copy of each
+    ///   duplicate file, if `duplicates` is given.
+    ///
+    /// Each directory is labeled by its path, and the duplicates by the root of the tree.
+    ///
+    /// # Arguments
+    ///
+    /// * `out` - The writer receiving the metrics.
+    /// * `options` - The display options, selecting the apparent size or the disk usage, the maximum depth and the style of the paths.
+    /// * `duplicates` - The duplicates found in the tree, if any.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use du::{FileTree, PrintOptions};
+    ///
+    /// # let dir = tempfile::tempdir()?;
+    /// std::fs::write(dir.path().join("notes.txt"), b"hello")?;
+    ///
+    /// let mut out = Vec::new();
+    /// FileTree::new(dir.path())?.write_prometheus(&mut out, &PrintOptions::default(), None)?;
+    /// let metrics = String::from_utf8(out).unwrap();
+    /// assert!(metrics.contains(&format!("du_directory_size_bytes{{path=\"{}\"}} 5\n", dir.path().display())));
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn write_prometheus<W: Write>(&self, mut out: W, options: &PrintOptions, duplicates: Option<&DuplicateReport>) -> io::Result<()> {
+        let directories: Vec<&Path> = self
+            .directories()
+            .map(|path| path.as_path())
+            .filter(|path| options.max_depth.is_none_or(|max_depth| path.strip_prefix(self.get_root()).map_or(0, |relative| relative.components().count()) <= max_depth))
+            .collect();
+        let label = |path: &Path| escape(&self.display_path(path, options.path_style).to_string_lossy());
+
+        writeln!(out, "# HELP du_directory_size_bytes The size of the directory, recursively.")?;
+        writeln!(out, "# TYPE du_directory_size_bytes gauge")?;
+        for path in &directories {
+            let size = if options.disk_usage { self.get_disk_usage(path) } else { self.get_size(path) };
+            writeln!(out, "du_directory_size_bytes{{path=\"{}\"}} {}", label(path), size.map_or(0, |size| size.value()))?;
+        }
+        writeln!(out, "# HELP du_directory_files The number of files below the directory, recursively.")?;
+        writeln!(out, "# TYPE du_directory_files gauge")?;
+        for path in &directories {
+            writeln!(out, "du_directory_files{{path=\"{}\"}} {}", label(path), self.get_counts(path).unwrap_or_default().files)?;
+        }
+        if let Some(duplicates) = duplicates {
+            writeln!(out, "# HELP du_duplicate_reclaimable_bytes The space freed by keeping a single copy of each duplicate file.")?;
+            writeln!(out, "# TYPE du_duplicate_reclaimable_bytes gauge")?;
+            writeln!(out, "du_duplicate_reclaimable_bytes{{path=\"{}\"}} {}", label(self.get_root()), duplicates.total_reclaimable().value())?;
+        }
+        Ok(())
+    }
+}
+
+/// Escapes the backslashes, double quotes and line feeds of a label value.
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hasher::Blake3Hasher;
+    use crate::print_tree::PathStyle;
+    use crate::size::Size;
+    use crate::test_utils::{create_file, create_fixture};
+
+    #[test]
+    fn write_prometheus_test() {
+        let fixture = create_fixture();
+        let root = fixture.path().join("Test");
+        // Une copie de Dossier1/Fichier1, pour avoir de la place à récupérer
+        create_file(&root.join("Copie"), 1024);
+        let mut file_tree = FileTree::new(&root).unwrap();
+        file_tree.compute_signatures_between(&Blake3Hasher, Size::new(1), None, |_| {}).unwrap();
+        let report = file_tree.find_duplicates_between(Size::new(1), None);
+
+        let mut out = Vec::new();
+        let options = PrintOptions { max_depth: Some(1), path_style: PathStyle::Relative, ..PrintOptions::default() };
+        file_tree.write_prometheus(&mut out, &options, Some(&report)).unwrap();
+        let metrics = String::from_utf8(out).unwrap();
+        let samples: Vec<_> = metrics.lines().filter(|line| !line.starts_with('#')).collect();
+        assert_eq!(samples, [
+            "du_directory_size_bytes{path=\".\"} 522304",
+            "du_directory_size_bytes{path=\"Dossier1\"} 1024",
+            "du_directory_size_bytes{path=\"Dossier2\"} 520256",
+            "du_directory_files{path=\".\"} 5",
+            "du_directory_files{path=\"Dossier1\"} 1",
+            "du_directory_files{path=\"Dossier2\"} 3",
+            "du_duplicate_reclaimable_bytes{path=\".\"} 1024",
+        ]);
+        assert!(metrics.contains("# TYPE du_duplicate_reclaimable_bytes gauge\n"));
+
+        let mut out = Vec::new();
+        file_tree.write_prometheus(&mut out, &options, None).unwrap();
+        assert!(!String::from_utf8(out).unwrap().contains("du_duplicate_reclaimable_bytes"));
+
+        assert_eq!(escape("a\"b\\c\nd"), "a\\\"b\\\\c\\nd");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn escaped_labels_test() {
+        let fixture = create_fixture();
+        let root = fixture.path().join("Test");
+        create_file(&root.join("Le \"grand\" \\ dossier/notes"), 10);
+
+        let mut out = Vec::new();
+        let options = PrintOptions { max_depth: Some(1), path_style: PathStyle::Relative, ..PrintOptions::default() };
+        FileTree::new(&root).unwrap().write_prometheus(&mut out, &options, None).unwrap();
+        let metrics = String::from_utf8(out).unwrap();
+        assert!(metrics.contains("du_directory_size_bytes{path=\"Le \\\"grand\\\" \\\\ dossier\"} 10\n"));
+        assert!(metrics.contains("du_directory_files{path=\"Le \\\"grand\\\" \\\\ dossier\"} 1\n"));
+    }
+}
@@ -1,25 +1,233 @@
+use serde::{Deserialize, Serialize};
 use std::fmt;
+use std::io;
+use std::str::FromStr;
 
 /// Represents the size of a file or directory.
-#[derive(PartialEq, PartialOrd, Eq, Ord, Copy, Clone, Debug)]
+#[derive(PartialEq, PartialOrd, Eq, Ord, Hash, Copy, Clone, Debug, Default, Serialize, Deserialize)]
 pub struct Size(u64);
 
 impl Size {
 
     /// Creates a new Size instance with the specified size in bytes.
-    
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use du::Size;
+    ///
+    /// assert_eq!(Size::new(1500).value(), 1500);
+    /// ```
     pub fn new(bytes: u64) -> Self {
         Self(bytes)
     }
 
     /// Gets the value of the size in bytes.
-    
     pub fn value(&self) -> u64 {
         self.0
     }
+
+    /// Parses a size written with an optional unit, such as `512`, `10M`, `1.5 GB` or `4KiB`.
+    ///
+    /// Units are case insensitive and, like the display, are powers of 1024.
+    ///
+    /// # Returns
+    ///
+    /// An `io::Result` containing the size, or an `InvalidInput` error if the text is not a size.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use du::Size;
+    ///
+    /// assert_eq!(Size::parse("10M")?, Size::new(10 * 1024 * 1024));
+    /// assert_eq!(Size::parse("1.5 kb")?, Size::new(1536));
+    /// assert!(Size::parse("10 parsecs").is_err());
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn parse(text: &str) -> io::Result<Self> {
+        let invalid = || io::Error::new(io::ErrorKind::InvalidInput, format!("invalid size: {:?}", text));
+        let text = text.trim();
+        let split = text.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(text.len());
+        let (number, unit) = text.split_at(split);
+        let number: f64 = number.parse().map_err(|_| invalid())?;
+
+        let exponent = match unit.trim().to_ascii_uppercase().as_str() {
+            "" | "B" => 0,
+            "K" | "KB" | "KIB" => 1,
+            "M" | "MB" | "MIB" => 2,
+            "G" | "GB" | "GIB" => 3,
+            "T" | "TB" | "TIB" => 4,
+            "P" | "PB" | "PIB" => 5,
+            _ => return Err(invalid()),
+        };
+        let bytes = number * 1024f64.powi(exponent);
+        if !bytes.is_finite() || bytes >= u64::MAX as f64 {
+            return Err(invalid());
+        }
+        Ok(Self(bytes.round() as u64))
+    }
+
+    /// Returns the number of blocks of `block_size` bytes holding the size, rounded up, as
+    /// counted by `du`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use du::Size;
+    ///
+    /// assert_eq!(Size::new(1025).blocks(1024), 2);
+    /// assert_eq!(Size::new(0).blocks(1024), 0);
+    /// ```
+    pub fn blocks(self, block_size: u64) -> u64 {
+        self.0.div_ceil(block_size)
+    }
+
+    /// Formats the size like `du -h`: the bytes below 1 K, then one decimal below 10 and none
+    /// above, always rounded up, with the suffixes `K`, `M`, `G`... or, in powers of 1000 with
+    /// `si`, `k`, `M`, `G`...
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use du::Size;
+    ///
+    /// assert_eq!(Size::new(512).du_human(false), "512");
+    /// assert_eq!(Size::new(4096).du_human(false), "4.0K");
+    /// assert_eq!(Size::new(1_500_000).du_human(false), "1.5M");
+    /// assert_eq!(Size::new(1_500_000).du_human(true), "1.5M");
+    /// assert_eq!(Size::new(15_000).du_human(true), "15k");
+    /// ```
+    pub fn du_human(self, si: bool) -> String {
+        let base: u128 = if si { 1000 } else { 1024 };
+        let suffixes = if si { ['k', 'M', 'G', 'T', 'P', 'E'] } else { ['K', 'M', 'G', 'T', 'P', 'E'] };
+        let bytes = self.0 as u128;
+        if bytes < base {
+            return bytes.to_string();
+        }
+        // En entiers, pour arrondir au dixième supérieur sans erreur de flottant
+        let mut divisor = base;
+        for (index, suffix) in suffixes.iter().enumerate() {
+            let tenths = (bytes * 10).div_ceil(divisor);
+            if tenths < 100 {
+                return format!("{}.{}{}", tenths / 10, tenths % 10, suffix);
+            }
+            let units = bytes.div_ceil(divisor);
+            if units < base || index + 1 == suffixes.len() {
+                return format!("{}{}", units, suffix);
+            }
+            divisor *= base;
+        }
+        unreachable!("a u64 is below 1024 EiB")
+    }
+}
+
+impl FromStr for Size {
+    type Err = io::Error;
+
+    fn from_str(text: &str) -> io::Result<Self> {
+        Self::parse(text)
+    }
 }
  
 
+/// A unit in which sizes can be displayed, a power of 1024 or of 1000 depending on `SizeFormat::si`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SizeUnit {
+    B,
+    KB,
+    MB,
+    GB,
+    TB,
+    PB,
+}
+
+impl FromStr for SizeUnit {
+    type Err = io::Error;
+
+    fn from_str(text: &str) -> io::Result<Self> {
+        match text.trim().to_ascii_uppercase().as_str() {
+            "B" => Ok(SizeUnit::B),
+            "K" | "KB" | "KIB" => Ok(SizeUnit::KB),
+            "M" | "MB" | "MIB" => Ok(SizeUnit::MB),
+            "G" | "GB" | "GIB" => Ok(SizeUnit::GB),
+            "T" | "TB" | "TIB" => Ok(SizeUnit::TB),
+            "P" | "PB" | "PIB" => Ok(SizeUnit::PB),
+            _ => Err(io::Error::new(io::ErrorKind::InvalidInput, format!("invalid unit: {:?} (expected B, KB, MB, GB, TB or PB)", text))),
+        }
+    }
+}
+
+/// How sizes are displayed: the unit system, and optionally a single unit for all sizes.
+///
+/// The default is the binary system, in the largest unit keeping the number above 1.
+///
+/// # Example
+///
+/// ```
+/// use du::{Size, SizeFormat, SizeUnit};
+///
+/// let size = Size::new(1_500_000);
+/// assert_eq!(size.display(SizeFormat::default()).to_string(), "1.43 MB");
+/// assert_eq!(size.display(SizeFormat { si: true, ..SizeFormat::default() }).to_string(), "1.5 MB");
+/// assert_eq!(size.display(SizeFormat { unit: Some(SizeUnit::KB), ..SizeFormat::default() }).to_string(), "1464.84 KB");
+/// assert_eq!(size.display(SizeFormat { bytes: true, ..SizeFormat::default() }).to_string(), "1500000");
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SizeFormat {
+    /// Use powers of 1000 (kB, MB, GB) instead of powers of 1024.
+    pub si: bool,
+    /// Display the raw number of bytes, without unit.
+    pub bytes: bool,
+    /// Display all the sizes in this unit.
+    pub unit: Option<SizeUnit>,
+}
+
+/// A size displayed in a `SizeFormat`, returned by `Size::display`.
+#[derive(Clone, Copy, Debug)]
+pub struct SizeDisplay {
+    size: Size,
+    format: SizeFormat,
+}
+
+impl Size {
+
+    /// Returns a wrapper displaying the size in the given format.
+    pub fn display(self, format: SizeFormat) -> SizeDisplay {
+        SizeDisplay { size: self, format }
+    }
+}
+
+impl fmt::Display for SizeDisplay {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.format.bytes {
+            return write!(f, "{}", self.size.0);
+        }
+        let (base, units) = if self.format.si {
+            (1000.0, ["B", "kB", "MB", "GB", "TB", "PB", "EB", "ZB", "YB"])
+        } else {
+            (1024.0, ["B", "KB", "MB", "GB", "TB", "PB", "EB", "ZB", "YB"])
+        };
+        let mut file_size = self.size.0 as f64;
+        let mut index = 0;
+        match self.format.unit {
+            Some(unit) => {
+                index = unit as usize;
+                file_size /= f64::powi(base, index as i32);
+            }
+            None => {
+                while file_size >= base && index < units.len() - 1 {
+                    file_size /= base;
+                    index += 1;
+                }
+            }
+        }
+
+        let rounded_size = (file_size * 100.0).round() / 100.0;
+        write!(f, "{} {}", rounded_size, units[index])
+    }
+}
+
 impl fmt::Display for Size {
 
     /// Formats the size in a human-readable format with appropriate units (e.g., KB, MB).
@@ -27,31 +235,19 @@ impl fmt::Display for Size {
     /// # Example
     ///
     /// ```
-    /// use your_crate_name::Size;
+    /// use du::Size;
     ///
     /// let size = Size::new(2048);
     /// assert_eq!(format!("{}", size), "2 KB");
     /// ```
-    
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let units = ["B", "KB", "MB", "GB", "TB", "PB", "EB", "ZB", "YB"];
-        let mut file_size  = self.0 as f64;
-        let mut index = 0;
-        while file_size >= 1024.0 && index < units.len() - 1 {
-            file_size /= 1024.0;
-            index += 1;
-        }
-
-        let rounded_size = (file_size * 100.0).round() / 100.0;
-        write!(f, "{} {}", rounded_size, units[index])
- 
+        self.display(SizeFormat::default()).fmt(f)
     }
 }
 
 impl std::ops::Add for Size {
 
     /// Adds two Size instances, returning a new Size instance with the combined size in bytes.
-
     type Output = Self;
     fn add(self, other: Self) -> Self::Output {
         Self(self.0 + other.0)
@@ -104,4 +300,55 @@ mod tests {
         let ftd = Size::new(1073741824);
         assert_eq!(format!("{ftd}"), "1 GB")
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn parse_test() {
+        assert_eq!(Size::parse("0").unwrap(), Size::new(0));
+        assert_eq!(Size::parse("512").unwrap(), Size::new(512));
+        assert_eq!(Size::parse("512B").unwrap(), Size::new(512));
+        assert_eq!(Size::parse("4k").unwrap(), Size::new(4096));
+        assert_eq!(Size::parse("4 KiB").unwrap(), Size::new(4096));
+        assert_eq!(Size::parse("10M").unwrap(), Size::new(10 * 1024 * 1024));
+        assert_eq!(Size::parse("2GB").unwrap(), Size::new(2 * 1024 * 1024 * 1024));
+        assert_eq!(Size::parse("0.5t").unwrap(), Size::new(512 * 1024 * 1024 * 1024));
+        assert_eq!("1.5K".parse::<Size>().unwrap(), Size::new(1536));
+    }
+
+    #[test]
+    fn parse_invalid_test() {
+        for text in ["", "M", "10X", "1.2.3M", "-5", "99999999999P"] {
+            assert!(Size::parse(text).is_err(), "{}", text);
+        }
+    }
+
+    #[test]
+    fn size_format_test() {
+        use super::{SizeFormat, SizeUnit};
+        let si = SizeFormat { si: true, ..SizeFormat::default() };
+        assert_eq!(Size::new(999).display(si).to_string(), "999 B");
+        assert_eq!(Size::new(1000).display(si).to_string(), "1 kB");
+        assert_eq!(Size::new(2_500_000_000).display(si).to_string(), "2.5 GB");
+
+        let megabytes = SizeFormat { unit: Some("mb".parse().unwrap()), ..SizeFormat::default() };
+        assert_eq!(Size::new(512 * 1024).display(megabytes).to_string(), "0.5 MB");
+        assert_eq!(Size::new(3 * 1024 * 1024 * 1024).display(megabytes).to_string(), "3072 MB");
+        assert_eq!(Size::new(3_000_000).display(SizeFormat { unit: Some(SizeUnit::MB), ..si }).to_string(), "3 MB");
+
+        assert_eq!(Size::new(0).display(SizeFormat { bytes: true, ..si }).to_string(), "0");
+        assert!("XB".parse::<SizeUnit>().is_err());
+    }
+
+    #[test]
+    fn du_human_test() {
+        assert_eq!(Size::new(0).du_human(false), "0");
+        assert_eq!(Size::new(1023).du_human(false), "1023");
+        assert_eq!(Size::new(1024).du_human(false), "1.0K");
+        assert_eq!(Size::new(1126).du_human(false), "1.1K");
+        assert_eq!(Size::new(1127).du_human(false), "1.2K");
+        assert_eq!(Size::new(10 * 1024).du_human(false), "10K");
+        assert_eq!(Size::new(10 * 1024 + 1).du_human(false), "11K");
+        assert_eq!(Size::new(1024 * 1024 - 1).du_human(false), "1.0M");
+        assert_eq!(Size::new(u64::MAX).du_human(false), "16E");
+        assert_eq!(Size::new(999_999).du_human(true), "1.0M");
+    }
+}
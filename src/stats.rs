@@ -0,0 +1,163 @@
+use rayon::prelude::*;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::Path;
+use crate::{error::{Error, Result}, file_tree::FileTree, print_tree::PrintOptions, size::Size};
+
+/// The total size and number of the files of one type.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct TypeStats {
+    /// The type: a lowercase extension, `(none)` for the files without one, or a MIME type,
+    /// or the name of the owner for `FileTree::owner_stats`.
+    #[serde(rename = "type")]
+    pub key: String,
+    /// The number of files.
+    pub count: u64,
+    /// The total size of the files.
+    pub size: Size,
+}
+
+
+impl FileTree {
+
+    /// Returns the total size and number of files per extension, the largest total first,
+    /// extensions being compared regardless of case.
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - The display options, selecting the apparent size or the disk usage.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use du::{FileTree, PrintOptions, Size};
+    ///
+    /// # let dir = tempfile::tempdir()?;
+    /// std::fs::write(dir.path().join("a.jpg"), [0; 100])?;
+    /// std::fs::write(dir.path().join("b.JPG"), [0; 50])?;
+    /// std::fs::write(dir.path().join("notes"), [0; 10])?;
+    ///
+    /// let stats = FileTree::new(dir.path())?.extension_stats(&PrintOptions::default());
+    /// assert_eq!((stats[0].key.as_str(), stats[0].count, stats[0].size), ("jpg", 2, Size::new(150)));
+    /// assert_eq!(stats[1].key, "(none)");
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn extension_stats(&self, options: &PrintOptions) -> Vec<TypeStats> {
+        self.type_stats(self.files().map(|path| {
+            let extension = path.extension().map_or_else(|| "(none)".to_string(), |extension| extension.to_string_lossy().to_lowercase());
+            (path.as_path(), extension)
+        }), options)
+    }
+
+    /// Returns the total size and number of files per MIME type, detected from the first bytes
+    /// of each file, the largest total first. The files of an unknown type are counted as
+    /// `application/octet-stream`.
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - The display options, selecting the apparent size or the disk usage.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the statistics, failing if a file could not be read.
+    pub fn mime_stats(&self, options: &PrintOptions) -> Result<Vec<TypeStats>> {
+        let files: Vec<_> = self.files().collect();
+        let types = files
+            .par_iter()
+            .map(|path| {
+                let mime_type = infer::get_from_path(path).map_err(|err| Error::io(path, err))?.map_or("application/octet-stream", |kind| kind.mime_type());
+                Ok((path.as_path(), mime_type.to_string()))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(self.type_stats(types.into_iter(), options))
+    }
+
+    /// Returns the total size and number of files per owning user, the largest total first.
+    /// The files whose owner could not be read are counted as `(unknown)`.
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - The display options, selecting the apparent size or the disk usage.
+    pub fn owner_stats(&self, options: &PrintOptions) -> Vec<TypeStats> {
+        self.type_stats(self.files().map(|path| {
+            let owner = self.get_ownership(path).and_then(|ownership| ownership.owner.as_ref());
+            (path.as_path(), owner.map_or_else(|| "(unknown)".to_string(), |owner| owner.name().into_owned()))
+        }), options)
+    }
+
+    fn type_stats<'a>(&self, types: impl Iterator<Item = (&'a Path, String)>, options: &PrintOptions) -> Vec<TypeStats> {
+        let mut stats: HashMap<String, TypeStats> = HashMap::new();
+        for (path, key) in types {
+            let entry = stats.entry(key).or_insert_with_key(|key| TypeStats { key: key.clone(), count: 0, size: Size::new(0) });
+            entry.count += 1;
+            entry.size = entry.size + self.node_size(path, options);
+        }
+        sorted(stats)
+    }
+}
+
+/// Adds up the statistics of several trees having the same key, the largest total first.
+pub(crate) fn merge(stats: impl IntoIterator<Item = TypeStats>) -> Vec<TypeStats> {
+    let mut merged: HashMap<String, TypeStats> = HashMap::new();
+    for stats in stats {
+        let entry = merged.entry(stats.key.clone()).or_insert_with_key(|key| TypeStats { key: key.clone(), count: 0, size: Size::new(0) });
+        entry.count += stats.count;
+        entry.size = entry.size + stats.size;
+    }
+    sorted(merged)
+}
+
+/// Returns the statistics sorted by decreasing size, then by key.
+fn sorted(stats: HashMap<String, TypeStats>) -> Vec<TypeStats> {
+    let mut stats: Vec<_> = stats.into_values().collect();
+    stats.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.key.cmp(&b.key)));
+    stats
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{create_file, create_fixture};
+    use std::fs;
+
+    #[test]
+    fn extension_stats_test() {
+        let fixture = create_fixture();
+        let root = fixture.path().join("Test");
+        create_file(&root.join("photo.PNG"), 300);
+        create_file(&root.join("Dossier1/image.png"), 200);
+        create_file(&root.join("archive.tar.gz"), 100);
+
+        let stats = FileTree::new(&root).unwrap().extension_stats(&PrintOptions::default());
+        let stats: Vec<_> = stats.iter().map(|stats| (stats.key.as_str(), stats.count, stats.size.value())).collect();
+        assert_eq!(stats, [("(none)", 4, 521280), ("png", 2, 500), ("gz", 1, 100)]);
+    }
+
+    #[test]
+    fn mime_stats_test() {
+        let fixture = create_fixture();
+        let root = fixture.path().join("Test");
+        fs::write(root.join("image.dat"), b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR").unwrap();
+
+        let stats = FileTree::new(&root).unwrap().mime_stats(&PrintOptions::default()).unwrap();
+        assert_eq!(stats.len(), 2);
+        assert_eq!((stats[0].key.as_str(), stats[0].count), ("application/octet-stream", 4));
+        assert_eq!((stats[1].key.as_str(), stats[1].count), ("image/png", 1));
+    }
+
+    #[test]
+    fn owner_stats_test() {
+        let fixture = create_fixture();
+        let root = fixture.path().join("Test");
+        let file_tree = FileTree::new(&root).unwrap();
+
+        let stats = file_tree.owner_stats(&PrintOptions::default());
+        assert_eq!(stats.len(), 1);
+        let owner = file_tree.get_ownership(&root).unwrap().owner_name();
+        assert_eq!((stats[0].key.as_str(), stats[0].count, stats[0].size.value()), (owner.as_ref(), 4, 521280));
+
+        let merged = merge(stats.iter().cloned().chain(stats.iter().cloned()));
+        assert_eq!((merged[0].count, merged[0].size.value()), (8, 2 * 521280));
+    }
+}
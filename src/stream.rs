@@ -0,0 +1,352 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::io::{self, BufRead};
+use std::path::{Path, PathBuf};
+use crate::{builder::FileTreeBuilder, error::{Error, Result}, file_tree::{EntryNode, Explorer, FileTree, Inherited}, size::Size};
+
+/// An entry reported by `FileTreeBuilder::stream` as soon as its whole subtree has been scanned.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StreamEntry<'a> {
+    /// The path of the entry.
+    pub path: &'a Path,
+    /// `true` for a directory, `false` for a file or a symbolic link.
+    pub is_dir: bool,
+    /// The size of the entry, or of its whole subtree for a directory.
+    pub size: Size,
+    /// The space used on disk by the entry, or by its whole subtree for a directory.
+    pub disk_usage: Size,
+    /// The depth of the entry, the root being at depth 0.
+    pub depth: usize,
+}
+
+/// An entry of a streaming scan as written by `usage --stream --format ndjson`, one JSON object
+/// per line, and read back by `FileTree::read_stream`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StreamRecord {
+    /// The path of the entry.
+    pub path: PathBuf,
+    /// `true` for a directory, `false` for a file or a symbolic link.
+    pub is_dir: bool,
+    /// The size of the entry, or of its whole subtree for a directory.
+    pub size: Size,
+    /// The space used on disk by the entry, or by its whole subtree for a directory.
+    pub disk_usage: Size,
+    /// The depth of the entry, the root being at depth 0.
+    pub depth: usize,
+}
+
+impl From<&StreamEntry<'_>> for StreamRecord {
+    fn from(entry: &StreamEntry) -> Self {
+        StreamRecord { path: entry.path.to_path_buf(), is_dir: entry.is_dir, size: entry.size, disk_usage: entry.disk_usage, depth: entry.depth }
+    }
+}
+
+/// The state of a streaming scan.
+struct Streamer<'a, F> {
+    explorer: Explorer<'a>,
+    visit: F,
+    /// The files having several hard links already counted.
+    links: HashSet<(u64, u64)>,
+    errors: Vec<(PathBuf, io::Error)>,
+    /// Set when `visit` fails, the error being propagated up to the root.
+    stopped: bool,
+    /// The entry on which the scan failed.
+    failed: Option<PathBuf>,
+}
+
+impl FileTreeBuilder {
+
+    /// Scans the file system like `build`, but without keeping the entries in memory: each entry
+    /// is passed to `visit` as soon as its subtree has been scanned, the children of a directory
+    /// in path order and before the directory itself.
+    ///
+    /// The directories are scanned one at a time, in order. The unreadable entries are left out
+    /// and returned at the end, unless the scan is strict.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use du::FileTreeBuilder;
+    ///
+    /// # let dir = tempfile::tempdir()?;
+    /// std::fs::create_dir(dir.path().join("docs"))?;
+    /// std::fs::write(dir.path().join("docs/a.txt"), [0; 1000])?;
+    ///
+    /// let mut lines = Vec::new();
+    /// FileTreeBuilder::new(dir.path()).stream(|entry| {
+    ///     lines.push((entry.depth, entry.size.value()));
+    ///     Ok(())
+    /// })?;
+    /// assert_eq!(lines, [(2, 1000), (1, 1000), (0, 1000)]);
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the entries that could not be read, failing if the root can't be
+    /// read, with a `WalkInterrupted` error if `visit` fails, or on the first unreadable entry if
+    /// the scan is strict.
+    pub fn stream(self, visit: impl FnMut(&StreamEntry) -> io::Result<()>) -> Result<Vec<(PathBuf, io::Error)>> {
+        let options = self.scan_options()?;
+        let root = std::path::absolute(self.root()).map_err(|err| Error::io(self.root(), err))?;
+        let metadata = fs::metadata(&root).map_err(|err| Error::io(&root, err))?;
+        let mut callback = |_: &_| {};
+        let mut streamer = Streamer {
+            explorer: Explorer::new(&root, &options, &mut callback),
+            visit,
+            links: HashSet::new(),
+            errors: Vec::new(),
+            stopped: false,
+            failed: None,
+        };
+        if let Err(source) = streamer.stream_entry(&root, &metadata, &Inherited::default(), 0) {
+            let path = streamer.failed.unwrap_or_else(|| root.clone());
+            return Err(match streamer.stopped {
+                true => Error::WalkInterrupted { path, source },
+                false => Error::Io { path, source },
+            });
+        }
+        Ok(streamer.errors)
+    }
+}
+
+impl FileTree {
+
+    /// Builds a file tree from the entries of a streaming scan written as NDJSON, one
+    /// `StreamRecord` per line, e.g. by a scan run on another machine.
+    ///
+    /// The links and special files are listed as empty files. The tree has no modification
+    /// times, owners nor signatures, and `refresh` scans the local file system at the path of
+    /// its root.
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - The reader of the stream.
+    /// * `origin` - Where the stream comes from, e.g. `user@host:/srv`, naming it in the errors.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the file tree, failing with an `InvalidStream` error if a line is
+    /// not a valid entry or if no entry is at depth 0.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use du::{FileTree, FileTreeBuilder, StreamRecord};
+    ///
+    /// # let dir = tempfile::tempdir()?;
+    /// std::fs::create_dir(dir.path().join("docs"))?;
+    /// std::fs::write(dir.path().join("docs/a.txt"), [0; 1000])?;
+    ///
+    /// let mut stream = Vec::new();
+    /// FileTreeBuilder::new(dir.path()).stream(|entry| {
+    ///     serde_json::to_writer(&mut stream, &StreamRecord::from(entry))?;
+    ///     stream.push(b'\n');
+    ///     Ok(())
+    /// })?;
+    ///
+    /// let file_tree = FileTree::read_stream(&stream[..], "local")?;
+    /// assert_eq!(file_tree.get_size(&dir.path().join("docs")).unwrap().value(), 1000);
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn read_stream<R: BufRead>(reader: R, origin: &str) -> Result<FileTree> {
+        let invalid = |reason: String| Error::InvalidStream { origin: origin.to_string(), reason };
+        let mut records = Vec::new();
+        for (index, line) in reader.lines().enumerate() {
+            let line = line.map_err(|err| invalid(format!("line {}: {}", index + 1, err)))?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            records.push(serde_json::from_str::<StreamRecord>(&line).map_err(|err| invalid(format!("line {}: {}", index + 1, err)))?);
+        }
+        let root = records.iter().find(|record| record.depth == 0).map(|record| record.path.clone()).ok_or_else(|| invalid("no entry at depth 0".to_string()))?;
+
+        let mut children: HashMap<PathBuf, Vec<PathBuf>> = HashMap::new();
+        let mut children_disk_usage: HashMap<PathBuf, Size> = HashMap::new();
+        for record in records.iter().filter(|record| record.depth > 0) {
+            if let Some(parent) = record.path.parent() {
+                children.entry(parent.to_path_buf()).or_default().push(record.path.clone());
+                let total = children_disk_usage.entry(parent.to_path_buf()).or_default();
+                *total = *total + record.disk_usage;
+            }
+        }
+        let (mut map, mut disk_usage) = (BTreeMap::new(), HashMap::new());
+        for record in records.into_iter().filter(|record| record.path.starts_with(&root)) {
+            // Le flux donne l'espace du sous-arbre d'un dossier : son espace propre en est déduit
+            let (node, own_disk_usage) = if record.is_dir {
+                let mut children = children.remove(&record.path).unwrap_or_default();
+                children.sort();
+                let from_children = children_disk_usage.get(&record.path).copied().unwrap_or_default();
+                (EntryNode::Directory(children), Size::new(record.disk_usage.value().saturating_sub(from_children.value())))
+            } else {
+                (EntryNode::File(record.size), record.disk_usage)
+            };
+            disk_usage.insert(record.path.clone(), own_disk_usage);
+            map.insert(record.path, node);
+        }
+        Ok(FileTree::from_entries(root, map, disk_usage))
+    }
+}
+
+impl<F: FnMut(&StreamEntry) -> io::Result<()>> Streamer<'_, F> {
+
+    /// Scans the entry at `path` and its subtree, recording the errors unless the scan is strict.
+    ///
+    /// Returns the size and disk usage counted in the size of the parent directory.
+    fn stream_entry(&mut self, path: &Path, metadata: &fs::Metadata, inherited: &Inherited, depth: usize) -> io::Result<(Size, Size)> {
+        match self.explore(path, metadata, inherited, depth) {
+            Err(err) if self.stopped || self.explorer.options.strict || depth == 0 => {
+                self.failed.get_or_insert_with(|| path.to_path_buf());
+                Err(err)
+            }
+            Err(err) => {
+                self.errors.push((path.to_path_buf(), err));
+                Ok((Size::new(0), Size::new(0)))
+            }
+            sizes => sizes,
+        }
+    }
+
+    fn explore(&mut self, path: &Path, metadata: &fs::Metadata, inherited: &Inherited, depth: usize) -> io::Result<(Size, Size)> {
+        let own_disk_usage = FileTree::allocated_size(path, metadata);
+        if metadata.is_file() {
+            let size = Size::new(metadata.len());
+            self.report(path, false, size, own_disk_usage, depth)?;
+            // Un fichier déjà compté par un autre lien physique ne compte pas dans le dossier
+            let counted = self.explorer.options.count_links
+                || Explorer::link_key(metadata).is_none_or(|inode| self.links.insert(inode));
+            return Ok(if counted { (size, own_disk_usage) } else { (Size::new(0), Size::new(0)) });
+        }
+        if !metadata.is_dir() {
+            // Un fichier spécial est listé sans taille
+            self.report(path, false, Size::new(0), own_disk_usage, depth)?;
+            return Ok((Size::new(0), own_disk_usage));
+        }
+
+        let inherited = self.explorer.inherited_by(path, inherited)?;
+        let mut children = fs::read_dir(path)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<io::Result<Vec<_>>>()?;
+        children.sort();
+
+        let (mut size, mut disk_usage) = (Size::new(0), own_disk_usage);
+        for child in children {
+            if self.explorer.is_excluded(&child) {
+                continue;
+            }
+            let (child_size, child_disk_usage) = match self.child_metadata(&child, &inherited) {
+                Ok(None) => continue,
+                Ok(Some((child_metadata, true))) => self.stream_entry(&child, &child_metadata, &inherited, depth + 1)?,
+                Ok(Some((child_metadata, false))) => {
+                    let link_disk_usage = FileTree::allocated_size(&child, &child_metadata);
+                    self.report(&child, false, Size::new(0), link_disk_usage, depth + 1)?;
+                    (Size::new(0), link_disk_usage)
+                }
+                Err(err) if self.explorer.options.strict => {
+                    self.failed = Some(child);
+                    return Err(err);
+                }
+                Err(err) => {
+                    self.errors.push((child, err));
+                    continue;
+                }
+            };
+            size = size + child_size;
+            disk_usage = disk_usage + child_disk_usage;
+        }
+        self.report(path, true, size, disk_usage, depth)?;
+        Ok((size, disk_usage))
+    }
+
+    /// Returns the metadata of a child to scan, followed if it is a link to follow, and whether
+    /// it must be explored rather than reported as a link. `None` if the child is skipped.
+    fn child_metadata(&self, path: &Path, inherited: &Inherited) -> io::Result<Option<(fs::Metadata, bool)>> {
+        let metadata = fs::symlink_metadata(path)?;
+        if self.explorer.is_skipped(path, &metadata, inherited) {
+            return Ok(None);
+        }
+        if !metadata.file_type().is_symlink() {
+            return Ok(Some((metadata, true)));
+        }
+        match self.explorer.followed_metadata(path, &inherited.ancestors)? {
+            Some(target_metadata) => Ok(Some((target_metadata, true))),
+            None => Ok(Some((metadata, false))),
+        }
+    }
+
+    fn report(&mut self, path: &Path, is_dir: bool, size: Size, disk_usage: Size, depth: usize) -> io::Result<()> {
+        self.explorer.entry_scanned(path, 0);
+        let result = (self.visit)(&StreamEntry { path, is_dir, size, disk_usage, depth });
+        self.stopped = result.is_err();
+        if self.stopped {
+            self.failed = Some(path.to_path_buf());
+        }
+        result
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::create_fixture;
+
+    #[test]
+    fn stream_matches_tree_test() {
+        let fixture = create_fixture();
+        let root = fixture.path().join("Test");
+        let file_tree = FileTree::new(&root).unwrap();
+
+        let mut paths = Vec::new();
+        FileTreeBuilder::new(&root).stream(|entry| {
+            assert_eq!(Some(entry.size), file_tree.get_size(entry.path));
+            assert_eq!(Some(entry.disk_usage), file_tree.get_disk_usage(entry.path));
+            paths.push(entry.path.to_path_buf());
+            Ok(())
+        }).unwrap();
+
+        assert_eq!(paths.len(), file_tree.iter().count());
+        assert_eq!(paths[0], root.join("Dossier1/Fichier1"));
+        assert_eq!(paths[1], root.join("Dossier1"));
+        assert_eq!(paths.last(), Some(&root));
+    }
+
+    #[test]
+    fn stream_stops_on_visit_error_test() {
+        let fixture = create_fixture();
+        let mut visited = 0;
+        let result = FileTreeBuilder::new(fixture.path()).stream(|_| {
+            visited += 1;
+            Err(io::Error::other("arrêt"))
+        });
+        assert!(matches!(result, Err(Error::WalkInterrupted { path, .. }) if path.ends_with("Test/Dossier1/Fichier1")));
+        assert_eq!(visited, 1);
+    }
+
+    #[test]
+    fn read_stream_test() {
+        let fixture = create_fixture();
+        let root = fixture.path().join("Test");
+        let file_tree = FileTree::new(&root).unwrap();
+
+        let mut stream = Vec::new();
+        FileTreeBuilder::new(&root).stream(|entry| {
+            serde_json::to_writer(&mut stream, &StreamRecord::from(entry))?;
+            stream.push(b'\n');
+            Ok(())
+        }).unwrap();
+        let remote = FileTree::read_stream(&stream[..], "serveur:/srv").unwrap();
+        assert_eq!(remote.get_root(), root);
+        assert_eq!(remote.iter().count(), file_tree.iter().count());
+        for path in file_tree.iter() {
+            assert_eq!(remote.get_size(path), file_tree.get_size(path));
+            assert_eq!(remote.get_disk_usage(path), file_tree.get_disk_usage(path));
+            assert_eq!(remote.get_children(path), file_tree.get_children(path));
+        }
+
+        let err = FileTree::read_stream(&b"{\"path\": 1}\n"[..], "serveur:/srv").unwrap_err();
+        assert!(matches!(&err, Error::InvalidStream { reason, .. } if reason.starts_with("line 1:")));
+        assert!(FileTree::read_stream(&b""[..], "serveur:/srv").is_err());
+    }
+}
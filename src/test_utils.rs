@@ -0,0 +1,82 @@
+use std::fs;
+use std::path::Path;
+use tempfile::TempDir;
+
+/// Creates a file of `len` zero bytes at `path`, creating its parent directories if needed.
+///
+/// The zeros are written, so that the file is not sparse.
+pub fn create_file(path: &Path, len: u64) {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).unwrap();
+    }
+    fs::write(path, vec![0; len as usize]).unwrap();
+}
+
+/// Creates the `Test` fixture tree used by the unit tests in a temporary directory.
+///
+/// ```text
+/// Test/
+///     Dossier1/Fichier1                 (1024 bytes)
+///     Dossier2/Fichier3                 (500000 bytes)
+///     Dossier2/SousDossier1/Fichier4    (256 bytes)
+///     Dossier2/SousDossier2/Fichier2    (20000 bytes)
+/// ```
+///
+/// `Dossier2` weighs 520256 bytes in total.
+pub fn create_fixture() -> TempDir {
+    fill_fixture(tempfile::tempdir().unwrap())
+}
+
+/// Creates the `Test` fixture tree in a temporary directory below the current directory,
+/// to test the trees built from relative roots.
+pub fn create_relative_fixture() -> TempDir {
+    fs::create_dir_all("target").unwrap();
+    fill_fixture(tempfile::Builder::new().prefix("fixture").tempdir_in("target").unwrap())
+}
+
+fn fill_fixture(dir: TempDir) -> TempDir {
+    let root = dir.path().join("Test");
+    create_file(&root.join("Dossier1/Fichier1"), 1024);
+    create_file(&root.join("Dossier2/Fichier3"), 500000);
+    create_file(&root.join("Dossier2/SousDossier1/Fichier4"), 256);
+    create_file(&root.join("Dossier2/SousDossier2/Fichier2"), 20000);
+    dir
+}
+
+/// Creates the archives `a.zip`, `a.tar` and `a.tar.gz` in `dir`, each containing:
+///
+/// ```text
+/// notes.txt         ("hello world")
+/// docs/copie.txt    ("hello world")
+/// docs/vide/
+/// ```
+pub fn create_archives(dir: &Path) {
+    let mut zip = zip::ZipWriter::new(fs::File::create(dir.join("a.zip")).unwrap());
+    let options = zip::write::SimpleFileOptions::default();
+    for name in ["notes.txt", "docs/copie.txt"] {
+        zip.start_file(name, options).unwrap();
+        std::io::Write::write_all(&mut zip, b"hello world").unwrap();
+    }
+    zip.add_directory("docs/vide/", options).unwrap();
+    zip.finish().unwrap();
+
+    let tar = |writer: &mut dyn std::io::Write| {
+        let mut tar = tar::Builder::new(writer);
+        for name in ["./notes.txt", "docs/copie.txt"] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(11);
+            header.set_mode(0o644);
+            tar.append_data(&mut header, name, &b"hello world"[..]).unwrap();
+        }
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::Directory);
+        header.set_size(0);
+        header.set_mode(0o755);
+        tar.append_data(&mut header, "docs/vide/", std::io::empty()).unwrap();
+        tar.finish().unwrap();
+    };
+    tar(&mut fs::File::create(dir.join("a.tar")).unwrap());
+    let mut gz = flate2::write::GzEncoder::new(fs::File::create(dir.join("a.tar.gz")).unwrap(), flate2::Compression::default());
+    tar(&mut gz);
+    gz.finish().unwrap();
+}
@@ -0,0 +1,84 @@
+use std::path::{Path, PathBuf};
+use crate::{file_tree::FileTree, print_tree::PrintOptions, size::Size};
+
+impl FileTree {
+
+    /// Returns the `count` largest files, sorted by decreasing size then by path.
+    ///
+    /// # Arguments
+    ///
+    /// * `count` - The maximum number of files to return.
+    /// * `options` - The display options, selecting the apparent size or the disk usage.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use du::{FileTree, PrintOptions, Size};
+    ///
+    /// # let dir = tempfile::tempdir()?;
+    /// std::fs::write(dir.path().join("small.txt"), [0; 10])?;
+    /// std::fs::write(dir.path().join("big.iso"), [0; 1000])?;
+    ///
+    /// let file_tree = FileTree::new(dir.path())?;
+    /// let largest = file_tree.largest_files(1, &PrintOptions::default());
+    /// assert_eq!(largest, [(dir.path().join("big.iso").as_path(), Size::new(1000))]);
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn largest_files(&self, count: usize, options: &PrintOptions) -> Vec<(&Path, Size)> {
+        self.largest(self.files(), count, options)
+    }
+
+    /// Returns the `count` largest directories, including the root, sorted by decreasing
+    /// size of their whole subtree then by path.
+    ///
+    /// # Arguments
+    ///
+    /// * `count` - The maximum number of directories to return.
+    /// * `options` - The display options, selecting the apparent size or the disk usage.
+    pub fn largest_directories(&self, count: usize, options: &PrintOptions) -> Vec<(&Path, Size)> {
+        self.largest(self.directories(), count, options)
+    }
+
+    fn largest<'a>(&'a self, paths: impl Iterator<Item = &'a PathBuf>, count: usize, options: &PrintOptions) -> Vec<(&'a Path, Size)> {
+        let mut entries: Vec<_> = paths
+            .filter_map(|path| {
+                let size = if options.disk_usage { self.get_disk_usage(path) } else { self.get_size(path) };
+                size.map(|size| (path.as_path(), size))
+            })
+            .collect();
+        entries.sort_by(|(a_path, a_size), (b_path, b_size)| b_size.cmp(a_size).then_with(|| a_path.cmp(b_path)));
+        entries.truncate(count);
+        entries
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::create_fixture;
+
+    #[test]
+    fn largest_files_test() {
+        let fixture = create_fixture();
+        let root = fixture.path().join("Test");
+        let file_tree = FileTree::new(&root).unwrap();
+
+        let largest = file_tree.largest_files(2, &PrintOptions::default());
+        assert_eq!(largest, [
+            (root.join("Dossier2/Fichier3").as_path(), Size::new(500000)),
+            (root.join("Dossier2/SousDossier2/Fichier2").as_path(), Size::new(20000)),
+        ]);
+        assert_eq!(file_tree.largest_files(10, &PrintOptions::default()).len(), 4);
+    }
+
+    #[test]
+    fn largest_directories_test() {
+        let fixture = create_fixture();
+        let root = fixture.path().join("Test");
+        let file_tree = FileTree::new(&root).unwrap();
+
+        let largest: Vec<_> = file_tree.largest_directories(3, &PrintOptions::default()).into_iter().map(|(path, _)| path).collect();
+        assert_eq!(largest, [root.as_path(), &root.join("Dossier2"), &root.join("Dossier2/SousDossier2")]);
+    }
+}
@@ -0,0 +1,189 @@
+use std::io::{self, Write};
+use std::path::Path;
+use crate::{file_tree::{EntryNode, FileTree}, print_tree::PrintOptions};
+
+/// The colors of the top-level entries, reused in order when there are more entries.
+const PALETTE: [&str; 10] = ["#4e79a7", "#f28e2b", "#e15759", "#76b7b2", "#59a14f", "#edc948", "#b07aa1", "#ff9da7", "#9c755f", "#bab0ac"];
+
+/// The space left around the children of a directory, in pixels.
+const PADDING: f64 = 2.0;
+
+/// A rectangle of the treemap.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Rect {
+    x: f64,
+    y: f64,
+    width: f64,
+    height: f64,
+}
+
+impl Rect {
+
+    /// Returns the rectangle shrunk by `margin` on each side, `None` if nothing is left.
+    fn shrink(&self, margin: f64) -> Option<Rect> {
+        let rect = Rect { x: self.x + margin, y: self.y + margin, width: self.width - 2.0 * margin, height: self.height - 2.0 * margin };
+        (rect.width >= 1.0 && rect.height >= 1.0).then_some(rect)
+    }
+}
+
+
+impl FileTree {
+
+    /// Writes an SVG treemap of the file tree: each file is a rectangle whose area is proportional
+    /// to its size, inside the rectangle of its directory, laid out with the squarified algorithm.
+    /// The entries are colored by top-level directory, and named in a tooltip.
+    ///
+    /// The entries too small to be drawn, and those deeper than `options.max_depth`, are merged
+    /// into the rectangle of their directory.
+    ///
+    /// # Arguments
+    ///
+    /// * `out` - The writer receiving the SVG.
+    /// * `width` - The width of the image, in pixels.
+    /// * `height` - The height of the image, in pixels.
+    /// * `options` - The display options, selecting the apparent size or the disk usage and the maximum depth.
+    pub fn write_treemap<W: Write>(&self, mut out: W, width: u32, height: u32, options: &PrintOptions) -> io::Result<()> {
+        writeln!(out, r#"<svg xmlns="http://www.w3.org/2000/svg" width="{0}" height="{1}" viewBox="0 0 {0} {1}" font-family="sans-serif">"#, width, height)?;
+        let rect = Rect { x: 0.0, y: 0.0, width: width as f64, height: height as f64 };
+        self.write_treemap_node(&mut out, self.get_root(), rect, None, options, 0)?;
+        writeln!(out, "</svg>")
+    }
+
+    fn write_treemap_node<W: Write>(&self, out: &mut W, path: &Path, rect: Rect, color: Option<&str>, options: &PrintOptions, depth: usize) -> io::Result<()> {
+        let size = self.node_size(path, options);
+        let title = escape(&format!("{} ({})", path.display(), size.display(options.size_format)));
+        let fill = color.unwrap_or("#ffffff");
+        writeln!(
+            out,
+            r##"<rect x="{:.2}" y="{:.2}" width="{:.2}" height="{:.2}" fill="{}" stroke="#333" stroke-width="0.5"><title>{}</title></rect>"##,
+            rect.x, rect.y, rect.width, rect.height, fill, title,
+        )?;
+
+        let Some(EntryNode::Directory(children)) = self.get_map_option(path) else { return Ok(()) };
+        let Some(inner) = rect.shrink(PADDING) else { return Ok(()) };
+        if !options.descends(depth) {
+            return Ok(());
+        }
+        let mut children: Vec<_> = children
+            .iter()
+            .map(|child| (child, self.node_size(child, options).value() as f64))
+            .filter(|(_, size)| *size > 0.0)
+            .collect();
+        children.sort_by(|(a_path, a_size), (b_path, b_size)| b_size.total_cmp(a_size).then_with(|| a_path.cmp(b_path)));
+
+        let sizes: Vec<_> = children.iter().map(|(_, size)| *size).collect();
+        for (index, ((child, _), child_rect)) in children.iter().zip(squarify(&sizes, inner)).enumerate() {
+            if child_rect.width < 1.0 || child_rect.height < 1.0 {
+                continue;
+            }
+            let child_color = color.unwrap_or(PALETTE[index % PALETTE.len()]);
+            self.write_treemap_node(out, child, child_rect, Some(child_color), options, depth + 1)?;
+        }
+        Ok(())
+    }
+}
+
+/// Lays out rectangles of areas proportional to `sizes`, sorted by decreasing size, in `rect`.
+///
+/// The squarified algorithm fills the rectangle by rows along its shorter side, adding each
+/// size to the current row as long as this improves the worst aspect ratio of the row.
+fn squarify(sizes: &[f64], rect: Rect) -> Vec<Rect> {
+    let total: f64 = sizes.iter().sum();
+    if total <= 0.0 {
+        return Vec::new();
+    }
+    let scale = rect.width * rect.height / total;
+    let areas: Vec<_> = sizes.iter().map(|size| size * scale).collect();
+
+    let mut rects = Vec::with_capacity(areas.len());
+    let mut rect = rect;
+    let mut start = 0;
+    while start < areas.len() {
+        let side = rect.width.min(rect.height);
+        let mut end = start + 1;
+        while end < areas.len() && worst_ratio(&areas[start..=end], side) <= worst_ratio(&areas[start..end], side) {
+            end += 1;
+        }
+
+        let row = &areas[start..end];
+        let row_area: f64 = row.iter().sum();
+        if rect.width >= rect.height {
+            // La rangée occupe une colonne à gauche du rectangle restant
+            let thickness = row_area / rect.height;
+            let mut y = rect.y;
+            for area in row {
+                let height = area / thickness;
+                rects.push(Rect { x: rect.x, y, width: thickness, height });
+                y += height;
+            }
+            rect = Rect { x: rect.x + thickness, width: rect.width - thickness, ..rect };
+        } else {
+            let thickness = row_area / rect.width;
+            let mut x = rect.x;
+            for area in row {
+                let width = area / thickness;
+                rects.push(Rect { x, y: rect.y, width, height: thickness });
+                x += width;
+            }
+            rect = Rect { y: rect.y + thickness, height: rect.height - thickness, ..rect };
+        }
+        start = end;
+    }
+    rects
+}
+
+/// Returns the worst aspect ratio of the rectangles of a row of `areas` laid along a side of length `side`.
+fn worst_ratio(areas: &[f64], side: f64) -> f64 {
+    let sum: f64 = areas.iter().sum();
+    let (min, max) = areas.iter().fold((f64::MAX, 0.0f64), |(min, max), &area| (min.min(area), max.max(area)));
+    let side2 = side * side;
+    let sum2 = sum * sum;
+    (side2 * max / sum2).max(sum2 / (side2 * min))
+}
+
+/// Escapes the characters having a meaning in SVG text.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::create_fixture;
+
+    #[test]
+    fn squarify_test() {
+        let rect = Rect { x: 0.0, y: 0.0, width: 600.0, height: 400.0 };
+        let sizes = [6.0, 6.0, 4.0, 3.0, 2.0, 2.0, 1.0];
+        let rects = squarify(&sizes, rect);
+        assert_eq!(rects.len(), sizes.len());
+        for (size, rect) in sizes.iter().zip(&rects) {
+            assert!((rect.width * rect.height - size * 10000.0).abs() < 1e-6);
+            assert!(rect.x >= 0.0 && rect.y >= 0.0 && rect.x + rect.width <= 600.0 + 1e-6 && rect.y + rect.height <= 400.0 + 1e-6);
+            assert!(rect.width / rect.height < 3.0 && rect.height / rect.width < 3.0);
+        }
+    }
+
+    #[test]
+    fn treemap_svg_test() {
+        let fixture = create_fixture();
+        let root = fixture.path().join("Test");
+        let file_tree = FileTree::new(&root).unwrap();
+
+        let mut out = Vec::new();
+        file_tree.write_treemap(&mut out, 800, 600, &PrintOptions::default()).unwrap();
+        let svg = String::from_utf8(out).unwrap();
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"800\" height=\"600\""));
+        assert!(svg.ends_with("</svg>\n"));
+        // Dossier1, une bande trop fine, est dessiné sans son contenu
+        assert_eq!(svg.matches("<rect").count(), file_tree.iter().count() - 1);
+        assert!(svg.contains("Dossier1 (1 KB)"));
+        assert!(!svg.contains("Fichier1"));
+        assert!(svg.contains(&format!("<title>{} (508.06 KB)</title>", root.join("Dossier2").display())));
+
+        let mut out = Vec::new();
+        file_tree.write_treemap(&mut out, 800, 600, &PrintOptions { max_depth: Some(1), ..PrintOptions::default() }).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap().matches("<rect").count(), 3);
+    }
+}